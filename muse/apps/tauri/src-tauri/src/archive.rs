@@ -0,0 +1,734 @@
+//! Portable project archives
+//!
+//! A project is exported as a single zip holding its `ProjectSnapshot`
+//! (`snapshot.json`), the app asset files the rows reference (`assets/`), and
+//! a `manifest.json` with the format version and a SHA-256 for each entry.
+//! Imports refuse newer format versions, any entry whose checksum doesn't
+//! match and, unless asked to overwrite, a project that already exists. They
+//! copy assets into the app's asset directory and rewrite the rows'
+//! references to point at the copies.
+//!
+//! References are only read from asset fields (`ASSET_FIELDS` and image
+//! `src` attributes), as `file://` URLs or the asset protocol URLs produced by
+//! `convertFileSrc`, and only files inside the asset directory are exported;
+//! remote URLs are left as they are.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+
+use crate::db::{self, ProjectSnapshot, ProjectStore, StoreError, SyncTable};
+use crate::deep_link::link::URI_COMPONENT;
+
+/// `format` value identifying our archives
+pub const ARCHIVE_FORMAT: &str = "rhei-project-archive";
+
+/// Current archive format version; readers accept this and older
+pub const ARCHIVE_VERSION: u32 = 1;
+
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+pub const SNAPSHOT_ENTRY: &str = "snapshot.json";
+
+/// Row fields that may hold an asset reference
+const ASSET_FIELDS: [&str; 5] = [
+    "portraitUrl",
+    "mediaUrl",
+    "imageUrl",
+    "coverUrl",
+    "coverImage",
+];
+
+/// Asset protocol URL prefixes (`convertFileSrc` on macOS/Linux and Windows)
+const ASSET_PREFIXES: [&str; 3] = [
+    "asset://localhost/",
+    "http://asset.localhost/",
+    "https://asset.localhost/",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("not a project archive")]
+    NotAnArchive,
+    #[error("archive format version {0} is newer than this app supports")]
+    UnsupportedVersion(u32),
+    #[error("archive entry `{0}` is missing")]
+    MissingEntry(String),
+    #[error("archive entry `{0}` failed its checksum")]
+    ChecksumMismatch(String),
+    #[error("`{0}` is not a valid project id")]
+    InvalidProjectId(String),
+    #[error("project `{0}` already exists")]
+    ProjectExists(String),
+    #[error("row `{id}` already belongs to project `{project}`")]
+    RowInUse { id: String, project: String },
+    #[error("archive I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("archive is corrupt: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("archive JSON is invalid: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl ArchiveError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotAnArchive => "archive_not_an_archive",
+            Self::UnsupportedVersion(_) => "archive_unsupported_version",
+            Self::MissingEntry(_) => "archive_missing_entry",
+            Self::ChecksumMismatch(_) => "archive_checksum_mismatch",
+            Self::InvalidProjectId(_) => "archive_invalid_project_id",
+            Self::ProjectExists(_) => "archive_project_exists",
+            Self::RowInUse { .. } => "archive_row_in_use",
+            Self::Io(_) => "archive_io",
+            Self::Zip(_) => "archive_zip",
+            Self::Json(_) => "archive_json",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(ArchiveError);
+
+/// A referenced file stored in the archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedAsset {
+    /// The reference as it appears in the rows
+    pub reference: String,
+    /// Entry name inside the archive
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    pub project_id: String,
+    pub snapshot_version: i64,
+    pub exported_at_ms: i64,
+    pub row_counts: BTreeMap<SyncTable, usize>,
+    /// SHA-256 (hex) of every other entry, keyed by entry name
+    pub checksums: BTreeMap<String, String>,
+    #[serde(default)]
+    pub assets: Vec<ArchivedAsset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSummary {
+    pub project_id: String,
+    pub snapshot_version: i64,
+    pub rows: usize,
+    pub assets: usize,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Visit the `src` of every image node inside a JSON value
+fn visit_image_srcs(value: &mut Value, f: &mut impl FnMut(&mut String)) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(|v| visit_image_srcs(v, f)),
+        Value::Object(map) => {
+            if map.get("type").and_then(Value::as_str) == Some("image") {
+                if let Some(Value::String(src)) =
+                    map.get_mut("attrs").and_then(|a| a.get_mut("src"))
+                {
+                    f(src);
+                }
+            }
+            map.values_mut().for_each(|v| visit_image_srcs(v, f));
+        }
+        _ => {}
+    }
+}
+
+/// Visit every string of a row that may reference an asset
+fn visit_asset_refs(row: &mut Value, f: &mut impl FnMut(&mut String)) {
+    if let Some(map) = row.as_object_mut() {
+        for field in ASSET_FIELDS {
+            if let Some(Value::String(s)) = map.get_mut(field) {
+                f(s);
+            }
+        }
+    }
+    visit_image_srcs(row, f);
+}
+
+/// Whether `id` is a single safe path component
+fn is_safe_project_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn snapshot_rows_mut(snapshot: &mut ProjectSnapshot) -> impl Iterator<Item = &mut Value> {
+    snapshot
+        .documents
+        .iter_mut()
+        .chain(&mut snapshot.entities)
+        .chain(&mut snapshot.relationships)
+        .chain(&mut snapshot.mentions)
+        .chain(&mut snapshot.analysis)
+        .chain(&mut snapshot.captures)
+}
+
+/// A snapshot row id the store already holds under another project, and that project
+fn foreign_row(
+    store: &ProjectStore,
+    snapshot: &ProjectSnapshot,
+) -> Result<Option<(String, String)>, StoreError> {
+    store.with_conn(|conn| {
+        for table in SyncTable::ALL {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT project_id FROM {} WHERE id = ?1",
+                table.as_str()
+            ))?;
+            for row in snapshot.rows(table) {
+                let (_, id) = db::row_object(row)?;
+                let owner: Option<String> =
+                    stmt.query_row(params![id], |row| row.get(0)).optional()?;
+                if let Some(owner) = owner.filter(|owner| *owner != snapshot.project_id) {
+                    return Ok(Some((id.to_string(), owner)));
+                }
+            }
+        }
+        Ok(None)
+    })
+}
+
+fn row_counts(snapshot: &ProjectSnapshot) -> BTreeMap<SyncTable, usize> {
+    BTreeMap::from([
+        (SyncTable::Documents, snapshot.documents.len()),
+        (SyncTable::Entities, snapshot.entities.len()),
+        (SyncTable::Relationships, snapshot.relationships.len()),
+        (SyncTable::Mentions, snapshot.mentions.len()),
+        (SyncTable::Analysis, snapshot.analysis.len()),
+        (SyncTable::Captures, snapshot.captures.len()),
+    ])
+}
+
+/// Local file a row string points at, if any
+pub fn local_asset_path(reference: &str) -> Option<PathBuf> {
+    if reference.starts_with("file://") {
+        return url::Url::parse(reference).ok()?.to_file_path().ok();
+    }
+    let encoded = ASSET_PREFIXES
+        .iter()
+        .find_map(|prefix| reference.strip_prefix(prefix))?;
+    let decoded = percent_decode_str(encoded).decode_utf8().ok()?;
+    Some(PathBuf::from(decoded.as_ref()))
+}
+
+/// `reference` re-pointed at `path`, in the same URL form
+fn rewrite_reference(reference: &str, path: &Path) -> String {
+    if reference.starts_with("file://") {
+        if let Ok(url) = url::Url::from_file_path(path) {
+            return url.to_string();
+        }
+    }
+    let prefix = ASSET_PREFIXES
+        .iter()
+        .find(|prefix| reference.starts_with(*prefix))
+        .copied()
+        .unwrap_or(ASSET_PREFIXES[0]);
+    format!(
+        "{}{}",
+        prefix,
+        utf8_percent_encode(&path.to_string_lossy(), URI_COMPONENT)
+    )
+}
+
+/// Write a project's rows and the files they reference under `assets_root`
+/// to `dest`
+pub fn export_archive(
+    store: &ProjectStore,
+    project_id: &str,
+    dest: &Path,
+    assets_root: &Path,
+    exported_at_ms: i64,
+) -> Result<ArchiveSummary, ArchiveError> {
+    let mut snapshot = store.snapshot(project_id)?;
+
+    // Collect referenced asset files, stored once per content hash
+    let assets_root = assets_root.canonicalize().ok();
+    let mut references = Vec::new();
+    for row in snapshot_rows_mut(&mut snapshot) {
+        visit_asset_refs(row, &mut |s| {
+            let path = local_asset_path(s).and_then(|p| p.canonicalize().ok());
+            if let (Some(path), Some(root)) = (path, &assets_root) {
+                if path.starts_with(root) && path.is_file() {
+                    references.push((s.clone(), path));
+                }
+            }
+        });
+    }
+    let mut assets = Vec::new();
+    let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut seen = HashMap::new();
+    for (reference, path) in references {
+        if seen.contains_key(&reference) {
+            continue;
+        }
+        let bytes = fs::read(&path)?;
+        let hash = sha256_hex(&bytes);
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(|e| format!(".{}", e.to_ascii_lowercase()))
+            .unwrap_or_default();
+        let entry = format!("assets/{}{}", hash, extension);
+        assets.push(ArchivedAsset {
+            reference: reference.clone(),
+            path: entry.clone(),
+            size: bytes.len() as u64,
+        });
+        seen.insert(reference, ());
+        files.entry(entry).or_insert(bytes);
+    }
+
+    files.insert(
+        SNAPSHOT_ENTRY.to_string(),
+        serde_json::to_vec_pretty(&snapshot)?,
+    );
+    let manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        project_id: project_id.to_string(),
+        snapshot_version: snapshot.version,
+        exported_at_ms,
+        row_counts: row_counts(&snapshot),
+        checksums: files
+            .iter()
+            .map(|(name, bytes)| (name.clone(), sha256_hex(bytes)))
+            .collect(),
+        assets,
+    };
+
+    // Write beside the destination and move into place when complete
+    let tmp = dest.with_extension("partial");
+    {
+        let mut zip = zip::ZipWriter::new(File::create(&tmp)?);
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(MANIFEST_ENTRY, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        for (name, bytes) in &files {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(bytes)?;
+        }
+        zip.finish()?.sync_all()?;
+    }
+    fs::rename(&tmp, dest)?;
+
+    Ok(ArchiveSummary {
+        project_id: project_id.to_string(),
+        snapshot_version: manifest.snapshot_version,
+        rows: manifest.row_counts.values().sum(),
+        assets: manifest.assets.len(),
+    })
+}
+
+fn read_entry(zip: &mut zip::ZipArchive<File>, name: &str) -> Result<Vec<u8>, ArchiveError> {
+    let mut file = match zip.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(ArchiveError::MissingEntry(name.to_string()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A verified archive, ready to import
+pub struct ProjectArchive {
+    pub manifest: ArchiveManifest,
+    pub snapshot: ProjectSnapshot,
+    /// Asset contents keyed by entry name
+    pub files: HashMap<String, Vec<u8>>,
+}
+
+/// Open an archive, checking its format, version and every checksum
+pub fn read_archive(src: &Path) -> Result<ProjectArchive, ArchiveError> {
+    let mut zip = zip::ZipArchive::new(File::open(src)?).map_err(|e| match e {
+        zip::result::ZipError::InvalidArchive(_) => ArchiveError::NotAnArchive,
+        e => e.into(),
+    })?;
+    let manifest: ArchiveManifest = match read_entry(&mut zip, MANIFEST_ENTRY) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|_| ArchiveError::NotAnArchive)?,
+        Err(ArchiveError::MissingEntry(_)) => return Err(ArchiveError::NotAnArchive),
+        Err(e) => return Err(e),
+    };
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err(ArchiveError::NotAnArchive);
+    }
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(manifest.version));
+    }
+    if !manifest.checksums.contains_key(SNAPSHOT_ENTRY) {
+        return Err(ArchiveError::MissingEntry(SNAPSHOT_ENTRY.to_string()));
+    }
+
+    let mut files = HashMap::new();
+    for (name, expected) in &manifest.checksums {
+        let bytes = read_entry(&mut zip, name)?;
+        if &sha256_hex(&bytes) != expected {
+            return Err(ArchiveError::ChecksumMismatch(name.clone()));
+        }
+        files.insert(name.clone(), bytes);
+    }
+    for asset in &manifest.assets {
+        if !files.contains_key(&asset.path) {
+            return Err(ArchiveError::MissingEntry(asset.path.clone()));
+        }
+    }
+    let snapshot = serde_json::from_slice(&files.remove(SNAPSHOT_ENTRY).unwrap_or_default())?;
+    Ok(ProjectArchive {
+        manifest,
+        snapshot,
+        files,
+    })
+}
+
+/// Import an archive into the store, as `project_id` if given, copying its
+/// assets under `assets_root/<project id>/`. An existing project is only
+/// replaced with `overwrite`.
+pub fn import_archive(
+    store: &ProjectStore,
+    src: &Path,
+    assets_root: &Path,
+    project_id: Option<&str>,
+    overwrite: bool,
+) -> Result<ArchiveSummary, ArchiveError> {
+    let ProjectArchive {
+        manifest,
+        mut snapshot,
+        files,
+    } = read_archive(src)?;
+    if let Some(project_id) = project_id {
+        snapshot.project_id = project_id.to_string();
+    }
+    if !is_safe_project_id(&snapshot.project_id) {
+        return Err(ArchiveError::InvalidProjectId(snapshot.project_id));
+    }
+    if !overwrite && store.project_ids()?.contains(&snapshot.project_id) {
+        return Err(ArchiveError::ProjectExists(snapshot.project_id));
+    }
+    // Row ids are global, so writing a copy would move another project's rows
+    if let Some((id, project)) = foreign_row(store, &snapshot)? {
+        return Err(ArchiveError::RowInUse { id, project });
+    }
+
+    let assets_dir = assets_root.join(&snapshot.project_id);
+    let mut rewrites = HashMap::new();
+    for asset in &manifest.assets {
+        // Entry names are only used for their file name, never as paths
+        let Some(file_name) = Path::new(&asset.path).file_name() else {
+            return Err(ArchiveError::MissingEntry(asset.path.clone()));
+        };
+        fs::create_dir_all(&assets_dir)?;
+        let target = assets_dir.join(file_name);
+        fs::write(&target, &files[&asset.path])?;
+        rewrites.insert(
+            asset.reference.clone(),
+            rewrite_reference(&asset.reference, &target),
+        );
+    }
+    if !rewrites.is_empty() {
+        for row in snapshot_rows_mut(&mut snapshot) {
+            visit_asset_refs(row, &mut |s| {
+                if let Some(new) = rewrites.get(s.as_str()) {
+                    *s = new.clone();
+                }
+            });
+        }
+    }
+
+    store.bootstrap_project(&snapshot)?;
+    Ok(ArchiveSummary {
+        rows: row_counts(&snapshot).values().sum(),
+        project_id: snapshot.project_id,
+        snapshot_version: snapshot.version,
+        assets: manifest.assets.len(),
+    })
+}
+
+/// Directory imported assets are copied into
+pub fn assets_root(app: &AppHandle) -> Result<PathBuf, ArchiveError> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(std::io::Error::other)?
+        .join("assets"))
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_project_archive(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+) -> Result<ArchiveSummary, ArchiveError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<ProjectStore>();
+        export_archive(
+            &store,
+            &project_id,
+            Path::new(&path),
+            &assets_root(&app)?,
+            crate::sync::outbox::now_ms(),
+        )
+    })
+    .await
+    .map_err(|e| ArchiveError::Io(std::io::Error::other(e)))?
+}
+
+/// Imports into `projectId` if given, otherwise the archived project's id;
+/// replaces an existing project only with `overwrite`
+#[tauri::command(rename_all = "camelCase")]
+pub async fn import_project_archive(
+    app: AppHandle,
+    path: String,
+    project_id: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<ArchiveSummary, ArchiveError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<ProjectStore>();
+        import_archive(
+            &store,
+            Path::new(&path),
+            &assets_root(&app)?,
+            project_id.as_deref(),
+            overwrite.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| ArchiveError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store_with_asset(dir: &Path) -> (ProjectStore, String) {
+        let image = dir.join("cover art.png");
+        fs::write(&image, b"\x89PNG fake").unwrap();
+        let reference = rewrite_reference(ASSET_PREFIXES[0], &image);
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .bootstrap_project(&ProjectSnapshot {
+                project_id: "p1".into(),
+                version: 4,
+                documents: vec![json!({
+                    "id": "d1",
+                    "title": "One",
+                    "content": {"type": "doc", "content": [{"type": "image", "attrs": {"src": reference}}]}
+                })],
+                entities: vec![json!({"id": "e1", "name": "Ada"})],
+                captures: vec![json!({"id": "c1", "mediaUrl": "https://example.com/remote.png"})],
+                synced_at: "2026-01-01T00:00:00Z".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        (store, reference)
+    }
+
+    #[test]
+    fn round_trips_rows_and_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, reference) = store_with_asset(dir.path());
+        let archive = dir.path().join("project.rhei.zip");
+
+        let exported = export_archive(&store, "p1", &archive, dir.path(), 0).unwrap();
+        assert_eq!((exported.rows, exported.assets), (3, 1));
+
+        let target = ProjectStore::open_in_memory().unwrap();
+        let assets_root = dir.path().join("imported");
+        let imported = import_archive(&target, &archive, &assets_root, Some("p2"), false).unwrap();
+        assert_eq!(imported.project_id, "p2");
+        assert_eq!(imported.snapshot_version, 4);
+
+        let doc = target.get(SyncTable::Documents, "d1").unwrap().unwrap();
+        assert_eq!(doc["projectId"], "p2");
+        let src = doc["content"]["content"][0]["attrs"]["src"]
+            .as_str()
+            .unwrap();
+        assert_ne!(src, reference);
+        let copied = local_asset_path(src).unwrap();
+        assert!(copied.starts_with(assets_root.join("p2")));
+        assert_eq!(fs::read(copied).unwrap(), b"\x89PNG fake");
+
+        // Remote URLs are untouched
+        let capture = target.get(SyncTable::Captures, "c1").unwrap().unwrap();
+        assert_eq!(capture["mediaUrl"], "https://example.com/remote.png");
+        assert_eq!(target.last_sync_version("p2").unwrap(), 4);
+    }
+
+    #[test]
+    fn exports_only_asset_fields_inside_the_asset_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("assets");
+        fs::create_dir_all(&assets).unwrap();
+        let portrait = assets.join("ada.png");
+        fs::write(&portrait, b"portrait").unwrap();
+        let secret = dir.path().join("id_rsa");
+        fs::write(&secret, b"secret").unwrap();
+        let file_url = |path: &Path| url::Url::from_file_path(path).unwrap().to_string();
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .bootstrap_project(&ProjectSnapshot {
+                project_id: "p1".into(),
+                documents: vec![json!({
+                    "id": "d1",
+                    "content": {"type": "doc", "content": [
+                        {"type": "image", "attrs": {"src": file_url(&secret)}},
+                        {"type": "paragraph", "content": [{"type": "text", "text": file_url(&portrait)}]},
+                    ]}
+                })],
+                entities: vec![json!({
+                    "id": "e1",
+                    "portraitUrl": file_url(&portrait),
+                    "notes": file_url(&secret),
+                })],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let archive = dir.path().join("project.zip");
+        let summary = export_archive(&store, "p1", &archive, &assets, 0).unwrap();
+        assert_eq!(summary.assets, 1);
+        let read = read_archive(&archive).unwrap();
+        assert_eq!(read.manifest.assets[0].reference, file_url(&portrait));
+        assert!(read.files.values().all(|bytes| bytes != b"secret"));
+    }
+
+    #[test]
+    fn refuses_hostile_ids_and_existing_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, _) = store_with_asset(dir.path());
+        let archive = dir.path().join("project.zip");
+        export_archive(&store, "p1", &archive, dir.path(), 0).unwrap();
+
+        let target = ProjectStore::open_in_memory().unwrap();
+        let assets_root = dir.path().join("imported");
+        for hostile in ["../escape", "..", "a/b", "", "/tmp/x"] {
+            assert!(matches!(
+                import_archive(&target, &archive, &assets_root, Some(hostile), false),
+                Err(ArchiveError::InvalidProjectId(id)) if id == hostile
+            ));
+        }
+        assert!(!dir.path().join("escape").exists());
+
+        assert!(matches!(
+            import_archive(&store, &archive, &assets_root, None, false),
+            Err(ArchiveError::ProjectExists(id)) if id == "p1"
+        ));
+        import_archive(&store, &archive, &assets_root, None, true).unwrap();
+        assert_eq!(store.last_sync_version("p1").unwrap(), 4);
+    }
+
+    #[test]
+    fn refuses_a_copy_that_would_take_over_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, _) = store_with_asset(dir.path());
+        let archive = dir.path().join("project.zip");
+        export_archive(&store, "p1", &archive, dir.path(), 0).unwrap();
+
+        let assets_root = dir.path().join("imported");
+        assert!(matches!(
+            import_archive(&store, &archive, &assets_root, Some("p2"), false),
+            Err(ArchiveError::RowInUse { id, project }) if id == "d1" && project == "p1"
+        ));
+        assert_eq!(store.list(SyncTable::Documents, "p1").unwrap().len(), 1);
+        assert_eq!(store.list(SyncTable::Entities, "p1").unwrap().len(), 1);
+        assert!(store.list(SyncTable::Documents, "p2").unwrap().is_empty());
+        assert!(!assets_root.join("p2").exists());
+
+        // A store without the original takes the copy
+        let other = ProjectStore::open_in_memory().unwrap();
+        import_archive(&other, &archive, &assets_root, Some("p2"), false).unwrap();
+        assert_eq!(other.list(SyncTable::Documents, "p2").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_tampered_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, _) = store_with_asset(dir.path());
+        let archive = dir.path().join("project.zip");
+        export_archive(&store, "p1", &archive, dir.path(), 0).unwrap();
+
+        // Rebuild the zip with an edited snapshot but the original manifest
+        let mut original = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        let tampered = dir.path().join("tampered.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&tampered).unwrap());
+        for i in 0..original.len() {
+            let mut entry = original.by_index(i).unwrap();
+            let name = entry.name().to_string();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).unwrap();
+            if name == SNAPSHOT_ENTRY {
+                bytes = String::from_utf8(bytes)
+                    .unwrap()
+                    .replace("Ada", "Eve")
+                    .into_bytes();
+            }
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&bytes).unwrap();
+        }
+        zip.finish().unwrap();
+
+        assert!(matches!(
+            read_archive(&tampered),
+            Err(ArchiveError::ChecksumMismatch(name)) if name == SNAPSHOT_ENTRY
+        ));
+    }
+
+    #[test]
+    fn rejects_newer_versions_and_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("future.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(MANIFEST_ENTRY, SimpleFileOptions::default())
+            .unwrap();
+        let manifest = json!({
+            "format": ARCHIVE_FORMAT, "version": ARCHIVE_VERSION + 1, "projectId": "p1",
+            "snapshotVersion": 0, "exportedAtMs": 0, "rowCounts": {}, "checksums": {}
+        });
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.finish().unwrap();
+        assert!(matches!(
+            read_archive(&path),
+            Err(ArchiveError::UnsupportedVersion(v)) if v == ARCHIVE_VERSION + 1
+        ));
+
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, "hello").unwrap();
+        assert!(matches!(
+            read_archive(&text),
+            Err(ArchiveError::NotAnArchive)
+        ));
+    }
+}
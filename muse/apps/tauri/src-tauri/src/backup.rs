@@ -0,0 +1,676 @@
+//! Automatic local backups
+//!
+//! A background thread copies the project store into `backups/` every few
+//! minutes, and `run()` takes one more copy when the app exits. After each
+//! backup, rotation keeps the newest scheduled or quit copy from each of the
+//! last few hours, days and weeks and deletes the rest; manual and
+//! `pre_restore` backups are only deleted by hand.
+//!
+//! Backups are plain SQLite files named `rhei-<unix ms>-<reason>.db`, with
+//! the settings beside them in `settings.json`. Restoring one copies it over
+//! the live store after a `pre_restore` backup of the current state, so a
+//! restore can itself be undone, and rebuilds the search index.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{migrations, ProjectStore, StoreError};
+use crate::history::History;
+use crate::search::embedding_jobs::EmbeddingWorker;
+use crate::search::fulltext;
+use crate::sync::conflicts::ConflictPolicy;
+use crate::sync::outbox::{now_ms, OutboxWorker};
+
+/// Directory under app data holding the backups
+pub const BACKUP_DIR: &str = "backups";
+
+const FILE_PREFIX: &str = "rhei-";
+const FILE_EXTENSION: &str = ".db";
+const SETTINGS_FILE: &str = "settings.json";
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+const WEEK_MS: i64 = 7 * DAY_MS;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("backup `{0}` not found")]
+    NotFound(String),
+    #[error("backup `{0}` was made by a newer version of the app")]
+    Incompatible(String),
+    #[error("backup I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Store(e.into())
+    }
+}
+
+impl BackupError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "backup_not_found",
+            Self::Incompatible(_) => "backup_incompatible",
+            Self::Io(_) => "backup_io",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(BackupError);
+
+/// Why a backup was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupReason {
+    Scheduled,
+    Quit,
+    Manual,
+    PreRestore,
+}
+
+impl BackupReason {
+    const ALL: [BackupReason; 4] = [
+        BackupReason::Scheduled,
+        BackupReason::Quit,
+        BackupReason::Manual,
+        BackupReason::PreRestore,
+    ];
+
+    /// Whether rotation may delete backups taken for this reason
+    pub fn rotates(self) -> bool {
+        matches!(self, Self::Scheduled | Self::Quit)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::Quit => "quit",
+            Self::Manual => "manual",
+            Self::PreRestore => "pre_restore",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// File name inside the backup directory
+    pub id: String,
+    pub created_at_ms: i64,
+    pub reason: BackupReason,
+    pub size: u64,
+}
+
+impl BackupInfo {
+    /// Parse a backup file name; `None` for anything else in the directory
+    fn parse(id: &str, size: u64) -> Option<Self> {
+        let stem = id.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_EXTENSION)?;
+        let (created_at, reason) = stem.split_once('-')?;
+        let reason = BackupReason::ALL
+            .into_iter()
+            .find(|r| r.as_str() == reason)?;
+        Some(Self {
+            id: id.to_string(),
+            created_at_ms: created_at.parse().ok()?,
+            reason,
+            size,
+        })
+    }
+}
+
+/// How many backups rotation keeps: the newest one in each of the last
+/// `hourly` hours, `daily` days and `weekly` weeks that have any
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Retention {
+    pub hourly: usize,
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            hourly: 24,
+            daily: 7,
+            weekly: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSettings {
+    /// Minutes between scheduled backups; 0 disables them
+    pub interval_minutes: u64,
+    pub retention: Retention,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            interval_minutes: 15,
+            retention: Retention::default(),
+        }
+    }
+}
+
+/// Backups in `dir`, newest first
+pub fn list_backups_in(dir: &Path) -> Result<Vec<BackupInfo>, BackupError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if let Some(info) = BackupInfo::parse(&name, entry.metadata()?.len()) {
+            backups.push(info);
+        }
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at_ms));
+    Ok(backups)
+}
+
+/// Copy the store into `dir`
+pub fn create_backup(
+    store: &ProjectStore,
+    dir: &Path,
+    reason: BackupReason,
+    now: i64,
+) -> Result<BackupInfo, BackupError> {
+    fs::create_dir_all(dir)?;
+    let id = format!(
+        "{}{}-{}{}",
+        FILE_PREFIX,
+        now,
+        reason.as_str(),
+        FILE_EXTENSION
+    );
+    let path = dir.join(&id);
+
+    // Copied under a name listing skips, then moved into place
+    let tmp = path.with_extension("partial");
+    store.with_conn(|conn| Ok(conn.backup(DatabaseName::Main, &tmp, None)?))?;
+    fs::rename(&tmp, &path)?;
+
+    Ok(BackupInfo {
+        size: fs::metadata(&path)?.len(),
+        id,
+        created_at_ms: now,
+        reason,
+    })
+}
+
+/// Backups rotation would delete from `backups` (newest first)
+pub fn expired_backups<'a>(
+    backups: &'a [BackupInfo],
+    retention: &Retention,
+) -> Vec<&'a BackupInfo> {
+    let rotating: Vec<&BackupInfo> = backups.iter().filter(|b| b.reason.rotates()).collect();
+    let mut keep: HashSet<&str> = rotating
+        .first()
+        .map(|b| b.id.as_str())
+        .into_iter()
+        .collect();
+    for (period, count) in [
+        (HOUR_MS, retention.hourly),
+        (DAY_MS, retention.daily),
+        (WEEK_MS, retention.weekly),
+    ] {
+        let mut buckets = HashSet::new();
+        for backup in &rotating {
+            if buckets.len() >= count {
+                break;
+            }
+            // Newest first, so the first backup seen in a bucket is its newest
+            if buckets.insert(backup.created_at_ms.div_euclid(period)) {
+                keep.insert(&backup.id);
+            }
+        }
+    }
+    rotating
+        .into_iter()
+        .filter(|b| !keep.contains(b.id.as_str()))
+        .collect()
+}
+
+/// Apply `retention` to `dir`; returns how many backups were deleted
+pub fn rotate_backups(dir: &Path, retention: &Retention) -> Result<usize, BackupError> {
+    let backups = list_backups_in(dir)?;
+    let expired = expired_backups(&backups, retention);
+    for backup in &expired {
+        fs::remove_file(dir.join(&backup.id))?;
+    }
+    Ok(expired.len())
+}
+
+/// Replace the store's contents with backup `id` from `dir`, first backing up
+/// the current state; returns that `pre_restore` backup
+pub fn restore_backup_from(
+    store: &ProjectStore,
+    dir: &Path,
+    id: &str,
+    now: i64,
+) -> Result<BackupInfo, BackupError> {
+    // Only names we listed, so `id` can't point outside the directory
+    let backup = list_backups_in(dir)?
+        .into_iter()
+        .find(|b| b.id == id)
+        .ok_or_else(|| BackupError::NotFound(id.to_string()))?;
+    let path = dir.join(&backup.id);
+
+    let source = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if migrations::schema_version(&source)? > migrations::MIGRATIONS.len() {
+        return Err(BackupError::Incompatible(backup.id));
+    }
+    drop(source);
+
+    let undo = create_backup(store, dir, BackupReason::PreRestore, now)?;
+    store.with_conn(|conn| {
+        conn.restore(DatabaseName::Main, &path, None::<fn(Progress)>)?;
+        // Older backups come back at their own schema version
+        migrations::migrate(conn)?;
+        let tx = conn.transaction()?;
+        fulltext::rebuild(&tx, None)?;
+        tx.commit()?;
+        Ok(())
+    })?;
+    Ok(undo)
+}
+
+struct SchedulerState {
+    settings: BackupSettings,
+    woken: bool,
+}
+
+/// Managed handle to the backup directory and scheduler
+pub struct Backups {
+    dir: PathBuf,
+    state: Mutex<SchedulerState>,
+    wake: Condvar,
+    /// Serializes backup + rotation between the scheduler, quit and commands
+    running: Mutex<()>,
+}
+
+/// Settings saved in `dir`, the defaults if there are none (or they're unreadable)
+pub fn load_settings(dir: &Path) -> BackupSettings {
+    fs::read(dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(dir: &Path, settings: &BackupSettings) -> Result<(), BackupError> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(SETTINGS_FILE);
+    let tmp = path.with_extension("partial");
+    fs::write(
+        &tmp,
+        serde_json::to_vec_pretty(settings).map_err(std::io::Error::other)?,
+    )?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+impl Backups {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                settings: load_settings(&dir),
+                woken: false,
+            }),
+            dir,
+            wake: Condvar::new(),
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn settings(&self) -> BackupSettings {
+        self.state.lock().unwrap().settings
+    }
+
+    /// Change and save the settings; the schedule restarts from now
+    pub fn configure(&self, settings: BackupSettings) -> Result<(), BackupError> {
+        let mut state = self.state.lock().unwrap();
+        save_settings(&self.dir, &settings)?;
+        state.settings = settings;
+        state.woken = true;
+        self.wake.notify_all();
+        Ok(())
+    }
+
+    /// Back up now and rotate
+    pub fn backup(
+        &self,
+        store: &ProjectStore,
+        reason: BackupReason,
+    ) -> Result<BackupInfo, BackupError> {
+        let _running = self.running.lock().unwrap();
+        let info = create_backup(store, &self.dir, reason, now_ms())?;
+        rotate_backups(&self.dir, &self.settings().retention)?;
+        Ok(info)
+    }
+
+    pub fn restore(&self, store: &ProjectStore, id: &str) -> Result<BackupInfo, BackupError> {
+        let _running = self.running.lock().unwrap();
+        restore_backup_from(store, &self.dir, id, now_ms())
+    }
+
+    /// Sleep for one interval; `false` if woken early by `configure`
+    fn wait_interval(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.woken = false;
+        let interval = state.settings.interval_minutes;
+        if interval == 0 {
+            let _state = self.wake.wait_while(state, |s| !s.woken).unwrap();
+            return false;
+        }
+        let timeout = Duration::from_secs(interval * 60);
+        let (_state, result) = self
+            .wake
+            .wait_timeout_while(state, timeout, |s| !s.woken)
+            .unwrap();
+        result.timed_out()
+    }
+}
+
+/// Take scheduled backups on a dedicated thread
+pub fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let backups = app.state::<Backups>();
+        if !backups.wait_interval() {
+            continue;
+        }
+        let store = app.state::<ProjectStore>();
+        if let Err(e) = backups.backup(&store, BackupReason::Scheduled) {
+            eprintln!("[backup] Scheduled backup failed: {}", e);
+        }
+    });
+}
+
+/// Final backup as the app exits
+pub fn backup_on_exit(app: &AppHandle) {
+    let (Some(backups), Some(store)) =
+        (app.try_state::<Backups>(), app.try_state::<ProjectStore>())
+    else {
+        return;
+    };
+    match backups.backup(&store, BackupReason::Quit) {
+        Ok(info) => println!("[backup] Saved {}", info.id),
+        Err(e) => eprintln!("[backup] Backup on quit failed: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn list_backups(backups: State<'_, Backups>) -> Result<Vec<BackupInfo>, BackupError> {
+    list_backups_in(backups.dir())
+}
+
+#[tauri::command]
+pub async fn create_backup_now(app: AppHandle) -> Result<BackupInfo, BackupError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<Backups>()
+            .backup(&app.state::<ProjectStore>(), BackupReason::Manual)
+    })
+    .await
+    .map_err(|e| BackupError::Io(std::io::Error::other(e)))?
+}
+
+/// Returns the `pre_restore` backup of the state that was replaced
+#[tauri::command]
+pub async fn restore_backup(app: AppHandle, id: String) -> Result<BackupInfo, BackupError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<ProjectStore>();
+        let undo = app.state::<Backups>().restore(&store, &id)?;
+        // Settings kept in the store came back with it
+        app.state::<ConflictPolicy>().reload(&store)?;
+        app.state::<EmbeddingWorker>().reload(&store)?;
+        app.state::<History>().reload(&store)?;
+        // The restored outbox may hold different pending mutations
+        app.state::<OutboxWorker>().notify();
+        Ok(undo)
+    })
+    .await
+    .map_err(|e| BackupError::Io(std::io::Error::other(e)))?
+}
+
+#[tauri::command]
+pub fn get_backup_settings(backups: State<'_, Backups>) -> BackupSettings {
+    backups.settings()
+}
+
+#[tauri::command]
+pub fn configure_backups(
+    backups: State<'_, Backups>,
+    settings: BackupSettings,
+) -> Result<(), BackupError> {
+    backups.configure(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SyncTable;
+    use crate::search::embeddings::ProviderConfig;
+    use crate::sync::conflicts::ConflictStrategy;
+    use serde_json::json;
+
+    fn info(created_at_ms: i64) -> BackupInfo {
+        BackupInfo {
+            id: format!("rhei-{}-scheduled.db", created_at_ms),
+            created_at_ms,
+            reason: BackupReason::Scheduled,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn backup_and_restore_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open(&dir.path().join("rhei.db")).unwrap();
+        let backup_dir = dir.path().join(BACKUP_DIR);
+        let row = json!({"id": "d1", "projectId": "p1", "title": "Draft"});
+        store.upsert(SyncTable::Documents, &row).unwrap();
+
+        let saved = create_backup(&store, &backup_dir, BackupReason::Manual, 1_000).unwrap();
+        store.delete(SyncTable::Documents, "d1").unwrap();
+        // A backup whose index is out of step with its rows
+        Connection::open(backup_dir.join(&saved.id))
+            .unwrap()
+            .execute("DELETE FROM documents_fts", [])
+            .unwrap();
+
+        let undo = restore_backup_from(&store, &backup_dir, &saved.id, 2_000).unwrap();
+        assert_eq!(undo.reason, BackupReason::PreRestore);
+        assert_eq!(store.get(SyncTable::Documents, "d1").unwrap(), Some(row));
+
+        let query = fulltext::SearchQuery {
+            project_id: "p1".into(),
+            text: "Draft".into(),
+            kinds: None,
+            fuzzy: false,
+            limit: 10,
+        };
+        let hits = store
+            .with_conn(|conn| fulltext::search(conn, &query))
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        // The pre-restore backup undoes the restore
+        restore_backup_from(&store, &backup_dir, &undo.id, 3_000).unwrap();
+        assert_eq!(store.get(SyncTable::Documents, "d1").unwrap(), None);
+
+        let listed = list_backups_in(&backup_dir).unwrap();
+        let times: Vec<i64> = listed.iter().map(|b| b.created_at_ms).collect();
+        assert_eq!(times, [3_000, 2_000, 1_000]);
+    }
+
+    #[test]
+    fn reload_picks_up_restored_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open(&dir.path().join("rhei.db")).unwrap();
+        let backup_dir = dir.path().join(BACKUP_DIR);
+        let policy = ConflictPolicy::load(&store).unwrap();
+        let worker = EmbeddingWorker::load(&store).unwrap();
+        let config = ProviderConfig::Hashing { dimensions: 32 };
+        worker.configure(&store, Some(config.clone())).unwrap();
+
+        let saved = create_backup(&store, &backup_dir, BackupReason::Manual, 1_000).unwrap();
+        policy
+            .set(
+                &store,
+                SyncTable::Documents,
+                ConflictStrategy::LastWriterWins,
+            )
+            .unwrap();
+        worker.configure(&store, None).unwrap();
+
+        restore_backup_from(&store, &backup_dir, &saved.id, 2_000).unwrap();
+        policy.reload(&store).unwrap();
+        worker.reload(&store).unwrap();
+        assert_eq!(
+            policy.strategy(SyncTable::Documents),
+            ConflictPolicy::default_strategy(SyncTable::Documents)
+        );
+        assert_eq!(worker.config(), Some(config));
+        assert!(worker.provider().is_some());
+    }
+
+    #[test]
+    fn rotation_keeps_newest_per_period() {
+        // Midday, three days into a week
+        let now = 100 * WEEK_MS + 3 * DAY_MS + 12 * HOUR_MS;
+        // Every 15 minutes for the last 3 hours, then daily for 10 days
+        let mut backups: Vec<BackupInfo> = (0..12).map(|i| info(now - i * 15 * 60_000)).collect();
+        backups.extend((1..=10).map(|d| info(now - d * DAY_MS)));
+        let retention = Retention {
+            hourly: 2,
+            daily: 3,
+            weekly: 2,
+        };
+
+        let expired: HashSet<i64> = expired_backups(&backups, &retention)
+            .iter()
+            .map(|b| b.created_at_ms)
+            .collect();
+        let kept: Vec<i64> = backups
+            .iter()
+            .map(|b| b.created_at_ms)
+            .filter(|t| !expired.contains(t))
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                now,
+                now - 15 * 60_000,
+                now - DAY_MS,
+                now - 2 * DAY_MS,
+                now - 4 * DAY_MS,
+            ]
+        );
+    }
+
+    #[test]
+    fn rotation_spares_manual_and_pre_restore_backups() {
+        let backups = [
+            info(3 * DAY_MS),
+            BackupInfo {
+                id: format!("rhei-{}-manual.db", 2 * DAY_MS),
+                reason: BackupReason::Manual,
+                ..info(2 * DAY_MS)
+            },
+            BackupInfo {
+                id: format!("rhei-{}-pre_restore.db", DAY_MS),
+                reason: BackupReason::PreRestore,
+                ..info(DAY_MS)
+            },
+            info(0),
+        ];
+        let retention = Retention {
+            hourly: 1,
+            daily: 1,
+            weekly: 0,
+        };
+        let expired: Vec<i64> = expired_backups(&backups, &retention)
+            .iter()
+            .map(|b| b.created_at_ms)
+            .collect();
+        assert_eq!(expired, [0]);
+    }
+
+    #[test]
+    fn settings_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Backups::new(dir.path().to_path_buf()).settings(),
+            BackupSettings::default()
+        );
+        let settings = BackupSettings {
+            interval_minutes: 60,
+            retention: Retention {
+                hourly: 2,
+                daily: 3,
+                weekly: 1,
+            },
+        };
+        Backups::new(dir.path().to_path_buf())
+            .configure(settings)
+            .unwrap();
+        assert_eq!(Backups::new(dir.path().to_path_buf()).settings(), settings);
+        // The settings file isn't mistaken for a backup
+        assert!(list_backups_in(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rotation_deletes_expired_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        for t in [0, DAY_MS, 2 * DAY_MS] {
+            fs::write(dir.path().join(info(t).id), b"").unwrap();
+        }
+        fs::write(dir.path().join("notes.txt"), b"keep me").unwrap();
+
+        let retention = Retention {
+            hourly: 1,
+            daily: 1,
+            weekly: 1,
+        };
+        assert_eq!(rotate_backups(dir.path(), &retention).unwrap(), 2);
+        assert_eq!(list_backups_in(dir.path()).unwrap(), [info(2 * DAY_MS)]);
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn restore_only_accepts_listed_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open(&dir.path().join("rhei.db")).unwrap();
+        let backup_dir = dir.path().join(BACKUP_DIR);
+        create_backup(&store, &backup_dir, BackupReason::Manual, 1_000).unwrap();
+
+        assert!(matches!(
+            restore_backup_from(&store, &backup_dir, "../rhei.db", 2_000),
+            Err(BackupError::NotFound(_))
+        ));
+    }
+}
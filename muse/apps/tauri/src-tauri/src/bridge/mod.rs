@@ -0,0 +1,581 @@
+//! Editor bridge hub
+//!
+//! Validates traffic between the editor WebView and the shell. Every message
+//! must arrive in a `BridgeEnvelope` carrying the nonce the host registered
+//! for that webview and the protocol version this shell speaks. Anything else
+//! is rejected with a structured `BridgeError` instead of being forwarded.
+//!
+//! The reverse direction (`send_to_editor`) wraps `NativeToEditorMessage`s in
+//! the same envelope and delivers them to a specific webview, so native code
+//! (menus, deep links, background jobs) can drive the editor directly.
+//!
+//! `editorReady` doubles as the version handshake (see `negotiation`). Both
+//! directions pass through here, which is where `recorder` taps the traffic.
+//!
+//! When the host names the document a webview is editing, content and title
+//! changes also update the local search index as they arrive.
+
+pub mod negotiation;
+pub mod protocol;
+pub mod recorder;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, Webview};
+
+use negotiation::{Capability, NegotiatedProtocol};
+use protocol::{
+    BridgeEnvelope, EditorToNativeMessage, NativeToEditorMessage, BASELINE_VERSION,
+    ENVELOPE_TO_EDITOR, ENVELOPE_TO_NATIVE,
+};
+use recorder::{BridgeRecorder, Direction};
+
+use crate::db::ProjectStore;
+use crate::search::fulltext;
+
+/// Event carrying validated editor → native messages
+pub const EDITOR_MESSAGE_EVENT: &str = "editor-message";
+
+/// Event carrying rejected bridge traffic
+pub const BRIDGE_ERROR_EVENT: &str = "editor-bridge-error";
+
+/// Event carrying the outcome of the `editorReady` version handshake
+pub const BRIDGE_NEGOTIATED_EVENT: &str = "editor-bridge-negotiated";
+
+/// Event carrying native → editor envelopes to the webview hosting the editor
+pub const EDITOR_COMMAND_EVENT: &str = "editor-command";
+
+/// Largest message accepted in either direction; a `contentChange` carries
+/// the document three times over (text, HTML and JSON)
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BridgeError {
+    #[error("malformed bridge message: {0}")]
+    Malformed(String),
+    #[error("bridge message of {0} bytes exceeds the {MAX_MESSAGE_BYTES} byte limit")]
+    TooLarge(usize),
+    #[error("unknown bridge message type `{0}`")]
+    UnknownMessageType(String),
+    #[error("unexpected envelope type `{0}`")]
+    InvalidEnvelope(String),
+    #[error("bridge is not configured for webview `{0}`")]
+    NotConfigured(String),
+    #[error("no editor webview labelled `{0}`")]
+    WebviewNotFound(String),
+    #[error("bridge nonce mismatch")]
+    NonceMismatch,
+    #[error("bridge version mismatch (editor {actual}, native {expected})")]
+    VersionMismatch { expected: String, actual: String },
+    #[error("editor does not support {0:?}")]
+    Unsupported(Capability),
+    #[error("failed to emit bridge event: {0}")]
+    Emit(String),
+}
+
+impl BridgeError {
+    /// Stable machine-readable code, mirrors the editor's `error` message codes
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Malformed(_) => "bridge_malformed_message",
+            Self::TooLarge(_) => "bridge_message_too_large",
+            Self::UnknownMessageType(_) => "bridge_unknown_message_type",
+            Self::InvalidEnvelope(_) => "bridge_invalid_envelope",
+            Self::NotConfigured(_) => "bridge_not_configured",
+            Self::WebviewNotFound(_) => "bridge_webview_not_found",
+            Self::NonceMismatch => "bridge_nonce_mismatch",
+            Self::VersionMismatch { .. } => "bridge_version_mismatch",
+            Self::Unsupported(_) => "bridge_capability_unsupported",
+            Self::Emit(_) => "bridge_emit_failed",
+        }
+    }
+}
+
+crate::db::serialize_error!(BridgeError);
+
+/// Decode a raw editor → native envelope, checking nonce and version
+///
+/// Until the handshake completes any supported version is accepted; after it,
+/// envelopes must carry the negotiated editor version.
+pub fn decode_editor_message(
+    raw: &str,
+    session: &BridgeSession,
+) -> Result<EditorToNativeMessage, BridgeError> {
+    if raw.len() > MAX_MESSAGE_BYTES {
+        return Err(BridgeError::TooLarge(raw.len()));
+    }
+    let envelope: BridgeEnvelope<Value> =
+        serde_json::from_str(raw).map_err(|e| BridgeError::Malformed(e.to_string()))?;
+
+    if envelope.kind != ENVELOPE_TO_NATIVE {
+        return Err(BridgeError::InvalidEnvelope(envelope.kind));
+    }
+    if envelope.nonce != session.nonce {
+        return Err(BridgeError::NonceMismatch);
+    }
+    let version_ok = match &session.negotiated {
+        Some(negotiated) => envelope.version == negotiated.editor_version,
+        None => negotiation::is_supported(&envelope.version),
+    };
+    if !version_ok {
+        return Err(BridgeError::VersionMismatch {
+            expected: session.negotiated.as_ref().map_or_else(
+                || negotiation::SUPPORTED_EDITOR_VERSIONS.to_string(),
+                |n| n.editor_version.clone(),
+            ),
+            actual: envelope.version,
+        });
+    }
+
+    let message_type = envelope
+        .payload
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| BridgeError::Malformed("payload is missing `type`".into()))?;
+    if !EditorToNativeMessage::TYPES.contains(&message_type) {
+        return Err(BridgeError::UnknownMessageType(message_type.to_string()));
+    }
+
+    serde_json::from_value(envelope.payload).map_err(|e| BridgeError::Malformed(e.to_string()))
+}
+
+/// Wrap a native → editor message in an envelope for the given session
+pub fn encode_native_message(
+    message: NativeToEditorMessage,
+    session: &BridgeSession,
+) -> Result<BridgeEnvelope<NativeToEditorMessage>, BridgeError> {
+    if let (Some(negotiated), Some(capability)) =
+        (&session.negotiated, Capability::required_for(&message))
+    {
+        if !negotiated.supports(capability) {
+            return Err(BridgeError::Unsupported(capability));
+        }
+    }
+    let size = serde_json::to_vec(&message)
+        .map_err(|e| BridgeError::Malformed(e.to_string()))?
+        .len();
+    if size > MAX_MESSAGE_BYTES {
+        return Err(BridgeError::TooLarge(size));
+    }
+
+    Ok(BridgeEnvelope {
+        kind: ENVELOPE_TO_EDITOR.to_string(),
+        payload: message,
+        nonce: session.nonce.clone(),
+        version: session.expected_version().to_string(),
+    })
+}
+
+/// Bridge state for one editor webview
+#[derive(Debug, Clone)]
+pub struct BridgeSession {
+    pub nonce: String,
+    pub negotiated: Option<NegotiatedProtocol>,
+    /// Stored document the editor is showing, if the host said
+    pub document_id: Option<String>,
+}
+
+impl BridgeSession {
+    /// Version stamped on envelopes: the editor's once negotiated, the
+    /// baseline before
+    pub fn expected_version(&self) -> &str {
+        self.negotiated
+            .as_ref()
+            .map_or(BASELINE_VERSION, |n| n.editor_version.as_str())
+    }
+}
+
+/// Per-webview bridge sessions, keyed by webview label
+#[derive(Default)]
+pub struct BridgeHub {
+    sessions: Mutex<HashMap<String, BridgeSession>>,
+}
+
+impl BridgeHub {
+    /// Register the nonce for a webview (replaces any previous session)
+    pub fn configure(&self, label: &str, nonce: String) {
+        self.sessions.lock().unwrap().insert(
+            label.to_string(),
+            BridgeSession {
+                nonce,
+                negotiated: None,
+                document_id: None,
+            },
+        );
+    }
+
+    /// Record which document the webview's editor is showing
+    pub fn set_document(
+        &self,
+        label: &str,
+        document_id: Option<String>,
+    ) -> Result<(), BridgeError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(label)
+            .ok_or_else(|| BridgeError::NotConfigured(label.to_string()))?;
+        session.document_id = document_id;
+        Ok(())
+    }
+
+    /// Labels of the webviews whose editor shows `document_id`
+    pub fn labels_showing(&self, document_id: &str) -> Vec<String> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut labels: Vec<String> = sessions
+            .iter()
+            .filter(|(_, s)| s.document_id.as_deref() == Some(document_id))
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    pub fn session(&self, label: &str) -> Option<BridgeSession> {
+        self.sessions.lock().unwrap().get(label).cloned()
+    }
+
+    /// Validate a raw message received from the given webview
+    ///
+    /// `editorReady` runs the version handshake and stores the result.
+    pub fn receive(&self, label: &str, raw: &str) -> Result<EditorToNativeMessage, BridgeError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(label)
+            .ok_or_else(|| BridgeError::NotConfigured(label.to_string()))?;
+
+        let message = decode_editor_message(raw, session)?;
+        if let EditorToNativeMessage::EditorReady { version } = &message {
+            session.negotiated = Some(negotiation::negotiate(version)?);
+        }
+        Ok(message)
+    }
+}
+
+/// Send a message to the editor hosted by the webview with the given label
+pub fn send_to_editor(
+    app: &AppHandle,
+    label: &str,
+    message: NativeToEditorMessage,
+) -> Result<(), BridgeError> {
+    if app.get_webview_window(label).is_none() {
+        return Err(BridgeError::WebviewNotFound(label.to_string()));
+    }
+
+    let session = app
+        .state::<BridgeHub>()
+        .session(label)
+        .ok_or_else(|| BridgeError::NotConfigured(label.to_string()))?;
+
+    let envelope = encode_native_message(message, &session)?;
+    if let Ok(payload) = serde_json::to_value(&envelope.payload) {
+        app.state::<BridgeRecorder>()
+            .record(Direction::NativeToEditor, label, payload, None);
+    }
+    app.emit_to(
+        EventTarget::webview_window(label),
+        EDITOR_COMMAND_EVENT,
+        &envelope,
+    )
+    .map_err(|e| BridgeError::Emit(e.to_string()))
+}
+
+/// Registers the bridge nonce generated by the host for this webview
+#[tauri::command]
+pub fn configure_editor_bridge(webview: Webview, hub: State<'_, BridgeHub>, nonce: String) {
+    hub.configure(webview.label(), nonce);
+}
+
+/// Names the stored document this webview's editor is showing (`None` when
+/// it shows something else), so its edits reach the search index
+#[tauri::command(rename_all = "camelCase")]
+pub fn set_editor_document(
+    webview: Webview,
+    hub: State<'_, BridgeHub>,
+    document_id: Option<String>,
+) -> Result<(), BridgeError> {
+    hub.set_document(webview.label(), document_id)
+}
+
+/// Receives the raw envelopes the host gets from its editor iframe and
+/// returns the validated message for the host to act on (also emitted)
+#[tauri::command]
+pub fn editor_message(
+    app: AppHandle,
+    webview: Webview,
+    hub: State<'_, BridgeHub>,
+    message: String,
+) -> Result<EditorToNativeMessage, BridgeError> {
+    let result = hub.receive(webview.label(), &message);
+    app.state::<BridgeRecorder>().record_raw(
+        webview.label(),
+        &message,
+        result.as_ref().err().map(BridgeError::code),
+    );
+
+    match result {
+        Ok(message) => {
+            if let Some(document_id) = hub.session(webview.label()).and_then(|s| s.document_id) {
+                let indexed = app
+                    .state::<ProjectStore>()
+                    .with_conn(|conn| fulltext::apply_editor_message(conn, &document_id, &message));
+                if let Err(e) = indexed {
+                    eprintln!("[search] Failed to index {}: {}", document_id, e);
+                }
+            }
+            if matches!(message, EditorToNativeMessage::EditorReady { .. }) {
+                let negotiated = hub.session(webview.label()).and_then(|s| s.negotiated);
+                app.emit(BRIDGE_NEGOTIATED_EVENT, &negotiated)
+                    .map_err(|e| BridgeError::Emit(e.to_string()))?;
+            }
+            app.emit(EDITOR_MESSAGE_EVENT, &message)
+                .map_err(|e| BridgeError::Emit(e.to_string()))?;
+            Ok(message)
+        }
+        Err(error) => {
+            eprintln!(
+                "[bridge] Rejected message from {}: {}",
+                webview.label(),
+                error
+            );
+            let _ = app.emit(BRIDGE_ERROR_EVENT, &error);
+            Err(error)
+        }
+    }
+}
+
+/// Returns the negotiated protocol for the given webview, if the handshake ran
+#[tauri::command(rename_all = "camelCase")]
+pub fn editor_bridge_protocol(
+    hub: State<'_, BridgeHub>,
+    label: String,
+) -> Option<NegotiatedProtocol> {
+    hub.session(&label).and_then(|s| s.negotiated)
+}
+
+/// Sends a typed message to the editor in the given webview
+#[tauri::command(rename_all = "camelCase")]
+pub fn send_editor_message(
+    app: AppHandle,
+    label: String,
+    message: NativeToEditorMessage,
+) -> Result<(), BridgeError> {
+    send_to_editor(&app, &label, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::protocol::BRIDGE_VERSION;
+    use serde_json::json;
+
+    fn session() -> BridgeSession {
+        BridgeSession {
+            nonce: "n0nce".into(),
+            negotiated: None,
+            document_id: None,
+        }
+    }
+
+    fn envelope(payload: Value) -> String {
+        json!({
+            "type": ENVELOPE_TO_NATIVE,
+            "payload": payload,
+            "nonce": "n0nce",
+            "version": BRIDGE_VERSION,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn decodes_envelopes_forwarded_by_the_host() {
+        // What useEditorBridge.test.ts shows the host passing to `editor_message`
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/bridge/editor-envelopes.json"
+        ));
+        let hub = BridgeHub::default();
+        hub.configure("main", "f1xture".into());
+        let envelopes: Vec<Value> = serde_json::from_str(fixture).unwrap();
+        let decoded: Vec<EditorToNativeMessage> = envelopes
+            .iter()
+            .map(|envelope| hub.receive("main", &envelope.to_string()).unwrap())
+            .collect();
+
+        assert_eq!(
+            decoded[0],
+            EditorToNativeMessage::EditorReady {
+                version: BRIDGE_VERSION.into()
+            }
+        );
+        let negotiated = hub.session("main").unwrap().negotiated.unwrap();
+        assert!(negotiated.supports(Capability::Title));
+        assert_eq!(
+            decoded[1],
+            EditorToNativeMessage::TitleChange {
+                title: "The Lighthouse".into()
+            }
+        );
+        assert!(matches!(
+            &decoded[2],
+            EditorToNativeMessage::ContentChange { content, .. } if content == "The storm broke."
+        ));
+        assert_eq!(
+            serde_json::to_value(&decoded[3]).unwrap(),
+            envelopes[3]["payload"]
+        );
+    }
+
+    #[test]
+    fn downgrades_a_1_0_editor_instead_of_refusing_it() {
+        let hub = BridgeHub::default();
+        hub.configure("main", "n0nce".into());
+        // What a 1.0 bundle accepts before the handshake
+        assert_eq!(hub.session("main").unwrap().expected_version(), "1.0.0");
+
+        let ready = envelope(json!({"type": "editorReady", "version": "1.0.0"}))
+            .replace(BRIDGE_VERSION, "1.0.0");
+        hub.receive("main", &ready).unwrap();
+        let session = hub.session("main").unwrap();
+        assert!(session.negotiated.as_ref().unwrap().downgraded);
+        assert_eq!(session.expected_version(), "1.0.0");
+
+        let title = NativeToEditorMessage::SetTitle {
+            title: "Arrival".into(),
+        };
+        assert_eq!(
+            encode_native_message(title, &session).unwrap_err().code(),
+            "bridge_capability_unsupported"
+        );
+        let content = NativeToEditorMessage::SetContent {
+            content: "<p>Hi</p>".into(),
+        };
+        assert!(encode_native_message(content, &session).is_ok());
+    }
+
+    #[test]
+    fn decodes_editor_messages() {
+        let message = EditorToNativeMessage::AiRequest {
+            selected_text: "the tide".into(),
+            prompt: None,
+            action: Some("rewrite".into()),
+        };
+        let raw = envelope(serde_json::to_value(&message).unwrap());
+        assert!(raw.contains(r#""selectedText":"the tide""#));
+        assert_eq!(decode_editor_message(&raw, &session()).unwrap(), message);
+
+        let raw = envelope(json!({"type": "selectionChange", "selection": null}));
+        assert_eq!(
+            decode_editor_message(&raw, &session()).unwrap(),
+            EditorToNativeMessage::SelectionChange { selection: None }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_editor_messages() {
+        let code = |raw: &str| decode_editor_message(raw, &session()).unwrap_err().code();
+
+        assert_eq!(
+            code(&envelope(json!({"type": "deleteEverything"}))),
+            "bridge_unknown_message_type"
+        );
+        assert_eq!(
+            code(&envelope(json!({"type": "titleChange"}))),
+            "bridge_malformed_message"
+        );
+        assert_eq!(code(&envelope(json!({}))), "bridge_malformed_message");
+        assert_eq!(code("{\"type\":"), "bridge_malformed_message");
+        let raw = envelope(json!({"type": "editorFocused"}));
+        assert_eq!(
+            code(&raw.replace("n0nce", "forged")),
+            "bridge_nonce_mismatch"
+        );
+        assert_eq!(
+            code(&raw.replace(ENVELOPE_TO_NATIVE, ENVELOPE_TO_EDITOR)),
+            "bridge_invalid_envelope"
+        );
+        assert_eq!(
+            code(&raw.replace(BRIDGE_VERSION, "2.0.0")),
+            "bridge_version_mismatch"
+        );
+
+        let content = "a".repeat(MAX_MESSAGE_BYTES);
+        let raw = envelope(json!({"type": "titleChange", "title": content}));
+        assert!(matches!(
+            decode_editor_message(&raw, &session()),
+            Err(BridgeError::TooLarge(size)) if size == raw.len()
+        ));
+    }
+
+    #[test]
+    fn encodes_native_messages() {
+        let message = NativeToEditorMessage::InsertImage {
+            src: "asset://localhost/map.png".into(),
+            alt: None,
+            at: Some(12),
+        };
+        let envelope = encode_native_message(message.clone(), &session()).unwrap();
+        let wire = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(
+            wire,
+            json!({
+                "type": ENVELOPE_TO_EDITOR,
+                "payload": {"type": "insertImage", "src": "asset://localhost/map.png", "at": 12},
+                "nonce": "n0nce",
+                "version": BASELINE_VERSION,
+            })
+        );
+        let back: BridgeEnvelope<NativeToEditorMessage> = serde_json::from_value(wire).unwrap();
+        assert_eq!(back.payload, message);
+    }
+
+    #[test]
+    fn refuses_native_messages_the_editor_cannot_take() {
+        assert!(serde_json::from_value::<NativeToEditorMessage>(json!({"type": "eval"})).is_err());
+
+        let mut old_editor = session();
+        old_editor.negotiated = Some(NegotiatedProtocol {
+            native_version: BRIDGE_VERSION.into(),
+            editor_version: "1.0.0".into(),
+            capabilities: vec![Capability::Content],
+            disabled: vec![Capability::Collaboration],
+            downgraded: true,
+        });
+        let err =
+            encode_native_message(NativeToEditorMessage::DisconnectCollaboration, &old_editor)
+                .unwrap_err();
+        assert_eq!(err.code(), "bridge_capability_unsupported");
+        let envelope = encode_native_message(
+            NativeToEditorMessage::SetContent {
+                content: "<p>Hi</p>".into(),
+            },
+            &old_editor,
+        )
+        .unwrap();
+        assert_eq!(envelope.version, "1.0.0");
+
+        let content = "a".repeat(MAX_MESSAGE_BYTES);
+        let err = encode_native_message(NativeToEditorMessage::SetContent { content }, &session())
+            .unwrap_err();
+        assert_eq!(err.code(), "bridge_message_too_large");
+    }
+
+    #[test]
+    fn message_types_match_the_serde_tags() {
+        for tag in EditorToNativeMessage::TYPES {
+            let err = serde_json::from_value::<EditorToNativeMessage>(json!({"type": tag}))
+                .err()
+                .map(|e| e.to_string())
+                .unwrap_or_default();
+            assert!(!err.contains("unknown variant"), "{}: {}", tag, err);
+        }
+        for tag in NativeToEditorMessage::TYPES {
+            let err = serde_json::from_value::<NativeToEditorMessage>(json!({"type": tag}))
+                .err()
+                .map(|e| e.to_string())
+                .unwrap_or_default();
+            assert!(!err.contains("unknown variant"), "{}: {}", tag, err);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Bridge protocol version negotiation
+//!
+//! The shell and the bundled editor (`resources/editor/`) ship independently.
+//! When the editor announces itself with `editorReady { version }`, the shell
+//! checks the version against the range it supports and works out which
+//! capabilities both sides can use. Capabilities the editor is too old for are
+//! disabled instead of failing at runtime.
+
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use super::protocol::{NativeToEditorMessage, BRIDGE_VERSION};
+use super::BridgeError;
+
+/// Editor bridge versions this shell can talk to
+pub const SUPPORTED_EDITOR_VERSIONS: &str = ">=1.0.0, <2.0.0";
+
+/// Feature groups of the native → editor protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    Content,
+    /// `setTitle`; 1.0 editors ignore it
+    Title,
+    Media,
+    Suggestions,
+    Collaboration,
+    EditorControl,
+}
+
+/// Compatibility matrix: minimum editor version for each capability, i.e. the
+/// first `bridge.ts` release that handles its messages
+pub const COMPATIBILITY_MATRIX: &[(Capability, &str)] = &[
+    (Capability::Content, ">=1.0.0"),
+    (Capability::Title, ">=1.1.0"),
+    (Capability::Media, ">=1.0.0"),
+    (Capability::Suggestions, ">=1.0.0"),
+    (Capability::Collaboration, ">=1.0.0"),
+    (Capability::EditorControl, ">=1.0.0"),
+];
+
+impl Capability {
+    /// Capability required to send a message, `None` for protocol-level messages
+    pub fn required_for(message: &NativeToEditorMessage) -> Option<Capability> {
+        use NativeToEditorMessage::*;
+        match message {
+            SetContent { .. } | InsertContent { .. } | ReplaceSelection { .. } => {
+                Some(Capability::Content)
+            }
+            SetTitle { .. } => Some(Capability::Title),
+            InsertImage { .. } => Some(Capability::Media),
+            AddSuggestion { .. }
+            | AcceptSuggestion { .. }
+            | RejectSuggestion { .. }
+            | AcceptAllSuggestions
+            | RejectAllSuggestions
+            | SelectSuggestion { .. } => Some(Capability::Suggestions),
+            ConnectCollaboration { .. } | DisconnectCollaboration => {
+                Some(Capability::Collaboration)
+            }
+            Focus | Blur | SetEditable { .. } | Undo | Redo => Some(Capability::EditorControl),
+            Configure { .. } => None,
+        }
+    }
+}
+
+/// Outcome of the `editorReady` handshake, published to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegotiatedProtocol {
+    pub native_version: String,
+    pub editor_version: String,
+    pub capabilities: Vec<Capability>,
+    pub disabled: Vec<Capability>,
+    /// True when the editor bundle is older than the shell
+    pub downgraded: bool,
+}
+
+impl NegotiatedProtocol {
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, BridgeError> {
+    Version::parse(version).map_err(|_| BridgeError::VersionMismatch {
+        expected: SUPPORTED_EDITOR_VERSIONS.to_string(),
+        actual: version.to_string(),
+    })
+}
+
+/// Whether an envelope version falls within the supported range
+pub fn is_supported(version: &str) -> bool {
+    let supported = VersionReq::parse(SUPPORTED_EDITOR_VERSIONS).expect("valid version range");
+    Version::parse(version).is_ok_and(|v| supported.matches(&v))
+}
+
+/// Negotiate capabilities for the version announced in `editorReady`
+pub fn negotiate(editor_version: &str) -> Result<NegotiatedProtocol, BridgeError> {
+    let editor = parse_version(editor_version)?;
+    if !is_supported(editor_version) {
+        return Err(BridgeError::VersionMismatch {
+            expected: SUPPORTED_EDITOR_VERSIONS.to_string(),
+            actual: editor_version.to_string(),
+        });
+    }
+
+    let native = Version::parse(BRIDGE_VERSION).expect("valid bridge version");
+    let (capabilities, disabled): (Vec<_>, Vec<_>) =
+        COMPATIBILITY_MATRIX.iter().partition(|(_, requirement)| {
+            VersionReq::parse(requirement)
+                .expect("valid capability requirement")
+                .matches(&editor)
+        });
+
+    Ok(NegotiatedProtocol {
+        native_version: BRIDGE_VERSION.to_string(),
+        editor_version: editor_version.to_string(),
+        capabilities: capabilities.into_iter().map(|&(c, _)| c).collect(),
+        disabled: disabled.into_iter().map(|&(c, _)| c).collect(),
+        downgraded: editor < native,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_a_current_editor_with_every_capability() {
+        let negotiated = negotiate(BRIDGE_VERSION).unwrap();
+        assert_eq!(negotiated.capabilities.len(), COMPATIBILITY_MATRIX.len());
+        assert!(negotiated.disabled.is_empty());
+        assert!(!negotiated.downgraded);
+        // A newer editor within the major version is fine too
+        let newer = negotiate("1.4.2").unwrap();
+        assert!(newer.disabled.is_empty());
+        assert!(!newer.downgraded);
+    }
+
+    #[test]
+    fn downgrades_an_older_editor() {
+        let negotiated = negotiate("1.0.3").unwrap();
+        assert!(negotiated.downgraded);
+        assert_eq!(negotiated.disabled, [Capability::Title]);
+        assert!(negotiated.supports(Capability::Content));
+        assert!(!negotiated.supports(Capability::Title));
+        let title = NativeToEditorMessage::SetTitle {
+            title: "Arrival".into(),
+        };
+        assert_eq!(Capability::required_for(&title), Some(Capability::Title));
+    }
+
+    #[test]
+    fn refuses_unsupported_editors() {
+        for version in ["0.9.0", "2.0.0", "1.0", "latest"] {
+            assert!(!is_supported(version), "{}", version);
+            assert_eq!(
+                negotiate(version).unwrap_err().code(),
+                "bridge_version_mismatch",
+                "{}",
+                version
+            );
+        }
+        assert!(is_supported("1.0.0"));
+    }
+}
@@ -0,0 +1,298 @@
+//! Editor bridge protocol types
+//!
+//! Mirrors the message unions in `packages/editor-webview/src/bridge.ts`.
+//! Keep both sides in sync: a variant added there must be added here, or the
+//! shell will reject it as an unknown message.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Protocol version spoken by this shell (matches `BRIDGE_VERSION` in bridge.ts)
+pub const BRIDGE_VERSION: &str = "1.1.0";
+
+/// Version stamped on envelopes until the `editorReady` handshake: the first
+/// bridge release, which every supported editor accepts (1.0 editors only
+/// accept their own version)
+pub const BASELINE_VERSION: &str = "1.0.0";
+
+/// Envelope type for native → editor traffic
+pub const ENVELOPE_TO_EDITOR: &str = "editor-bridge";
+
+/// Envelope type for editor → native traffic
+pub const ENVELOPE_TO_NATIVE: &str = "editor-bridge-response";
+
+/// Wire envelope wrapping every bridge payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeEnvelope<T> {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub payload: T,
+    pub nonce: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SuggestionType {
+    Insert,
+    Replace,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestionAnchor {
+    pub block_id: String,
+    pub offset: u32,
+}
+
+/// Suggestion as reported by the editor's suggestion plugin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub id: String,
+    pub from: u32,
+    pub to: u32,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_content: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: SuggestionType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub created_at: String,
+    pub agent_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor_start: Option<SuggestionAnchor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor_end: Option<SuggestionAnchor>,
+}
+
+/// Suggestion payload sent to the editor (`NewSuggestionPayload`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSuggestionPayload {
+    pub id: String,
+    pub from: u32,
+    pub to: u32,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_content: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: SuggestionType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorSelection {
+    pub from: u32,
+    pub to: u32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollaborationUser {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FontStyle {
+    Default,
+    Serif,
+    Mono,
+}
+
+/// Options for the `configure` message (`EditorBridgeOptions`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorBridgeOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editable: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_style: Option<FontStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge_nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_origins: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_version: Option<String>,
+}
+
+/// Messages sent FROM the editor TO native
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum EditorToNativeMessage {
+    // Content updates
+    ContentChange {
+        content: String,
+        html: String,
+        json: Value,
+    },
+    TitleChange {
+        title: String,
+    },
+    SelectionChange {
+        selection: Option<EditorSelection>,
+    },
+    // Suggestion events
+    ReviewRequired {
+        suggestions: Vec<Suggestion>,
+    },
+    SuggestionAccepted {
+        suggestion: Suggestion,
+    },
+    SuggestionRejected {
+        suggestion: Suggestion,
+    },
+    AllSuggestionsResolved,
+    // Editor state
+    EditorReady {
+        version: String,
+    },
+    EditorFocused,
+    EditorBlurred,
+    // AI requests
+    AiRequest {
+        selected_text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        action: Option<String>,
+    },
+    // Media insert requests
+    InsertImageRequested {
+        position: u32,
+    },
+    // Errors
+    Error {
+        code: String,
+        message: String,
+    },
+}
+
+impl EditorToNativeMessage {
+    /// Every `type` tag the editor may send
+    pub const TYPES: &'static [&'static str] = &[
+        "contentChange",
+        "titleChange",
+        "selectionChange",
+        "reviewRequired",
+        "suggestionAccepted",
+        "suggestionRejected",
+        "allSuggestionsResolved",
+        "editorReady",
+        "editorFocused",
+        "editorBlurred",
+        "aiRequest",
+        "insertImageRequested",
+        "error",
+    ];
+}
+
+/// Messages sent FROM native TO the editor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum NativeToEditorMessage {
+    // Content operations
+    SetContent {
+        content: String,
+    },
+    SetTitle {
+        title: String,
+    },
+    InsertContent {
+        content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<u32>,
+    },
+    ReplaceSelection {
+        content: String,
+    },
+    // Media operations
+    InsertImage {
+        src: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<u32>,
+    },
+    // Suggestion operations
+    AddSuggestion {
+        suggestion: NewSuggestionPayload,
+    },
+    AcceptSuggestion {
+        id: String,
+    },
+    RejectSuggestion {
+        id: String,
+    },
+    AcceptAllSuggestions,
+    RejectAllSuggestions,
+    SelectSuggestion {
+        id: Option<String>,
+    },
+    // Collaboration
+    ConnectCollaboration {
+        project_id: String,
+        document_id: String,
+        user: CollaborationUser,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auth_token: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        convex_url: Option<String>,
+    },
+    DisconnectCollaboration,
+    // Editor control
+    Focus,
+    Blur,
+    SetEditable {
+        editable: bool,
+    },
+    Undo,
+    Redo,
+    // Configuration
+    Configure {
+        options: EditorBridgeOptions,
+    },
+}
+
+impl NativeToEditorMessage {
+    /// Every `type` tag the editor accepts
+    pub const TYPES: &'static [&'static str] = &[
+        "setContent",
+        "setTitle",
+        "insertContent",
+        "replaceSelection",
+        "insertImage",
+        "addSuggestion",
+        "acceptSuggestion",
+        "rejectSuggestion",
+        "acceptAllSuggestions",
+        "rejectAllSuggestions",
+        "selectSuggestion",
+        "connectCollaboration",
+        "disconnectCollaboration",
+        "focus",
+        "blur",
+        "setEditable",
+        "undo",
+        "redo",
+        "configure",
+    ];
+}
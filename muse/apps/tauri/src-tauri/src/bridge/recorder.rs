@@ -0,0 +1,401 @@
+//! Bridge traffic recorder and replay
+//!
+//! Opt-in: recording starts with `start_bridge_recording` or when
+//! `RHEI_BRIDGE_RECORD=<path>` is set at launch. Every message crossing the
+//! bridge is appended to a JSONL session file, one `RecordedMessage` per line,
+//! including rejected editor traffic. `replay_bridge_session` feeds the
+//! native → editor half of a session back into an editor webview, in order.
+//!
+//! Session files get attached to bug reports, so secrets (collaboration
+//! tokens, bridge nonces) are redacted before anything is written.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use super::protocol::NativeToEditorMessage;
+use super::{send_to_editor, BridgeError};
+
+/// Env var enabling recording from launch
+pub const RECORD_ENV: &str = "RHEI_BRIDGE_RECORD";
+
+/// Fields whose values never reach a session file, at any depth
+const REDACTED_FIELDS: [&str; 5] = [
+    "authToken",
+    "accessToken",
+    "refreshToken",
+    "nonce",
+    "bridgeNonce",
+];
+
+/// Stands in for a redacted value
+pub const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    EditorToNative,
+    NativeToEditor,
+}
+
+/// One line of a session file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedMessage {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub direction: Direction,
+    /// Label of the webview the message came from or went to
+    pub label: String,
+    pub message: Value,
+    /// Error code when the shell rejected the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+}
+
+/// Replace the values of secret fields throughout a message
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) && !field.is_null() {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+struct Recording {
+    path: PathBuf,
+    file: File,
+}
+
+/// Managed recorder state; a no-op unless a recording is active
+#[derive(Default)]
+pub struct BridgeRecorder {
+    active: Mutex<Option<Recording>>,
+}
+
+impl BridgeRecorder {
+    pub fn start(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *self.active.lock().unwrap() = Some(Recording {
+            path: path.to_path_buf(),
+            file,
+        });
+        Ok(())
+    }
+
+    /// Stops recording, returning the session file path
+    pub fn stop(&self) -> Option<PathBuf> {
+        self.active.lock().unwrap().take().map(|r| r.path)
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        self.active.lock().unwrap().as_ref().map(|r| r.path.clone())
+    }
+
+    pub fn record(
+        &self,
+        direction: Direction,
+        label: &str,
+        mut message: Value,
+        rejected: Option<&str>,
+    ) {
+        let mut active = self.active.lock().unwrap();
+        let Some(recording) = active.as_mut() else {
+            return;
+        };
+        redact(&mut message);
+
+        let entry = RecordedMessage {
+            timestamp_ms: now_ms(),
+            direction,
+            label: label.to_string(),
+            message,
+            rejected: rejected.map(str::to_string),
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("[bridge-recorder] Failed to serialize entry: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = writeln!(recording.file, "{}", line).and_then(|_| recording.file.flush()) {
+            eprintln!(
+                "[bridge-recorder] Failed to write {}: {}",
+                recording.path.display(),
+                e
+            );
+        }
+    }
+
+    /// Record a raw editor → native string. Text that isn't JSON can't be
+    /// redacted, so only its size is kept.
+    pub fn record_raw(&self, label: &str, raw: &str, rejected: Option<&str>) {
+        let message = serde_json::from_str(raw)
+            .unwrap_or_else(|_| Value::String(format!("<{} bytes, not JSON>", raw.len())));
+        self.record(Direction::EditorToNative, label, message, rejected);
+    }
+}
+
+/// Read a session file
+pub fn read_session(path: &Path) -> std::io::Result<Vec<RecordedMessage>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("line {}: {}", index + 1, e),
+            )
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Native → editor messages of a session, in order, with delays between them
+///
+/// `configure` is skipped: it carries the recorded session's nonce and would
+/// disable the live bridge.
+pub fn replay_plan(entries: &[RecordedMessage]) -> Vec<(Duration, NativeToEditorMessage)> {
+    let mut previous: Option<u64> = None;
+    entries
+        .iter()
+        .filter(|e| e.direction == Direction::NativeToEditor && e.rejected.is_none())
+        .filter_map(|e| {
+            let message: NativeToEditorMessage = serde_json::from_value(e.message.clone()).ok()?;
+            if matches!(message, NativeToEditorMessage::Configure { .. }) {
+                return None;
+            }
+            let delay = previous.map_or(0, |p| e.timestamp_ms.saturating_sub(p));
+            previous = Some(e.timestamp_ms);
+            Some((Duration::from_millis(delay), message))
+        })
+        .collect()
+}
+
+fn default_session_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| e.to_string())?
+        .join("bridge-sessions");
+    Ok(dir.join(format!("session-{}.jsonl", now_ms())))
+}
+
+/// Starts recording bridge traffic, returning the session file path
+#[tauri::command]
+pub fn start_bridge_recording(
+    app: AppHandle,
+    recorder: State<'_, BridgeRecorder>,
+    path: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let path = match path {
+        Some(path) => path,
+        None => default_session_path(&app)?,
+    };
+    recorder.start(&path).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Stops recording, returning the session file path if one was active
+#[tauri::command]
+pub fn stop_bridge_recording(recorder: State<'_, BridgeRecorder>) -> Option<PathBuf> {
+    recorder.stop()
+}
+
+/// Replays a recorded session into the editor in the given webview
+///
+/// With `realtime`, the original gaps between messages are kept; otherwise
+/// messages are sent back to back. Returns the number of messages scheduled.
+#[tauri::command(rename_all = "camelCase")]
+pub fn replay_bridge_session(
+    app: AppHandle,
+    path: PathBuf,
+    label: String,
+    realtime: Option<bool>,
+) -> Result<usize, String> {
+    let entries = read_session(&path).map_err(|e| e.to_string())?;
+    let plan = replay_plan(&entries);
+    let count = plan.len();
+    let realtime = realtime.unwrap_or(false);
+
+    thread::spawn(move || {
+        for (delay, message) in plan {
+            if realtime {
+                thread::sleep(delay);
+            }
+            if let Err(e) = send_to_editor(&app, &label, message) {
+                eprintln!("[bridge-recorder] Replay message failed: {}", e);
+                if matches!(
+                    e,
+                    BridgeError::WebviewNotFound(_) | BridgeError::NotConfigured(_)
+                ) {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(timestamp_ms: u64, direction: Direction, message: Value) -> RecordedMessage {
+        RecordedMessage {
+            timestamp_ms,
+            direction,
+            label: "main".into(),
+            message,
+            rejected: None,
+        }
+    }
+
+    #[test]
+    fn records_sessions_with_secrets_redacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions").join("session.jsonl");
+        let recorder = BridgeRecorder::default();
+        // Nothing is written until a recording starts
+        recorder.record_raw("main", "{}", None);
+        recorder.start(&path).unwrap();
+
+        let connect = NativeToEditorMessage::ConnectCollaboration {
+            project_id: "p1".into(),
+            document_id: "d1".into(),
+            user: super::super::protocol::CollaborationUser {
+                id: "u1".into(),
+                name: "Ada".into(),
+                avatar_url: None,
+            },
+            auth_token: Some("secret-token".into()),
+            convex_url: None,
+        };
+        recorder.record(
+            Direction::NativeToEditor,
+            "main",
+            serde_json::to_value(&connect).unwrap(),
+            None,
+        );
+        let raw = json!({
+            "type": "editor-bridge-response",
+            "payload": {"type": "editorFocused"},
+            "nonce": "secret-nonce",
+            "version": "1.1.0",
+        });
+        recorder.record_raw("main", &raw.to_string(), None);
+        recorder.record_raw(
+            "main",
+            "{\"nonce\": \"secret-nonce\"",
+            Some("bridge_malformed_message"),
+        );
+        assert_eq!(recorder.stop(), Some(path.clone()));
+        recorder.record_raw("main", "{}", None);
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("secret"), "{}", text);
+        let entries = read_session(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].message["authToken"], REDACTED);
+        assert_eq!(entries[0].message["projectId"], "p1");
+        assert_eq!(entries[1].direction, Direction::EditorToNative);
+        assert_eq!(entries[1].message["nonce"], REDACTED);
+        assert_eq!(entries[1].message["payload"]["type"], "editorFocused");
+        assert_eq!(entries[2].message, "<24 bytes, not JSON>");
+        assert_eq!(
+            entries[2].rejected.as_deref(),
+            Some("bridge_malformed_message")
+        );
+    }
+
+    #[test]
+    fn reports_the_bad_line_of_a_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let good = serde_json::to_string(&entry(
+            1,
+            Direction::NativeToEditor,
+            json!({"type": "focus"}),
+        ))
+        .unwrap();
+        fs::write(&path, format!("{}\n\n{}\nnot json\n", good, good)).unwrap();
+        let err = read_session(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 4:"), "{}", err);
+    }
+
+    #[test]
+    fn plans_native_messages_with_their_gaps() {
+        let mut rejected = entry(1_500, Direction::NativeToEditor, json!({"type": "blur"}));
+        rejected.rejected = Some("bridge_capability_unsupported".into());
+        let entries = [
+            entry(
+                1_000,
+                Direction::NativeToEditor,
+                json!({"type": "configure", "options": {}}),
+            ),
+            entry(
+                1_200,
+                Direction::NativeToEditor,
+                json!({"type": "setContent", "content": "<p>A</p>"}),
+            ),
+            entry(
+                1_300,
+                Direction::EditorToNative,
+                json!({"type": "editorFocused"}),
+            ),
+            rejected,
+            entry(
+                1_700,
+                Direction::NativeToEditor,
+                json!({"type": "notAMessage"}),
+            ),
+            entry(2_000, Direction::NativeToEditor, json!({"type": "undo"})),
+        ];
+        let plan = replay_plan(&entries);
+        assert_eq!(
+            plan,
+            [
+                (
+                    Duration::ZERO,
+                    NativeToEditorMessage::SetContent {
+                        content: "<p>A</p>".into()
+                    }
+                ),
+                (Duration::from_millis(800), NativeToEditorMessage::Undo),
+            ]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Encrypted file credential backend
+//!
+//! Fallback for systems without a usable OS secret store (headless Linux, no
+//! Secret Service daemon). Secrets are sealed with XChaCha20-Poly1305 under a
+//! random key kept in a separate owner-only file; the account and key name are
+//! bound as associated data, so entries can't be swapped between accounts.
+//! This keeps tokens out of webview storage and off disk in plain text; it is
+//! not a substitute for the OS keychain against an attacker with file access.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use super::CredentialError;
+
+const KEY_FILE: &str = "credentials.key";
+const STORE_FILE: &str = "credentials.json";
+const NONCE_LEN: usize = 24;
+
+#[derive(Default, Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    /// account -> key -> base64(nonce || ciphertext)
+    entries: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+pub struct EncryptedFileStore {
+    dir: PathBuf,
+    /// Serializes key creation and every read-modify-write of the store file
+    lock: Mutex<()>,
+}
+
+fn io_error(e: std::io::Error) -> CredentialError {
+    CredentialError::Storage(e.to_string())
+}
+
+/// Write a file readable only by the owner, replacing it atomically
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), CredentialError> {
+    static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+    let tmp = path.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp).map_err(io_error)?;
+    file.write_all(contents).map_err(io_error)?;
+    file.sync_all().map_err(io_error)?;
+    fs::rename(&tmp, path).map_err(io_error)
+}
+
+fn associated_data(account: &str, key: &str) -> Vec<u8> {
+    [account.as_bytes(), b"\0", key.as_bytes()].concat()
+}
+
+impl EncryptedFileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn cipher(&self) -> Result<XChaCha20Poly1305, CredentialError> {
+        let path = self.dir.join(KEY_FILE);
+        let key = match fs::read(&path) {
+            Ok(key) if key.len() == 32 => key,
+            Ok(_) => return Err(CredentialError::Storage("corrupt key file".into())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                fs::create_dir_all(&self.dir).map_err(io_error)?;
+                let mut key = vec![0u8; 32];
+                getrandom::getrandom(&mut key)
+                    .map_err(|e| CredentialError::Storage(e.to_string()))?;
+                write_private(&path, &key)?;
+                key
+            }
+            Err(e) => return Err(io_error(e)),
+        };
+        XChaCha20Poly1305::new_from_slice(&key).map_err(|e| CredentialError::Storage(e.to_string()))
+    }
+
+    fn load(&self) -> Result<StoreFile, CredentialError> {
+        match fs::read(self.dir.join(STORE_FILE)) {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).map_err(|e| CredentialError::Storage(e.to_string()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(StoreFile::default()),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    fn save(&self, store: &StoreFile) -> Result<(), CredentialError> {
+        fs::create_dir_all(&self.dir).map_err(io_error)?;
+        let bytes = serde_json::to_vec_pretty(store)
+            .map_err(|e| CredentialError::Storage(e.to_string()))?;
+        write_private(&self.dir.join(STORE_FILE), &bytes)
+    }
+
+    pub fn get(&self, account: &str, key: &str) -> Result<Option<String>, CredentialError> {
+        let _guard = self.lock.lock().unwrap();
+        let store = self.load()?;
+        let Some(sealed) = store.entries.get(account).and_then(|e| e.get(key)) else {
+            return Ok(None);
+        };
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|_| CredentialError::Corrupt)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(CredentialError::Corrupt);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()?
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data(account, key),
+                },
+            )
+            .map_err(|_| CredentialError::Corrupt)?;
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|_| CredentialError::Corrupt)
+    }
+
+    pub fn set(&self, account: &str, key: &str, secret: &str) -> Result<(), CredentialError> {
+        let _guard = self.lock.lock().unwrap();
+        let cipher = self.cipher()?;
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| CredentialError::Storage(e.to_string()))?;
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: secret.as_bytes(),
+                    aad: &associated_data(account, key),
+                },
+            )
+            .map_err(|e| CredentialError::Storage(e.to_string()))?;
+
+        let mut store = self.load()?;
+        store.version = 1;
+        store
+            .entries
+            .entry(account.to_string())
+            .or_default()
+            .insert(
+                key.to_string(),
+                STANDARD.encode([&nonce[..], &ciphertext].concat()),
+            );
+        self.save(&store)
+    }
+
+    /// Returns whether an entry was removed
+    pub fn delete(&self, account: &str, key: &str) -> Result<bool, CredentialError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut store = self.load()?;
+        let Some(entries) = store.entries.get_mut(account) else {
+            return Ok(false);
+        };
+        let removed = entries.remove(key).is_some();
+        if entries.is_empty() {
+            store.entries.remove(account);
+        }
+        if removed {
+            self.save(&store)?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_per_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EncryptedFileStore::new(dir.path());
+
+        store.set("alice", "session", "token-a").unwrap();
+        store.set("bob", "session", "token-b").unwrap();
+        assert_eq!(
+            store.get("alice", "session").unwrap().as_deref(),
+            Some("token-a")
+        );
+        assert_eq!(
+            store.get("bob", "session").unwrap().as_deref(),
+            Some("token-b")
+        );
+        assert_eq!(store.get("carol", "session").unwrap(), None);
+
+        assert!(store.delete("alice", "session").unwrap());
+        assert!(!store.delete("alice", "session").unwrap());
+        assert_eq!(store.get("alice", "session").unwrap(), None);
+        assert_eq!(
+            store.get("bob", "session").unwrap().as_deref(),
+            Some("token-b")
+        );
+    }
+
+    #[test]
+    fn never_writes_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EncryptedFileStore::new(dir.path());
+        store.set("alice", "session", "super-secret-token").unwrap();
+
+        let contents = fs::read_to_string(dir.path().join(STORE_FILE)).unwrap();
+        assert!(!contents.contains("super-secret-token"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join(KEY_FILE))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn keeps_concurrent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EncryptedFileStore::new(dir.path());
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let store = &store;
+                scope.spawn(move || {
+                    store
+                        .set("alice", &format!("key-{}", i), &format!("secret-{}", i))
+                        .unwrap();
+                });
+            }
+        });
+
+        for i in 0..8 {
+            assert_eq!(
+                store.get("alice", &format!("key-{}", i)).unwrap(),
+                Some(format!("secret-{}", i))
+            );
+        }
+        let leftovers = fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == "tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn detects_entries_moved_between_accounts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EncryptedFileStore::new(dir.path());
+        store.set("alice", "session", "token-a").unwrap();
+
+        let mut file = store.load().unwrap();
+        let sealed = file.entries["alice"]["session"].clone();
+        file.entries
+            .entry("mallory".into())
+            .or_default()
+            .insert("session".into(), sealed);
+        store.save(&file).unwrap();
+
+        assert!(matches!(
+            store.get("mallory", "session"),
+            Err(CredentialError::Corrupt)
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+//! Credential storage for session tokens
+//!
+//! Secrets are kept in the OS secret store (Keychain, Credential Manager,
+//! Secret Service) under the `team.rhei.app` service, one entry per
+//! `account:key`. When no secret store is reachable the encrypted file store in
+//! `file` takes over, so desktop builds never need to put tokens in webview
+//! storage. The frontend reaches this through `packages/auth`'s Tauri storage
+//! adapter.
+
+pub mod file;
+
+use std::path::PathBuf;
+
+use tauri::State;
+
+use file::EncryptedFileStore;
+
+/// Service name for OS secret store entries
+pub const SERVICE: &str = "team.rhei.app";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialError {
+    #[error("account and key must be non-empty")]
+    InvalidName,
+    #[error("stored credential failed to decrypt")]
+    Corrupt,
+    #[error("credential storage failed: {0}")]
+    Storage(String),
+}
+
+impl CredentialError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidName => "credential_invalid_name",
+            Self::Corrupt => "credential_corrupt",
+            Self::Storage(_) => "credential_storage",
+        }
+    }
+}
+
+crate::db::serialize_error!(CredentialError);
+
+/// Managed credential store: OS secret store first, encrypted file second
+pub struct CredentialStore {
+    file: EncryptedFileStore,
+    use_keyring: bool,
+}
+
+fn validate(account: &str, key: &str) -> Result<(), CredentialError> {
+    if account.is_empty() || key.is_empty() {
+        return Err(CredentialError::InvalidName);
+    }
+    Ok(())
+}
+
+fn keyring_entry(account: &str, key: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, &format!("{}:{}", account, key))
+}
+
+impl CredentialStore {
+    /// Store backed by the OS secret store, with the fallback file in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            file: EncryptedFileStore::new(dir),
+            use_keyring: true,
+        }
+    }
+
+    /// Store that only uses the encrypted file (headless and tests)
+    pub fn file_only(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            file: EncryptedFileStore::new(dir),
+            use_keyring: false,
+        }
+    }
+
+    pub fn get(&self, account: &str, key: &str) -> Result<Option<String>, CredentialError> {
+        validate(account, key)?;
+        if self.use_keyring {
+            match keyring_entry(account, key).and_then(|entry| entry.get_password()) {
+                Ok(secret) => return Ok(Some(secret)),
+                Err(keyring::Error::NoEntry) => {}
+                Err(e) => eprintln!("[credentials] Secret store unavailable: {}", e),
+            }
+        }
+        self.file.get(account, key)
+    }
+
+    pub fn set(&self, account: &str, key: &str, secret: &str) -> Result<(), CredentialError> {
+        validate(account, key)?;
+        if self.use_keyring {
+            match keyring_entry(account, key).and_then(|entry| entry.set_password(secret)) {
+                Ok(()) => {
+                    // Don't leave a stale copy behind from an earlier fallback
+                    self.file.delete(account, key)?;
+                    return Ok(());
+                }
+                Err(e) => eprintln!(
+                    "[credentials] Secret store unavailable, using encrypted file: {}",
+                    e
+                ),
+            }
+        }
+        self.file.set(account, key, secret)
+    }
+
+    pub fn delete(&self, account: &str, key: &str) -> Result<(), CredentialError> {
+        validate(account, key)?;
+        if self.use_keyring {
+            match keyring_entry(account, key).and_then(|entry| entry.delete_credential()) {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => eprintln!("[credentials] Secret store unavailable: {}", e),
+            }
+        }
+        self.file.delete(account, key).map(|_| ())
+    }
+}
+
+#[tauri::command]
+pub fn get_credential(
+    store: State<'_, CredentialStore>,
+    account: String,
+    key: String,
+) -> Result<Option<String>, CredentialError> {
+    store.get(&account, &key)
+}
+
+#[tauri::command]
+pub fn set_credential(
+    store: State<'_, CredentialStore>,
+    account: String,
+    key: String,
+    secret: String,
+) -> Result<(), CredentialError> {
+    store.set(&account, &key, &secret)
+}
+
+#[tauri::command]
+pub fn delete_credential(
+    store: State<'_, CredentialStore>,
+    account: String,
+    key: String,
+) -> Result<(), CredentialError> {
+    store.delete(&account, &key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_only_store_scopes_by_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CredentialStore::file_only(dir.path());
+
+        store
+            .set("default", "rhei-auth", "{\"token\":\"a\"}")
+            .unwrap();
+        store.set("work", "rhei-auth", "{\"token\":\"b\"}").unwrap();
+        assert_eq!(
+            store.get("default", "rhei-auth").unwrap().as_deref(),
+            Some("{\"token\":\"a\"}")
+        );
+
+        store.delete("default", "rhei-auth").unwrap();
+        assert_eq!(store.get("default", "rhei-auth").unwrap(), None);
+        assert!(store.get("work", "rhei-auth").unwrap().is_some());
+        assert!(matches!(
+            store.get("", "rhei-auth"),
+            Err(CredentialError::InvalidName)
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+//! Schema migrations for the local project store
+//!
+//! Applied in order and tracked with `PRAGMA user_version`; a migration's
+//! position in `MIGRATIONS` is its version, so entries are append-only.
+
+use rusqlite::Connection;
+
+/// One row table per `SyncTable`; rows are the frontend's JSON objects
+const SYNC_TABLES_V1: &str = "
+    CREATE TABLE documents (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_documents_project ON documents(project_id);
+
+    CREATE TABLE entities (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_entities_project ON entities(project_id);
+
+    CREATE TABLE relationships (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_relationships_project ON relationships(project_id);
+
+    CREATE TABLE mentions (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_mentions_project ON mentions(project_id);
+
+    CREATE TABLE analysis (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_analysis_project ON analysis(project_id);
+
+    CREATE TABLE captures (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_captures_project ON captures(project_id);
+
+    CREATE TABLE sync_meta (
+        project_id TEXT PRIMARY KEY,
+        last_sync_version INTEGER NOT NULL DEFAULT 0,
+        last_sync_at TEXT
+    );
+";
+
+/// Durable mutation outbox (see `sync::outbox`); rowid is the push order
+const OUTBOX_V2: &str = "
+    CREATE TABLE outbox (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        tbl TEXT NOT NULL,
+        pk TEXT,
+        mutation TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at INTEGER NOT NULL DEFAULT 0,
+        last_error TEXT,
+        dead INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_outbox_due ON outbox(dead, next_attempt_at);
+    CREATE INDEX idx_outbox_row ON outbox(tbl, pk);
+";
+
+/// Conflict detection (see `sync::conflicts`): server row shadows, delivered
+/// mutations awaiting their echo, the review queue, and held outbox rows
+const CONFLICTS_V3: &str = "
+    CREATE TABLE row_shadows (
+        tbl TEXT NOT NULL,
+        id TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (tbl, id)
+    );
+    CREATE INDEX idx_row_shadows_project ON row_shadows(project_id);
+
+    CREATE TABLE sync_echoes (
+        mutation_id TEXT PRIMARY KEY,
+        tbl TEXT NOT NULL,
+        pk TEXT NOT NULL,
+        mutation TEXT NOT NULL
+    );
+    CREATE INDEX idx_sync_echoes_row ON sync_echoes(tbl, pk);
+
+    CREATE TABLE conflicts (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        tbl TEXT NOT NULL,
+        pk TEXT NOT NULL,
+        strategy TEXT NOT NULL,
+        local_value TEXT,
+        server_value TEXT,
+        base_value TEXT,
+        server_version INTEGER NOT NULL,
+        fields TEXT NOT NULL DEFAULT '[]',
+        created_at INTEGER NOT NULL,
+        resolved_at INTEGER,
+        resolution TEXT
+    );
+    CREATE INDEX idx_conflicts_open ON conflicts(project_id, resolved_at);
+
+    ALTER TABLE outbox ADD COLUMN held INTEGER NOT NULL DEFAULT 0;
+";
+
+/// Full-text index (see `search::fulltext`). FTS rows share the rowid of the
+/// row they index; deletes are mirrored by trigger, writes by `put_row`.
+const FULLTEXT_V4: &str = "
+    CREATE VIRTUAL TABLE documents_fts USING fts5(
+        title, body,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE VIRTUAL TABLE entities_fts USING fts5(
+        name, aliases, notes,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE VIRTUAL TABLE documents_vocab USING fts5vocab(documents_fts, 'row');
+    CREATE VIRTUAL TABLE entities_vocab USING fts5vocab(entities_fts, 'row');
+
+    CREATE TRIGGER documents_fts_delete AFTER DELETE ON documents BEGIN
+        DELETE FROM documents_fts WHERE rowid = old.rowid;
+    END;
+    CREATE TRIGGER entities_fts_delete AFTER DELETE ON entities BEGIN
+        DELETE FROM entities_fts WHERE rowid = old.rowid;
+    END;
+";
+
+/// Cached embeddings and their HNSW graph (see `search::vector`). Nodes are
+/// never renumbered, so a stale edge can't point at a different row.
+const VECTORS_V5: &str = "
+    CREATE TABLE vectors (
+        node INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        id TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        level INTEGER NOT NULL,
+        model TEXT,
+        content_hash TEXT,
+        vector BLOB NOT NULL,
+        UNIQUE (kind, id)
+    );
+    CREATE INDEX idx_vectors_entry ON vectors(project_id, level);
+
+    CREATE TABLE vector_edges (
+        node INTEGER NOT NULL REFERENCES vectors(node) ON DELETE CASCADE,
+        layer INTEGER NOT NULL,
+        neighbors BLOB NOT NULL,
+        PRIMARY KEY (node, layer)
+    );
+
+    CREATE TRIGGER documents_vectors_delete AFTER DELETE ON documents BEGIN
+        DELETE FROM vectors WHERE kind = 'document' AND id = old.id;
+    END;
+    CREATE TRIGGER entities_vectors_delete AFTER DELETE ON entities BEGIN
+        DELETE FROM vectors WHERE kind = 'entity' AND id = old.id;
+    END;
+";
+
+/// Local embedding queue (see `search::embedding_jobs`). Every document and
+/// entity write (re)queues its row; existing rows are queued once here.
+const EMBEDDING_JOBS_V6: &str = "
+    CREATE TABLE embedding_jobs (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        target_type TEXT NOT NULL,
+        target_id TEXT NOT NULL,
+        source TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at INTEGER NOT NULL DEFAULT 0,
+        last_error TEXT,
+        UNIQUE (target_type, target_id)
+    );
+    CREATE INDEX idx_embedding_jobs_due ON embedding_jobs(next_attempt_at);
+
+    -- A rewrite re-queues with a fresh seq; OR REPLACE would be overridden by
+    -- the outer upsert's conflict clause
+
+    CREATE TRIGGER documents_embedding_insert AFTER INSERT ON documents BEGIN
+        DELETE FROM embedding_jobs WHERE target_type = 'document' AND target_id = new.id;
+        INSERT INTO embedding_jobs (target_type, target_id, source)
+        VALUES ('document', new.id, 'write');
+    END;
+    CREATE TRIGGER documents_embedding_update AFTER UPDATE OF data ON documents BEGIN
+        DELETE FROM embedding_jobs WHERE target_type = 'document' AND target_id = new.id;
+        INSERT INTO embedding_jobs (target_type, target_id, source)
+        VALUES ('document', new.id, 'write');
+    END;
+    CREATE TRIGGER entities_embedding_insert AFTER INSERT ON entities BEGIN
+        DELETE FROM embedding_jobs WHERE target_type = 'entity' AND target_id = new.id;
+        INSERT INTO embedding_jobs (target_type, target_id, source)
+        VALUES ('entity', new.id, 'write');
+    END;
+    CREATE TRIGGER entities_embedding_update AFTER UPDATE OF data ON entities BEGIN
+        DELETE FROM embedding_jobs WHERE target_type = 'entity' AND target_id = new.id;
+        INSERT INTO embedding_jobs (target_type, target_id, source)
+        VALUES ('entity', new.id, 'write');
+    END;
+
+    INSERT INTO embedding_jobs (target_type, target_id, source)
+        SELECT 'document', id, 'backfill' FROM documents;
+    INSERT INTO embedding_jobs (target_type, target_id, source)
+        SELECT 'entity', id, 'backfill' FROM entities;
+";
+
+/// Shell settings that outlive a launch (see `ProjectStore::setting`)
+const SETTINGS_V7: &str = "
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// Schema version that introduced the full-text index; stores migrated past
+/// it are reindexed once
+pub const FULLTEXT_VERSION: usize = 4;
+
+pub const MIGRATIONS: &[&str] = &[
+    SYNC_TABLES_V1,
+    OUTBOX_V2,
+    CONFLICTS_V3,
+    FULLTEXT_V4,
+    VECTORS_V5,
+    EMBEDDING_JOBS_V6,
+    SETTINGS_V7,
+];
+
+/// Current schema version of `conn`
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map(|v| v as usize)
+}
+
+/// Bring `conn` up to the latest schema, one transaction per migration
+pub fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let current = schema_version(conn)?;
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
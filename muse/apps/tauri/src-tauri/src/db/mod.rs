@@ -0,0 +1,875 @@
+//! Local SQLite project store
+//!
+//! Durable offline copy of the `SyncTable` tables from `@mythos/sync`, kept in
+//! the app data directory so it survives webview cache clears and isn't bound
+//! by browser storage quotas. Rows are stored as the frontend's JSON objects,
+//! keyed by `id` and `projectId`, next to the last server `version` seen for
+//! each row. The last server copy of each row is kept as its shadow, the
+//! common base when local and remote edits have to be merged. Schema changes
+//! go through `migrations`.
+
+pub mod migrations;
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+/// File name of the store inside the app data directory
+pub const DB_FILE: &str = "rhei.db";
+
+/// Mirrors `SyncTable` in `packages/sync/src/types.ts`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncTable {
+    Documents,
+    Entities,
+    Relationships,
+    Mentions,
+    Analysis,
+    Captures,
+}
+
+impl SyncTable {
+    pub const ALL: [SyncTable; 6] = [
+        SyncTable::Documents,
+        SyncTable::Entities,
+        SyncTable::Relationships,
+        SyncTable::Mentions,
+        SyncTable::Analysis,
+        SyncTable::Captures,
+    ];
+
+    /// SQL table name (never user input, safe to interpolate)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncTable::Documents => "documents",
+            SyncTable::Entities => "entities",
+            SyncTable::Relationships => "relationships",
+            SyncTable::Mentions => "mentions",
+            SyncTable::Analysis => "analysis",
+            SyncTable::Captures => "captures",
+        }
+    }
+}
+
+/// Mirrors `MutationType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MutationType {
+    Upsert,
+    Delete,
+}
+
+/// Mirrors `SyncEvent`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncEvent {
+    pub id: String,
+    pub table: SyncTable,
+    #[serde(rename = "type")]
+    pub kind: MutationType,
+    pub row: Value,
+    pub version: i64,
+    pub user_id: String,
+    pub timestamp: String,
+}
+
+/// Mirrors `ProjectSnapshot`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSnapshot {
+    pub project_id: String,
+    pub version: i64,
+    #[serde(default)]
+    pub documents: Vec<Value>,
+    #[serde(default)]
+    pub entities: Vec<Value>,
+    #[serde(default)]
+    pub relationships: Vec<Value>,
+    #[serde(default)]
+    pub mentions: Vec<Value>,
+    #[serde(default)]
+    pub analysis: Vec<Value>,
+    #[serde(default)]
+    pub captures: Vec<Value>,
+    pub synced_at: String,
+}
+
+impl ProjectSnapshot {
+    pub(crate) fn rows(&self, table: SyncTable) -> &[Value] {
+        match table {
+            SyncTable::Documents => &self.documents,
+            SyncTable::Entities => &self.entities,
+            SyncTable::Relationships => &self.relationships,
+            SyncTable::Mentions => &self.mentions,
+            SyncTable::Analysis => &self.analysis,
+            SyncTable::Captures => &self.captures,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("row must be an object with a string `id`")]
+    InvalidRow,
+    #[error("row `{0}` has no `projectId`")]
+    MissingProject(String),
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("stored row is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("failed to create database directory: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl StoreError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidRow => "store_invalid_row",
+            Self::MissingProject(_) => "store_missing_project",
+            Self::Sqlite(_) => "store_sqlite",
+            Self::Json(_) => "store_json",
+            Self::Io(_) => "store_io",
+        }
+    }
+}
+
+/// Serialize an error for the frontend as `{ code, message }`
+macro_rules! serialize_error {
+    ($error:ident) => {
+        impl serde::Serialize for $error {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeStruct;
+                let mut state = serializer.serialize_struct(stringify!($error), 2)?;
+                state.serialize_field("code", self.code())?;
+                state.serialize_field("message", &self.to_string())?;
+                state.end()
+            }
+        }
+    };
+}
+pub(crate) use serialize_error;
+
+serialize_error!(StoreError);
+
+/// The row as an object, and its `id`
+pub fn row_object(row: &Value) -> Result<(&Map<String, Value>, &str), StoreError> {
+    let object = row.as_object().ok_or(StoreError::InvalidRow)?;
+    let id = object
+        .get("id")
+        .and_then(Value::as_str)
+        .ok_or(StoreError::InvalidRow)?;
+    Ok((object, id))
+}
+
+/// Insert or replace one row, stamping `projectId` when given. A `version`
+/// marks the data as the server's, which also records it as the row's shadow.
+pub fn put_row(
+    conn: &Connection,
+    table: SyncTable,
+    project_id: Option<&str>,
+    row: &Value,
+    version: Option<i64>,
+) -> Result<(), StoreError> {
+    let (object, id) = row_object(row)?;
+    let project_id = match project_id {
+        Some(project_id) => project_id,
+        None => object
+            .get("projectId")
+            .and_then(Value::as_str)
+            .ok_or_else(|| StoreError::MissingProject(id.to_string()))?,
+    };
+    let mut object = object.clone();
+    object.insert("projectId".into(), Value::String(project_id.to_string()));
+    let data = serde_json::to_string(&object)?;
+
+    let rowid: i64 = conn.query_row(
+        &format!(
+            "INSERT INTO {table} (id, project_id, data, version) VALUES (?1, ?2, ?3, COALESCE(?4, 0))
+             ON CONFLICT(id) DO UPDATE SET
+                 project_id = excluded.project_id,
+                 data = excluded.data,
+                 version = COALESCE(?4, {table}.version)
+             RETURNING rowid",
+            table = table.as_str()
+        ),
+        params![id, project_id, data, version],
+        |row| row.get(0),
+    )?;
+    crate::search::fulltext::index_row(conn, table, rowid, &object)?;
+    if let Some(version) = version {
+        put_shadow(conn, table, project_id, id, version, &data)?;
+    }
+    Ok(())
+}
+
+/// Record the last server copy of a row, the base for conflict merges
+pub fn put_shadow(
+    conn: &Connection,
+    table: SyncTable,
+    project_id: &str,
+    id: &str,
+    version: i64,
+    data: &str,
+) -> Result<(), StoreError> {
+    conn.execute(
+        "INSERT INTO row_shadows (tbl, id, project_id, version, data) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(tbl, id) DO UPDATE SET
+             project_id = excluded.project_id,
+             version = excluded.version,
+             data = excluded.data",
+        params![table.as_str(), id, project_id, version, data],
+    )?;
+    Ok(())
+}
+
+pub fn remove_shadow(conn: &Connection, table: SyncTable, id: &str) -> Result<(), StoreError> {
+    conn.execute(
+        "DELETE FROM row_shadows WHERE tbl = ?1 AND id = ?2",
+        params![table.as_str(), id],
+    )?;
+    Ok(())
+}
+
+/// Last server copy of a row and its version
+pub fn shadow(
+    conn: &Connection,
+    table: SyncTable,
+    id: &str,
+) -> Result<Option<(i64, Value)>, StoreError> {
+    let shadow: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT version, data FROM row_shadows WHERE tbl = ?1 AND id = ?2",
+            params![table.as_str(), id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    shadow
+        .map(|(version, data)| Ok((version, serde_json::from_str(&data)?)))
+        .transpose()
+}
+
+/// Apply one server event to the rows and their shadows
+pub fn apply_server_event(conn: &Connection, event: &SyncEvent) -> Result<(), StoreError> {
+    match event.kind {
+        MutationType::Upsert => put_row(conn, event.table, None, &event.row, Some(event.version)),
+        MutationType::Delete => {
+            let (_, id) = row_object(&event.row)?;
+            remove_row(conn, event.table, id)?;
+            remove_shadow(conn, event.table, id)
+        }
+    }
+}
+
+/// Delete one row; deleting a chapter also deletes its scenes, as in Dexie,
+/// along with their shadows
+pub fn remove_row(conn: &Connection, table: SyncTable, id: &str) -> Result<bool, StoreError> {
+    if table == SyncTable::Documents {
+        let scenes = "SELECT id FROM documents WHERE json_extract(data, '$.parentId') = ?1
+             AND EXISTS (SELECT 1 FROM documents WHERE id = ?1 AND json_extract(data, '$.type') = 'chapter')";
+        conn.execute(
+            &format!(
+                "DELETE FROM row_shadows WHERE tbl = 'documents' AND id IN ({})",
+                scenes
+            ),
+            params![id],
+        )?;
+        conn.execute(
+            &format!("DELETE FROM documents WHERE id IN ({})", scenes),
+            params![id],
+        )?;
+    }
+    let changed = conn.execute(
+        &format!("DELETE FROM {} WHERE id = ?1", table.as_str()),
+        params![id],
+    )?;
+    Ok(changed > 0)
+}
+
+/// All rows of a project, in insertion order
+pub fn list_rows(
+    conn: &Connection,
+    table: SyncTable,
+    project_id: &str,
+) -> Result<Vec<Value>, StoreError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT data FROM {} WHERE project_id = ?1 ORDER BY rowid",
+        table.as_str()
+    ))?;
+    let rows = stmt.query_map(params![project_id], |row| row.get::<_, String>(0))?;
+    rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+}
+
+/// Managed handle to the project database
+pub struct ProjectStore {
+    conn: Mutex<Connection>,
+}
+
+impl ProjectStore {
+    /// Open (creating if needed) and migrate the database at `path`
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::from_connection(conn)
+    }
+
+    /// Fresh in-memory store (tests)
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self, StoreError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let before = migrations::schema_version(&conn)?;
+        migrations::migrate(&mut conn)?;
+        if before > 0 && before < migrations::FULLTEXT_VERSION {
+            crate::search::fulltext::rebuild(&conn, None)?;
+        }
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Run `f` with exclusive access to the connection
+    pub fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        f(&mut self.lock())
+    }
+
+    /// Exclusive access to the connection, for callers with their own errors
+    pub fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap()
+    }
+
+    pub fn get(&self, table: SyncTable, id: &str) -> Result<Option<Value>, StoreError> {
+        self.with_conn(|conn| {
+            let data: Option<String> = conn
+                .query_row(
+                    &format!("SELECT data FROM {} WHERE id = ?1", table.as_str()),
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(data.map(|d| serde_json::from_str(&d)).transpose()?)
+        })
+    }
+
+    /// All rows of a project, in insertion order
+    pub fn list(&self, table: SyncTable, project_id: &str) -> Result<Vec<Value>, StoreError> {
+        self.with_conn(|conn| list_rows(conn, table, project_id))
+    }
+
+    /// Write a locally edited row (keeps the last seen server version)
+    pub fn upsert(&self, table: SyncTable, row: &Value) -> Result<(), StoreError> {
+        self.with_conn(|conn| put_row(conn, table, None, row, None))
+    }
+
+    pub fn delete(&self, table: SyncTable, id: &str) -> Result<bool, StoreError> {
+        self.with_conn(|conn| remove_row(conn, table, id))
+    }
+
+    /// Last server version seen for a row, `None` if the row isn't stored
+    pub fn row_version(&self, table: SyncTable, id: &str) -> Result<Option<i64>, StoreError> {
+        self.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT version FROM {} WHERE id = ?1", table.as_str()),
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })
+    }
+
+    /// Replace a project's rows with a server snapshot
+    pub fn bootstrap_project(&self, snapshot: &ProjectSnapshot) -> Result<(), StoreError> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "DELETE FROM row_shadows WHERE project_id = ?1",
+                params![snapshot.project_id],
+            )?;
+            for table in SyncTable::ALL {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE project_id = ?1", table.as_str()),
+                    params![snapshot.project_id],
+                )?;
+                for row in snapshot.rows(table) {
+                    put_row(
+                        &tx,
+                        table,
+                        Some(&snapshot.project_id),
+                        row,
+                        Some(snapshot.version),
+                    )?;
+                }
+            }
+            tx.execute(
+                "INSERT INTO sync_meta (project_id, last_sync_version, last_sync_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(project_id) DO UPDATE SET
+                     last_sync_version = excluded.last_sync_version,
+                     last_sync_at = excluded.last_sync_at",
+                params![snapshot.project_id, snapshot.version, snapshot.synced_at],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Apply server activity in one transaction
+    pub fn apply_events(&self, events: &[SyncEvent]) -> Result<(), StoreError> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for event in events {
+                apply_server_event(&tx, event)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Projects with at least one stored row
+    pub fn project_ids(&self) -> Result<Vec<String>, StoreError> {
+        let sql = SyncTable::ALL
+            .iter()
+            .map(|table| format!("SELECT project_id FROM {}", table.as_str()))
+            .collect::<Vec<_>>()
+            .join(" UNION ");
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!("{} ORDER BY 1", sql))?;
+            let ids = stmt.query_map([], |row| row.get(0))?;
+            Ok(ids.collect::<Result<_, _>>()?)
+        })
+    }
+
+    /// The project's rows as a `ProjectSnapshot` at its last synced version
+    ///
+    /// Read in one transaction, so the rows and version are consistent.
+    pub fn snapshot(&self, project_id: &str) -> Result<ProjectSnapshot, StoreError> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            let mut snapshot = ProjectSnapshot {
+                project_id: project_id.to_string(),
+                ..Default::default()
+            };
+            for table in SyncTable::ALL {
+                let rows = list_rows(&tx, table, project_id)?;
+                match table {
+                    SyncTable::Documents => snapshot.documents = rows,
+                    SyncTable::Entities => snapshot.entities = rows,
+                    SyncTable::Relationships => snapshot.relationships = rows,
+                    SyncTable::Mentions => snapshot.mentions = rows,
+                    SyncTable::Analysis => snapshot.analysis = rows,
+                    SyncTable::Captures => snapshot.captures = rows,
+                }
+            }
+            let meta: Option<(i64, Option<String>)> = tx
+                .query_row(
+                    "SELECT last_sync_version, last_sync_at FROM sync_meta WHERE project_id = ?1",
+                    params![project_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            if let Some((version, synced_at)) = meta {
+                snapshot.version = version;
+                snapshot.synced_at = synced_at.unwrap_or_default();
+            }
+            tx.commit()?;
+            Ok(snapshot)
+        })
+    }
+
+    pub fn last_sync_version(&self, project_id: &str) -> Result<i64, StoreError> {
+        self.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT last_sync_version FROM sync_meta WHERE project_id = ?1",
+                    params![project_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .unwrap_or(0))
+        })
+    }
+
+    pub fn set_last_sync_version(
+        &self,
+        project_id: &str,
+        version: i64,
+        synced_at: &str,
+    ) -> Result<(), StoreError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO sync_meta (project_id, last_sync_version, last_sync_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(project_id) DO UPDATE SET
+                     last_sync_version = excluded.last_sync_version,
+                     last_sync_at = excluded.last_sync_at",
+                params![project_id, version, synced_at],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// A stored shell setting, `None` if it was never saved
+    pub fn setting<T: serde::de::DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, StoreError> {
+        self.with_conn(|conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM settings WHERE key = ?1",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+        })
+    }
+
+    pub fn set_setting<T: Serialize>(&self, key: &str, value: &T) -> Result<(), StoreError> {
+        let value = serde_json::to_string(value)?;
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Remove every row and the sync state of a project, including its
+    /// pending mutations, conflicts, echoes and embedding jobs
+    pub fn clear_project(&self, project_id: &str) -> Result<(), StoreError> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "DELETE FROM sync_echoes WHERE json_extract(mutation, '$.projectId') = ?1",
+                params![project_id],
+            )?;
+            for table in SyncTable::ALL {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE project_id = ?1", table.as_str()),
+                    params![project_id],
+                )?;
+            }
+            for meta in ["row_shadows", "sync_meta", "vectors", "outbox", "conflicts"] {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE project_id = ?1", meta),
+                    params![project_id],
+                )?;
+            }
+            // With its row and vector gone a job has nothing left to do, which
+            // also covers jobs of rows deleted before the project was cleared
+            tx.execute(
+                "DELETE FROM embedding_jobs WHERE target_type IN ('document', 'entity')
+                   AND NOT EXISTS (SELECT 1 FROM vectors
+                                   WHERE kind = target_type AND id = target_id)
+                   AND NOT EXISTS (SELECT 1 FROM documents
+                                   WHERE target_type = 'document' AND id = target_id)
+                   AND NOT EXISTS (SELECT 1 FROM entities
+                                   WHERE target_type = 'entity' AND id = target_id)",
+                [],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+}
+
+#[tauri::command]
+pub fn get_local_row(
+    store: State<'_, ProjectStore>,
+    table: SyncTable,
+    id: String,
+) -> Result<Option<Value>, StoreError> {
+    store.get(table, &id)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn list_local_rows(
+    store: State<'_, ProjectStore>,
+    table: SyncTable,
+    project_id: String,
+) -> Result<Vec<Value>, StoreError> {
+    store.list(table, &project_id)
+}
+
+#[tauri::command]
+pub fn upsert_local_row(
+    store: State<'_, ProjectStore>,
+    table: SyncTable,
+    row: Value,
+) -> Result<(), StoreError> {
+    store.upsert(table, &row)
+}
+
+#[tauri::command]
+pub fn delete_local_row(
+    store: State<'_, ProjectStore>,
+    table: SyncTable,
+    id: String,
+) -> Result<bool, StoreError> {
+    store.delete(table, &id)
+}
+
+#[tauri::command]
+pub fn bootstrap_local_project(
+    store: State<'_, ProjectStore>,
+    snapshot: ProjectSnapshot,
+) -> Result<(), StoreError> {
+    store.bootstrap_project(&snapshot)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn get_last_sync_version(
+    store: State<'_, ProjectStore>,
+    project_id: String,
+) -> Result<i64, StoreError> {
+    store.last_sync_version(&project_id)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn set_last_sync_version(
+    store: State<'_, ProjectStore>,
+    project_id: String,
+    version: i64,
+    synced_at: String,
+) -> Result<(), StoreError> {
+    store.set_last_sync_version(&project_id, version, &synced_at)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn clear_local_project(
+    store: State<'_, ProjectStore>,
+    project_id: String,
+) -> Result<(), StoreError> {
+    store.clear_project(&project_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot() -> ProjectSnapshot {
+        ProjectSnapshot {
+            project_id: "p1".into(),
+            version: 7,
+            documents: vec![
+                json!({"id": "ch1", "type": "chapter", "title": "One", "orderIndex": 0}),
+                json!({"id": "sc1", "type": "scene", "parentId": "ch1", "content": {"type": "doc"}}),
+            ],
+            entities: vec![json!({"id": "e1", "name": "Ada", "aliases": ["A"]})],
+            synced_at: "2026-01-01T00:00:00Z".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DB_FILE);
+        ProjectStore::open(&path).unwrap();
+        let store = ProjectStore::open(&path).unwrap();
+        let version = store
+            .with_conn(|conn| Ok(migrations::schema_version(conn)?))
+            .unwrap();
+        assert_eq!(version, migrations::MIGRATIONS.len());
+    }
+
+    #[test]
+    fn errors_serialize_as_code_and_message() {
+        assert_eq!(
+            serde_json::to_value(StoreError::MissingProject("e1".into())).unwrap(),
+            json!({"code": "store_missing_project", "message": "row `e1` has no `projectId`"})
+        );
+    }
+
+    #[test]
+    fn bootstrap_replaces_project_rows_and_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DB_FILE);
+        {
+            let store = ProjectStore::open(&path).unwrap();
+            store
+                .upsert(
+                    SyncTable::Documents,
+                    &json!({"id": "stale", "projectId": "p1"}),
+                )
+                .unwrap();
+            store.bootstrap_project(&snapshot()).unwrap();
+        }
+
+        let store = ProjectStore::open(&path).unwrap();
+        let docs = store.list(SyncTable::Documents, "p1").unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[1]["content"], json!({"type": "doc"}));
+        assert_eq!(docs[1]["projectId"], "p1");
+        assert_eq!(store.get(SyncTable::Documents, "stale").unwrap(), None);
+        assert_eq!(store.last_sync_version("p1").unwrap(), 7);
+        assert_eq!(
+            store.row_version(SyncTable::Entities, "e1").unwrap(),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn events_upsert_and_delete() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        store.bootstrap_project(&snapshot()).unwrap();
+
+        store
+            .apply_events(&[
+                SyncEvent {
+                    id: "ev1".into(),
+                    table: SyncTable::Entities,
+                    kind: MutationType::Upsert,
+                    row: json!({"id": "e1", "projectId": "p1", "name": "Ada L."}),
+                    version: 9,
+                    user_id: "u1".into(),
+                    timestamp: "t".into(),
+                },
+                SyncEvent {
+                    id: "ev2".into(),
+                    table: SyncTable::Documents,
+                    kind: MutationType::Delete,
+                    row: json!({"id": "ch1"}),
+                    version: 10,
+                    user_id: "u1".into(),
+                    timestamp: "t".into(),
+                },
+            ])
+            .unwrap();
+
+        assert_eq!(
+            store.get(SyncTable::Entities, "e1").unwrap().unwrap()["name"],
+            "Ada L."
+        );
+        assert_eq!(
+            store.row_version(SyncTable::Entities, "e1").unwrap(),
+            Some(9)
+        );
+        // Deleting the chapter takes its scene with it
+        assert!(store.list(SyncTable::Documents, "p1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn local_upsert_keeps_server_version_and_requires_project() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        store.bootstrap_project(&snapshot()).unwrap();
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "e1", "projectId": "p1", "name": "B"}),
+            )
+            .unwrap();
+        assert_eq!(
+            store.row_version(SyncTable::Entities, "e1").unwrap(),
+            Some(7)
+        );
+
+        assert!(matches!(
+            store.upsert(SyncTable::Entities, &json!({"id": "e2"})),
+            Err(StoreError::MissingProject(_))
+        ));
+        assert!(matches!(
+            store.upsert(SyncTable::Entities, &json!({"name": "no id"})),
+            Err(StoreError::InvalidRow)
+        ));
+
+        store.clear_project("p1").unwrap();
+        assert!(store.list(SyncTable::Entities, "p1").unwrap().is_empty());
+        assert_eq!(store.last_sync_version("p1").unwrap(), 0);
+    }
+
+    #[test]
+    fn deletes_leave_no_sync_state_behind() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        store.bootstrap_project(&snapshot()).unwrap();
+
+        // A local chapter delete drops its scene's shadow with the scene
+        assert!(store.delete(SyncTable::Documents, "ch1").unwrap());
+        assert_eq!(
+            store
+                .with_conn(|conn| shadow(conn, SyncTable::Documents, "sc1"))
+                .unwrap(),
+            None
+        );
+        assert!(store
+            .with_conn(|conn| shadow(conn, SyncTable::Entities, "e1"))
+            .unwrap()
+            .is_some());
+
+        let count = |table: &str| -> i64 {
+            store
+                .with_conn(|conn| {
+                    Ok(conn.query_row(
+                        &format!("SELECT COUNT(*) FROM {} WHERE project_id = 'p1'", table),
+                        [],
+                        |row| row.get(0),
+                    )?)
+                })
+                .unwrap()
+        };
+        store
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO outbox (id, project_id, tbl, mutation) VALUES ('m1', 'p1', 'entities', '{}');
+                     INSERT INTO conflicts (id, project_id, tbl, pk, strategy, server_version, created_at)
+                     VALUES ('c1', 'p1', 'entities', 'e1', 'manual', 8, 0);",
+                )?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(count("outbox"), 1);
+        assert_eq!(count("conflicts"), 1);
+
+        // Another project's echo and embedding job stay
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "x1", "projectId": "p2", "name": "Other"}),
+            )
+            .unwrap();
+        store
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    r#"INSERT INTO sync_echoes (mutation_id, tbl, pk, mutation)
+                       VALUES ('m1', 'entities', 'e1', '{"id":"m1","projectId":"p1"}'),
+                              ('m2', 'entities', 'x1', '{"id":"m2","projectId":"p2"}');"#,
+                )?;
+                Ok(())
+            })
+            .unwrap();
+        let remaining = |sql: &str| -> Vec<String> {
+            store
+                .with_conn(|conn| {
+                    let mut stmt = conn.prepare(sql)?;
+                    let ids = stmt
+                        .query_map([], |row| row.get(0))?
+                        .collect::<Result<Vec<String>, _>>()?;
+                    Ok(ids)
+                })
+                .unwrap()
+        };
+        assert!(remaining("SELECT target_id FROM embedding_jobs").len() > 1);
+
+        store.clear_project("p1").unwrap();
+        for table in ["outbox", "conflicts", "row_shadows"] {
+            assert_eq!(count(table), 0, "{}", table);
+        }
+        assert_eq!(remaining("SELECT target_id FROM embedding_jobs"), ["x1"]);
+        assert_eq!(remaining("SELECT mutation_id FROM sync_echoes"), ["m2"]);
+    }
+}
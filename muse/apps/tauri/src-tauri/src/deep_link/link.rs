@@ -0,0 +1,306 @@
+//! `RheiDeepLink` parsing and building
+//!
+//! Mirrors `packages/core/src/deeplinks/rhei.ts`:
+//!
+//! ```text
+//! rhei://project/:projectId
+//! rhei://project/:projectId/document/:documentId
+//! rhei://project/:projectId/entity/:entityId
+//! rhei://project/:projectId/artifact/:artifactKey
+//! rhei://project/:projectId/artifact/:artifactKey#focusId
+//! rhei://project/:projectId/artifact/:artifactKey?focus=focusId
+//! ```
+//!
+//! The legacy `mythos://` scheme is accepted on input; built links always use
+//! `rhei://`. Unlike the TS parser, trailing path segments are rejected rather
+//! than ignored.
+
+use std::fmt;
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+pub const SCHEME: &str = "rhei";
+pub const LEGACY_SCHEME: &str = "mythos";
+
+/// Characters escaped by JS `encodeURIComponent`
+pub const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "target",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RheiDeepLink {
+    Project {
+        project_id: String,
+    },
+    Document {
+        project_id: String,
+        document_id: String,
+        focus_id: Option<String>,
+    },
+    Entity {
+        project_id: String,
+        entity_id: String,
+    },
+    Artifact {
+        project_id: String,
+        artifact_key: String,
+        focus_id: Option<String>,
+    },
+}
+
+/// Strip a recognized scheme, returning the remainder
+fn strip_scheme(url: &str) -> Option<&str> {
+    [SCHEME, LEGACY_SCHEME].iter().find_map(|scheme| {
+        url.strip_prefix(scheme)
+            .and_then(|rest| rest.strip_prefix("://"))
+    })
+}
+
+/// Whether a URL uses the `rhei://` or legacy `mythos://` scheme
+pub fn is_rhei_deep_link(url: &str) -> bool {
+    strip_scheme(url).is_some()
+}
+
+fn decode(component: &str) -> Option<String> {
+    let decoded = percent_decode_str(component).decode_utf8().ok()?;
+    if decoded.is_empty() || decoded.chars().any(char::is_control) {
+        return None;
+    }
+    Some(decoded.into_owned())
+}
+
+fn encode(component: &str) -> String {
+    utf8_percent_encode(component, URI_COMPONENT).to_string()
+}
+
+impl RheiDeepLink {
+    /// Parse a `rhei://` or `mythos://` URL, `None` for anything else
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = strip_scheme(url)?;
+
+        let path_end = rest.find(['?', '#']).unwrap_or(rest.len());
+        let path = &rest[..path_end];
+        let hash = rest
+            .find('#')
+            .map(|i| rest[i + 1..].split('?').next().unwrap_or(""));
+        let query = rest
+            .find('?')
+            .map(|i| rest[i + 1..].split('#').next().unwrap_or(""));
+
+        // Prefer hash, fall back to ?focus=
+        let focus_id = hash.filter(|h| !h.is_empty()).and_then(decode).or_else(|| {
+            query.and_then(|q| {
+                url::form_urlencoded::parse(q.as_bytes())
+                    .find(|(key, _)| key == "focus")
+                    .map(|(_, value)| value.into_owned())
+                    .filter(|v| !v.is_empty() && !v.chars().any(char::is_control))
+            })
+        });
+
+        let path = path.strip_suffix('/').unwrap_or(path);
+        let segments: Vec<&str> = path.split('/').collect();
+
+        match segments.as_slice() {
+            ["project", project_id] => Some(Self::Project {
+                project_id: decode(project_id)?,
+            }),
+            ["project", project_id, "document", document_id] => Some(Self::Document {
+                project_id: decode(project_id)?,
+                document_id: decode(document_id)?,
+                focus_id,
+            }),
+            ["project", project_id, "entity", entity_id] => Some(Self::Entity {
+                project_id: decode(project_id)?,
+                entity_id: decode(entity_id)?,
+            }),
+            ["project", project_id, "artifact", artifact_key] => Some(Self::Artifact {
+                project_id: decode(project_id)?,
+                artifact_key: decode(artifact_key)?,
+                focus_id,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn project_id(&self) -> &str {
+        match self {
+            Self::Project { project_id }
+            | Self::Document { project_id, .. }
+            | Self::Entity { project_id, .. }
+            | Self::Artifact { project_id, .. } => project_id,
+        }
+    }
+}
+
+/// Builds the canonical `rhei://` URL (equivalent to `buildRheiUrl`)
+impl fmt::Display for RheiDeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://project/{}", SCHEME, encode(self.project_id()))?;
+        let focus = match self {
+            Self::Project { .. } => None,
+            Self::Document {
+                document_id,
+                focus_id,
+                ..
+            } => {
+                write!(f, "/document/{}", encode(document_id))?;
+                focus_id.as_ref()
+            }
+            Self::Entity { entity_id, .. } => {
+                write!(f, "/entity/{}", encode(entity_id))?;
+                None
+            }
+            Self::Artifact {
+                artifact_key,
+                focus_id,
+                ..
+            } => {
+                write!(f, "/artifact/{}", encode(artifact_key))?;
+                focus_id.as_ref()
+            }
+        };
+        match focus {
+            Some(focus) => write!(f, "#{}", encode(focus)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parses_all_targets() {
+        assert_eq!(
+            RheiDeepLink::parse("rhei://project/p1"),
+            Some(RheiDeepLink::Project {
+                project_id: "p1".into()
+            })
+        );
+        assert_eq!(
+            RheiDeepLink::parse("rhei://project/p1/document/d1#block-7"),
+            Some(RheiDeepLink::Document {
+                project_id: "p1".into(),
+                document_id: "d1".into(),
+                focus_id: Some("block-7".into()),
+            })
+        );
+        assert_eq!(
+            RheiDeepLink::parse("rhei://project/p1/entity/e1"),
+            Some(RheiDeepLink::Entity {
+                project_id: "p1".into(),
+                entity_id: "e1".into(),
+            })
+        );
+        assert_eq!(
+            RheiDeepLink::parse("rhei://project/p1/artifact/a%20b?focus=row%3A3"),
+            Some(RheiDeepLink::Artifact {
+                project_id: "p1".into(),
+                artifact_key: "a b".into(),
+                focus_id: Some("row:3".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn hash_focus_wins_over_query() {
+        let link = RheiDeepLink::parse("rhei://project/p/document/d?focus=q#h").unwrap();
+        assert_eq!(
+            link,
+            RheiDeepLink::Document {
+                project_id: "p".into(),
+                document_id: "d".into(),
+                focus_id: Some("h".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn accepts_legacy_scheme_and_builds_rhei() {
+        let link = RheiDeepLink::parse("mythos://project/p1/entity/e1").unwrap();
+        assert_eq!(link.to_string(), "rhei://project/p1/entity/e1");
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for url in [
+            "",
+            "https://rhei.team/project/p1",
+            "rhei://",
+            "rhei://project",
+            "rhei://project/",
+            "rhei://project//document/d",
+            "rhei://project/p1/document/d1/extra",
+            "rhei://project/p1/chapter/c1",
+            "rhei://auth/callback?code=abc",
+            "rhei://project/%FF",
+            "rhei://project/a%00b",
+        ] {
+            assert_eq!(RheiDeepLink::parse(url), None, "{url}");
+        }
+    }
+
+    fn component() -> impl Strategy<Value = String> {
+        "[^\\p{Cc}]{1,24}"
+    }
+
+    fn deep_link() -> impl Strategy<Value = RheiDeepLink> {
+        prop_oneof![
+            component().prop_map(|project_id| RheiDeepLink::Project { project_id }),
+            (component(), component(), proptest::option::of(component())).prop_map(
+                |(project_id, document_id, focus_id)| RheiDeepLink::Document {
+                    project_id,
+                    document_id,
+                    focus_id,
+                }
+            ),
+            (component(), component()).prop_map(|(project_id, entity_id)| {
+                RheiDeepLink::Entity {
+                    project_id,
+                    entity_id,
+                }
+            }),
+            (component(), component(), proptest::option::of(component())).prop_map(
+                |(project_id, artifact_key, focus_id)| RheiDeepLink::Artifact {
+                    project_id,
+                    artifact_key,
+                    focus_id,
+                }
+            ),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn build_then_parse_round_trips(link in deep_link()) {
+            prop_assert_eq!(RheiDeepLink::parse(&link.to_string()), Some(link));
+        }
+
+        #[test]
+        fn parse_never_panics(url in "\\PC*") {
+            let _ = RheiDeepLink::parse(&url);
+        }
+
+        #[test]
+        fn parsed_links_rebuild_to_equivalent_links(url in "(rhei|mythos)://project/[a-z0-9%]{1,8}(/(document|entity|artifact)/[a-z0-9%]{1,8})?(#[a-z0-9]{0,4})?") {
+            if let Some(link) = RheiDeepLink::parse(&url) {
+                prop_assert_eq!(RheiDeepLink::parse(&link.to_string()), Some(link));
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Deep link routing
+//!
+//! URLs delivered by the OS are classified here before anything reaches the
+//! webview. Navigation links are parsed into `RheiDeepLink` and emitted as
+//! typed events, OAuth callbacks are checked against the `state` values the
+//! shell issued (see `oauth`) and forwarded only for Convex Auth redirects,
+//! and everything else is dropped. Links that arrive before the frontend listens
+//! are held in `pending`.
+
+pub mod link;
+pub mod pending;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+pub use link::RheiDeepLink;
+use pending::PendingDeepLinks;
+
+use crate::oauth::{CallbackDisposition, OAuthFlows};
+
+/// Event carrying a parsed `RheiDeepLink`
+pub const NAVIGATE_EVENT: &str = "deep-link://navigate";
+
+/// Event carrying raw OAuth callback URLs (see `src/lib/auth.ts`)
+pub const AUTH_CALLBACK_EVENT: &str = "deep-link://auth-callback";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkRoute {
+    Navigate(RheiDeepLink),
+    AuthCallback(String),
+}
+
+/// Whether a URL is an OAuth callback (`rhei://auth/callback?...`)
+pub fn is_auth_callback(url: &str) -> bool {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), link::SCHEME | link::LEGACY_SCHEME) {
+        return false;
+    }
+    parsed.host_str() == Some("auth") && parsed.path() == "/callback"
+}
+
+/// Classify a URL, `None` if it should be dropped
+pub fn route_url(url: &str) -> Option<DeepLinkRoute> {
+    if is_auth_callback(url) {
+        return Some(DeepLinkRoute::AuthCallback(url.to_string()));
+    }
+    RheiDeepLink::parse(url).map(DeepLinkRoute::Navigate)
+}
+
+/// Deliver a routed link to the frontend
+pub fn dispatch(app: &AppHandle, route: &DeepLinkRoute) -> tauri::Result<()> {
+    match route {
+        DeepLinkRoute::Navigate(link) => app.emit(NAVIGATE_EVENT, link),
+        DeepLinkRoute::AuthCallback(url) => app.emit(AUTH_CALLBACK_EVENT, url),
+    }
+}
+
+/// Route and deliver URLs received from the OS, queueing them if the
+/// frontend isn't listening yet
+pub fn handle_urls<'a>(app: &AppHandle, urls: impl IntoIterator<Item = &'a str>) {
+    let pending = app.state::<PendingDeepLinks>();
+    let oauth = app.state::<OAuthFlows>();
+    for url in urls {
+        let Some(route) = route_url(url) else {
+            eprintln!("[deep-link] Dropping unrecognized URL: {}", url);
+            continue;
+        };
+        if let DeepLinkRoute::AuthCallback(callback) = &route {
+            match oauth.accept_callback(callback) {
+                CallbackDisposition::Forward => {}
+                CallbackDisposition::Accepted => continue,
+                CallbackDisposition::Rejected(e) => {
+                    eprintln!("[deep-link] Rejecting auth callback: {}", e);
+                    continue;
+                }
+            }
+        }
+        if let Some(route) = pending.offer(route) {
+            if let Err(e) = dispatch(app, &route) {
+                eprintln!("[deep-link] Failed to emit {}: {}", url, e);
+            }
+        }
+    }
+}
+
+/// Parses a deep link URL for the frontend, `None` if it isn't a valid link
+#[tauri::command]
+pub fn parse_deep_link(url: String) -> Option<RheiDeepLink> {
+    RheiDeepLink::parse(&url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_only_the_auth_callback() {
+        for url in [
+            "rhei://auth/callback?code=c&state=s",
+            "rhei://auth/callback",
+            "mythos://auth/callback?code=c",
+        ] {
+            assert!(is_auth_callback(url), "{url}");
+        }
+        for url in [
+            "rhei://project/callback",
+            "rhei://project/p1/document/callback",
+            "rhei://project/auth/callback",
+            "rhei://auth/callback/extra",
+            "rhei://auth/other",
+            "https://auth/callback",
+            "not a url",
+        ] {
+            assert!(!is_auth_callback(url), "{url}");
+        }
+        assert!(matches!(
+            route_url("rhei://project/callback"),
+            Some(DeepLinkRoute::Navigate(RheiDeepLink::Project { project_id })) if project_id == "callback"
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+//! Pending deep link queue
+//!
+//! A cold launch via `rhei://` can deliver URLs before the React listeners in
+//! `useDeepLinks` / `src/lib/auth.ts` are registered, and an emitted event with
+//! no listener is lost. Links are therefore queued per consumer until that
+//! consumer drains the queue with `take_pending_*`, after which they are
+//! emitted live. A page reload puts every consumer back into queueing mode.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::{DeepLinkRoute, RheiDeepLink};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeepLinkKind {
+    Navigate,
+    AuthCallback,
+}
+
+impl DeepLinkRoute {
+    pub fn kind(&self) -> DeepLinkKind {
+        match self {
+            Self::Navigate(_) => DeepLinkKind::Navigate,
+            Self::AuthCallback(_) => DeepLinkKind::AuthCallback,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PendingState {
+    queued: Vec<DeepLinkRoute>,
+    ready: HashSet<DeepLinkKind>,
+}
+
+#[derive(Default)]
+pub struct PendingDeepLinks {
+    state: Mutex<PendingState>,
+}
+
+impl PendingDeepLinks {
+    /// Queue a route if its consumer isn't listening yet
+    ///
+    /// Returns the route back when it should be dispatched immediately.
+    pub fn offer(&self, route: DeepLinkRoute) -> Option<DeepLinkRoute> {
+        let mut state = self.state.lock().unwrap();
+        if state.ready.contains(&route.kind()) {
+            return Some(route);
+        }
+        state.queued.push(route);
+        None
+    }
+
+    /// Take queued routes of one kind, in arrival order, and mark it ready
+    pub fn drain(&self, kind: DeepLinkKind) -> Vec<DeepLinkRoute> {
+        let mut state = self.state.lock().unwrap();
+        state.ready.insert(kind);
+        let (taken, kept) = std::mem::take(&mut state.queued)
+            .into_iter()
+            .partition(|route| route.kind() == kind);
+        state.queued = kept;
+        taken
+    }
+
+    /// Forget listener readiness (the frontend reloaded)
+    pub fn reset(&self) {
+        self.state.lock().unwrap().ready.clear();
+    }
+}
+
+/// Returns navigation links received before the frontend was listening
+#[tauri::command]
+pub fn take_pending_deep_links(pending: State<'_, PendingDeepLinks>) -> Vec<RheiDeepLink> {
+    pending
+        .drain(DeepLinkKind::Navigate)
+        .into_iter()
+        .filter_map(|route| match route {
+            DeepLinkRoute::Navigate(link) => Some(link),
+            DeepLinkRoute::AuthCallback(_) => None,
+        })
+        .collect()
+}
+
+/// Returns OAuth callback URLs received before the frontend was listening
+#[tauri::command]
+pub fn take_pending_auth_callbacks(pending: State<'_, PendingDeepLinks>) -> Vec<String> {
+    pending
+        .drain(DeepLinkKind::AuthCallback)
+        .into_iter()
+        .filter_map(|route| match route {
+            DeepLinkRoute::AuthCallback(url) => Some(url),
+            DeepLinkRoute::Navigate(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn navigate(project_id: &str) -> DeepLinkRoute {
+        DeepLinkRoute::Navigate(RheiDeepLink::Project {
+            project_id: project_id.into(),
+        })
+    }
+
+    fn callback(state: &str) -> DeepLinkRoute {
+        DeepLinkRoute::AuthCallback(format!("rhei://auth/callback?code=c&state={}", state))
+    }
+
+    #[test]
+    fn queues_until_drained_then_delivers() {
+        let pending = PendingDeepLinks::default();
+        assert_eq!(pending.offer(navigate("p1")), None);
+        assert_eq!(pending.offer(callback("s1")), None);
+        assert_eq!(pending.offer(navigate("p2")), None);
+
+        assert_eq!(
+            pending.drain(DeepLinkKind::Navigate),
+            vec![navigate("p1"), navigate("p2")]
+        );
+        assert_eq!(pending.offer(navigate("p3")), Some(navigate("p3")));
+        assert_eq!(pending.drain(DeepLinkKind::Navigate), vec![]);
+
+        // The auth consumer hasn't drained yet, so its link stays queued
+        assert_eq!(pending.offer(callback("s2")), None);
+        assert_eq!(
+            pending.drain(DeepLinkKind::AuthCallback),
+            vec![callback("s1"), callback("s2")]
+        );
+        assert_eq!(pending.offer(callback("s3")), Some(callback("s3")));
+    }
+
+    #[test]
+    fn requeues_after_reset() {
+        let pending = PendingDeepLinks::default();
+        pending.drain(DeepLinkKind::Navigate);
+        pending.drain(DeepLinkKind::AuthCallback);
+        assert!(pending.offer(navigate("p1")).is_some());
+
+        pending.reset();
+        assert_eq!(pending.offer(navigate("p2")), None);
+        assert_eq!(pending.offer(callback("s1")), None);
+        assert_eq!(pending.drain(DeepLinkKind::Navigate), vec![navigate("p2")]);
+        assert_eq!(
+            pending.drain(DeepLinkKind::AuthCallback),
+            vec![callback("s1")]
+        );
+    }
+}
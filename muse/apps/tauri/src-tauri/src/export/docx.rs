@@ -0,0 +1,723 @@
+//! DOCX manuscripts in standard submission format
+//!
+//! The layout follows William Shunn's manuscript format: a title page with
+//! contact details, an approximate word count, the title and byline; then
+//! double-spaced 12pt text with indented paragraphs, one-inch margins and a
+//! `Surname / TITLE / page` header from page two on. Every chapter starts on a
+//! new page a third of the way down. Scene breaks (consecutive scene blocks,
+//! horizontal rules, scene documents within a chapter) become a centred `#`,
+//! and the text ends with `END`.
+//!
+//! Styles carry the formatting, so editors can restyle the file in Word.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use super::{manuscript, write_zip, ExportError, ManuscriptDocument};
+use crate::db::{ProjectSnapshot, ProjectStore};
+use crate::folder::html;
+use crate::sync::outbox::iso_timestamp;
+
+const NS_W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Right edge of the text area (6.5in in twips), for the title page tab
+const TEXT_WIDTH: u32 = 9360;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="xml" ContentType="application/xml"/>
+  <Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+  <Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>
+  <Override PartName="/word/settings.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.settings+xml"/>
+  <Override PartName="/word/header1.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.header+xml"/>
+  <Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+  <Override PartName="/docProps/app.xml" ContentType="application/vnd.openxmlformats-officedocument.extended-properties+xml"/>
+</Types>
+"#;
+
+const PACKAGE_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+  <Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties" Target="docProps/app.xml"/>
+</Relationships>
+"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/settings" Target="settings.xml"/>
+  <Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/>
+</Relationships>
+"#;
+
+const SETTINGS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:settings xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:defaultTabStop w:val="720"/>
+  <w:characterSpacingControl w:val="doNotCompress"/>
+</w:settings>
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DocxOptions {
+    /// Manuscript title; `Untitled` when empty
+    pub title: String,
+    /// Legal name, first line of the contact block
+    pub author: String,
+    /// Byline; the author's name when empty
+    pub pen_name: Option<String>,
+    /// Address, phone, email and the like, one per line
+    pub contact: Vec<String>,
+    /// Header surname; the byline's last word when empty
+    pub surname: Option<String>,
+    /// Header title keyword; the title in capitals when empty
+    pub keyword: Option<String>,
+    /// Underline instead of italics, as typewritten manuscripts did
+    pub italics_as_underline: bool,
+    pub font: String,
+    /// Export only these documents instead of the whole manuscript
+    pub document_ids: Option<Vec<String>>,
+}
+
+impl Default for DocxOptions {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            author: String::new(),
+            pen_name: None,
+            contact: Vec::new(),
+            surname: None,
+            keyword: None,
+            italics_as_underline: false,
+            font: "Times New Roman".to_string(),
+            document_ids: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocxSummary {
+    pub project_id: String,
+    pub documents: usize,
+    pub chapters: usize,
+    pub scene_breaks: usize,
+    /// Exact count; the title page shows it rounded
+    pub words: usize,
+    pub bytes: u64,
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    html::escape(text, &mut out);
+    out
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Word count as a submission states it: to the nearest hundred for short
+/// fiction, the nearest thousand from novelette length up
+pub fn rounded_word_count(words: usize) -> String {
+    let step = if words < 17_500 { 100 } else { 1000 };
+    let rounded = ((words + step / 2) / step * step).max(step.min(words));
+    let digits = rounded.to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("about {} words", grouped)
+}
+
+/// A run of plain text; tabs and newlines become Word's own elements
+fn text_run(properties: &str, text: &str) -> String {
+    let mut out = String::from("<w:r>");
+    if !properties.is_empty() {
+        out.push_str(&format!("<w:rPr>{}</w:rPr>", properties));
+    }
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push_str("<w:br/>");
+        }
+        for (j, part) in line.split('\t').enumerate() {
+            if j > 0 {
+                out.push_str("<w:tab/>");
+            }
+            if !part.is_empty() {
+                out.push_str(&format!(
+                    "<w:t xml:space=\"preserve\">{}</w:t>",
+                    escape(part)
+                ));
+            }
+        }
+    }
+    out.push_str("</w:r>");
+    out
+}
+
+fn paragraph(style: Option<&str>, runs: &str) -> String {
+    match style {
+        Some(style) => format!(
+            "<w:p><w:pPr><w:pStyle w:val=\"{}\"/></w:pPr>{}</w:p>",
+            style, runs
+        ),
+        None => format!("<w:p>{}</w:p>", runs),
+    }
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Builds `word/document.xml`'s body
+struct Body<'a> {
+    options: &'a DocxOptions,
+    xml: String,
+    /// Text has been written since the chapter began
+    chapter_has_text: bool,
+    /// A scene break is owed before the next text
+    break_pending: bool,
+    chapters: usize,
+    scene_breaks: usize,
+}
+
+impl Body<'_> {
+    fn runs(&self, nodes: &[Value]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            match node_type(node) {
+                "text" => {
+                    let marks: Vec<&str> = node
+                        .get("marks")
+                        .and_then(Value::as_array)
+                        .map_or(&[][..], Vec::as_slice)
+                        .iter()
+                        .map(node_type)
+                        .collect();
+                    let has = |mark: &str| marks.contains(&mark);
+                    let italic = has("italic") && !self.options.italics_as_underline;
+                    let underline =
+                        has("underline") || (has("italic") && self.options.italics_as_underline);
+                    // Property order is fixed by the schema
+                    let mut properties = String::new();
+                    if has("code") {
+                        properties.push_str(r#"<w:rFonts w:ascii="Courier New" w:hAnsi="Courier New" w:cs="Courier New"/>"#);
+                    }
+                    if has("bold") {
+                        properties.push_str("<w:b/>");
+                    }
+                    if italic {
+                        properties.push_str("<w:i/>");
+                    }
+                    if has("strike") {
+                        properties.push_str("<w:strike/>");
+                    }
+                    if underline {
+                        properties.push_str(r#"<w:u w:val="single"/>"#);
+                    }
+                    let text = node.get("text").and_then(Value::as_str).unwrap_or_default();
+                    out.push_str(&text_run(&properties, text));
+                }
+                "hardBreak" => out.push_str("<w:r><w:br/></w:r>"),
+                _ => out.push_str(&self.runs(children(node))),
+            }
+        }
+        out
+    }
+
+    fn chapter(&mut self, title: &str) {
+        self.xml
+            .push_str(&paragraph(Some("ChapterTitle"), &text_run("", title)));
+        self.chapters += 1;
+        self.chapter_has_text = false;
+        self.break_pending = false;
+    }
+
+    /// Body text, after any scene break it is owed
+    fn text(&mut self, style: Option<&str>, runs: &str) {
+        if self.break_pending && self.chapter_has_text {
+            self.xml
+                .push_str(&paragraph(Some("SceneBreak"), &text_run("", "#")));
+            self.scene_breaks += 1;
+        }
+        self.break_pending = false;
+        self.chapter_has_text = true;
+        self.xml.push_str(&paragraph(style, runs));
+    }
+
+    fn blocks(&mut self, nodes: &[Value], style: Option<&str>) {
+        for node in nodes {
+            self.block(node, style);
+        }
+    }
+
+    fn block(&mut self, node: &Value, style: Option<&str>) {
+        match node_type(node) {
+            "paragraph" => {
+                let runs = self.runs(children(node));
+                self.text(style, &runs);
+            }
+            "heading" => {
+                let runs = self.runs(children(node));
+                self.text(Some("SectionHeading"), &runs);
+            }
+            "blockquote" => self.blocks(children(node), Some("BlockQuote")),
+            "bulletList" | "orderedList" | "taskList" => {
+                let mut number = node
+                    .get("attrs")
+                    .and_then(|a| a.get("start"))
+                    .and_then(Value::as_u64)
+                    .unwrap_or(1);
+                for item in children(node) {
+                    let marker = match node_type(node) {
+                        "orderedList" => {
+                            number += 1;
+                            format!("{}.", number - 1)
+                        }
+                        "taskList" => {
+                            let checked = item
+                                .get("attrs")
+                                .and_then(|a| a.get("checked"))
+                                .and_then(Value::as_bool)
+                                == Some(true);
+                            if checked { "\u{2612}" } else { "\u{2610}" }.to_string()
+                        }
+                        _ => "\u{2022}".to_string(),
+                    };
+                    self.list_item(item, marker);
+                }
+            }
+            "codeBlock" => {
+                let code = html::plain_text(node);
+                let runs = text_run(
+                    r#"<w:rFonts w:ascii="Courier New" w:hAnsi="Courier New" w:cs="Courier New"/>"#,
+                    &code,
+                );
+                self.text(Some("Code"), &runs);
+            }
+            "sceneBlock" => {
+                self.break_pending = true;
+                self.blocks(children(node), style);
+            }
+            "horizontalRule" => self.break_pending = true,
+            "image" => {}
+            _ if children(node).iter().any(|c| node_type(c) == "text") => {
+                let runs = self.runs(children(node));
+                self.text(style, &runs);
+            }
+            _ => self.blocks(children(node), style),
+        }
+    }
+
+    /// The marker leads the item's first paragraph
+    fn list_item(&mut self, item: &Value, marker: String) {
+        let mut marker = Some(text_run("", &format!("{}\t", marker)));
+        for child in children(item) {
+            if node_type(child) == "paragraph" {
+                let runs = format!(
+                    "{}{}",
+                    marker.take().unwrap_or_default(),
+                    self.runs(children(child))
+                );
+                self.text(Some("ListParagraph"), &runs);
+            } else {
+                self.block(child, Some("ListParagraph"));
+            }
+        }
+        if let Some(marker) = marker {
+            self.text(Some("ListParagraph"), &marker);
+        }
+    }
+
+    fn document(&mut self, document: &ManuscriptDocument) {
+        let mut nodes = children(&document.content);
+        if document.kind == "scene" {
+            // Scene titles are for the binder, not the manuscript
+            self.break_pending = true;
+        } else {
+            self.chapter(&document.title);
+        }
+        if document.opens_with_title() {
+            nodes = &nodes[1..];
+        }
+        self.blocks(nodes, None);
+    }
+}
+
+fn styles(font: &str) -> String {
+    let font = escape(font);
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="{w}">
+  <w:docDefaults>
+    <w:rPrDefault><w:rPr><w:rFonts w:ascii="{font}" w:hAnsi="{font}" w:eastAsia="{font}" w:cs="{font}"/><w:sz w:val="24"/><w:szCs w:val="24"/><w:lang w:val="en-US"/></w:rPr></w:rPrDefault>
+    <w:pPrDefault><w:pPr><w:widowControl/><w:spacing w:before="0" w:after="0" w:line="480" w:lineRule="auto"/></w:pPr></w:pPrDefault>
+  </w:docDefaults>
+  <w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/><w:pPr><w:ind w:firstLine="720"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="ContactInfo"><w:name w:val="Contact Info"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:line="240" w:lineRule="auto"/><w:ind w:firstLine="0"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:next w:val="Byline"/><w:qFormat/><w:pPr><w:spacing w:before="3600"/><w:ind w:firstLine="0"/><w:jc w:val="center"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Byline"><w:name w:val="Byline"/><w:basedOn w:val="Normal"/><w:pPr><w:ind w:firstLine="0"/><w:jc w:val="center"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="ChapterTitle"><w:name w:val="Chapter Title"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:pageBreakBefore/><w:spacing w:before="2880" w:after="480"/><w:ind w:firstLine="0"/><w:jc w:val="center"/><w:outlineLvl w:val="0"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="SectionHeading"><w:name w:val="Section Heading"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:ind w:firstLine="0"/><w:jc w:val="center"/><w:outlineLvl w:val="1"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="SceneBreak"><w:name w:val="Scene Break"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:ind w:firstLine="0"/><w:jc w:val="center"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="BlockQuote"><w:name w:val="Block Quote"/><w:basedOn w:val="Normal"/><w:pPr><w:ind w:left="720" w:right="720" w:firstLine="0"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="ListParagraph"><w:name w:val="List Paragraph"/><w:basedOn w:val="Normal"/><w:pPr><w:ind w:left="1080" w:hanging="360"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Code"><w:name w:val="Code"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:line="240" w:lineRule="auto"/><w:ind w:left="720" w:firstLine="0"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Header"><w:name w:val="header"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:line="240" w:lineRule="auto"/><w:ind w:firstLine="0"/><w:jc w:val="right"/></w:pPr></w:style>
+</w:styles>
+"#,
+        w = NS_W,
+        font = font
+    )
+}
+
+/// `Surname / KEYWORD / ` followed by the page number
+fn header(surname: &str, keyword: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:hdr xmlns:w="{}"><w:p><w:pPr><w:pStyle w:val="Header"/></w:pPr>{}<w:fldSimple w:instr=" PAGE "><w:r><w:t>2</w:t></w:r></w:fldSimple></w:p></w:hdr>
+"#,
+        NS_W,
+        text_run("", &format!("{} / {} / ", surname, keyword))
+    )
+}
+
+fn title_page(options: &DocxOptions, title: &str, byline: &str, words: usize) -> String {
+    let mut xml = String::new();
+    let tab = format!(
+        r#"<w:tabs><w:tab w:val="right" w:pos="{}"/></w:tabs>"#,
+        TEXT_WIDTH
+    );
+    xml.push_str(&format!(
+        r#"<w:p><w:pPr><w:pStyle w:val="ContactInfo"/>{}</w:pPr>{}{}</w:p>"#,
+        tab,
+        text_run("", options.author.trim()),
+        text_run("", &format!("\t{}", rounded_word_count(words)))
+    ));
+    for line in options
+        .contact
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+    {
+        xml.push_str(&paragraph(Some("ContactInfo"), &text_run("", line)));
+    }
+    xml.push_str(&paragraph(Some("Title"), &text_run("", title)));
+    if !byline.is_empty() {
+        xml.push_str(&paragraph(
+            Some("Byline"),
+            &text_run("", &format!("by {}", byline)),
+        ));
+    }
+    xml
+}
+
+/// Write the manuscript as a Word document at `dest`
+pub fn write_docx(
+    snapshot: &ProjectSnapshot,
+    options: &DocxOptions,
+    dest: &Path,
+    now_ms: i64,
+) -> Result<DocxSummary, ExportError> {
+    let documents = manuscript(snapshot, options.document_ids.as_deref());
+    if documents.is_empty() {
+        return Err(ExportError::EmptyManuscript);
+    }
+    let title = non_empty(Some(&options.title)).unwrap_or("Untitled");
+    let byline = non_empty(options.pen_name.as_deref()).unwrap_or(options.author.trim());
+    let surname = non_empty(options.surname.as_deref())
+        .or_else(|| byline.split_whitespace().last())
+        .unwrap_or_default();
+    let keyword = non_empty(options.keyword.as_deref())
+        .map(str::to_string)
+        .unwrap_or_else(|| title.to_uppercase());
+    let font = non_empty(Some(&options.font)).unwrap_or("Times New Roman");
+    let words = documents.iter().map(ManuscriptDocument::word_count).sum();
+
+    let mut body = Body {
+        options,
+        xml: title_page(options, title, byline, words),
+        chapter_has_text: false,
+        break_pending: false,
+        chapters: 0,
+        scene_breaks: 0,
+    };
+    // A story told in scenes alone still opens on its own page
+    if documents[0].kind == "scene" {
+        body.chapter(title);
+    }
+    for document in &documents {
+        body.document(document);
+    }
+    body.xml
+        .push_str(&paragraph(Some("SceneBreak"), &text_run("", "END")));
+
+    let document_xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="{}" xmlns:r="{}"><w:body>{}<w:sectPr><w:headerReference w:type="default" r:id="rId3"/><w:pgSz w:w="12240" w:h="15840"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="720" w:footer="720" w:gutter="0"/><w:titlePg/></w:sectPr></w:body></w:document>
+"#,
+        NS_W, NS_R, body.xml
+    );
+    let timestamp = format!("{}Z", &iso_timestamp(now_ms)[..19]);
+    let core = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <dc:title>{}</dc:title>
+  <dc:creator>{}</dc:creator>
+  <dcterms:created xsi:type="dcterms:W3CDTF">{t}</dcterms:created>
+  <dcterms:modified xsi:type="dcterms:W3CDTF">{t}</dcterms:modified>
+</cp:coreProperties>
+"#,
+        escape(title),
+        escape(byline),
+        t = timestamp
+    );
+    let app = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties"><Application>Rhei</Application><Words>{}</Words></Properties>
+"#,
+        words
+    );
+
+    let entries = vec![
+        ("[Content_Types].xml".to_string(), CONTENT_TYPES.into()),
+        ("_rels/.rels".to_string(), PACKAGE_RELS.into()),
+        (
+            "word/_rels/document.xml.rels".to_string(),
+            DOCUMENT_RELS.into(),
+        ),
+        ("word/document.xml".to_string(), document_xml.into_bytes()),
+        ("word/styles.xml".to_string(), styles(font).into_bytes()),
+        ("word/settings.xml".to_string(), SETTINGS.into()),
+        (
+            "word/header1.xml".to_string(),
+            header(surname, &keyword).into_bytes(),
+        ),
+        ("docProps/core.xml".to_string(), core.into_bytes()),
+        ("docProps/app.xml".to_string(), app.into_bytes()),
+    ];
+    write_zip(dest, &entries)?;
+
+    Ok(DocxSummary {
+        project_id: snapshot.project_id.clone(),
+        documents: documents.len(),
+        chapters: body.chapters,
+        scene_breaks: body.scene_breaks,
+        words,
+        bytes: fs::metadata(dest)?.len(),
+    })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_docx(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+    options: DocxOptions,
+) -> Result<DocxSummary, ExportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let snapshot = app.state::<ProjectStore>().snapshot(&project_id)?;
+        write_docx(
+            &snapshot,
+            &options,
+            Path::new(&path),
+            crate::sync::outbox::now_ms(),
+        )
+    })
+    .await
+    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{entry, parse_xml};
+    use serde_json::json;
+
+    fn paragraph(text: &str) -> Value {
+        json!({"type": "paragraph", "content": [{"type": "text", "text": text}]})
+    }
+
+    fn snapshot() -> ProjectSnapshot {
+        let words = vec!["word"; 1180].join(" ");
+        ProjectSnapshot {
+            project_id: "p1".into(),
+            version: 1,
+            documents: vec![
+                json!({"id": "c1", "type": "chapter", "title": "The Storm", "orderIndex": 0,
+                "content": {"type": "doc", "content": [
+                    {"type": "heading", "attrs": {"level": 1}, "content": [{"type": "text", "text": "The Storm"}]},
+                    {"type": "paragraph", "content": [
+                        {"type": "text", "text": "It broke "},
+                        {"type": "text", "text": "at dawn", "marks": [{"type": "italic"}]},
+                        {"type": "text", "text": " & <fast>."}
+                    ]},
+                    {"type": "horizontalRule"},
+                    paragraph(&words)
+                ]}}),
+                json!({"id": "s1", "type": "scene", "title": "Dock", "parentId": "c1", "orderIndex": 0,
+                "content": {"type": "doc", "content": [
+                    {"type": "sceneBlock", "attrs": {"sceneId": "a"}, "content": [paragraph("Kael ran.")]},
+                    {"type": "sceneBlock", "attrs": {"sceneId": "b"}, "content": [paragraph("Quiet.")]}
+                ]}}),
+                json!({"id": "c2", "type": "chapter", "title": "Calm", "orderIndex": 1,
+                "content": {"type": "doc", "content": [
+                    {"type": "sceneBlock", "attrs": {"sceneId": "c"}, "content": [paragraph("The sea settled.")]},
+                    {"type": "orderedList", "attrs": {"start": 3}, "content": [
+                        {"type": "listItem", "content": [paragraph("Third")]}
+                    ]}
+                ]}}),
+                json!({"id": "n1", "type": "note", "title": "Research", "orderIndex": 2,
+                       "content": {"type": "doc", "content": [paragraph("Secret.")]}}),
+            ],
+            entities: vec![],
+            relationships: vec![],
+            mentions: vec![],
+            analysis: vec![],
+            captures: vec![],
+            synced_at: String::new(),
+        }
+    }
+
+    /// Paragraph styles and texts of the body, in order
+    fn outline(dest: &Path) -> Vec<String> {
+        let elements = parse_xml(&entry(dest, "word/document.xml")).unwrap();
+        let mut out: Vec<String> = Vec::new();
+        for e in &elements {
+            match e.name.as_str() {
+                "w:p" => out.push(String::new()),
+                "w:pStyle" => out
+                    .last_mut()
+                    .unwrap()
+                    .push_str(&format!("{}:", e.attr("w:val"))),
+                "w:t" => out.last_mut().unwrap().push_str(&e.text),
+                // Tab characters, not tab stops
+                "w:tab" if e.attr("w:pos").is_empty() => out.last_mut().unwrap().push('\t'),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn writes_a_standard_manuscript() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("tides.docx");
+        let options = DocxOptions {
+            title: "Tides".into(),
+            author: "Ada Vell".into(),
+            pen_name: Some("A. V. Storm".into()),
+            contact: vec!["1 Quay St".into(), "ada@example.com".into()],
+            ..DocxOptions::default()
+        };
+        let summary = write_docx(&snapshot(), &options, &dest, 1_760_500_000_123).unwrap();
+        assert_eq!(
+            (
+                summary.documents,
+                summary.chapters,
+                summary.scene_breaks,
+                summary.words
+            ),
+            (3, 2, 3, 1195)
+        );
+
+        let body = outline(&dest);
+        let text: Vec<&str> = body
+            .iter()
+            .map(String::as_str)
+            .filter(|p| p.len() < 80)
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "ContactInfo:Ada Vell\tabout 1,200 words",
+                "ContactInfo:1 Quay St",
+                "ContactInfo:ada@example.com",
+                "Title:Tides",
+                "Byline:by A. V. Storm",
+                "ChapterTitle:The Storm",
+                "It broke at dawn & <fast>.",
+                "SceneBreak:#",
+                "SceneBreak:#",
+                "Kael ran.",
+                "SceneBreak:#",
+                "Quiet.",
+                "ChapterTitle:Calm",
+                "The sea settled.",
+                "ListParagraph:3.\tThird",
+                "SceneBreak:END",
+            ]
+        );
+
+        let document = entry(&dest, "word/document.xml");
+        assert!(
+            document.contains(r#"<w:rPr><w:i/></w:rPr><w:t xml:space="preserve">at dawn</w:t>"#)
+        );
+        assert!(!document.contains("Secret"));
+        assert!(entry(&dest, "word/header1.xml").contains("Storm / TIDES / "));
+        assert!(entry(&dest, "docProps/core.xml")
+            .contains("<dcterms:modified xsi:type=\"dcterms:W3CDTF\">2025-10-15T03:46:40Z"));
+
+        // Every part is well-formed and every relationship resolves
+        let types = entry(&dest, "[Content_Types].xml");
+        for (rels, base) in [
+            ("_rels/.rels", ""),
+            ("word/_rels/document.xml.rels", "word/"),
+        ] {
+            for e in parse_xml(&entry(&dest, rels)).unwrap() {
+                if e.name == "Relationship" {
+                    let target = format!("{}{}", base, e.attr("Target"));
+                    parse_xml(&entry(&dest, &target)).unwrap();
+                    assert!(types.contains(&format!("PartName=\"/{}\"", target)));
+                }
+            }
+        }
+        parse_xml(&types).unwrap();
+    }
+
+    #[test]
+    fn underlines_italics_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("story.docx");
+        let options = DocxOptions {
+            title: "Dock".into(),
+            author: "Ada Vell".into(),
+            italics_as_underline: true,
+            document_ids: Some(vec!["s1".into(), "c1".into()]),
+            ..DocxOptions::default()
+        };
+        write_docx(&snapshot(), &options, &dest, 0).unwrap();
+        let document = entry(&dest, "word/document.xml");
+        assert!(document.contains(
+            r#"<w:rPr><w:u w:val="single"/></w:rPr><w:t xml:space="preserve">at dawn</w:t>"#
+        ));
+        assert!(!document.contains("<w:i/>"));
+        assert!(entry(&dest, "word/header1.xml").contains("Vell / DOCK / "));
+    }
+
+    #[test]
+    fn rounds_word_counts() {
+        assert_eq!(rounded_word_count(40), "about 40 words");
+        assert_eq!(rounded_word_count(4_349), "about 4,300 words");
+        assert_eq!(rounded_word_count(17_499), "about 17,500 words");
+        assert_eq!(rounded_word_count(81_632), "about 82,000 words");
+        assert_eq!(rounded_word_count(1_234_567), "about 1,235,000 words");
+    }
+}
@@ -0,0 +1,863 @@
+//! EPUB 3 writer
+//!
+//! Each manuscript document becomes one XHTML file under `OEBPS/text/`, in
+//! spine order. The navigation document nests the binder hierarchy with each
+//! document's headings and scene blocks beneath it, linked through the
+//! anchors `html::render_with` adds. Fonts and the cover image are copied in
+//! from files the user picked; the editor's inline images are remote URLs and
+//! are left out.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::{manuscript, write_zip, ExportError, ManuscriptDocument};
+use crate::db::{ProjectSnapshot, ProjectStore};
+use crate::folder::html::{self, HtmlOptions, OutlineEntry};
+use crate::sync::outbox::iso_timestamp;
+
+pub const MIMETYPE: &str = "application/epub+zip";
+pub const PACKAGE_PATH: &str = "OEBPS/content.opf";
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLESHEET: &str = "\
+body { margin: 0 5%; line-height: 1.5; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.2; text-align: center; }
+p { margin: 0; text-indent: 1.5em; }
+h1 + p, h2 + p, h3 + p, h4 + p, h5 + p, h6 + p, hr + p, .scene-block > p:first-child { text-indent: 0; }
+.scene-block + .scene-block::before { content: \"*\"; display: block; margin: 1em 0; text-align: center; }
+hr { margin: 1em 30%; border: none; border-top: 1px solid; }
+blockquote { margin: 1em 2em; }
+pre { white-space: pre-wrap; }
+.cover { margin: 0; padding: 0; text-align: center; }
+.cover img { max-width: 100%; max-height: 100%; }
+";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EpubOptions {
+    /// Book title; `Untitled` when empty
+    pub title: String,
+    pub authors: Vec<String>,
+    /// BCP 47 tag; `en` when empty
+    pub language: String,
+    /// ISBN or URN; derived from the project id when absent
+    pub identifier: Option<String>,
+    pub description: Option<String>,
+    pub publisher: Option<String>,
+    /// Path to a JPEG, PNG, GIF, WebP or SVG image
+    pub cover_image: Option<String>,
+    /// Paths to TTF, OTF, WOFF or WOFF2 files; the first sets the body face
+    pub fonts: Vec<String>,
+    /// Export only these documents instead of the whole manuscript
+    pub document_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpubSummary {
+    pub project_id: String,
+    pub documents: usize,
+    /// Links in the table of contents
+    pub toc_entries: usize,
+    pub fonts: usize,
+    pub cover: bool,
+    pub bytes: u64,
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    html::escape(text, &mut out);
+    out
+}
+
+fn image_type(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+fn font_type(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => return None,
+    })
+}
+
+/// A file copied into the book
+struct Resource {
+    id: String,
+    /// Relative to the package document
+    href: String,
+    media_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+fn resource(
+    path: &str,
+    id: &str,
+    dir: &str,
+    media_type: fn(&str) -> Option<&'static str>,
+) -> Result<Resource, ExportError> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let media_type =
+        media_type(&extension).ok_or_else(|| ExportError::UnsupportedFile(path.to_string()))?;
+    Ok(Resource {
+        id: id.to_string(),
+        href: format!("{}/{}.{}", dir, id, extension),
+        media_type,
+        bytes: fs::read(path)?,
+    })
+}
+
+/// `@font-face` rule with family, weight and style guessed from the file
+/// name (`Literata-BoldItalic.ttf`); also returns the family
+fn font_face(path: &str, font: &Resource) -> (String, String) {
+    let stem = Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let family: String = stem
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !matches!(c, '"' | '\\'))
+        .collect();
+    let family = if family.trim().is_empty() {
+        font.id.clone()
+    } else {
+        family
+    };
+    let lower = stem.to_ascii_lowercase();
+    let weight = if lower.contains("bold") { 700 } else { 400 };
+    let style = if lower.contains("italic") || lower.contains("oblique") {
+        "italic"
+    } else {
+        "normal"
+    };
+    let rule = format!(
+        "@font-face {{ font-family: \"{}\"; src: url(\"../{}\"); font-weight: {}; font-style: {}; }}\n",
+        family, font.href, weight, style
+    );
+    (rule, family)
+}
+
+fn identifier(options: &EpubOptions, project_id: &str) -> String {
+    if let Some(id) = options.identifier.as_deref().map(str::trim) {
+        if !id.is_empty() {
+            return id.to_string();
+        }
+    }
+    let is_uuid = project_id.len() == 36
+        && project_id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == '-');
+    if is_uuid {
+        format!("urn:uuid:{}", project_id.to_ascii_lowercase())
+    } else {
+        format!("urn:rhei:project:{}", project_id)
+    }
+}
+
+/// An XHTML content document
+fn page(title: &str, language: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{lang}" xml:lang="{lang}">
+<head>
+<meta charset="UTF-8" />
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="{css}" />
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        lang = escape(language),
+        title = escape(title),
+        css = stylesheet,
+        body = body
+    )
+}
+
+/// A table of contents link; `rank` is its nesting in the nav
+struct TocEntry {
+    rank: usize,
+    label: String,
+    href: String,
+}
+
+/// Nested `<ol>` for the nav. A rank can only go one deeper than the entry
+/// before it, so skipped heading levels don't produce empty list items.
+fn nav_list(entries: &[TocEntry]) -> String {
+    let mut out = String::from("<ol>\n");
+    let mut current: Option<usize> = None;
+    for entry in entries {
+        let rank = current.map_or(0, |c| entry.rank.min(c + 1));
+        match current {
+            Some(c) if rank > c => out.push_str("\n<ol>\n"),
+            Some(c) => {
+                out.push_str("</li>\n");
+                for _ in rank..c {
+                    out.push_str("</ol>\n</li>\n");
+                }
+            }
+            None => {}
+        }
+        out.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            escape(&entry.href),
+            escape(&entry.label)
+        ));
+        current = Some(rank);
+    }
+    if let Some(c) = current {
+        out.push_str("</li>\n");
+        for _ in 0..c {
+            out.push_str("</ol>\n</li>\n");
+        }
+    }
+    out.push_str("</ol>");
+    out
+}
+
+/// The document's heading and its place in the table of contents
+fn chapter(document: &ManuscriptDocument, href: &str, toc: &mut Vec<TocEntry>) -> String {
+    let rendered = html::render_with(
+        &document.content,
+        HtmlOptions {
+            anchors: true,
+            skip_images: true,
+        },
+    );
+    toc.push(TocEntry {
+        rank: document.depth,
+        label: document.title.clone(),
+        href: href.to_string(),
+    });
+
+    // Content that already opens with the title keeps it as the heading
+    let titled = document.opens_with_title();
+    let mut outline: &[OutlineEntry] = &rendered.outline;
+    if titled {
+        outline = &outline[1..];
+    }
+
+    let shallowest = outline.iter().filter_map(|e| e.level).min().unwrap_or(1);
+    let mut heading_offset = None;
+    for entry in outline {
+        let offset = match entry.level {
+            Some(level) => {
+                let offset = usize::from(level - shallowest);
+                heading_offset = Some(offset);
+                offset
+            }
+            None => heading_offset.map_or(0, |o| o + 1),
+        };
+        if entry.title.is_empty() {
+            continue;
+        }
+        toc.push(TocEntry {
+            rank: document.depth + 1 + offset,
+            label: entry.title.clone(),
+            href: format!("{}#{}", href, entry.id),
+        });
+    }
+
+    let mut body = format!("<section class=\"{}\">\n", escape(&document.kind));
+    if !titled {
+        let level = (document.depth + 1).min(6);
+        body.push_str(&format!(
+            "<h{level}>{}</h{level}>\n",
+            escape(&document.title)
+        ));
+    }
+    body.push_str(&rendered.html);
+    body.push_str("\n</section>");
+    body
+}
+
+/// Write the manuscript as an EPUB 3 book at `dest`
+pub fn write_epub(
+    snapshot: &ProjectSnapshot,
+    options: &EpubOptions,
+    dest: &Path,
+    modified_ms: i64,
+) -> Result<EpubSummary, ExportError> {
+    let documents = manuscript(snapshot, options.document_ids.as_deref());
+    if documents.is_empty() {
+        return Err(ExportError::EmptyManuscript);
+    }
+    let title = match options.title.trim() {
+        "" => "Untitled",
+        title => title,
+    };
+    let language = match options.language.trim() {
+        "" => "en",
+        language => language,
+    };
+
+    let cover = options
+        .cover_image
+        .as_deref()
+        .map(|path| resource(path, "cover-image", "images", image_type))
+        .transpose()?;
+    let mut stylesheet = String::new();
+    let mut body_family = None;
+    let mut fonts = Vec::new();
+    for (i, path) in options.fonts.iter().enumerate() {
+        let font = resource(path, &format!("font-{}", i + 1), "fonts", font_type)?;
+        let (rule, family) = font_face(path, &font);
+        stylesheet.push_str(&rule);
+        body_family.get_or_insert(family);
+        fonts.push(font);
+    }
+    if let Some(family) = body_family {
+        stylesheet.push_str(&format!("body {{ font-family: \"{}\", serif; }}\n", family));
+    }
+    stylesheet.push_str(STYLESHEET);
+
+    // Content documents, relative to OEBPS/
+    let mut files: Vec<(String, String)> = Vec::new();
+    let mut toc = Vec::new();
+    let mut spine = Vec::new();
+    let mut manifest = vec![
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#
+            .to_string(),
+        r#"<item id="css" href="css/style.css" media-type="text/css"/>"#.to_string(),
+    ];
+    if let Some(cover) = &cover {
+        let body = format!(
+            "<section class=\"cover\" epub:type=\"cover\"><img src=\"../{}\" alt=\"{}\" /></section>",
+            escape(&cover.href),
+            escape(title)
+        );
+        files.push((
+            "text/cover.xhtml".to_string(),
+            page(title, language, "../css/style.css", &body),
+        ));
+        manifest.push(
+            r#"<item id="cover" href="text/cover.xhtml" media-type="application/xhtml+xml"/>"#
+                .to_string(),
+        );
+        spine.push("cover".to_string());
+    }
+    for (i, document) in documents.iter().enumerate() {
+        let id = format!("doc-{:03}", i + 1);
+        let href = format!("text/{}.xhtml", id);
+        let body = chapter(document, &href, &mut toc);
+        files.push((
+            href.clone(),
+            page(&document.title, language, "../css/style.css", &body),
+        ));
+        manifest.push(format!(
+            r#"<item id="{}" href="{}" media-type="application/xhtml+xml"/>"#,
+            id, href
+        ));
+        spine.push(id);
+    }
+    for resource in cover.iter().chain(&fonts) {
+        let properties = if resource.id == "cover-image" {
+            r#" properties="cover-image""#
+        } else {
+            ""
+        };
+        manifest.push(format!(
+            r#"<item id="{}" href="{}" media-type="{}"{}/>"#,
+            resource.id, resource.href, resource.media_type, properties
+        ));
+    }
+
+    let mut landmarks = String::new();
+    if cover.is_some() {
+        landmarks.push_str("<li><a epub:type=\"cover\" href=\"text/cover.xhtml\">Cover</a></li>\n");
+    }
+    landmarks.push_str("<li><a epub:type=\"toc\" href=\"nav.xhtml\">Contents</a></li>\n");
+    landmarks
+        .push_str("<li><a epub:type=\"bodymatter\" href=\"text/doc-001.xhtml\">Start</a></li>");
+    let nav = page(
+        title,
+        language,
+        "css/style.css",
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n{}\n</nav>\n\
+             <nav epub:type=\"landmarks\" id=\"landmarks\" hidden=\"hidden\">\n<ol>\n{}\n</ol>\n</nav>",
+            nav_list(&toc),
+            landmarks
+        ),
+    );
+
+    let mut metadata = vec![
+        format!(
+            r#"<dc:identifier id="book-id">{}</dc:identifier>"#,
+            escape(&identifier(options, &snapshot.project_id))
+        ),
+        format!("<dc:title>{}</dc:title>", escape(title)),
+        format!("<dc:language>{}</dc:language>", escape(language)),
+    ];
+    for (i, author) in options
+        .authors
+        .iter()
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .enumerate()
+    {
+        metadata.push(format!(
+            r#"<dc:creator id="creator-{n}">{}</dc:creator>"#,
+            escape(author),
+            n = i + 1
+        ));
+        metadata.push(format!(
+            r##"<meta refines="#creator-{}" property="role" scheme="marc:relators">aut</meta>"##,
+            i + 1
+        ));
+    }
+    for (element, value) in [
+        ("dc:description", &options.description),
+        ("dc:publisher", &options.publisher),
+    ] {
+        if let Some(value) = value.as_deref().filter(|v| !v.trim().is_empty()) {
+            metadata.push(format!("<{0}>{1}</{0}>", element, escape(value)));
+        }
+    }
+    // dcterms:modified takes whole seconds
+    metadata.push(format!(
+        r#"<meta property="dcterms:modified">{}Z</meta>"#,
+        &iso_timestamp(modified_ms)[..19]
+    ));
+    if cover.is_some() {
+        metadata.push(r#"<meta name="cover" content="cover-image"/>"#.to_string());
+    }
+    let package = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    {}
+  </metadata>
+  <manifest>
+    {}
+  </manifest>
+  <spine>
+    {}
+  </spine>
+</package>
+"#,
+        escape(language),
+        metadata.join("\n    "),
+        manifest.join("\n    "),
+        spine
+            .iter()
+            .map(|id| format!(r#"<itemref idref="{}"/>"#, id))
+            .collect::<Vec<_>>()
+            .join("\n    ")
+    );
+
+    let mut entries = vec![
+        ("mimetype".to_string(), MIMETYPE.as_bytes().to_vec()),
+        ("META-INF/container.xml".to_string(), CONTAINER.into()),
+        (PACKAGE_PATH.to_string(), package.into_bytes()),
+        ("OEBPS/nav.xhtml".to_string(), nav.into_bytes()),
+        ("OEBPS/css/style.css".to_string(), stylesheet.into_bytes()),
+    ];
+    for (href, xhtml) in files {
+        entries.push((format!("OEBPS/{}", href), xhtml.into_bytes()));
+    }
+    let (cover_included, font_count) = (cover.is_some(), fonts.len());
+    for resource in cover.into_iter().chain(fonts) {
+        entries.push((format!("OEBPS/{}", resource.href), resource.bytes));
+    }
+    write_zip(dest, &entries)?;
+
+    Ok(EpubSummary {
+        project_id: snapshot.project_id.clone(),
+        documents: documents.len(),
+        toc_entries: toc.len(),
+        fonts: font_count,
+        cover: cover_included,
+        bytes: fs::metadata(dest)?.len(),
+    })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_epub(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+    options: EpubOptions,
+) -> Result<EpubSummary, ExportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let snapshot = app.state::<ProjectStore>().snapshot(&project_id)?;
+        write_epub(
+            &snapshot,
+            &options,
+            Path::new(&path),
+            crate::sync::outbox::now_ms(),
+        )
+    })
+    .await
+    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{entry, parse_xml};
+    use serde_json::{json, Value};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::fs::File;
+    use std::io::Read;
+
+    fn resolve(base: &str, href: &str) -> String {
+        let mut parts: Vec<&str> = base.split('/').collect();
+        parts.pop();
+        for part in href.split('/') {
+            match part {
+                ".." => {
+                    parts.pop();
+                }
+                "." => {}
+                part => parts.push(part),
+            }
+        }
+        parts.join("/")
+    }
+
+    /// Structural checks in the spirit of epubcheck; returns the problems
+    fn validate(path: &Path) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut zip = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut entries = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).unwrap();
+            if i == 0 {
+                if file.name() != "mimetype" {
+                    problems.push("mimetype is not the first entry".into());
+                }
+                if file.compression() != zip::CompressionMethod::Stored {
+                    problems.push("mimetype is compressed".into());
+                }
+            }
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).unwrap();
+            entries.insert(file.name().to_string(), bytes);
+        }
+        if entries.get("mimetype").map(Vec::as_slice) != Some(MIMETYPE.as_bytes()) {
+            problems.push("mimetype content is wrong".into());
+        }
+
+        let mut documents = HashMap::new();
+        for (name, bytes) in &entries {
+            if [".xml", ".opf", ".xhtml"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+            {
+                match std::str::from_utf8(bytes)
+                    .map_err(|e| e.to_string())
+                    .and_then(parse_xml)
+                {
+                    Ok(elements) => {
+                        documents.insert(name.clone(), elements);
+                    }
+                    Err(e) => problems.push(format!("{} is not well-formed: {}", name, e)),
+                }
+            }
+        }
+        let Some(container) = documents.get("META-INF/container.xml") else {
+            problems.push("META-INF/container.xml is missing".into());
+            return problems;
+        };
+        let rootfiles: Vec<_> = container.iter().filter(|e| e.name == "rootfile").collect();
+        let opf_path = match rootfiles.as_slice() {
+            [rootfile] if rootfile.attr("media-type") == "application/oebps-package+xml" => {
+                rootfile.attr("full-path").to_string()
+            }
+            _ => {
+                problems.push("container.xml needs one package rootfile".into());
+                return problems;
+            }
+        };
+        let Some(opf) = documents.get(&opf_path) else {
+            problems.push(format!("package document {} is missing", opf_path));
+            return problems;
+        };
+
+        // Metadata
+        let package = &opf[0];
+        if package.name != "package" || package.attr("version") != "3.0" {
+            problems.push("root is not an EPUB 3 package".into());
+        }
+        let unique = package.attr("unique-identifier");
+        if !opf
+            .iter()
+            .any(|e| e.name == "dc:identifier" && e.attr("id") == unique && !e.text.is_empty())
+        {
+            problems.push("unique-identifier doesn't name a dc:identifier".into());
+        }
+        for required in ["dc:title", "dc:language"] {
+            if !opf
+                .iter()
+                .any(|e| e.name == required && !e.text.trim().is_empty())
+            {
+                problems.push(format!("{} is missing", required));
+            }
+        }
+        let modified: Vec<_> = opf
+            .iter()
+            .filter(|e| e.attr("property") == "dcterms:modified")
+            .collect();
+        let well_formed_date = |s: &str| {
+            s.len() == 20
+                && s.bytes().enumerate().all(|(i, b)| match i {
+                    4 | 7 => b == b'-',
+                    10 => b == b'T',
+                    13 | 16 => b == b':',
+                    19 => b == b'Z',
+                    _ => b.is_ascii_digit(),
+                })
+        };
+        if modified.len() != 1 || !well_formed_date(&modified[0].text) {
+            problems.push("needs one dcterms:modified as CCYY-MM-DDThh:mm:ssZ".into());
+        }
+
+        // Manifest
+        let mut items = HashMap::new();
+        let mut listed = HashSet::new();
+        let mut navs = 0;
+        for item in opf.iter().filter(|e| e.name == "item") {
+            let target = resolve(&opf_path, item.attr("href"));
+            if !entries.contains_key(&target) {
+                problems.push(format!("manifest item {} is missing", target));
+            }
+            if items.insert(item.attr("id").to_string(), item).is_some() {
+                problems.push(format!("duplicate manifest id {}", item.attr("id")));
+            }
+            let properties: Vec<_> = item.attr("properties").split_whitespace().collect();
+            navs += usize::from(properties.contains(&"nav"));
+            if properties.contains(&"cover-image") && !item.attr("media-type").starts_with("image/")
+            {
+                problems.push("cover-image is not an image".into());
+            }
+            listed.insert(target);
+        }
+        if navs != 1 {
+            problems.push(format!("{} nav documents", navs));
+        }
+        for name in entries.keys() {
+            let container_file = name == "mimetype" || name.starts_with("META-INF/");
+            if !container_file && *name != opf_path && !listed.contains(name) {
+                problems.push(format!("{} is not in the manifest", name));
+            }
+        }
+
+        // Spine
+        let spine: Vec<_> = opf.iter().filter(|e| e.name == "itemref").collect();
+        if spine.is_empty() {
+            problems.push("spine is empty".into());
+        }
+        for itemref in spine {
+            match items.get(itemref.attr("idref")) {
+                Some(item) if item.attr("media-type") == "application/xhtml+xml" => {}
+                _ => problems.push(format!("spine idref {} is invalid", itemref.attr("idref"))),
+            }
+        }
+
+        // Links and images in content documents, nav included
+        let ids: HashMap<&String, HashSet<&str>> = documents
+            .iter()
+            .map(|(name, elements)| {
+                let ids = elements
+                    .iter()
+                    .filter_map(|e| e.attrs.get("id"))
+                    .map(String::as_str);
+                (name, ids.collect())
+            })
+            .collect();
+        for (name, elements) in documents.iter().filter(|(n, _)| n.ends_with(".xhtml")) {
+            for e in elements {
+                let reference = match e.name.as_str() {
+                    "a" | "link" => e.attr("href"),
+                    "img" => e.attr("src"),
+                    _ => continue,
+                };
+                let (file, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+                let target = resolve(name, file);
+                if !entries.contains_key(&target) {
+                    problems.push(format!("{} links to missing {}", name, reference));
+                } else if !fragment.is_empty()
+                    && !ids.get(&target).is_some_and(|ids| ids.contains(fragment))
+                {
+                    problems.push(format!("{} links to missing anchor {}", name, reference));
+                }
+            }
+        }
+        problems
+    }
+
+    fn doc(nodes: Value) -> Value {
+        json!({"type": "doc", "content": nodes})
+    }
+
+    fn paragraph(text: &str) -> Value {
+        json!({"type": "paragraph", "content": [{"type": "text", "text": text}]})
+    }
+
+    fn snapshot() -> ProjectSnapshot {
+        ProjectSnapshot {
+            project_id: "0f8fad5b-d9cb-469f-a165-70867728950e".into(),
+            version: 1,
+            documents: vec![
+                json!({"id": "c1", "type": "chapter", "title": "The Storm", "orderIndex": 0,
+                "content": doc(json!([
+                    {"type": "heading", "attrs": {"level": 1}, "content": [{"type": "text", "text": "The Storm"}]},
+                    paragraph("It broke at dawn."),
+                    {"type": "image", "attrs": {"src": "https://example.com/storm.png"}}
+                ]))}),
+                json!({"id": "s1", "type": "scene", "title": "Harbour & Quay", "parentId": "c1", "orderIndex": 0,
+                "content": doc(json!([
+                    {"type": "sceneBlock", "attrs": {"sceneId": "a", "sceneName": "Dock"}, "content": [paragraph("Kael ran.")]},
+                    {"type": "heading", "attrs": {"level": 3}, "content": [{"type": "text", "text": "Aftermath"}]},
+                    {"type": "sceneBlock", "attrs": {"sceneId": "b"}, "content": [paragraph("Quiet.")]}
+                ]))}),
+                json!({"id": "c2", "type": "chapter", "title": "Calm", "orderIndex": 1,
+                       "content": serde_json::to_string(&doc(json!([paragraph("The sea <settled>.")]))).unwrap()}),
+                json!({"id": "n1", "type": "note", "title": "Research", "orderIndex": 2,
+                       "content": doc(json!([paragraph("Secret.")]))}),
+            ],
+            entities: vec![],
+            relationships: vec![],
+            mentions: vec![],
+            analysis: vec![],
+            captures: vec![],
+            synced_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn writes_a_valid_book() {
+        let dir = tempfile::tempdir().unwrap();
+        let cover = dir.path().join("Cover.PNG");
+        fs::write(&cover, b"\x89PNG\r\n\x1a\n").unwrap();
+        let font = dir.path().join("Literata-BoldItalic.ttf");
+        fs::write(&font, b"\0\x01\0\0").unwrap();
+        let options = EpubOptions {
+            title: "Tides".into(),
+            authors: vec!["Ada Vell".into(), " ".into()],
+            language: "en-GB".into(),
+            description: Some("A storm & its wake".into()),
+            cover_image: Some(cover.to_string_lossy().into_owned()),
+            fonts: vec![font.to_string_lossy().into_owned()],
+            ..EpubOptions::default()
+        };
+        let dest = dir.path().join("tides.epub");
+        let summary = write_epub(&snapshot(), &options, &dest, 1_760_500_000_123).unwrap();
+        assert_eq!(validate(&dest), Vec::<String>::new());
+        assert_eq!(
+            (summary.documents, summary.toc_entries, summary.fonts),
+            (3, 6, 1)
+        );
+        assert!(summary.cover);
+
+        let opf = entry(&dest, PACKAGE_PATH);
+        assert!(opf.contains(
+            r#"<dc:identifier id="book-id">urn:uuid:0f8fad5b-d9cb-469f-a165-70867728950e</dc:identifier>"#
+        ));
+        assert!(opf.contains(r#"<meta property="dcterms:modified">2025-10-15T03:46:40Z</meta>"#));
+        assert!(opf.contains("<dc:creator id=\"creator-1\">Ada Vell</dc:creator>"));
+        assert!(!opf.contains("creator-2"));
+        let spine: Vec<_> = opf.lines().filter(|l| l.contains("itemref")).collect();
+        assert_eq!(
+            spine,
+            [
+                r#"    <itemref idref="cover"/>"#,
+                r#"    <itemref idref="doc-001"/>"#,
+                r#"    <itemref idref="doc-002"/>"#,
+                r#"    <itemref idref="doc-003"/>"#
+            ]
+        );
+
+        // The scene nests under its chapter, the heading-less scene under the heading
+        let nav = entry(&dest, "OEBPS/nav.xhtml");
+        let toc = &nav[nav.find("<ol>").unwrap()..nav.find("</nav>").unwrap()];
+        assert_eq!(
+            toc.replace('\n', ""),
+            concat!(
+                "<ol><li><a href=\"text/doc-001.xhtml\">The Storm</a>",
+                "<ol><li><a href=\"text/doc-002.xhtml\">Harbour &amp; Quay</a>",
+                "<ol><li><a href=\"text/doc-002.xhtml#scene-1\">Dock</a></li>",
+                "<li><a href=\"text/doc-002.xhtml#heading-2\">Aftermath</a>",
+                "<ol><li><a href=\"text/doc-002.xhtml#scene-3\">Scene</a></li></ol></li></ol></li></ol></li>",
+                "<li><a href=\"text/doc-003.xhtml\">Calm</a></li></ol>"
+            )
+        );
+
+        // An opening heading that repeats the title isn't doubled; images are dropped
+        let storm = entry(&dest, "OEBPS/text/doc-001.xhtml");
+        assert_eq!(storm.matches("The Storm</h1>").count(), 1);
+        assert!(!storm.contains("<img"));
+        assert!(entry(&dest, "OEBPS/text/doc-002.xhtml").contains("<h2>Harbour &amp; Quay</h2>"));
+        assert!(entry(&dest, "OEBPS/text/doc-003.xhtml").contains("The sea &lt;settled&gt;."));
+        assert!(!(1..=3)
+            .any(|i| entry(&dest, &format!("OEBPS/text/doc-{:03}.xhtml", i)).contains("Secret")));
+
+        let css = entry(&dest, "OEBPS/css/style.css");
+        assert!(css.contains(
+            "font-family: \"Literata\"; src: url(\"../fonts/font-1.ttf\"); font-weight: 700; font-style: italic;"
+        ));
+        assert!(css.contains("body { font-family: \"Literata\", serif; }"));
+    }
+
+    #[test]
+    fn rejects_unusable_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("book.epub");
+        let cover = dir.path().join("cover.bmp");
+        fs::write(&cover, b"BM").unwrap();
+        let options = EpubOptions {
+            cover_image: Some(cover.to_string_lossy().into_owned()),
+            ..EpubOptions::default()
+        };
+        let err = write_epub(&snapshot(), &options, &dest, 0).unwrap_err();
+        assert_eq!(err.code(), "export_unsupported_file");
+
+        let only = EpubOptions {
+            document_ids: Some(vec!["missing".into()]),
+            ..EpubOptions::default()
+        };
+        let err = write_epub(&snapshot(), &only, &dest, 0).unwrap_err();
+        assert_eq!(err.code(), "export_empty_manuscript");
+        assert!(!dest.exists());
+
+        // A chosen note is exported like any other document
+        let note = EpubOptions {
+            document_ids: Some(vec!["n1".into()]),
+            ..EpubOptions::default()
+        };
+        write_epub(&snapshot(), &note, &dest, 0).unwrap();
+        assert_eq!(validate(&dest), Vec::<String>::new());
+        assert!(entry(&dest, PACKAGE_PATH).contains("<dc:title>Untitled</dc:title>"));
+    }
+}
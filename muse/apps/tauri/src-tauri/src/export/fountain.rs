@@ -0,0 +1,219 @@
+//! Fountain screenplays
+//!
+//! Screenplay documents are written back element by element, so a script
+//! imported from Fountain keeps its transitions, parentheticals, notes and
+//! forcing prefixes. Other manuscript documents export as action under a
+//! section per document, for drafting a script from prose.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use super::{manuscript, ExportError};
+use crate::db::{ProjectSnapshot, ProjectStore};
+use crate::import::fountain::{doc_elements, is_natural_heading, to_fountain};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FountainOptions {
+    /// With `author`, replaces the title page kept from an import
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Export only these documents; otherwise the project's screenplays, or
+    /// its manuscript when it has none
+    pub document_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FountainSummary {
+    pub project_id: String,
+    pub documents: usize,
+    /// Scene headings written
+    pub scenes: usize,
+    pub bytes: u64,
+}
+
+fn non_empty(value: Option<&String>) -> Option<&str> {
+    value.map(|v| v.trim()).filter(|v| !v.is_empty())
+}
+
+fn title_page(
+    snapshot: &ProjectSnapshot,
+    options: &FountainOptions,
+    first_id: &str,
+) -> Option<String> {
+    let title = non_empty(options.title.as_ref());
+    let author = non_empty(options.author.as_ref());
+    if title.is_none() && author.is_none() {
+        return snapshot
+            .documents
+            .iter()
+            .find(|row| row.get("id").and_then(Value::as_str) == Some(first_id))
+            .and_then(|row| row.pointer("/metadata/fountain/titlePage"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+    }
+    let mut page = Vec::new();
+    if let Some(title) = title {
+        page.push(format!("Title: {}", title));
+    }
+    if let Some(author) = author {
+        page.push("Credit: Written by".to_string());
+        page.push(format!("Author: {}", author));
+    }
+    Some(page.join("\n"))
+}
+
+/// Write the project's script as a `.fountain` file at `dest`
+pub fn write_fountain(
+    snapshot: &ProjectSnapshot,
+    options: &FountainOptions,
+    dest: &Path,
+) -> Result<FountainSummary, ExportError> {
+    let mut documents = manuscript(snapshot, options.document_ids.as_deref());
+    if options.document_ids.is_none() && documents.iter().any(|d| d.kind == "screenplay") {
+        documents.retain(|d| d.kind == "screenplay");
+    }
+    if documents.is_empty() {
+        return Err(ExportError::EmptyManuscript);
+    }
+
+    let mut elements = Vec::new();
+    for document in &documents {
+        let prose = document.kind != "screenplay";
+        if prose && !document.opens_with_title() {
+            elements.push(format!("# {}", document.title));
+        }
+        elements.extend(doc_elements(&document.content, prose));
+    }
+    let scenes = elements
+        .iter()
+        .filter(|e| is_natural_heading(e) || (e.starts_with('.') && !e.starts_with("..")))
+        .count();
+    let text = to_fountain(
+        title_page(snapshot, options, &documents[0].id).as_deref(),
+        &elements,
+    );
+
+    let tmp = dest.with_extension("partial");
+    fs::write(&tmp, &text)?;
+    fs::rename(&tmp, dest)?;
+    Ok(FountainSummary {
+        project_id: snapshot.project_id.clone(),
+        documents: documents.len(),
+        scenes,
+        bytes: text.len() as u64,
+    })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_fountain(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+    options: FountainOptions,
+) -> Result<FountainSummary, ExportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let snapshot = app.state::<ProjectStore>().snapshot(&project_id)?;
+        write_fountain(&snapshot, &options, Path::new(&path))
+    })
+    .await
+    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SyncTable;
+    use crate::import::fountain::{import_script, tests::CORPUS};
+    use serde_json::json;
+
+    #[test]
+    fn writes_imported_scripts_back_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        for (name, source) in CORPUS {
+            let path = dir.path().join(format!("{}.fountain", name));
+            fs::write(&path, source).unwrap();
+            let report = import_script(&store, "p1", &path).unwrap();
+            let dest = dir.path().join("out.fountain");
+            let options = FountainOptions {
+                document_ids: Some(vec![report.document.id]),
+                ..FountainOptions::default()
+            };
+            let summary = write_fountain(&store.snapshot("p1").unwrap(), &options, &dest).unwrap();
+            assert_eq!(fs::read_to_string(&dest).unwrap(), source, "{}", name);
+            assert_eq!(summary.scenes, report.document.scenes, "{}", name);
+            assert_eq!(summary.bytes, source.len() as u64);
+        }
+        assert!(!dir.path().join("out.partial").exists());
+    }
+
+    #[test]
+    fn prefers_screenplays_and_keeps_prose_as_action() {
+        let paragraph =
+            |text: &str| json!({"type": "paragraph", "content": [{"type": "text", "text": text}]});
+        let mut snapshot = ProjectSnapshot {
+            project_id: "p1".into(),
+            documents: vec![json!({
+                "id": "d1", "projectId": "p1", "type": "chapter", "title": "Arrival", "orderIndex": 0,
+                "content": {"type": "doc", "content": [
+                    {"type": "sceneBlock", "attrs": {"sceneName": "The Quay"}, "content": [
+                        paragraph("CUT TO:"),
+                        {"type": "paragraph", "content": [
+                            {"type": "text", "text": "She "},
+                            {"type": "text", "text": "runs", "marks": [{"type": "italic"}]},
+                        ]},
+                    ]},
+                    {"type": "horizontalRule"},
+                    {"type": "blockquote", "content": [paragraph("ADA"), paragraph("Go.")]},
+                ]},
+            })],
+            ..ProjectSnapshot::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("arrival.fountain");
+        let options = FountainOptions {
+            title: Some("Arrival".into()),
+            author: Some("Ada Marsh".into()),
+            ..FountainOptions::default()
+        };
+        let summary = write_fountain(&snapshot, &options, &dest).unwrap();
+        assert_eq!(
+            fs::read_to_string(&dest).unwrap(),
+            "Title: Arrival\nCredit: Written by\nAuthor: Ada Marsh\n\n# Arrival\n\n.The Quay\n\n!CUT TO:\n\nShe *runs*\n\n===\n\nADA\n\nGo.\n"
+        );
+        assert_eq!(summary.scenes, 1);
+
+        snapshot.documents.push(json!({
+            "id": "d2", "projectId": "p1", "type": "screenplay", "title": "Script", "orderIndex": 1,
+            "metadata": {"fountain": {"titlePage": "Title: Script"}},
+            "content": {"type": "doc", "content": [paragraph("CUT TO:")]},
+        }));
+        let summary = write_fountain(&snapshot, &FountainOptions::default(), &dest).unwrap();
+        assert_eq!(summary.documents, 1);
+        assert_eq!(
+            fs::read_to_string(&dest).unwrap(),
+            "Title: Script\n\nCUT TO:\n"
+        );
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .upsert(
+                SyncTable::Documents,
+                &json!({"id": "n1", "projectId": "p1", "type": "note", "title": "Notes"}),
+            )
+            .unwrap();
+        let err = write_fountain(
+            &store.snapshot("p1").unwrap(),
+            &FountainOptions::default(),
+            &dest,
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), "export_empty_manuscript");
+    }
+}
@@ -0,0 +1,348 @@
+//! Manuscript exports
+//!
+//! Formats share one view of the project: its manuscript documents in
+//! binder order (depth-first by `parentId`, then `orderIndex`), leaving out
+//! planning material. Writers run off the main thread and build the whole
+//! file in one pass, so long books export without blocking the webview.
+
+pub mod docx;
+pub mod epub;
+pub mod fountain;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+use zip::write::SimpleFileOptions;
+
+use crate::db::{ProjectSnapshot, StoreError};
+use crate::folder::html::plain_text;
+use crate::folder::markdown::{as_doc, text_content, word_count};
+
+/// Document types that are planning material rather than manuscript
+pub const NON_MANUSCRIPT_TYPES: [&str; 3] = ["note", "outline", "worldbuilding"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("the project has no manuscript documents to export")]
+    EmptyManuscript,
+    #[error("`{0}` is not a supported file type")]
+    UnsupportedFile(String),
+    #[error("export I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("export could not be packaged: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl ExportError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::EmptyManuscript => "export_empty_manuscript",
+            Self::UnsupportedFile(_) => "export_unsupported_file",
+            Self::Io(_) => "export_io",
+            Self::Zip(_) => "export_zip",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(ExportError);
+
+/// A document as it appears in an export
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManuscriptDocument {
+    pub id: String,
+    pub kind: String,
+    pub title: String,
+    /// Nesting below the top level of the export
+    pub depth: usize,
+    /// The ProseMirror doc
+    pub content: Value,
+}
+
+impl ManuscriptDocument {
+    /// Whether the content starts with a heading that repeats the title
+    pub fn opens_with_title(&self) -> bool {
+        let first = self
+            .content
+            .get("content")
+            .and_then(Value::as_array)
+            .and_then(|nodes| nodes.first());
+        first.is_some_and(|node| {
+            node.get("type").and_then(Value::as_str) == Some("heading")
+                && plain_text(node).trim() == self.title
+        })
+    }
+
+    pub fn word_count(&self) -> usize {
+        word_count(&text_content(&self.content))
+    }
+}
+
+/// Write a zip package beside `dest` and move it into place when complete.
+/// A `mimetype` entry is stored uncompressed, since EPUB and ODF readers
+/// sniff it at a fixed offset.
+pub(crate) fn write_zip(dest: &Path, entries: &[(String, Vec<u8>)]) -> Result<(), ExportError> {
+    let tmp = dest.with_extension("partial");
+    {
+        let mut zip = zip::ZipWriter::new(File::create(&tmp)?);
+        for (name, bytes) in entries {
+            let method = if name == "mimetype" {
+                zip::CompressionMethod::Stored
+            } else {
+                zip::CompressionMethod::Deflated
+            };
+            zip.start_file(
+                name.as_str(),
+                SimpleFileOptions::default().compression_method(method),
+            )?;
+            zip.write_all(bytes)?;
+        }
+        zip.finish()?.sync_all()?;
+    }
+    fs::rename(&tmp, dest)?;
+    Ok(())
+}
+
+fn str_field<'a>(row: &'a Map<String, Value>, name: &str) -> &'a str {
+    row.get(name).and_then(Value::as_str).unwrap_or_default()
+}
+
+/// Manuscript documents in binder order. With `only`, exactly those
+/// documents are taken (of any type), still in binder order; otherwise
+/// planning types are left out along with everything filed under them.
+pub fn manuscript(snapshot: &ProjectSnapshot, only: Option<&[String]>) -> Vec<ManuscriptDocument> {
+    let rows: Vec<&Map<String, Value>> = snapshot
+        .documents
+        .iter()
+        .filter_map(Value::as_object)
+        .filter(|row| !str_field(row, "id").is_empty())
+        .collect();
+    let ids: HashSet<&str> = rows.iter().map(|row| str_field(row, "id")).collect();
+    let mut children: HashMap<Option<&str>, Vec<&Map<String, Value>>> = HashMap::new();
+    for row in &rows {
+        let parent = row
+            .get("parentId")
+            .and_then(Value::as_str)
+            .filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(row);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| {
+            let order = |row: &Map<String, Value>| row.get("orderIndex").and_then(Value::as_f64);
+            order(a)
+                .partial_cmp(&order(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| str_field(a, "id").cmp(str_field(b, "id")))
+        });
+    }
+
+    struct Walk<'a> {
+        children: HashMap<Option<&'a str>, Vec<&'a Map<String, Value>>>,
+        only: Option<HashSet<&'a str>>,
+        visited: HashSet<&'a str>,
+        out: Vec<ManuscriptDocument>,
+    }
+
+    impl<'a> Walk<'a> {
+        /// Planning documents are walked too, so their children aren't
+        /// picked up again as orphans
+        fn visit(&mut self, row: &'a Map<String, Value>, depth: usize, planning: bool) {
+            let id = str_field(row, "id");
+            if !self.visited.insert(id) {
+                return;
+            }
+            let kind = str_field(row, "type");
+            let planning = planning || NON_MANUSCRIPT_TYPES.contains(&kind);
+            let included = match &self.only {
+                Some(only) => only.contains(id),
+                None => !planning,
+            };
+            if included {
+                let title = str_field(row, "title").trim();
+                self.out.push(ManuscriptDocument {
+                    id: id.to_string(),
+                    kind: kind.to_string(),
+                    title: if title.is_empty() { "Untitled" } else { title }.to_string(),
+                    depth,
+                    content: as_doc(row.get("content").unwrap_or(&Value::Null)),
+                });
+            }
+            let depth = depth + usize::from(included);
+            for child in self.children.get(&Some(id)).cloned().unwrap_or_default() {
+                self.visit(child, depth, planning);
+            }
+        }
+    }
+
+    let roots = children.get(&None).cloned().unwrap_or_default();
+    let mut walk = Walk {
+        children,
+        only: only.map(|ids| ids.iter().map(String::as_str).collect()),
+        visited: HashSet::new(),
+        out: Vec::new(),
+    };
+    for row in roots {
+        walk.visit(row, 0, false);
+    }
+    // Documents caught in a parent cycle have no root; take them as top level
+    for row in &rows {
+        walk.visit(row, 0, false);
+    }
+    walk.out
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use quick_xml::events::{BytesStart, Event};
+    use quick_xml::Reader;
+    use serde_json::json;
+    use std::io::Read;
+
+    pub(crate) struct Element {
+        pub name: String,
+        pub attrs: HashMap<String, String>,
+        pub text: String,
+    }
+
+    impl Element {
+        pub fn attr(&self, name: &str) -> &str {
+            self.attrs.get(name).map_or("", String::as_str)
+        }
+    }
+
+    fn element(start: &BytesStart) -> Result<Element, String> {
+        let mut attrs = HashMap::new();
+        for attr in start.attributes() {
+            let attr = attr.map_err(|e| e.to_string())?;
+            let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+            let value = attr.unescape_value().map_err(|e| e.to_string())?;
+            if attrs.insert(key.clone(), value.into_owned()).is_some() {
+                return Err(format!("duplicate attribute {}", key));
+            }
+        }
+        Ok(Element {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            attrs,
+            text: String::new(),
+        })
+    }
+
+    /// Every element with its attributes and text; errors if not well-formed
+    pub(crate) fn parse_xml(xml: &str) -> Result<Vec<Element>, String> {
+        let mut reader = Reader::from_str(xml);
+        let mut elements = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+        let mut roots = 0;
+        loop {
+            let event = reader.read_event().map_err(|e| e.to_string())?;
+            match event {
+                Event::Start(start) => {
+                    roots += usize::from(open.is_empty());
+                    open.push(elements.len());
+                    elements.push(element(&start)?);
+                }
+                Event::Empty(start) => {
+                    roots += usize::from(open.is_empty());
+                    elements.push(element(&start)?);
+                }
+                Event::End(_) => {
+                    open.pop();
+                }
+                Event::Text(text) => {
+                    let text = text.decode().map_err(|e| e.to_string())?;
+                    if let Some(&i) = open.last() {
+                        elements[i].text.push_str(&text);
+                    } else if !text.trim().is_empty() {
+                        return Err("text outside the root element".into());
+                    }
+                }
+                Event::GeneralRef(reference) => {
+                    let name = reference.decode().map_err(|e| e.to_string())?;
+                    let c = match &*name {
+                        "amp" => '&',
+                        "lt" => '<',
+                        "gt" => '>',
+                        "quot" => '"',
+                        "apos" => '\'',
+                        _ => reference
+                            .resolve_char_ref()
+                            .map_err(|e| e.to_string())?
+                            .ok_or_else(|| format!("undefined entity &{};", name))?,
+                    };
+                    if let Some(&i) = open.last() {
+                        elements[i].text.push(c);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        match (open.is_empty(), roots) {
+            (true, 1) => Ok(elements),
+            (false, _) => Err("unclosed elements".into()),
+            _ => Err(format!("{} root elements", roots)),
+        }
+    }
+
+    pub(crate) fn entry(path: &Path, name: &str) -> String {
+        let mut zip = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut text = String::new();
+        zip.by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn orders_manuscript_depth_first() {
+        let snapshot = ProjectSnapshot {
+            project_id: "p1".into(),
+            version: 1,
+            documents: vec![
+                json!({"id": "c2", "type": "chapter", "title": "Two", "orderIndex": 2}),
+                json!({"id": "s2", "type": "scene", "title": "Later", "parentId": "c1", "orderIndex": 1}),
+                json!({"id": "c1", "type": "chapter", "title": " One ", "orderIndex": 1}),
+                json!({"id": "s1", "type": "scene", "parentId": "c1", "orderIndex": 0}),
+                json!({"id": "n1", "type": "note", "title": "Ideas", "orderIndex": 0}),
+                json!({"id": "n2", "type": "scene", "title": "Filed", "parentId": "n1"}),
+                json!({"id": "x1", "type": "chapter", "title": "Loop", "parentId": "x2"}),
+                json!({"id": "x2", "type": "chapter", "title": "Back", "parentId": "x1"}),
+            ],
+            entities: vec![],
+            relationships: vec![],
+            mentions: vec![],
+            analysis: vec![],
+            captures: vec![],
+            synced_at: String::new(),
+        };
+        let outline = |docs: Vec<ManuscriptDocument>| {
+            docs.into_iter()
+                .map(|d| format!("{}{}:{}", " ".repeat(d.depth), d.id, d.title))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            outline(manuscript(&snapshot, None)),
+            [
+                "c1:One",
+                " s1:Untitled",
+                " s2:Later",
+                "c2:Two",
+                "x1:Loop",
+                " x2:Back"
+            ]
+        );
+
+        let only = ["s2".to_string(), "n2".to_string(), "c2".to_string()];
+        assert_eq!(
+            outline(manuscript(&snapshot, Some(&only))),
+            ["n2:Filed", "s2:Later", "c2:Two"]
+        );
+    }
+}
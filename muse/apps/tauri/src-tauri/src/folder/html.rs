@@ -0,0 +1,350 @@
+//! ProseMirror JSON as HTML
+//!
+//! Tags and attributes match the editor extensions' `parseHTML` rules, so
+//! the output can go straight into `setContent`. Void elements are written
+//! self-closed and characters XML forbids are dropped, which keeps the
+//! output valid XHTML as well (EPUB export uses it with anchors on).
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::markdown::as_doc;
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn attr<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    node.get("attrs")?.get(name).filter(|v| !v.is_null())
+}
+
+fn attr_str<'a>(node: &'a Value, name: &str) -> Option<&'a str> {
+    attr(node, name).and_then(Value::as_str)
+}
+
+/// Escape text for element content and double-quoted attributes
+pub fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' || matches!(c, '\u{fffe}' | '\u{ffff}') => {}
+            c => out.push(c),
+        }
+    }
+}
+
+fn attribute(out: &mut String, name: &str, value: &str) {
+    out.push(' ');
+    out.push_str(name);
+    out.push_str("=\"");
+    escape(value, out);
+    out.push('"');
+}
+
+/// Text of a node and its descendants, hard breaks as spaces
+pub fn plain_text(node: &Value) -> String {
+    match node_type(node) {
+        "text" => node
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        "hardBreak" => " ".to_string(),
+        _ => children(node).iter().map(plain_text).collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HtmlOptions {
+    /// Give headings and scene blocks ids and list them in the outline
+    pub anchors: bool,
+    /// Leave out images (the editor's are remote URLs)
+    pub skip_images: bool,
+}
+
+/// A heading or scene block, in document order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineEntry {
+    pub id: String,
+    pub title: String,
+    /// Heading level; scene blocks have none
+    pub level: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rendered {
+    pub html: String,
+    pub outline: Vec<OutlineEntry>,
+}
+
+/// Render stored document content (a doc object or its JSON string)
+pub fn render(content: &Value) -> String {
+    render_with(content, HtmlOptions::default()).html
+}
+
+pub fn render_with(content: &Value, options: HtmlOptions) -> Rendered {
+    let mut renderer = Renderer {
+        options,
+        rendered: Rendered::default(),
+    };
+    renderer.nodes(children(&as_doc(content)));
+    renderer.rendered
+}
+
+struct Renderer {
+    options: HtmlOptions,
+    rendered: Rendered,
+}
+
+impl Renderer {
+    fn out(&mut self) -> &mut String {
+        &mut self.rendered.html
+    }
+
+    fn push(&mut self, html: &str) {
+        self.rendered.html.push_str(html);
+    }
+
+    fn nodes(&mut self, nodes: &[Value]) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn wrap(&mut self, tag: &str, node: &Value) {
+        self.push(&format!("<{}>", tag));
+        self.nodes(children(node));
+        self.push(&format!("</{}>", tag));
+    }
+
+    /// Write an ` id` for the next outline entry, if anchors are on
+    fn anchor(&mut self, prefix: &str, title: String, level: Option<u8>) {
+        if !self.options.anchors {
+            return;
+        }
+        let id = format!("{}-{}", prefix, self.rendered.outline.len() + 1);
+        attribute(&mut self.rendered.html, "id", &id);
+        self.rendered
+            .outline
+            .push(OutlineEntry { id, title, level });
+    }
+
+    fn node(&mut self, node: &Value) {
+        match node_type(node) {
+            "text" => render_text(node, self.out()),
+            "paragraph" => self.wrap("p", node),
+            "blockquote" => self.wrap("blockquote", node),
+            "bulletList" => self.wrap("ul", node),
+            "listItem" => self.wrap("li", node),
+            "heading" => {
+                let level = attr(node, "level")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(1)
+                    .clamp(1, 6) as u8;
+                self.push(&format!("<h{}", level));
+                let title = plain_text(node).trim().to_string();
+                self.anchor("heading", title, Some(level));
+                self.push(">");
+                self.nodes(children(node));
+                self.push(&format!("</h{}>", level));
+            }
+            "orderedList" => {
+                self.push("<ol");
+                match attr(node, "start").and_then(Value::as_u64) {
+                    Some(start) if start != 1 => attribute(self.out(), "start", &start.to_string()),
+                    _ => {}
+                }
+                self.push(">");
+                self.nodes(children(node));
+                self.push("</ol>");
+            }
+            "taskList" => {
+                self.push("<ul data-type=\"taskList\">");
+                self.nodes(children(node));
+                self.push("</ul>");
+            }
+            "taskItem" => {
+                let checked = attr(node, "checked").and_then(Value::as_bool) == Some(true);
+                self.push("<li data-type=\"taskItem\"");
+                attribute(
+                    self.out(),
+                    "data-checked",
+                    if checked { "true" } else { "false" },
+                );
+                self.push(">");
+                self.nodes(children(node));
+                self.push("</li>");
+            }
+            "codeBlock" => {
+                self.push("<pre><code");
+                if let Some(language) = attr_str(node, "language") {
+                    attribute(self.out(), "class", &format!("language-{}", language));
+                }
+                self.push(">");
+                escape(&plain_text(node), self.out());
+                self.push("</code></pre>");
+            }
+            "sceneBlock" => {
+                self.push("<section data-scene-block=\"\" class=\"scene-block\"");
+                let name = attr_str(node, "sceneName");
+                self.anchor("scene", name.unwrap_or("Scene").to_string(), None);
+                if let Some(id) = attr_str(node, "sceneId") {
+                    attribute(self.out(), "data-scene-id", id);
+                }
+                if let Some(name) = name {
+                    attribute(self.out(), "data-scene-name", name);
+                }
+                if let Some(tension) = attr(node, "tensionLevel") {
+                    attribute(self.out(), "data-tension", &tension.to_string());
+                }
+                self.push(">");
+                self.nodes(children(node));
+                self.push("</section>");
+            }
+            "image" if self.options.skip_images => {}
+            "image" => {
+                self.push("<img");
+                for name in ["src", "alt", "title"] {
+                    if let Some(value) = attr_str(node, name) {
+                        attribute(self.out(), name, value);
+                    }
+                }
+                self.push(" />");
+            }
+            "hardBreak" => self.push("<br />"),
+            "horizontalRule" => self.push("<hr />"),
+            // Nodes without an HTML form keep their content
+            _ if children(node).iter().any(|c| node_type(c) == "text") => self.wrap("p", node),
+            _ => self.nodes(children(node)),
+        }
+    }
+}
+
+fn render_text(node: &Value, out: &mut String) {
+    let marks = node
+        .get("marks")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+    let mut closing = Vec::new();
+    for mark in marks {
+        let (open, close) = match node_type(mark) {
+            "bold" => ("<strong>".to_string(), "</strong>"),
+            "italic" => ("<em>".to_string(), "</em>"),
+            "strike" => ("<s>".to_string(), "</s>"),
+            "underline" => ("<u>".to_string(), "</u>"),
+            "code" => ("<code>".to_string(), "</code>"),
+            "link" => {
+                let mut open = "<a".to_string();
+                attribute(
+                    &mut open,
+                    "href",
+                    attr_str(mark, "href").unwrap_or_default(),
+                );
+                open.push('>');
+                (open, "</a>")
+            }
+            "entity" => {
+                let mut open = "<span".to_string();
+                if let Some(id) = attr_str(mark, "entityId") {
+                    attribute(&mut open, "data-entity-id", id);
+                }
+                if let Some(kind) = attr_str(mark, "entityType") {
+                    attribute(&mut open, "data-entity-type", kind);
+                }
+                open.push('>');
+                (open, "</span>")
+            }
+            _ => continue,
+        };
+        out.push_str(&open);
+        closing.push(close);
+    }
+    escape(
+        node.get("text").and_then(Value::as_str).unwrap_or_default(),
+        out,
+    );
+    for close in closing.into_iter().rev() {
+        out.push_str(close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_editor_html() {
+        let doc = json!({"type": "doc", "content": [
+            {"type": "heading", "attrs": {"level": 2}, "content": [{"type": "text", "text": "Storm & \"Sea\""}]},
+            {"type": "sceneBlock", "attrs": {"sceneId": "s1", "sceneName": "Dock", "tensionLevel": 7}, "content": [
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "Kael", "marks": [{"type": "entity", "attrs": {"entityId": "e1", "entityType": "character"}}]},
+                    {"type": "text", "text": " ran"},
+                    {"type": "hardBreak"},
+                    {"type": "text", "text": "<fast>", "marks": [{"type": "bold"}, {"type": "italic"}]}
+                ]}
+            ]},
+            {"type": "taskList", "content": [
+                {"type": "taskItem", "attrs": {"checked": true}, "content": [{"type": "paragraph", "content": [{"type": "text", "text": "Done"}]}]}
+            ]},
+            {"type": "orderedList", "attrs": {"start": 3}, "content": [
+                {"type": "listItem", "content": [{"type": "paragraph"}]}
+            ]},
+            {"type": "codeBlock", "attrs": {"language": "rust"}, "content": [{"type": "text", "text": "a < b"}]},
+            {"type": "horizontalRule"}
+        ]});
+        assert_eq!(
+            render(&doc),
+            concat!(
+                "<h2>Storm &amp; &quot;Sea&quot;</h2>",
+                "<section data-scene-block=\"\" class=\"scene-block\" data-scene-id=\"s1\" data-scene-name=\"Dock\" data-tension=\"7\">",
+                "<p><span data-entity-id=\"e1\" data-entity-type=\"character\">Kael</span> ran<br />",
+                "<strong><em>&lt;fast&gt;</em></strong></p></section>",
+                "<ul data-type=\"taskList\"><li data-type=\"taskItem\" data-checked=\"true\"><p>Done</p></li></ul>",
+                "<ol start=\"3\"><li><p></p></li></ol>",
+                "<pre><code class=\"language-rust\">a &lt; b</code></pre>",
+                "<hr />"
+            )
+        );
+        assert_eq!(render(&Value::String(doc.to_string())), render(&doc));
+
+        let anchored = render_with(
+            &doc,
+            HtmlOptions {
+                anchors: true,
+                skip_images: true,
+            },
+        );
+        assert!(anchored.html.starts_with("<h2 id=\"heading-1\">"));
+        assert!(anchored
+            .html
+            .contains("class=\"scene-block\" id=\"scene-2\""));
+        assert_eq!(
+            anchored.outline,
+            [
+                OutlineEntry {
+                    id: "heading-1".into(),
+                    title: "Storm & \"Sea\"".into(),
+                    level: Some(2),
+                },
+                OutlineEntry {
+                    id: "scene-2".into(),
+                    title: "Dock".into(),
+                    level: None,
+                },
+            ]
+        );
+    }
+}
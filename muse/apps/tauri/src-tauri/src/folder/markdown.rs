@@ -0,0 +1,1149 @@
+//! ProseMirror JSON ↔ Markdown
+//!
+//! Covers the editor's writing nodes: paragraphs, headings, blockquotes,
+//! bullet/ordered/task lists, code blocks, rules and hard breaks, with bold,
+//! italic, strike, code, underline (`<u>`), link and entity marks. Entity
+//! marks become links to `entity:<type>/<id>`. Empty paragraphs are written
+//! as `<p></p>` so blank lines in a manuscript survive.
+//!
+//! `parse` produces the JSON the editor's `getJSON()` does for those nodes;
+//! anything else (scene blocks, images, tables, extra attributes) is rendered
+//! as plain text and `super` keeps the original JSON beside the file.
+
+use serde_json::{json, Value};
+
+/// Marks in the editor schema's rank order; `parse` emits marks in this order
+const MARK_RANK: [&str; 7] = [
+    "link",
+    "bold",
+    "code",
+    "italic",
+    "strike",
+    "underline",
+    "entity",
+];
+
+const ENTITY_SCHEME: &str = "entity:";
+
+/// The entity mark's default `entityType`
+const DEFAULT_ENTITY_TYPE: &str = "character";
+
+const EMPTY_PARAGRAPH: &str = "<p></p>";
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn attr<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    node.get("attrs").and_then(|attrs| attrs.get(name))
+}
+
+/// `content` as stored: a ProseMirror doc, or one serialized to a string
+pub fn as_doc(content: &Value) -> Value {
+    match content {
+        Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| {
+            json!({"type": "doc", "content": [{"type": "paragraph", "content": [{"type": "text", "text": s}]}]})
+        }),
+        other => other.clone(),
+    }
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Render a ProseMirror doc as Markdown (no trailing newline)
+pub fn render(doc: &Value) -> String {
+    let blocks = children(doc);
+    // The editor's empty document
+    if blocks.len() == 1 && node_type(&blocks[0]) == "paragraph" && children(&blocks[0]).is_empty()
+    {
+        return String::new();
+    }
+    render_blocks(blocks).join("\n")
+}
+
+fn render_blocks(nodes: &[Value]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut alternate = false;
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        // Back-to-back lists only stay apart if their markers differ
+        alternate = i > 0
+            && list_family(&nodes[i - 1])
+                .is_some_and(|prev| list_family(node) == Some(prev) && !alternate);
+        if list_family(node).is_some() {
+            render_list(node, alternate, &mut lines);
+        } else {
+            render_block(node, &mut lines);
+        }
+    }
+    lines
+}
+
+/// Lists that would merge if written one after another with the same marker
+fn list_family(node: &Value) -> Option<bool> {
+    match node_type(node) {
+        "bulletList" | "taskList" => Some(false),
+        "orderedList" => Some(true),
+        _ => None,
+    }
+}
+
+/// A list, with `*` bullets or `)` numbers instead of `-` and `.` when
+/// `alternate`
+fn render_list(node: &Value, alternate: bool, lines: &mut Vec<String>) {
+    let bullet = if alternate { "*" } else { "-" };
+    match node_type(node) {
+        "taskList" => {
+            for item in children(node) {
+                let checked = attr(item, "checked").and_then(Value::as_bool) == Some(true);
+                let marker = format!("{} [{}] ", bullet, if checked { 'x' } else { ' ' });
+                render_item(item, &marker, lines);
+            }
+        }
+        "orderedList" => {
+            let start = attr(node, "start").and_then(Value::as_u64).unwrap_or(1);
+            let delimiter = if alternate { ')' } else { '.' };
+            for (i, item) in children(node).iter().enumerate() {
+                render_item(item, &format!("{}{} ", start + i as u64, delimiter), lines);
+            }
+        }
+        _ => {
+            for item in children(node) {
+                render_item(item, &format!("{} ", bullet), lines);
+            }
+        }
+    }
+}
+
+fn render_block(node: &Value, lines: &mut Vec<String>) {
+    match node_type(node) {
+        "paragraph" => {
+            if children(node).is_empty() {
+                lines.push(EMPTY_PARAGRAPH.to_string());
+            } else {
+                lines.extend(render_inlines(children(node)));
+            }
+        }
+        "heading" => {
+            let level = attr(node, "level")
+                .and_then(Value::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6) as usize;
+            let mut text = render_inlines(children(node));
+            text[0] = format!("{} {}", "#".repeat(level), text[0]);
+            lines.extend(text);
+        }
+        "blockquote" => {
+            for line in render_blocks(children(node)) {
+                lines.push(if line.is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                });
+            }
+        }
+        "bulletList" | "taskList" | "orderedList" => render_list(node, false, lines),
+        "codeBlock" => {
+            let text = plain_text(node);
+            let longest = text
+                .lines()
+                .map(|line| line.chars().take_while(|&c| c == '`').count())
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat(longest.max(2) + 1);
+            let language = attr(node, "language")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            lines.push(format!("{}{}", fence, language));
+            if !text.is_empty() {
+                lines.extend(text.split('\n').map(str::to_string));
+            }
+            lines.push(fence);
+        }
+        "horizontalRule" => lines.push("---".to_string()),
+        // Unsupported nodes keep their text readable; the sidecar keeps the rest
+        _ => {
+            let blocks = children(node);
+            if blocks.iter().any(is_inline) {
+                lines.extend(render_inlines(blocks));
+            } else if blocks.is_empty() {
+                lines.push(EMPTY_PARAGRAPH.to_string());
+            } else {
+                lines.extend(render_blocks(blocks));
+            }
+        }
+    }
+}
+
+fn render_item(item: &Value, marker: &str, lines: &mut Vec<String>) {
+    // Continuation lines align with the item's text ("- [ ] " items with "- ")
+    let indent = if marker.starts_with(['-', '*']) {
+        2
+    } else {
+        marker.len()
+    };
+    let blocks = children(item);
+    let mut body = Vec::new();
+    for (i, block) in blocks.iter().enumerate() {
+        // Nested lists stay tight under their parent paragraph
+        if i > 0 && !node_type(block).ends_with("List") {
+            body.push(String::new());
+        }
+        render_block(block, &mut body);
+    }
+    if body.is_empty() {
+        body.push(String::new());
+    }
+    for (i, line) in body.into_iter().enumerate() {
+        lines.push(if i == 0 {
+            format!("{}{}", marker, line).trim_end().to_string()
+        } else if line.is_empty() {
+            line
+        } else {
+            format!("{}{}", " ".repeat(indent), line)
+        });
+    }
+}
+
+fn is_inline(node: &Value) -> bool {
+    matches!(node_type(node), "text" | "hardBreak")
+}
+
+fn plain_text(node: &Value) -> String {
+    let mut out = String::new();
+    for child in children(node) {
+        match node_type(child) {
+            "text" => out.push_str(
+                child
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            ),
+            "hardBreak" => out.push('\n'),
+            _ => out.push_str(&plain_text(child)),
+        }
+    }
+    out
+}
+
+fn marks(node: &Value) -> Vec<&Value> {
+    node.get("marks")
+        .and_then(Value::as_array)
+        .map(|marks| marks.iter().collect())
+        .unwrap_or_default()
+}
+
+fn open_mark(mark: &Value) -> String {
+    match node_type(mark) {
+        "bold" => "**".into(),
+        "italic" => "*".into(),
+        "strike" => "~~".into(),
+        "underline" => "<u>".into(),
+        "link" | "entity" => "[".into(),
+        _ => String::new(),
+    }
+}
+
+fn close_mark(mark: &Value) -> String {
+    match node_type(mark) {
+        "bold" => "**".into(),
+        "italic" => "*".into(),
+        "strike" => "~~".into(),
+        "underline" => "</u>".into(),
+        "link" => {
+            let href = attr(mark, "href")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            format!("]({})", link_destination(href))
+        }
+        "entity" => {
+            let id = attr(mark, "entityId")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let kind = attr(mark, "entityType")
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_ENTITY_TYPE);
+            format!("]({}{}/{})", ENTITY_SCHEME, kind, id)
+        }
+        _ => String::new(),
+    }
+}
+
+fn link_destination(href: &str) -> String {
+    if href.is_empty() || href.contains([' ', '(', ')']) {
+        format!("<{}>", href)
+    } else {
+        href.to_string()
+    }
+}
+
+/// Inline content as Markdown lines (hard breaks end a line with `\`)
+fn render_inlines(nodes: &[Value]) -> Vec<String> {
+    let mut out = String::new();
+    let mut active: Vec<&Value> = Vec::new();
+    for node in nodes {
+        let node_marks: Vec<&Value> = marks(node)
+            .into_iter()
+            .filter(|m| node_type(m) != "code")
+            .collect();
+        // Delimiters next to whitespace don't count as emphasis in CommonMark,
+        // so whitespace at a mark boundary moves outside it
+        let keep = active.iter().take_while(|m| node_marks.contains(m)).count();
+        if active.len() > keep {
+            let spaces = out.len() - out.trim_end_matches(' ').len();
+            let trailing = out.split_off(out.len() - spaces);
+            while active.len() > keep {
+                out.push_str(&close_mark(active.pop().unwrap()));
+            }
+            out.push_str(&trailing);
+        }
+        let code = marks(node).iter().any(|m| node_type(m) == "code");
+        let mut text = node.get("text").and_then(Value::as_str).unwrap_or_default();
+        if !code && node_marks.iter().any(|m| !active.contains(m)) {
+            let trimmed = text.trim_start_matches(' ');
+            out.push_str(&text[..text.len() - trimmed.len()]);
+            text = trimmed;
+        }
+        for mark in node_marks {
+            if !active.contains(&mark) {
+                out.push_str(&open_mark(mark));
+                active.push(mark);
+            }
+        }
+
+        match node_type(node) {
+            "text" if code => out.push_str(&code_span(text)),
+            "text" => escape_into(text, &mut out),
+            "hardBreak" => out.push_str("\\\n"),
+            _ => escape_into(&plain_text(node), &mut out),
+        }
+    }
+    let spaces = out.len() - out.trim_end_matches(' ').len();
+    let trailing = out.split_off(out.len() - spaces);
+    while let Some(mark) = active.pop() {
+        out.push_str(&close_mark(mark));
+    }
+    out.push_str(&trailing);
+    out.split('\n').map(escape_line_start).collect()
+}
+
+fn code_span(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest + 1);
+    let pad = text.starts_with('`')
+        || text.ends_with('`')
+        || (text.starts_with(' ') && text.ends_with(' ') && !text.trim().is_empty());
+    if pad {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '~' | '[' | ']' | '<') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+/// Escape what would read as a block marker at the start of a line
+fn escape_line_start(line: &str) -> String {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let after_digits = line[digits..].chars().next();
+    let marker = match line.chars().next() {
+        Some('#' | '>') => true,
+        Some('-' | '+') => line.len() == 1 || line[1..].starts_with([' ', '-']),
+        Some(c) if c.is_ascii_digit() => matches!(after_digits, Some('.' | ')')),
+        _ => false,
+    };
+    if !marker {
+        return line.to_string();
+    }
+    if digits > 0 {
+        format!("{}\\{}", &line[..digits], &line[digits..])
+    } else {
+        format!("\\{}", line)
+    }
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// Parse Markdown into a ProseMirror doc
+pub fn parse(markdown: &str) -> Value {
+    let normalized = markdown.replace("\r\n", "\n");
+    let lines: Vec<&str> = normalized.split('\n').collect();
+    let mut content = parse_blocks(&lines);
+    if content.is_empty() {
+        content.push(json!({"type": "paragraph"}));
+    }
+    json!({"type": "doc", "content": content})
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn fence_start(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let run = trimmed.chars().take_while(|&x| x == c).count();
+    let info = trimmed[run..].trim();
+    (run >= 3 && !(c == '`' && info.contains('`'))).then_some((c, run, info))
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    let mut text = rest.trim();
+    // Optional closing sequence
+    let without = text.trim_end_matches('#');
+    if without.len() < text.len() && (without.is_empty() || without.ends_with(' ')) {
+        text = without.trim_end();
+    }
+    Some((level, text))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| compact.chars().all(|x| x == c))
+}
+
+/// A list marker at the start of `line`: (ordered start, content indent)
+fn list_marker(line: &str) -> Option<(Option<u64>, usize)> {
+    let first = line.chars().next()?;
+    if matches!(first, '-' | '*' | '+') {
+        let rest = &line[1..];
+        return (rest.is_empty() || rest.starts_with(' ')).then_some((None, 2));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = &line[digits..];
+    if !(rest.starts_with(". ") || rest.starts_with(") ") || rest == "." || rest == ")") {
+        return None;
+    }
+    Some((line[..digits].parse().ok(), digits + 2))
+}
+
+/// Lines that end a paragraph without a blank line
+fn interrupts(line: &str) -> bool {
+    line == EMPTY_PARAGRAPH
+        || fence_start(line).is_some()
+        || heading(line).is_some()
+        || line.starts_with('>')
+        || is_rule(line)
+        || list_marker(line).is_some()
+}
+
+fn parse_blocks(lines: &[&str]) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if is_blank(line) {
+            i += 1;
+            continue;
+        }
+
+        if line.trim() == EMPTY_PARAGRAPH {
+            blocks.push(json!({"type": "paragraph"}));
+            i += 1;
+        } else if let Some((c, run, info)) = fence_start(line) {
+            let mut text = Vec::new();
+            i += 1;
+            while i < lines.len() {
+                let closing = lines[i].trim();
+                if closing.len() >= run && closing.chars().all(|x| x == c) {
+                    i += 1;
+                    break;
+                }
+                text.push(lines[i]);
+                i += 1;
+            }
+            let language = if info.is_empty() {
+                Value::Null
+            } else {
+                Value::String(info.to_string())
+            };
+            let mut block = json!({"type": "codeBlock", "attrs": {"language": language}});
+            let text = text.join("\n");
+            if !text.is_empty() {
+                block["content"] = json!([{"type": "text", "text": text}]);
+            }
+            blocks.push(block);
+        } else if let Some((level, text)) = heading(line) {
+            let mut block = json!({"type": "heading", "attrs": {"level": level}});
+            let inlines = parse_inlines(text);
+            if !inlines.is_empty() {
+                block["content"] = Value::Array(inlines);
+            }
+            blocks.push(block);
+            i += 1;
+        } else if is_rule(line) {
+            blocks.push(json!({"type": "horizontalRule"}));
+            i += 1;
+        } else if line.starts_with('>') {
+            let mut inner = Vec::new();
+            while i < lines.len() && lines[i].starts_with('>') {
+                let rest = &lines[i][1..];
+                inner.push(rest.strip_prefix(' ').unwrap_or(rest));
+                i += 1;
+            }
+            blocks.push(json!({"type": "blockquote", "content": parse_blocks(&inner)}));
+        } else if let Some((start, _)) = list_marker(line) {
+            let (list, next) = parse_list(lines, i, start);
+            blocks.push(list);
+            i = next;
+        } else {
+            let mut paragraph = vec![line];
+            i += 1;
+            while i < lines.len() && !is_blank(lines[i]) && !interrupts(lines[i]) {
+                paragraph.push(lines[i]);
+                i += 1;
+            }
+            blocks.push(paragraph_block(&paragraph));
+        }
+    }
+    blocks
+}
+
+fn paragraph_block(lines: &[&str]) -> Value {
+    let inlines = parse_inlines(&join_paragraph(lines));
+    if inlines.is_empty() {
+        json!({"type": "paragraph"})
+    } else {
+        json!({"type": "paragraph", "content": inlines})
+    }
+}
+
+/// Join paragraph lines: soft breaks become spaces, hard breaks `\n`
+fn join_paragraph(lines: &[&str]) -> String {
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = if i == 0 { *line } else { line.trim_start() };
+        let last = i + 1 == lines.len();
+        let backslashes = line.len() - line.trim_end_matches('\\').len();
+        if !last && backslashes % 2 == 1 {
+            text.push_str(&line[..line.len() - 1]);
+            text.push('\n');
+        } else if !last && line.ends_with("  ") {
+            text.push_str(line.trim_end());
+            text.push('\n');
+        } else if last {
+            text.push_str(line.trim_end());
+        } else {
+            text.push_str(line);
+            text.push(' ');
+        }
+    }
+    text
+}
+
+fn parse_list(lines: &[&str], mut i: usize, start: Option<u64>) -> (Value, usize) {
+    let ordered = start.is_some();
+    let task = !ordered && task_marker(lines[i].get(2..).unwrap_or_default()).is_some();
+    let delimiter = list_delimiter(lines[i]);
+    let mut items = Vec::new();
+
+    while i < lines.len() {
+        let Some((item_start, indent)) = list_marker(lines[i]) else {
+            break;
+        };
+        // A different marker, or items with and without checkboxes, start a new list
+        let first = lines[i].get(indent..).unwrap_or_default();
+        let checkbox = if ordered { None } else { task_marker(first) };
+        if item_start.is_some() != ordered
+            || list_delimiter(lines[i]) != delimiter
+            || checkbox.is_some() != task
+        {
+            break;
+        }
+        let (checked, first) = checkbox.unwrap_or((false, first));
+        let mut body = vec![first];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            let leading = line.len() - line.trim_start_matches(' ').len();
+            if is_blank(line) {
+                // Blank lines belong to the item only if indented content follows
+                let next = lines[i..].iter().position(|l| !is_blank(l));
+                match next.map(|n| lines[i + n]) {
+                    Some(l) if l.len() - l.trim_start_matches(' ').len() >= indent => {
+                        body.push("");
+                        i += 1;
+                    }
+                    _ => break,
+                }
+            } else if leading >= indent {
+                body.push(&line[indent..]);
+                i += 1;
+            } else if !body.last().is_some_and(|l| is_blank(l)) && !interrupts(line.trim_start()) {
+                // Lazy continuation of the item's paragraph
+                body.push(line.trim_start());
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        let mut content = parse_blocks(&body);
+        if content.is_empty() {
+            content.push(json!({"type": "paragraph"}));
+        }
+        items.push(if task {
+            json!({"type": "taskItem", "attrs": {"checked": checked}, "content": content})
+        } else {
+            json!({"type": "listItem", "content": content})
+        });
+
+        // A blank line between items keeps the list going
+        let next = lines[i..].iter().position(|l| !is_blank(l));
+        match next {
+            Some(n) if list_marker(lines[i + n]).is_some() => i += n,
+            _ => break,
+        }
+    }
+
+    let list = match (start, task) {
+        (Some(start), _) => {
+            json!({"type": "orderedList", "attrs": {"start": start}, "content": items})
+        }
+        (None, true) => json!({"type": "taskList", "content": items}),
+        (None, false) => json!({"type": "bulletList", "content": items}),
+    };
+    (list, i)
+}
+
+/// The bullet character, or the `.`/`)` after an item number
+fn list_delimiter(line: &str) -> Option<char> {
+    line.chars().find(|c| !c.is_ascii_digit())
+}
+
+fn task_marker(text: &str) -> Option<(bool, &str)> {
+    let checked = match text.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let rest = &text[3..];
+    if rest.is_empty() {
+        Some((checked, rest))
+    } else {
+        rest.strip_prefix(' ').map(|rest| (checked, rest))
+    }
+}
+
+/// Inline parser state: text nodes are emitted with the marks active at the time
+struct Inlines {
+    nodes: Vec<Value>,
+    text: String,
+    marks: Vec<Value>,
+}
+
+impl Inlines {
+    fn flush(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.text);
+        let marks = sorted_marks(&self.marks);
+        self.push_text(text, marks);
+    }
+
+    fn push_text(&mut self, text: String, marks: Vec<Value>) {
+        // Adjacent text with the same marks is one node in ProseMirror
+        if let Some(last) = self.nodes.last_mut() {
+            let last_marks = last.get("marks").cloned().unwrap_or(json!([]));
+            if node_type(last) == "text" && last_marks == Value::Array(marks.clone()) {
+                let joined = format!("{}{}", last["text"].as_str().unwrap_or_default(), text);
+                last["text"] = Value::String(joined);
+                return;
+            }
+        }
+        let mut node = json!({"type": "text", "text": text});
+        if !marks.is_empty() {
+            node["marks"] = Value::Array(marks);
+        }
+        self.nodes.push(node);
+    }
+
+    fn has(&self, kind: &str) -> bool {
+        self.marks.iter().any(|m| node_type(m) == kind)
+    }
+
+    fn toggle(&mut self, kind: &str) {
+        self.flush();
+        if let Some(index) = self.marks.iter().position(|m| node_type(m) == kind) {
+            self.marks.remove(index);
+        } else {
+            self.marks.push(json!({"type": kind}));
+        }
+    }
+}
+
+fn sorted_marks(marks: &[Value]) -> Vec<Value> {
+    let mut marks = marks.to_vec();
+    marks.sort_by_key(|m| {
+        MARK_RANK
+            .iter()
+            .position(|&kind| kind == node_type(m))
+            .unwrap_or(MARK_RANK.len())
+    });
+    marks
+}
+
+fn parse_inlines(text: &str) -> Vec<Value> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut state = Inlines {
+        nodes: Vec::new(),
+        text: String::new(),
+        marks: Vec::new(),
+    };
+    parse_span(&chars, &mut state);
+    state.flush();
+    state.nodes
+}
+
+/// Whether `delimiter` occurs unescaped in `chars`
+fn has_closer(chars: &[char], delimiter: &str) -> bool {
+    let delimiter: Vec<char> = delimiter.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i..].starts_with(&delimiter) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+fn parse_span(chars: &[char], state: &mut Inlines) {
+    let outer = state.marks.len();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                state.text.push(chars[i + 1]);
+                i += 2;
+            }
+            '\n' => {
+                state.flush();
+                state.nodes.push(json!({"type": "hardBreak"}));
+                i += 1;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&x| x == '`').count();
+                match find_code_close(chars, i + run, run) {
+                    Some(end) => {
+                        let mut code: String = chars[i + run..end].iter().collect();
+                        if code.len() >= 2
+                            && code.starts_with(' ')
+                            && code.ends_with(' ')
+                            && !code.trim().is_empty()
+                        {
+                            code = code[1..code.len() - 1].to_string();
+                        }
+                        state.flush();
+                        let mut marks = state.marks.clone();
+                        marks.push(json!({"type": "code"}));
+                        let marks = sorted_marks(&marks);
+                        state.push_text(code, marks);
+                        i = end + run;
+                    }
+                    None => {
+                        state.text.extend(&chars[i..i + run]);
+                        i += run;
+                    }
+                }
+            }
+            '*' | '_' => {
+                let mut run = chars[i..].iter().take_while(|&&x| x == c).count();
+                let end = i + run;
+                // `snake_case` stays literal
+                let intraword = c == '_'
+                    && i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && chars.get(end).is_some_and(|n| n.is_alphanumeric());
+                if intraword {
+                    state.text.extend(&chars[i..end]);
+                    i = end;
+                    continue;
+                }
+                let single: String = c.to_string();
+                let double: String = [c, c].iter().collect();
+                while run > 0 {
+                    if run >= 2 && state.has("bold") {
+                        state.toggle("bold");
+                        run -= 2;
+                    } else if state.has("italic") {
+                        state.toggle("italic");
+                        run -= 1;
+                    } else if run >= 2 && has_closer(&chars[end..], &double) {
+                        state.toggle("bold");
+                        run -= 2;
+                    } else if has_closer(&chars[end..], &single) {
+                        state.toggle("italic");
+                        run -= 1;
+                    } else {
+                        state.text.extend(std::iter::repeat_n(c, run));
+                        run = 0;
+                    }
+                }
+                i = end;
+            }
+            '~' if chars.get(i + 1) == Some(&'~') => {
+                if state.has("strike") || has_closer(&chars[i + 2..], "~~") {
+                    state.toggle("strike");
+                } else {
+                    state.text.push_str("~~");
+                }
+                i += 2;
+            }
+            '<' if chars[i..].starts_with(&['<', 'u', '>'])
+                && has_closer(&chars[i + 3..], "</u>") =>
+            {
+                if !state.has("underline") {
+                    state.toggle("underline");
+                }
+                i += 3;
+            }
+            '<' if chars[i..].starts_with(&['<', '/', 'u', '>']) && state.has("underline") => {
+                state.toggle("underline");
+                i += 4;
+            }
+            '[' => match link_at(chars, i) {
+                Some((text_end, href, end)) => {
+                    state.flush();
+                    let mark = link_mark(&href);
+                    state.marks.push(mark);
+                    parse_span(&chars[i + 1..text_end], state);
+                    state.flush();
+                    state.marks.pop();
+                    i = end;
+                }
+                None => {
+                    state.text.push('[');
+                    i += 1;
+                }
+            },
+            _ => {
+                state.text.push(c);
+                i += 1;
+            }
+        }
+    }
+    // Marks opened in this span end with it
+    if state.marks.len() > outer {
+        state.flush();
+        state.marks.truncate(outer);
+    }
+}
+
+fn find_code_close(chars: &[char], from: usize, run: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let len = chars[i..].iter().take_while(|&&x| x == '`').count();
+            if len == run {
+                return Some(i);
+            }
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// `[text](href)` starting at `start`: (end of text, href, index after `)`)
+fn link_at(chars: &[char], start: usize) -> Option<(usize, String, usize)> {
+    let mut depth = 0;
+    let mut i = start;
+    let text_end = loop {
+        match chars.get(i)? {
+            '\\' => i += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&x| x == '`').count();
+                if let Some(end) = find_code_close(chars, i + run, run) {
+                    i = end + run - 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+    let mut i = text_end + 2;
+    let href: String;
+    if chars.get(i) == Some(&'<') {
+        let close = chars[i..].iter().position(|&c| c == '>')? + i;
+        href = chars[i + 1..close].iter().collect();
+        i = close + 1;
+    } else {
+        let mut depth = 0;
+        let begin = i;
+        loop {
+            match chars.get(i)? {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                c if c.is_whitespace() => break,
+                _ => {}
+            }
+            i += 1;
+        }
+        href = chars[begin..i].iter().collect();
+    }
+    (chars.get(i) == Some(&')')).then(|| (text_end, href, i + 1))
+}
+
+fn link_mark(href: &str) -> Value {
+    match href.strip_prefix(ENTITY_SCHEME) {
+        Some(target) => {
+            let (kind, id) = target
+                .split_once('/')
+                .unwrap_or((DEFAULT_ENTITY_TYPE, target));
+            json!({"type": "entity", "attrs": {"entityId": id, "entityType": kind}})
+        }
+        None => json!({"type": "link", "attrs": {"href": href}}),
+    }
+}
+
+// ============================================================================
+// Plain text
+// ============================================================================
+
+fn is_block(node: &Value) -> bool {
+    !matches!(node_type(node), "text" | "hardBreak" | "image" | "mention")
+}
+
+/// The editor's `getText()`: blocks separated by a blank line, hard breaks
+/// as newlines. This is what the web app stores as `contentText`.
+pub fn text_content(doc: &Value) -> String {
+    fn walk(node: &Value, out: &mut String, first: &mut bool) {
+        if is_block(node) {
+            if !*first {
+                out.push_str("\n\n");
+            }
+            *first = false;
+        }
+        match node_type(node) {
+            "text" => out.push_str(node.get("text").and_then(Value::as_str).unwrap_or_default()),
+            "hardBreak" => out.push('\n'),
+            _ => {}
+        }
+        for child in children(node) {
+            walk(child, out, first);
+        }
+    }
+    let mut out = String::new();
+    let mut first = true;
+    for child in children(doc) {
+        walk(child, &mut out, &mut first);
+    }
+    out
+}
+
+/// Word count of plain text, as shown in the editor
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn text(text: &str, marks: Value) -> Value {
+        if marks == json!([]) {
+            json!({"type": "text", "text": text})
+        } else {
+            json!({"type": "text", "text": text, "marks": marks})
+        }
+    }
+
+    fn paragraph(content: Vec<Value>) -> Value {
+        json!({"type": "paragraph", "content": content})
+    }
+
+    fn sample() -> Value {
+        json!({"type": "doc", "content": [
+            {"type": "heading", "attrs": {"level": 2}, "content": [text("Chapter 3: The *Storm*", json!([]))]},
+            paragraph(vec![
+                text("Ada ", json!([])),
+                text("watch", json!([{"type": "bold"}])),
+                text("ed", json!([{"type": "bold"}, {"type": "italic"}])),
+                text(" the ", json!([])),
+                text("sea", json!([{"type": "italic"}])),
+                text(" from ", json!([])),
+                text("the lighthouse", json!([{"type": "link", "attrs": {"href": "https://example.com/a(b)"}}])),
+                text(". ", json!([])),
+                text("Kael", json!([{"type": "entity", "attrs": {"entityId": "e1", "entityType": "character"}}])),
+                text(" said ", json!([])),
+                text("x = `y`", json!([{"type": "code"}])),
+                json!({"type": "hardBreak"}),
+                text("under", json!([{"type": "underline"}])),
+                text(" and ", json!([])),
+                text("gone", json!([{"type": "strike"}])),
+                text(" 1. not_a *list* [x] \\ <tag>", json!([])),
+            ]),
+            {"type": "paragraph"},
+            paragraph(vec![text("# not a heading", json!([]))]),
+            {"type": "blockquote", "content": [
+                paragraph(vec![text("Quoted", json!([]))]),
+                paragraph(vec![text("Twice", json!([]))]),
+            ]},
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [
+                    paragraph(vec![text("One", json!([]))]),
+                    {"type": "orderedList", "attrs": {"start": 3}, "content": [
+                        {"type": "listItem", "content": [paragraph(vec![text("Three", json!([]))])]},
+                        {"type": "listItem", "content": [
+                            paragraph(vec![text("Four", json!([]))]),
+                            paragraph(vec![text("More", json!([]))]),
+                        ]},
+                    ]},
+                ]},
+                {"type": "listItem", "content": [paragraph(vec![text("Two", json!([]))])]},
+            ]},
+            {"type": "taskList", "content": [
+                {"type": "taskItem", "attrs": {"checked": true}, "content": [paragraph(vec![text("Done", json!([]))])]},
+                {"type": "taskItem", "attrs": {"checked": false}, "content": [paragraph(vec![text("Todo", json!([]))])]},
+            ]},
+            {"type": "codeBlock", "attrs": {"language": "rust"}, "content": [text("fn main() {\n```\n}", json!([]))]},
+            {"type": "horizontalRule"},
+            {"type": "codeBlock", "attrs": {"language": null}},
+        ]})
+    }
+
+    #[test]
+    fn round_trips_supported_nodes_and_marks() {
+        let doc = sample();
+        let markdown = render(&doc);
+        assert_eq!(parse(&markdown), doc, "{}", markdown);
+        assert!(
+            markdown.contains("[Kael](entity:character/e1)"),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("**watch*ed*** the *sea*"), "{}", markdown);
+
+        // Whitespace at a mark boundary is written outside the delimiters
+        let spaced = json!({"type": "doc", "content": [paragraph(vec![
+            text("a", json!([])),
+            text(" b ", json!([{"type": "bold"}])),
+            text("c", json!([])),
+        ])]});
+        assert_eq!(render(&spaced), "a **b** c");
+        assert!(markdown.contains("<p></p>"));
+
+        let empty = json!({"type": "doc", "content": [{"type": "paragraph"}]});
+        assert_eq!(render(&empty), "");
+        assert_eq!(parse(""), empty);
+    }
+
+    #[test]
+    fn parses_hand_written_markdown() {
+        let doc = parse(
+            "# Title #\n\nA paragraph\nwrapped by hand,  \nthen broken.\n\n\
+             * one\n* two\ncontinued\n\n1) first\n\n2) second\n\n___\n\n~~~\ncode\n~~~\n\n\
+             A snake_case _word_ and 2 * 3 stay put.",
+        );
+        assert_eq!(
+            doc,
+            json!({"type": "doc", "content": [
+                {"type": "heading", "attrs": {"level": 1}, "content": [text("Title", json!([]))]},
+                paragraph(vec![
+                    text("A paragraph wrapped by hand,", json!([])),
+                    json!({"type": "hardBreak"}),
+                    text("then broken.", json!([])),
+                ]),
+                {"type": "bulletList", "content": [
+                    {"type": "listItem", "content": [paragraph(vec![text("one", json!([]))])]},
+                    {"type": "listItem", "content": [paragraph(vec![text("two continued", json!([]))])]},
+                ]},
+                {"type": "orderedList", "attrs": {"start": 1}, "content": [
+                    {"type": "listItem", "content": [paragraph(vec![text("first", json!([]))])]},
+                    {"type": "listItem", "content": [paragraph(vec![text("second", json!([]))])]},
+                ]},
+                {"type": "horizontalRule"},
+                {"type": "codeBlock", "attrs": {"language": null}, "content": [text("code", json!([]))]},
+                paragraph(vec![
+                    text("A snake_case ", json!([])),
+                    text("word", json!([{"type": "italic"}])),
+                    text(" and 2 * 3 stay put.", json!([])),
+                ]),
+            ]})
+        );
+    }
+
+    #[test]
+    fn text_content_matches_the_editor() {
+        let doc = json!({"type": "doc", "content": [
+            paragraph(vec![text("One", json!([])), json!({"type": "hardBreak"}), text("line", json!([]))]),
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [paragraph(vec![text("Item", json!([]))])]},
+            ]},
+        ]});
+        assert_eq!(text_content(&doc), "One\nline\n\n\n\n\n\nItem");
+        assert_eq!(word_count(&text_content(&doc)), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn plain_paragraphs_round_trip(s in "[a-zA-Z0-9 #>*_~`\\[\\]()<>!\\\\.+-]{1,40}") {
+            let s = s.trim();
+            prop_assume!(!s.is_empty());
+            let doc = json!({"type": "doc", "content": [paragraph(vec![text(s, json!([]))])]});
+            prop_assert_eq!(parse(&render(&doc)), doc);
+        }
+    }
+}
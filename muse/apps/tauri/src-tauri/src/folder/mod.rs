@@ -0,0 +1,883 @@
+//! Story-as-code folder mirror
+//!
+//! A project as a folder of plain-text files that can live in git and be
+//! edited with other tools:
+//!
+//! ```text
+//! rhei.toml                      format, project and relationships
+//! documents/<title>.md           TOML front matter + Markdown body
+//! entities/<type>/<name>.toml    one file per entity
+//! .rhei/<table>.json             mentions, analysis and captures
+//! .rhei/content/<id>.json        what a document's Markdown can't express
+//! ```
+//!
+//! Export and import round-trip losslessly. A document's `contentText` and
+//! `wordCount` are derived from its body the way the editor and server derive
+//! them; where the stored row differs, or the Markdown can't reproduce the
+//! editor JSON exactly, the originals go into a sidecar keyed by a hash of
+//! the body. Once the body is edited the sidecar no longer applies and the
+//! Markdown wins.
+//!
+//! Export rewrites only files whose bytes change and removes files it no
+//! longer produces from `documents/`, `entities/` and `.rhei/`. Nothing else
+//! in the folder is touched.
+
+pub mod html;
+pub mod markdown;
+pub mod rows;
+pub mod watcher;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::db::{ProjectSnapshot, ProjectStore, StoreError, SyncTable};
+
+/// `format` value identifying our folders
+pub const FOLDER_FORMAT: &str = "rhei-story-folder";
+
+/// Current folder layout version; readers accept this and older
+pub const FOLDER_VERSION: u32 = 1;
+
+pub const MANIFEST_FILE: &str = "rhei.toml";
+pub const DOCUMENTS_DIR: &str = "documents";
+pub const ENTITIES_DIR: &str = "entities";
+pub const META_DIR: &str = ".rhei";
+const CONTENT_DIR: &str = "content";
+
+/// Tables kept as JSON under `.rhei/`; they're derived, not hand-edited
+const DERIVED_TABLES: [SyncTable; 3] = [
+    SyncTable::Mentions,
+    SyncTable::Analysis,
+    SyncTable::Captures,
+];
+
+/// Document fields that live in the body or are derived from it
+const BODY_FIELDS: [&str; 3] = ["content", "contentText", "wordCount"];
+
+const SIDECAR_HASH: &str = "markdownSha256";
+
+#[derive(Debug, thiserror::Error)]
+pub enum FolderError {
+    #[error("`{0}` is not a story folder")]
+    NotAFolder(String),
+    #[error("story folder version {0} is newer than this app supports")]
+    UnsupportedVersion(u32),
+    #[error("the folder belongs to project `{0}`")]
+    ProjectMismatch(String),
+    #[error("`{0}` already has files that aren't a story folder")]
+    NotEmpty(String),
+    #[error("{path}: {message}")]
+    Invalid { path: String, message: String },
+    #[error("`{id}` is defined in both {first} and {second}")]
+    DuplicateId {
+        id: String,
+        first: String,
+        second: String,
+    },
+    #[error("story folder I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("watching the story folder failed: {0}")]
+    Watch(String),
+    #[error("story folder JSON is invalid: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl FolderError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotAFolder(_) => "folder_not_a_folder",
+            Self::UnsupportedVersion(_) => "folder_unsupported_version",
+            Self::ProjectMismatch(_) => "folder_project_mismatch",
+            Self::NotEmpty(_) => "folder_not_empty",
+            Self::Invalid { .. } => "folder_invalid",
+            Self::DuplicateId { .. } => "folder_duplicate_id",
+            Self::Watch(_) => "folder_watch",
+            Self::Io(_) => "folder_io",
+            Self::Json(_) => "folder_json",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(FolderError);
+
+fn invalid(path: &Path, message: impl Into<String>) -> FolderError {
+    FolderError::Invalid {
+        path: path.display().to_string(),
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderManifest {
+    pub format: String,
+    pub version: u32,
+    pub project_id: String,
+    pub snapshot_version: i64,
+    #[serde(default)]
+    pub synced_at: String,
+    #[serde(default)]
+    pub relationships: Vec<toml::Table>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSummary {
+    pub project_id: String,
+    pub documents: usize,
+    pub entities: usize,
+    pub relationships: usize,
+    /// Documents with a sidecar next to their Markdown
+    pub sidecars: usize,
+    /// Files written or removed; unchanged files are left alone
+    pub changed: usize,
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// File name stem for a title: lowercase words joined by `-`
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 64 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Row ids as file names (server ids are already safe)
+pub(crate) fn id_file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn row_id(row: &Map<String, Value>) -> Option<&str> {
+    row.get("id").and_then(Value::as_str)
+}
+
+fn str_field<'a>(row: &'a Map<String, Value>, key: &str) -> &'a str {
+    row.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+/// A document as it is written to disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentFile {
+    pub text: String,
+    /// Stored fields the Markdown can't reproduce, or `None`
+    pub sidecar: Option<Map<String, Value>>,
+}
+
+/// Render a document row as front matter + Markdown, with a sidecar if needed
+pub fn document_file(row: &Map<String, Value>) -> DocumentFile {
+    let mut front = row.clone();
+    for field in BODY_FIELDS {
+        front.remove(field);
+    }
+    let doc = row
+        .get("content")
+        .map(markdown::as_doc)
+        .unwrap_or_else(|| markdown::parse(""));
+    let body = markdown::render(&doc);
+    let text = rows::with_front_matter(&front, &body);
+
+    // Whatever reading the file back wouldn't reproduce goes in the sidecar
+    let derived = derive_body_fields(&body, None);
+    let mut sidecar = Map::new();
+    for field in BODY_FIELDS {
+        if row.get(field) != derived.get(field) {
+            sidecar.insert(
+                field.to_string(),
+                row.get(field).cloned().unwrap_or(Value::Null),
+            );
+        }
+    }
+    if sidecar.is_empty() {
+        return DocumentFile {
+            text,
+            sidecar: None,
+        };
+    }
+    sidecar.insert(SIDECAR_HASH.to_string(), Value::String(sha256_hex(&body)));
+    DocumentFile {
+        text,
+        sidecar: Some(sidecar),
+    }
+}
+
+/// `content`, `contentText` and `wordCount` for a body, taking the sidecar's
+/// values (`null` meaning absent) while the body is unchanged
+fn derive_body_fields(body: &str, sidecar: Option<&Map<String, Value>>) -> Map<String, Value> {
+    let sidecar = sidecar
+        .filter(|s| s.get(SIDECAR_HASH).and_then(Value::as_str) == Some(sha256_hex(body).as_str()));
+    let stored = |field: &str| sidecar.and_then(|s| s.get(field)).cloned();
+
+    let mut fields = Map::new();
+    let content = stored("content").unwrap_or_else(|| markdown::parse(body));
+    let text = stored("contentText").unwrap_or_else(|| {
+        let doc = if content.is_null() {
+            markdown::parse(body)
+        } else {
+            markdown::as_doc(&content)
+        };
+        Value::String(markdown::text_content(&doc))
+    });
+    let words = stored("wordCount")
+        .unwrap_or_else(|| Value::from(markdown::word_count(text.as_str().unwrap_or_default())));
+    for (field, value) in BODY_FIELDS.into_iter().zip([content, text, words]) {
+        if !value.is_null() {
+            fields.insert(field.to_string(), value);
+        }
+    }
+    fields
+}
+
+/// Read a document file (and its sidecar, if any) back into a row
+pub fn read_document(
+    text: &str,
+    sidecar: Option<&Map<String, Value>>,
+) -> Result<Map<String, Value>, String> {
+    let (mut row, body) = rows::split_front_matter(text)?;
+    for field in BODY_FIELDS {
+        row.remove(field);
+    }
+    row.extend(derive_body_fields(&body, sidecar));
+    Ok(row)
+}
+
+pub fn entity_file(row: &Map<String, Value>) -> String {
+    rows::to_toml_string(row)
+}
+
+pub fn read_entity(text: &str) -> Result<Map<String, Value>, String> {
+    rows::from_toml_str(text)
+}
+
+fn table_rows(snapshot: &ProjectSnapshot, table: SyncTable) -> &[Value] {
+    match table {
+        SyncTable::Documents => &snapshot.documents,
+        SyncTable::Entities => &snapshot.entities,
+        SyncTable::Relationships => &snapshot.relationships,
+        SyncTable::Mentions => &snapshot.mentions,
+        SyncTable::Analysis => &snapshot.analysis,
+        SyncTable::Captures => &snapshot.captures,
+    }
+}
+
+fn table_rows_mut(snapshot: &mut ProjectSnapshot, table: SyncTable) -> &mut Vec<Value> {
+    match table {
+        SyncTable::Documents => &mut snapshot.documents,
+        SyncTable::Entities => &mut snapshot.entities,
+        SyncTable::Relationships => &mut snapshot.relationships,
+        SyncTable::Mentions => &mut snapshot.mentions,
+        SyncTable::Analysis => &mut snapshot.analysis,
+        SyncTable::Captures => &mut snapshot.captures,
+    }
+}
+
+fn objects(rows: &[Value], table: SyncTable) -> Result<Vec<&Map<String, Value>>, FolderError> {
+    rows.iter()
+        .map(|row| {
+            row.as_object()
+                .filter(|row| row_id(row).is_some())
+                .ok_or_else(|| invalid(Path::new(table.as_str()), "row without an id"))
+        })
+        .collect()
+}
+
+/// Where each document and entity of `snapshot` goes, relative to the root
+fn plan_paths(snapshot: &ProjectSnapshot) -> Result<HashMap<String, PathBuf>, FolderError> {
+    let mut paths = HashMap::new();
+    let mut taken = HashSet::new();
+    let mut claim = |dir: PathBuf, stem: String, extension: &str| {
+        let mut n = 1;
+        loop {
+            let name = if n == 1 {
+                format!("{}.{}", stem, extension)
+            } else {
+                format!("{}-{}.{}", stem, n, extension)
+            };
+            let path = dir.join(name);
+            if taken.insert(path.clone()) {
+                return path;
+            }
+            n += 1;
+        }
+    };
+
+    let mut documents = objects(&snapshot.documents, SyncTable::Documents)?;
+    // Manuscript order, so earlier chapters keep the plain names
+    documents.sort_by(|a, b| {
+        let order = |row: &Map<String, Value>| row.get("orderIndex").and_then(Value::as_f64);
+        order(a)
+            .partial_cmp(&order(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| row_id(a).cmp(&row_id(b)))
+    });
+    for row in documents {
+        let path = claim(
+            PathBuf::from(DOCUMENTS_DIR),
+            slugify(str_field(row, "title")),
+            "md",
+        );
+        paths.insert(format!("documents/{}", str_field(row, "id")), path);
+    }
+
+    let mut entities = objects(&snapshot.entities, SyncTable::Entities)?;
+    entities.sort_by_key(|row| (str_field(row, "type"), str_field(row, "name"), row_id(row)));
+    for row in entities {
+        let dir = Path::new(ENTITIES_DIR).join(slugify(str_field(row, "type")));
+        let path = claim(dir, slugify(str_field(row, "name")), "toml");
+        paths.insert(format!("entities/{}", str_field(row, "id")), path);
+    }
+    Ok(paths)
+}
+
+fn sorted_by_id(rows: Vec<&Map<String, Value>>) -> Vec<&Map<String, Value>> {
+    let mut rows = rows;
+    rows.sort_by_key(|row| row_id(row));
+    rows
+}
+
+/// Every file of the folder for `snapshot`, keyed by relative path
+pub fn folder_files(
+    snapshot: &ProjectSnapshot,
+) -> Result<(BTreeMap<PathBuf, String>, FolderSummary), FolderError> {
+    let paths = plan_paths(snapshot)?;
+    let mut files = BTreeMap::new();
+    let mut summary = FolderSummary {
+        project_id: snapshot.project_id.clone(),
+        ..Default::default()
+    };
+
+    for row in objects(&snapshot.documents, SyncTable::Documents)? {
+        let file = document_file(row);
+        let id = str_field(row, "id");
+        files.insert(paths[&format!("documents/{}", id)].clone(), file.text);
+        if let Some(sidecar) = file.sidecar {
+            let path = sidecar_path(id);
+            files.insert(path, serde_json::to_string_pretty(&sidecar)? + "\n");
+            summary.sidecars += 1;
+        }
+        summary.documents += 1;
+    }
+    for row in objects(&snapshot.entities, SyncTable::Entities)? {
+        let path = paths[&format!("entities/{}", str_field(row, "id"))].clone();
+        files.insert(path, entity_file(row));
+        summary.entities += 1;
+    }
+    for table in DERIVED_TABLES {
+        let rows = sorted_by_id(objects(table_rows(snapshot, table), table)?);
+        if !rows.is_empty() {
+            let path = Path::new(META_DIR).join(format!("{}.json", table.as_str()));
+            files.insert(path, serde_json::to_string_pretty(&rows)? + "\n");
+        }
+    }
+
+    let relationships = sorted_by_id(objects(&snapshot.relationships, SyncTable::Relationships)?);
+    summary.relationships = relationships.len();
+    let manifest = FolderManifest {
+        format: FOLDER_FORMAT.to_string(),
+        version: FOLDER_VERSION,
+        project_id: snapshot.project_id.clone(),
+        snapshot_version: snapshot.version,
+        synced_at: snapshot.synced_at.clone(),
+        relationships: relationships.into_iter().map(rows::to_table).collect(),
+    };
+    let manifest =
+        toml::to_string(&manifest).map_err(|e| invalid(Path::new(MANIFEST_FILE), e.to_string()))?;
+    files.insert(PathBuf::from(MANIFEST_FILE), manifest);
+    Ok((files, summary))
+}
+
+/// Files under the directories the mirror owns, relative to `root`
+pub(crate) fn managed_files(root: &Path) -> Result<Vec<PathBuf>, FolderError> {
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        let entries = match fs::read_dir(root.join(dir)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                walk(root, &path, out)?;
+            } else {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    for dir in [DOCUMENTS_DIR, ENTITIES_DIR, META_DIR] {
+        walk(root, Path::new(dir), &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Files the mirror reads back: Markdown, TOML and JSON (editor swap files
+/// and the like are ignored)
+fn is_mirror_file(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str());
+    match path
+        .components()
+        .next()
+        .and_then(|c| c.as_os_str().to_str())
+    {
+        Some(DOCUMENTS_DIR) => extension == Some("md"),
+        Some(ENTITIES_DIR) => extension == Some("toml"),
+        Some(META_DIR) => extension == Some("json"),
+        _ => false,
+    }
+}
+
+fn read_manifest(root: &Path) -> Result<Option<FolderManifest>, FolderError> {
+    let path = root.join(MANIFEST_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let manifest: FolderManifest =
+        toml::from_str(&text).map_err(|e| invalid(&path, e.to_string()))?;
+    if manifest.format != FOLDER_FORMAT {
+        return Err(FolderError::NotAFolder(root.display().to_string()));
+    }
+    if manifest.version > FOLDER_VERSION {
+        return Err(FolderError::UnsupportedVersion(manifest.version));
+    }
+    Ok(Some(manifest))
+}
+
+/// Write `snapshot` into `root`, creating it if needed
+pub fn write_folder(snapshot: &ProjectSnapshot, root: &Path) -> Result<FolderSummary, FolderError> {
+    let existing = managed_files(root)?;
+    match read_manifest(root)? {
+        Some(manifest) if manifest.project_id != snapshot.project_id => {
+            return Err(FolderError::ProjectMismatch(manifest.project_id));
+        }
+        Some(_) => {}
+        // Never delete files we didn't write
+        None if existing.iter().any(|p| is_mirror_file(p)) => {
+            return Err(FolderError::NotEmpty(root.display().to_string()));
+        }
+        None => {}
+    }
+
+    let (files, mut summary) = folder_files(snapshot)?;
+    for (relative, text) in &files {
+        let path = root.join(relative);
+        if fs::read(&path).ok().as_deref() == Some(text.as_bytes()) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("partial");
+        fs::write(&partial, text)?;
+        fs::rename(&partial, &path)?;
+        summary.changed += 1;
+    }
+    for relative in existing {
+        if is_mirror_file(&relative) && !files.contains_key(&relative) {
+            fs::remove_file(root.join(&relative))?;
+            summary.changed += 1;
+            // Drop directories the removal emptied
+            let mut dir = relative.parent();
+            while let Some(parent) = dir.filter(|d| !d.as_os_str().is_empty()) {
+                if fs::remove_dir(root.join(parent)).is_err() {
+                    break;
+                }
+                dir = parent.parent();
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Relative path of a document's sidecar
+pub fn sidecar_path(document_id: &str) -> PathBuf {
+    Path::new(META_DIR)
+        .join(CONTENT_DIR)
+        .join(format!("{}.json", id_file_name(document_id)))
+}
+
+/// The table a hand-editable file mirrors: documents or entities
+pub fn mirrored_table(relative: &Path) -> Option<SyncTable> {
+    if !is_mirror_file(relative) {
+        return None;
+    }
+    if relative.starts_with(DOCUMENTS_DIR) {
+        Some(SyncTable::Documents)
+    } else if relative.starts_with(ENTITIES_DIR) {
+        Some(SyncTable::Entities)
+    } else {
+        None
+    }
+}
+
+/// Read the row behind a document (with its sidecar) or entity file
+pub fn read_mirror_file(root: &Path, relative: &Path) -> Result<Map<String, Value>, FolderError> {
+    let text = fs::read_to_string(root.join(relative))?;
+    if mirrored_table(relative) == Some(SyncTable::Entities) {
+        return read_entity(&text).map_err(|e| invalid(relative, e));
+    }
+    let (front, _) = rows::split_front_matter(&text).map_err(|e| invalid(relative, e))?;
+    let id = row_id(&front).ok_or_else(|| invalid(relative, "missing `id`"))?;
+    let sidecar: Option<Map<String, Value>> = match fs::read_to_string(root.join(sidecar_path(id)))
+    {
+        Ok(json) => Some(serde_json::from_str(&json)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    read_document(&text, sidecar.as_ref()).map_err(|e| invalid(relative, e))
+}
+
+fn read_json_rows(path: &Path) -> Result<Vec<Value>, FolderError> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(serde_json::from_str(&text)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read a folder written by `write_folder` (and possibly edited since)
+pub fn read_folder(root: &Path) -> Result<ProjectSnapshot, FolderError> {
+    let manifest =
+        read_manifest(root)?.ok_or_else(|| FolderError::NotAFolder(root.display().to_string()))?;
+    let mut snapshot = ProjectSnapshot {
+        project_id: manifest.project_id,
+        version: manifest.snapshot_version,
+        synced_at: manifest.synced_at,
+        ..Default::default()
+    };
+    let mut seen: HashMap<(SyncTable, String), PathBuf> = HashMap::new();
+    let mut add = |snapshot: &mut ProjectSnapshot,
+                   table: SyncTable,
+                   row: Map<String, Value>,
+                   path: &Path|
+     -> Result<(), FolderError> {
+        let id = row_id(&row)
+            .ok_or_else(|| invalid(path, "missing `id`"))?
+            .to_string();
+        if let Some(first) = seen.insert((table, id.clone()), path.to_path_buf()) {
+            return Err(FolderError::DuplicateId {
+                id,
+                first: first.display().to_string(),
+                second: path.display().to_string(),
+            });
+        }
+        table_rows_mut(snapshot, table).push(Value::Object(row));
+        Ok(())
+    };
+
+    for (i, table) in manifest.relationships.into_iter().enumerate() {
+        let path = PathBuf::from(format!("{}#relationships[{}]", MANIFEST_FILE, i));
+        let row = rows::from_table(table).map_err(|e| invalid(&path, e))?;
+        add(&mut snapshot, SyncTable::Relationships, row, &path)?;
+    }
+
+    for relative in managed_files(root)? {
+        if let Some(table) = mirrored_table(&relative) {
+            let row = read_mirror_file(root, &relative)?;
+            add(&mut snapshot, table, row, &relative)?;
+        }
+    }
+
+    for table in DERIVED_TABLES {
+        let relative = Path::new(META_DIR).join(format!("{}.json", table.as_str()));
+        for row in read_json_rows(&root.join(&relative))? {
+            let Value::Object(row) = row else {
+                return Err(invalid(&relative, "expected an array of rows"));
+            };
+            add(&mut snapshot, table, row, &relative)?;
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Mirror a stored project into `root`
+pub fn export_folder(
+    store: &ProjectStore,
+    project_id: &str,
+    root: &Path,
+) -> Result<FolderSummary, FolderError> {
+    write_folder(&store.snapshot(project_id)?, root)
+}
+
+/// Replace a project's local rows with the folder's contents
+pub fn import_folder(
+    store: &ProjectStore,
+    root: &Path,
+    project_id: Option<&str>,
+) -> Result<FolderSummary, FolderError> {
+    let mut snapshot = read_folder(root)?;
+    if let Some(project_id) = project_id {
+        snapshot.project_id = project_id.to_string();
+    }
+    let (_, summary) = folder_files(&snapshot)?;
+    store.bootstrap_project(&snapshot)?;
+    Ok(FolderSummary {
+        changed: 0,
+        ..summary
+    })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_story_folder(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+) -> Result<FolderSummary, FolderError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        export_folder(&app.state::<ProjectStore>(), &project_id, Path::new(&path))
+    })
+    .await
+    .map_err(|e| FolderError::Io(std::io::Error::other(e)))?
+}
+
+/// Imports into `projectId` if given, otherwise the folder's project
+#[tauri::command(rename_all = "camelCase")]
+pub async fn import_story_folder(
+    app: AppHandle,
+    path: String,
+    project_id: Option<String>,
+) -> Result<FolderSummary, FolderError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        import_folder(
+            &app.state::<ProjectStore>(),
+            Path::new(&path),
+            project_id.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| FolderError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paragraph(text: &str) -> Value {
+        json!({"type": "paragraph", "content": [{"type": "text", "text": text}]})
+    }
+
+    fn snapshot() -> ProjectSnapshot {
+        let simple = json!({"type": "doc", "content": [paragraph("The storm broke."), paragraph("Ada ran.")]});
+        ProjectSnapshot {
+            project_id: "p1".into(),
+            version: 9,
+            synced_at: "2024-05-01T10:00:00Z".into(),
+            documents: vec![
+                json!({
+                    "id": "d1", "projectId": "p1", "type": "chapter", "title": "Chapter 1: Storm",
+                    "orderIndex": 0, "content": simple, "contentText": "The storm broke.\n\nAda ran.",
+                    "wordCount": 5, "metadata": {"pov": null}, "createdAt": 1, "updatedAt": 2
+                }),
+                // Same title, a scene block Markdown can't express, stale derived fields
+                json!({
+                    "id": "d2", "projectId": "p1", "type": "scene", "title": "Chapter 1: Storm",
+                    "parentId": "d1", "orderIndex": 1.5,
+                    "content": {"type": "doc", "content": [
+                        {"type": "sceneBlock", "attrs": {"sceneId": "s1"}, "content": [paragraph("Inside.")]}
+                    ]},
+                    "contentText": "stale", "wordCount": 99, "createdAt": 1, "updatedAt": 2
+                }),
+                // Never opened in the editor: no content at all
+                json!({"id": "d3", "projectId": "p1", "type": "note", "title": "Ideas", "orderIndex": 2,
+                       "wordCount": 0, "createdAt": 1, "updatedAt": 1}),
+                // Content serialized as a string
+                json!({"id": "d4", "projectId": "p1", "type": "note", "title": "Légende", "orderIndex": 3,
+                       "content": simple.to_string(), "contentText": "The storm broke.\n\nAda ran.",
+                       "wordCount": 5, "createdAt": 1, "updatedAt": 1}),
+            ],
+            entities: vec![
+                json!({"id": "e1", "projectId": "p1", "type": "character", "name": "Ada Lovelace",
+                       "canonicalName": "ada lovelace", "aliases": ["Ada"], "notes": "Keeps the light.\nQuiet.",
+                       "properties": {"age": 40, "eyes": null}, "createdAt": 1, "updatedAt": 1}),
+                json!({"id": "e2", "projectId": "p1", "type": "magic_system", "name": "Tides",
+                       "canonicalName": "tides", "aliases": [], "properties": {}, "createdAt": 1, "updatedAt": 1}),
+            ],
+            relationships: vec![
+                json!({"id": "r1", "projectId": "p1", "sourceId": "e1", "targetId": "e2",
+                                       "type": "knows", "bidirectional": false, "strength": 7, "createdAt": 1}),
+            ],
+            mentions: vec![
+                json!({"id": "m1", "projectId": "p1", "entityId": "e1", "documentId": "d1",
+                                  "positionStart": 17, "positionEnd": 20, "context": "Ada ran.", "createdAt": 1}),
+            ],
+            analysis: vec![],
+            captures: vec![],
+        }
+    }
+
+    fn sorted(snapshot: &ProjectSnapshot) -> ProjectSnapshot {
+        let mut snapshot = snapshot.clone();
+        for table in SyncTable::ALL {
+            table_rows_mut(&mut snapshot, table)
+                .sort_by_key(|row| row["id"].as_str().unwrap().to_string());
+        }
+        snapshot
+    }
+
+    #[test]
+    fn round_trips_a_project_losslessly() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = snapshot();
+        let summary = write_folder(&original, dir.path()).unwrap();
+        assert_eq!(
+            summary,
+            FolderSummary {
+                project_id: "p1".into(),
+                documents: 4,
+                entities: 2,
+                relationships: 1,
+                sidecars: 3,
+                changed: 11,
+            }
+        );
+
+        let root = dir.path();
+        let chapter = fs::read_to_string(root.join("documents/chapter-1-storm.md")).unwrap();
+        assert!(
+            chapter.ends_with("+++\n\nThe storm broke.\n\nAda ran.\n"),
+            "{}",
+            chapter
+        );
+        assert!(root.join("documents/chapter-1-storm-2.md").exists());
+        assert!(root.join("documents/légende.md").exists());
+        assert!(root.join("entities/character/ada-lovelace.toml").exists());
+        assert!(root.join("entities/magic-system/tides.toml").exists());
+        assert!(!root.join(".rhei/content/d1.json").exists());
+        assert!(root.join(".rhei/content/d2.json").exists());
+        assert!(root.join(".rhei/mentions.json").exists());
+        let manifest = fs::read_to_string(root.join(MANIFEST_FILE)).unwrap();
+        assert!(manifest.contains("[[relationships]]"), "{}", manifest);
+
+        assert_eq!(sorted(&read_folder(root).unwrap()), sorted(&original));
+
+        // A second export changes nothing
+        assert_eq!(write_folder(&original, root).unwrap().changed, 0);
+    }
+
+    #[test]
+    fn external_edits_win_over_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write_folder(&snapshot(), root).unwrap();
+
+        let path = root.join("documents/chapter-1-storm-2.md");
+        let text = fs::read_to_string(&path).unwrap();
+        let edited = text.replace("title = \"Chapter 1: Storm\"", "title = \"Aftermath\"")
+            + "\nA **new** line.\n";
+        fs::write(&path, edited).unwrap();
+
+        let read = read_folder(root).unwrap();
+        let d2 = read.documents.iter().find(|d| d["id"] == "d2").unwrap();
+        assert_eq!(d2["title"], "Aftermath");
+        assert_eq!(d2["contentText"], "Inside.\n\nA new line.");
+        assert_eq!(d2["wordCount"], 4);
+        assert_eq!(
+            d2["content"]["content"][1]["content"][1],
+            json!({"type": "text", "text": "new", "marks": [{"type": "bold"}]})
+        );
+
+        // Deleting a file drops its row
+        fs::remove_file(root.join("documents/ideas.md")).unwrap();
+        let read = read_folder(root).unwrap();
+        assert_eq!(read.documents.len(), 3);
+
+        // Re-exporting without a row removes its files and emptied directories
+        let mut smaller = read.clone();
+        smaller.documents.retain(|d| d["id"] != "d2");
+        smaller.entities.retain(|e| e["id"] != "e2");
+        write_folder(&smaller, root).unwrap();
+        assert!(!root.join(".rhei/content/d2.json").exists());
+        assert!(!root.join(".rhei/content/d3.json").exists());
+        assert!(!root.join("entities/magic-system").exists());
+        assert!(root.join("entities/character/ada-lovelace.toml").exists());
+    }
+
+    #[test]
+    fn guards_against_foreign_folders_and_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("documents")).unwrap();
+        fs::write(root.join("documents/mine.md"), "notes").unwrap();
+        assert!(matches!(
+            write_folder(&snapshot(), root),
+            Err(FolderError::NotEmpty(_))
+        ));
+        fs::remove_file(root.join("documents/mine.md")).unwrap();
+
+        write_folder(&snapshot(), root).unwrap();
+        let other = ProjectSnapshot {
+            project_id: "p2".into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            write_folder(&other, root),
+            Err(FolderError::ProjectMismatch(id)) if id == "p1"
+        ));
+
+        fs::copy(
+            root.join("entities/character/ada-lovelace.toml"),
+            root.join("entities/character/copy.toml"),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_folder(root),
+            Err(FolderError::DuplicateId { id, .. }) if id == "e1"
+        ));
+    }
+
+    #[test]
+    fn imports_into_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        store.bootstrap_project(&snapshot()).unwrap();
+        export_folder(&store, "p1", dir.path()).unwrap();
+
+        let copy = ProjectStore::open_in_memory().unwrap();
+        let summary = import_folder(&copy, dir.path(), None).unwrap();
+        assert_eq!((summary.documents, summary.entities), (4, 2));
+        assert_eq!(
+            sorted(&copy.snapshot("p1").unwrap()),
+            sorted(&store.snapshot("p1").unwrap())
+        );
+    }
+}
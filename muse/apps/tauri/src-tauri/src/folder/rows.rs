@@ -0,0 +1,182 @@
+//! Rows as TOML
+//!
+//! TOML has no null and tops out at `i64`, so fields holding either are
+//! written as JSON strings under `_json` instead. Everything else is native
+//! TOML and reads back to the same JSON. TOML datetimes written by hand come
+//! back as strings.
+
+use serde_json::{Map, Number, Value};
+
+/// Table of fields TOML can't hold, as JSON text
+pub const JSON_KEY: &str = "_json";
+
+const FRONT_MATTER_FENCE: &str = "+++";
+
+fn to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None if n.is_f64() => toml::Value::Float(n.as_f64()?),
+            None => return None,
+        },
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Array(items) => {
+            toml::Value::Array(items.iter().map(to_toml).collect::<Option<_>>()?)
+        }
+        Value::Object(map) => toml::Value::Table(
+            map.iter()
+                .map(|(k, v)| Some((k.clone(), to_toml(v)?)))
+                .collect::<Option<_>>()?,
+        ),
+    })
+}
+
+fn to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(to_json).collect()),
+        toml::Value::Table(table) => {
+            Value::Object(table.into_iter().map(|(k, v)| (k, to_json(v))).collect())
+        }
+    }
+}
+
+/// A row as a TOML table
+pub fn to_table(row: &Map<String, Value>) -> toml::Table {
+    let mut table = toml::Table::new();
+    let mut json = toml::Table::new();
+    for (key, value) in row {
+        match to_toml(value) {
+            Some(value) => {
+                table.insert(key.clone(), value);
+            }
+            None => {
+                json.insert(key.clone(), toml::Value::String(value.to_string()));
+            }
+        }
+    }
+    if !json.is_empty() {
+        table.insert(JSON_KEY.to_string(), toml::Value::Table(json));
+    }
+    table
+}
+
+/// A row from a TOML table written by `to_table` (or by hand)
+pub fn from_table(mut table: toml::Table) -> Result<Map<String, Value>, String> {
+    let json = table.remove(JSON_KEY);
+    let mut row: Map<String, Value> = table.into_iter().map(|(k, v)| (k, to_json(v))).collect();
+    match json {
+        None => {}
+        Some(toml::Value::Table(json)) => {
+            for (key, value) in json {
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| format!("`{}.{}` must be a JSON string", JSON_KEY, key))?;
+                let value = serde_json::from_str(text)
+                    .map_err(|e| format!("`{}.{}`: {}", JSON_KEY, key, e))?;
+                row.insert(key, value);
+            }
+        }
+        Some(_) => return Err(format!("`{}` must be a table", JSON_KEY)),
+    }
+    Ok(row)
+}
+
+pub fn to_toml_string(row: &Map<String, Value>) -> String {
+    // Tables of strings, numbers and arrays always serialize
+    toml::to_string(&to_table(row)).expect("TOML table serializes")
+}
+
+pub fn from_toml_str(text: &str) -> Result<Map<String, Value>, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    from_table(table)
+}
+
+/// `+++`-fenced TOML front matter followed by `body`
+pub fn with_front_matter(row: &Map<String, Value>, body: &str) -> String {
+    let mut out = format!(
+        "{}\n{}{}\n",
+        FRONT_MATTER_FENCE,
+        to_toml_string(row),
+        FRONT_MATTER_FENCE
+    );
+    if !body.is_empty() {
+        out.push('\n');
+        out.push_str(body);
+        out.push('\n');
+    }
+    out
+}
+
+/// Split a file into its front matter row and body
+pub fn split_front_matter(text: &str) -> Result<(Map<String, Value>, String), String> {
+    let text = text
+        .strip_prefix('\u{feff}')
+        .unwrap_or(text)
+        .replace("\r\n", "\n");
+    let rest = text
+        .strip_prefix(FRONT_MATTER_FENCE)
+        .and_then(|rest| rest.strip_prefix('\n'))
+        .ok_or("missing `+++` front matter")?;
+    let (front, body) = match rest.strip_prefix(FRONT_MATTER_FENCE) {
+        Some(body) => ("", body),
+        None => {
+            let end = rest
+                .find("\n+++")
+                .filter(|&end| matches!(rest[end + 4..].chars().next(), None | Some('\n')))
+                .ok_or("unterminated front matter")?;
+            (&rest[..end + 1], &rest[end + 4..])
+        }
+    };
+    Ok((from_toml_str(front)?, body.trim_matches('\n').to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rows_survive_toml_including_nulls_and_large_numbers() {
+        let row = json!({
+            "id": "e1",
+            "name": "Kael \"the Grey\"",
+            "notes": "Line one\nLine two",
+            "aliases": ["K", "Grey"],
+            "properties": {"age": 40, "height": 1.8, "eyes": null, "tags": []},
+            "strength": 7.0,
+            "big": 18446744073709551615u64,
+            "visibleIn": [{"mode": "writer"}]
+        });
+        let row = row.as_object().unwrap();
+        let text = to_toml_string(row);
+        assert!(text.contains("[_json]"), "{}", text);
+        assert_eq!(&from_toml_str(&text).unwrap(), row);
+
+        let doc = with_front_matter(row, "Body\n\ntext");
+        let (front, body) = split_front_matter(&doc).unwrap();
+        assert_eq!(&front, row);
+        assert_eq!(body, "Body\n\ntext");
+    }
+
+    #[test]
+    fn reads_hand_written_front_matter() {
+        let (row, body) = split_front_matter(
+            "+++\r\ntitle = \"Storm\"\r\ndate = 2024-05-01T10:00:00Z\r\n+++\r\nText",
+        )
+        .unwrap();
+        assert_eq!(
+            Value::Object(row),
+            json!({"title": "Storm", "date": "2024-05-01T10:00:00Z"})
+        );
+        assert_eq!(body, "Text");
+        assert!(split_front_matter("no front matter").is_err());
+        assert!(split_front_matter("+++\ntitle = 1\n").is_err());
+    }
+}
@@ -0,0 +1,758 @@
+//! Picks up external edits to a story folder
+//!
+//! File events are debounced into batches. Each changed document or entity
+//! file is read back and compared with the stored row, and differences
+//! become local `Mutation`s, applied to the store and queued in the outbox.
+//! Files are matched to rows by the `id` in their front matter or TOML, not
+//! by name, so a file that disappears while its id turns up in another file
+//! is a rename, and one whose id is gone is a delete. Files that fail to
+//! parse are reported and otherwise left alone; a half-saved file never
+//! deletes anything.
+//!
+//! Our own exports come back through the watcher too. They match the store,
+//! so they produce no mutations.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::{
+    html, id_file_name, managed_files, mirrored_table, read_manifest, read_mirror_file, row_id,
+    rows, FolderError, CONTENT_DIR, META_DIR,
+};
+use crate::bridge::protocol::NativeToEditorMessage;
+use crate::bridge::{send_to_editor, BridgeHub};
+use crate::db::{MutationType, ProjectStore, SyncTable};
+use crate::sync::outbox::{self, OutboxWorker};
+use crate::sync::Mutation;
+
+/// Emitted with a `FolderSyncReport` after a batch changed something
+pub const FOLDER_CHANGED_EVENT: &str = "story-folder://changed";
+
+/// Quiet period that ends a batch of file events
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What happened to one file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FolderChange {
+    /// The row was edited; `fields` are the changed row fields
+    #[serde(rename_all = "camelCase")]
+    Updated {
+        table: SyncTable,
+        id: String,
+        path: String,
+        fields: Vec<String>,
+    },
+    /// A file for a row the store doesn't have yet
+    #[serde(rename_all = "camelCase")]
+    Created {
+        table: SyncTable,
+        id: String,
+        path: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Renamed {
+        table: SyncTable,
+        id: String,
+        from: String,
+        to: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Deleted {
+        table: SyncTable,
+        id: String,
+        path: String,
+    },
+    /// The file couldn't be read back; nothing was changed for it
+    #[serde(rename_all = "camelCase")]
+    Invalid { path: String, message: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSyncReport {
+    pub project_id: String,
+    pub changes: Vec<FolderChange>,
+    /// Mutations applied locally and queued for the server
+    pub mutations: Vec<Mutation>,
+}
+
+/// Which row each file holds, as of the last batch
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FolderIndex {
+    files: HashMap<PathBuf, (SyncTable, String)>,
+}
+
+impl FolderIndex {
+    /// Index the files currently in `root`; unreadable files are skipped
+    pub fn scan(root: &Path) -> Result<Self, FolderError> {
+        let mut index = Self::default();
+        for relative in managed_files(root)? {
+            let Some(table) = mirrored_table(&relative) else {
+                continue;
+            };
+            if let Some(id) = read_id(root, &relative) {
+                index.files.insert(relative, (table, id));
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn get(&self, relative: &Path) -> Option<&(SyncTable, String)> {
+        self.files.get(relative)
+    }
+
+    fn path_of(&self, table: SyncTable, id: &str) -> Option<&PathBuf> {
+        self.files
+            .iter()
+            .find(|(_, (t, i))| *t == table && i == id)
+            .map(|(path, _)| path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Just the `id` of a file, without the sidecar
+fn read_id(root: &Path, relative: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(root.join(relative)).ok()?;
+    let row = match mirrored_table(relative)? {
+        SyncTable::Documents => rows::split_front_matter(&text).ok()?.0,
+        _ => rows::from_toml_str(&text).ok()?,
+    };
+    row_id(&row).map(str::to_string)
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Paths under `root` that a batch of events touched, made relative.
+/// A sidecar edit counts as an edit of its document.
+fn relative_paths(root: &Path, index: &FolderIndex, paths: &[PathBuf]) -> BTreeSet<PathBuf> {
+    let content_dir = Path::new(META_DIR).join(CONTENT_DIR);
+    let mut touched = BTreeSet::new();
+    for path in paths {
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) if path.is_relative() => path.clone(),
+            Err(_) => continue,
+        };
+        if relative.parent() == Some(content_dir.as_path()) {
+            let stem = relative.file_stem().and_then(|s| s.to_str());
+            touched.extend(
+                index
+                    .files
+                    .iter()
+                    .filter(|(_, (t, id))| {
+                        *t == SyncTable::Documents && Some(id_file_name(id).as_str()) == stem
+                    })
+                    .map(|(path, _)| path.clone()),
+            );
+        } else if mirrored_table(&relative).is_some() || index.files.contains_key(&relative) {
+            touched.insert(relative);
+        } else {
+            // A directory moved or removed as a whole
+            let on_disk = if root.join(&relative).is_dir() {
+                managed_files(root).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            touched.extend(
+                index
+                    .files
+                    .keys()
+                    .cloned()
+                    .chain(on_disk.into_iter().filter(|p| mirrored_table(p).is_some()))
+                    .filter(|p| p.starts_with(&relative)),
+            );
+        }
+    }
+    touched
+}
+
+/// Names of the fields that differ between two rows
+fn changed_fields(before: &Map<String, Value>, after: &Map<String, Value>) -> Vec<String> {
+    let mut fields: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// Bring the store up to date with the files at `paths` (absolute, or
+/// relative to `root`), applying and queueing a mutation per changed row
+pub fn sync_paths(
+    store: &ProjectStore,
+    project_id: &str,
+    root: &Path,
+    index: &mut FolderIndex,
+    paths: &[PathBuf],
+) -> Result<FolderSyncReport, FolderError> {
+    let mut report = FolderSyncReport {
+        project_id: project_id.to_string(),
+        ..Default::default()
+    };
+    let touched = relative_paths(root, index, paths);
+    let mut removed = Vec::new();
+    let mut claimed: HashMap<(SyncTable, String), PathBuf> = HashMap::new();
+
+    for relative in &touched {
+        let table = match mirrored_table(relative) {
+            Some(table) if root.join(relative).is_file() => table,
+            // Gone, or moved somewhere we don't mirror
+            _ => {
+                if let Some(entry) = index.files.remove(relative) {
+                    removed.push((relative.clone(), entry));
+                }
+                continue;
+            }
+        };
+        let mut row = match read_mirror_file(root, relative) {
+            Ok(row) => row,
+            Err(FolderError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                if let Some(entry) = index.files.remove(relative) {
+                    removed.push((relative.clone(), entry));
+                }
+                continue;
+            }
+            Err(e) => {
+                report.changes.push(FolderChange::Invalid {
+                    path: display(relative),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let Some(id) = row_id(&row).map(str::to_string) else {
+            continue;
+        };
+        // A row pasted in from another project's folder
+        if let Some(other) = row.get("projectId").filter(|p| *p != project_id) {
+            report.changes.push(FolderChange::Invalid {
+                path: display(relative),
+                message: format!(
+                    "belongs to project `{}`, not `{}`",
+                    other.as_str().unwrap_or_default(),
+                    project_id
+                ),
+            });
+            continue;
+        }
+
+        // Another file already holds this row: a copy, not a rename
+        let elsewhere = index
+            .path_of(table, &id)
+            .filter(|path| *path != relative)
+            .filter(|path| root.join(path).is_file() && !touched.contains(*path))
+            .or_else(|| claimed.get(&(table, id.clone())));
+        if let Some(first) = elsewhere {
+            report.changes.push(FolderChange::Invalid {
+                path: display(relative),
+                message: format!("`{}` is already defined in {}", id, display(first)),
+            });
+            continue;
+        }
+        claimed.insert((table, id.clone()), relative.clone());
+
+        // The file held a different row before: that one may be gone
+        let previous = index.files.insert(relative.clone(), (table, id.clone()));
+        if let Some(entry) = previous.filter(|(t, i)| (*t, i.as_str()) != (table, id.as_str())) {
+            removed.push((relative.clone(), entry));
+        }
+        // Its old file may have been seen already in this batch, or not yet
+        let moved_from = match removed
+            .iter()
+            .position(|(_, (t, i))| *t == table && *i == id)
+        {
+            Some(i) => Some(removed.remove(i).0),
+            None => index
+                .files
+                .iter()
+                .find(|(path, (t, i))| *path != relative && *t == table && *i == id)
+                .map(|(path, _)| path.clone()),
+        };
+        if let Some(from) = moved_from {
+            index.files.remove(&from);
+            report.changes.push(FolderChange::Renamed {
+                table,
+                id: id.clone(),
+                from: display(&from),
+                to: display(relative),
+            });
+        }
+
+        if !row.contains_key("projectId") {
+            row.insert("projectId".into(), Value::String(project_id.to_string()));
+        }
+        let stored = store.get(table, &id)?;
+        let stored = stored.as_ref().and_then(Value::as_object);
+        if stored == Some(&row) {
+            continue;
+        }
+        report.changes.push(match stored {
+            Some(stored) => FolderChange::Updated {
+                table,
+                id: id.clone(),
+                path: display(relative),
+                fields: changed_fields(stored, &row),
+            },
+            None => FolderChange::Created {
+                table,
+                id: id.clone(),
+                path: display(relative),
+            },
+        });
+        let row = Value::Object(row);
+        let mut upsert = Mutation::local(project_id, table, MutationType::Upsert, &id);
+        upsert.base_version = store.row_version(table, &id)?;
+        store.upsert(table, &row)?;
+        upsert.row = Some(row);
+        outbox::enqueue(store, &upsert)?;
+        report.mutations.push(upsert);
+    }
+
+    for (relative, (table, id)) in removed {
+        // Still in another file: renamed or re-titled, not deleted
+        if index.path_of(table, &id).is_some() {
+            continue;
+        }
+        let mut delete = Mutation::local(project_id, table, MutationType::Delete, &id);
+        delete.base_version = store.row_version(table, &id)?;
+        if store.delete(table, &id)? {
+            outbox::enqueue(store, &delete)?;
+            report.mutations.push(delete);
+        }
+        report.changes.push(FolderChange::Deleted {
+            table,
+            id,
+            path: display(&relative),
+        });
+    }
+    Ok(report)
+}
+
+/// Collect events until none arrive for `quiet`; `None` once the watcher is gone
+pub fn next_batch(events: &Receiver<Vec<PathBuf>>, quiet: Duration) -> Option<Vec<PathBuf>> {
+    let mut batch = events.recv().ok()?;
+    loop {
+        match events.recv_timeout(quiet) {
+            Ok(paths) => batch.extend(paths),
+            Err(RecvTimeoutError::Timeout) => return Some(batch),
+            Err(RecvTimeoutError::Disconnected) => return Some(batch),
+        }
+    }
+}
+
+/// Show new content in editors displaying an updated document
+fn refresh_editors(app: &AppHandle, report: &FolderSyncReport) {
+    for change in &report.changes {
+        let FolderChange::Updated {
+            table: SyncTable::Documents,
+            id,
+            fields,
+            ..
+        } = change
+        else {
+            continue;
+        };
+        if !fields.iter().any(|f| f == "content") {
+            continue;
+        }
+        let Some(content) = report
+            .mutations
+            .iter()
+            .find(|m| m.row_key() == Some(id.as_str()))
+            .and_then(|m| m.row.as_ref()?.get("content"))
+        else {
+            continue;
+        };
+        let html = html::render(content);
+        for label in app.state::<BridgeHub>().labels_showing(id) {
+            let message = NativeToEditorMessage::SetContent {
+                content: html.clone(),
+            };
+            if let Err(e) = send_to_editor(app, &label, message) {
+                eprintln!("[folder] Couldn't refresh editor {}: {}", label, e);
+            }
+        }
+    }
+}
+
+/// The folder being watched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedFolder {
+    pub project_id: String,
+    pub path: String,
+}
+
+struct ActiveWatch {
+    folder: WatchedFolder,
+    // Dropping the watcher closes the event channel, which ends the thread
+    _watcher: RecommendedWatcher,
+}
+
+/// At most one watched story folder
+#[derive(Default)]
+pub struct FolderWatcher {
+    active: Mutex<Option<ActiveWatch>>,
+}
+
+impl FolderWatcher {
+    pub fn current(&self) -> Option<WatchedFolder> {
+        self.active
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|a| a.folder.clone())
+    }
+
+    pub fn stop(&self) {
+        self.active.lock().unwrap().take();
+    }
+}
+
+/// Only `project_id`'s own story folder may feed its rows
+fn check_manifest(root: &Path, project_id: &str) -> Result<(), FolderError> {
+    match read_manifest(root)? {
+        Some(manifest) if manifest.project_id == project_id => Ok(()),
+        Some(manifest) => Err(FolderError::ProjectMismatch(manifest.project_id)),
+        None => Err(FolderError::NotAFolder(root.display().to_string())),
+    }
+}
+
+/// Watch `root` for edits to `project_id`, replacing any previous watch
+pub fn start_watching(app: &AppHandle, project_id: &str, root: &Path) -> Result<(), FolderError> {
+    let root = root.canonicalize()?;
+    check_manifest(&root, project_id)?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => {
+                let _ = tx.send(event.paths);
+            }
+            Err(e) => eprintln!("[folder] Watch error: {}", e),
+        })
+        .map_err(|e| FolderError::Watch(e.to_string()))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| FolderError::Watch(e.to_string()))?;
+    let mut index = FolderIndex::scan(&root)?;
+
+    let folder = WatchedFolder {
+        project_id: project_id.to_string(),
+        path: root.display().to_string(),
+    };
+    *app.state::<FolderWatcher>().active.lock().unwrap() = Some(ActiveWatch {
+        folder,
+        _watcher: watcher,
+    });
+
+    let app = app.clone();
+    let project_id = project_id.to_string();
+    std::thread::spawn(move || {
+        while let Some(paths) = next_batch(&rx, DEBOUNCE) {
+            let store = app.state::<ProjectStore>();
+            match sync_paths(&store, &project_id, &root, &mut index, &paths) {
+                Ok(report) if report.changes.is_empty() => {}
+                Ok(report) => {
+                    if !report.mutations.is_empty() {
+                        app.state::<OutboxWorker>().notify();
+                    }
+                    refresh_editors(&app, &report);
+                    let _ = app.emit(FOLDER_CHANGED_EVENT, &report);
+                }
+                Err(e) => eprintln!("[folder] Sync from {} failed: {}", root.display(), e),
+            }
+        }
+        println!("[folder] Stopped watching {}", root.display());
+    });
+    Ok(())
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn watch_story_folder(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+) -> Result<WatchedFolder, FolderError> {
+    start_watching(&app, &project_id, Path::new(&path))?;
+    Ok(app
+        .state::<FolderWatcher>()
+        .current()
+        .expect("watch just started"))
+}
+
+#[tauri::command]
+pub fn unwatch_story_folder(watcher: State<'_, FolderWatcher>) {
+    watcher.stop();
+}
+
+#[tauri::command]
+pub fn get_watched_story_folder(watcher: State<'_, FolderWatcher>) -> Option<WatchedFolder> {
+    watcher.current()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ProjectSnapshot;
+    use crate::folder::{export_folder, sidecar_path};
+    use serde_json::json;
+    use std::fs;
+
+    fn setup() -> (tempfile::TempDir, ProjectStore, FolderIndex) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        let content = json!({"type": "doc", "content": [
+            {"type": "paragraph", "content": [{"type": "text", "text": "The storm broke."}]}
+        ]});
+        store
+            .bootstrap_project(&ProjectSnapshot {
+                project_id: "p1".into(),
+                version: 3,
+                documents: vec![json!({
+                    "id": "d1", "projectId": "p1", "type": "chapter", "title": "Storm",
+                    "orderIndex": 0, "content": content, "contentText": "The storm broke.",
+                    "wordCount": 3
+                })],
+                entities: vec![
+                    json!({"id": "e1", "projectId": "p1", "type": "character", "name": "Kael"}),
+                    json!({"id": "e2", "projectId": "p1", "type": "location", "name": "Dock"}),
+                ],
+                ..Default::default()
+            })
+            .unwrap();
+        export_folder(&store, "p1", dir.path()).unwrap();
+        let index = FolderIndex::scan(dir.path()).unwrap();
+        (dir, store, index)
+    }
+
+    fn sync(
+        store: &ProjectStore,
+        root: &Path,
+        index: &mut FolderIndex,
+        paths: &[&str],
+    ) -> FolderSyncReport {
+        let paths: Vec<PathBuf> = paths.iter().map(|p| root.join(p)).collect();
+        sync_paths(store, "p1", root, index, &paths).unwrap()
+    }
+
+    #[test]
+    fn external_edits_become_mutations() {
+        let (dir, store, mut index) = setup();
+        let root = dir.path();
+        assert_eq!(index.len(), 3);
+
+        // Our own export echoes back without changes
+        let report = sync(
+            &store,
+            root,
+            &mut index,
+            &["documents/storm.md", "rhei.toml"],
+        );
+        assert!(report.changes.is_empty() && report.mutations.is_empty());
+
+        let path = root.join("documents/storm.md");
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(
+            &path,
+            text.replace("The storm broke.", "The storm *broke* twice."),
+        )
+        .unwrap();
+        let report = sync(&store, root, &mut index, &["documents/storm.md"]);
+        assert_eq!(
+            report.changes,
+            vec![FolderChange::Updated {
+                table: SyncTable::Documents,
+                id: "d1".into(),
+                path: "documents/storm.md".into(),
+                fields: vec!["content".into(), "contentText".into(), "wordCount".into()],
+            }]
+        );
+        let mutation = &report.mutations[0];
+        assert_eq!(mutation.kind, MutationType::Upsert);
+        assert_eq!(mutation.base_version, Some(3));
+        assert_eq!(mutation.project_id, "p1");
+        let stored = store.get(SyncTable::Documents, "d1").unwrap().unwrap();
+        assert_eq!(stored["contentText"], "The storm broke twice.");
+        assert_eq!(stored["wordCount"], 4);
+        assert_eq!(outbox::pending(&store).unwrap().len(), 1);
+
+        // A half-written file is reported, not applied
+        fs::write(root.join("entities/character/kael.toml"), "name = \"Ka").unwrap();
+        let report = sync(&store, root, &mut index, &["entities/character/kael.toml"]);
+        assert!(matches!(
+            &report.changes[..],
+            [FolderChange::Invalid { .. }]
+        ));
+        assert!(report.mutations.is_empty());
+        assert!(store.get(SyncTable::Entities, "e1").unwrap().is_some());
+    }
+
+    #[test]
+    fn detects_renames_and_deletes() {
+        let (dir, store, mut index) = setup();
+        let root = dir.path();
+
+        // Moving a file keeps its row; a changed title goes through as an edit
+        let text = fs::read_to_string(root.join("documents/storm.md")).unwrap();
+        fs::write(
+            root.join("documents/the-storm.md"),
+            text.replace("title = \"Storm\"", "title = \"The Storm\""),
+        )
+        .unwrap();
+        fs::remove_file(root.join("documents/storm.md")).unwrap();
+        let report = sync(
+            &store,
+            root,
+            &mut index,
+            &["documents/storm.md", "documents/the-storm.md"],
+        );
+        assert_eq!(
+            report.changes,
+            vec![
+                FolderChange::Renamed {
+                    table: SyncTable::Documents,
+                    id: "d1".into(),
+                    from: "documents/storm.md".into(),
+                    to: "documents/the-storm.md".into(),
+                },
+                FolderChange::Updated {
+                    table: SyncTable::Documents,
+                    id: "d1".into(),
+                    path: "documents/the-storm.md".into(),
+                    fields: vec!["title".into()],
+                },
+            ]
+        );
+        assert_eq!(
+            index.get(Path::new("documents/the-storm.md")),
+            Some(&(SyncTable::Documents, "d1".to_string()))
+        );
+
+        // A copy doesn't steal the row
+        fs::copy(
+            root.join("entities/location/dock.toml"),
+            root.join("entities/location/dock-copy.toml"),
+        )
+        .unwrap();
+        let report = sync(
+            &store,
+            root,
+            &mut index,
+            &["entities/location/dock-copy.toml"],
+        );
+        assert!(matches!(
+            &report.changes[..],
+            [FolderChange::Invalid { .. }]
+        ));
+        fs::remove_file(root.join("entities/location/dock-copy.toml")).unwrap();
+
+        fs::remove_file(root.join("entities/location/dock.toml")).unwrap();
+        let report = sync(&store, root, &mut index, &["entities/location/dock.toml"]);
+        assert_eq!(
+            report.changes,
+            vec![FolderChange::Deleted {
+                table: SyncTable::Entities,
+                id: "e2".into(),
+                path: "entities/location/dock.toml".into(),
+            }]
+        );
+        assert_eq!(report.mutations[0].kind, MutationType::Delete);
+        assert_eq!(report.mutations[0].pk.as_deref(), Some("e2"));
+        assert!(store.get(SyncTable::Entities, "e2").unwrap().is_none());
+
+        // Renaming a directory moves every file in it
+        fs::rename(
+            root.join("entities/character"),
+            root.join("entities/people"),
+        )
+        .unwrap();
+        let report = sync(
+            &store,
+            root,
+            &mut index,
+            &["entities/character", "entities/people"],
+        );
+        assert!(matches!(
+            &report.changes[..],
+            [FolderChange::Renamed { id, to, .. }] if id == "e1" && to == "entities/people/kael.toml"
+        ));
+        assert!(report.mutations.is_empty());
+
+        // A new file creates a row
+        fs::write(
+            root.join("entities/location/harbor.toml"),
+            "id = \"e3\"\ntype = \"location\"\nname = \"Harbor\"\n",
+        )
+        .unwrap();
+        let report = sync(&store, root, &mut index, &["entities/location/harbor.toml"]);
+        assert!(matches!(&report.changes[..], [FolderChange::Created { id, .. }] if id == "e3"));
+        let harbor = store.get(SyncTable::Entities, "e3").unwrap().unwrap();
+        assert_eq!(harbor["projectId"], "p1");
+        assert!(!root.join(sidecar_path("d1")).exists());
+    }
+
+    #[test]
+    fn ignores_rows_of_other_projects() {
+        let (dir, store, mut index) = setup();
+        let root = dir.path();
+        assert!(check_manifest(root, "p1").is_ok());
+        assert!(matches!(
+            check_manifest(root, "p2"),
+            Err(FolderError::ProjectMismatch(id)) if id == "p1"
+        ));
+        assert!(matches!(
+            check_manifest(&root.join("entities"), "p1"),
+            Err(FolderError::NotAFolder(_))
+        ));
+
+        fs::write(
+            root.join("entities/location/harbor.toml"),
+            "id = \"e3\"\nprojectId = \"p2\"\ntype = \"location\"\nname = \"Harbor\"\n",
+        )
+        .unwrap();
+        let report = sync(&store, root, &mut index, &["entities/location/harbor.toml"]);
+        assert!(matches!(
+            &report.changes[..],
+            [FolderChange::Invalid { message, .. }]
+                if message == "belongs to project `p2`, not `p1`"
+        ));
+        assert!(report.mutations.is_empty());
+        assert!(store.get(SyncTable::Entities, "e3").unwrap().is_none());
+    }
+
+    #[test]
+    fn debounces_event_bursts() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(vec![PathBuf::from("a")]).unwrap();
+        tx.send(vec![PathBuf::from("b")]).unwrap();
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            tx.send(vec![PathBuf::from("c")]).unwrap();
+        });
+        let batch = next_batch(&rx, Duration::from_millis(200)).unwrap();
+        assert_eq!(batch, ["a", "b", "c"].map(PathBuf::from));
+        sender.join().unwrap();
+        assert_eq!(next_batch(&rx, Duration::from_millis(10)), None);
+    }
+}
@@ -0,0 +1,967 @@
+//! Built-in project history
+//!
+//! Every project gets a bare git repository under `history/` in app data.
+//! A commit holds the project as its story folder (see `folder`), written
+//! straight into the object database, so there is no working tree to keep in
+//! step. Commits are made on a schedule, when the app exits and on request,
+//! and only when the project's content has changed. Their messages say what
+//! changed ("Edited Chapter 3; added entity Kael").
+//!
+//! The sync position (`snapshotVersion`, `syncedAt`) is left out of the
+//! committed manifest. History tracks what was written, not when it synced.
+//!
+//! Any revision can be diffed against another. A document from a past
+//! revision can be checked out as a new scratch note, which leaves the
+//! current document alone.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use git2::{
+    DiffFindOptions, DiffOptions, ErrorCode, ObjectType, Oid, Patch, Repository, Signature, Sort,
+    Time, Tree, TreeWalkMode, TreeWalkResult,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{MutationType, ProjectSnapshot, ProjectStore, StoreError, SyncTable};
+use crate::folder::{
+    self, rows, FolderError, DOCUMENTS_DIR, ENTITIES_DIR, MANIFEST_FILE, META_DIR,
+};
+use crate::sync::outbox::{self, iso_timestamp, now_ms, OutboxWorker};
+use crate::sync::{random_uuid, Mutation};
+
+/// Directory under app data holding the repositories
+pub const HISTORY_DIR: &str = "history";
+
+const AUTHOR_NAME: &str = "Rhei";
+const AUTHOR_EMAIL: &str = "history@rhei.local";
+
+/// Changes named in a commit summary before the rest are counted
+const SUMMARY_CHANGES: usize = 3;
+
+/// Store setting holding the `HistorySettings`
+const SETTINGS_SETTING: &str = "historySettings";
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("revision `{0}` not found")]
+    RevisionNotFound(String),
+    #[error("document `{document_id}` isn't in revision `{revision}`")]
+    DocumentNotFound {
+        revision: String,
+        document_id: String,
+    },
+    #[error("history repository failed: {0}")]
+    Git(#[from] git2::Error),
+    #[error(transparent)]
+    Folder(#[from] FolderError),
+    #[error("history I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl HistoryError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RevisionNotFound(_) => "history_revision_not_found",
+            Self::DocumentNotFound { .. } => "history_document_not_found",
+            Self::Git(_) => "history_git",
+            Self::Folder(e) => e.code(),
+            Self::Io(_) => "history_io",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(HistoryError);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    pub id: String,
+    /// First line of the message
+    pub summary: String,
+    /// Full message: the summary, then one line per change
+    pub message: String,
+    pub created_at_ms: i64,
+    pub parent: Option<String>,
+}
+
+impl Revision {
+    fn from_commit(commit: &git2::Commit<'_>) -> Self {
+        Self {
+            id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            message: commit.message().unwrap_or_default().trim_end().to_string(),
+            created_at_ms: commit.time().seconds() * 1000,
+            parent: commit.parent_ids().next().map(|id| id.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+    /// Unified diff of the file
+    pub patch: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionDiff {
+    pub from: Option<String>,
+    pub to: String,
+    /// The changes, described as in a commit message
+    pub changes: Vec<String>,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySettings {
+    /// Minutes between automatic commits; 0 commits only on quit and request
+    pub interval_minutes: u64,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            interval_minutes: 10,
+        }
+    }
+}
+
+fn repo_path(dir: &Path, project_id: &str) -> PathBuf {
+    dir.join(format!("{}.git", folder::id_file_name(project_id)))
+}
+
+/// The project's repository, or `None` if nothing was committed yet
+fn open_repo(dir: &Path, project_id: &str) -> Result<Option<Repository>, HistoryError> {
+    match Repository::open_bare(repo_path(dir, project_id)) {
+        Ok(repo) => Ok(Some(repo)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn head_commit(repo: &Repository) -> Result<Option<git2::Commit<'_>>, HistoryError> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_commit()?)),
+        Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn find_commit<'r>(repo: &'r Repository, revision: &str) -> Result<git2::Commit<'r>, HistoryError> {
+    repo.revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| HistoryError::RevisionNotFound(revision.to_string()))
+}
+
+/// `/`-separated path, as stored in git trees
+fn tree_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The story folder files for `snapshot`, without the sync position
+pub fn project_files(snapshot: &ProjectSnapshot) -> Result<BTreeMap<String, String>, HistoryError> {
+    let snapshot = ProjectSnapshot {
+        version: 0,
+        synced_at: String::new(),
+        ..snapshot.clone()
+    };
+    let (files, _) = folder::folder_files(&snapshot)?;
+    Ok(files
+        .into_iter()
+        .map(|(path, text)| (tree_path(&path), text))
+        .collect())
+}
+
+fn write_tree(repo: &Repository, files: &BTreeMap<String, String>) -> Result<Oid, HistoryError> {
+    // Files directly in this directory, and the files of each subdirectory
+    let mut here = Vec::new();
+    let mut dirs: BTreeMap<&str, BTreeMap<String, String>> = BTreeMap::new();
+    for (path, text) in files {
+        match path.split_once('/') {
+            Some((dir, rest)) => {
+                dirs.entry(dir)
+                    .or_default()
+                    .insert(rest.to_string(), text.clone());
+            }
+            None => here.push((path, text)),
+        }
+    }
+    let mut builder = repo.treebuilder(None)?;
+    for (name, text) in here {
+        builder.insert(name, repo.blob(text.as_bytes())?, 0o100644)?;
+    }
+    for (name, files) in dirs {
+        builder.insert(name, write_tree(repo, &files)?, 0o040000)?;
+    }
+    Ok(builder.write()?)
+}
+
+/// Every file in a committed tree
+fn tree_files(
+    repo: &Repository,
+    tree: &Tree<'_>,
+) -> Result<BTreeMap<String, String>, HistoryError> {
+    let mut files = BTreeMap::new();
+    let mut failure = None;
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() != Some(ObjectType::Blob) {
+            return TreeWalkResult::Ok;
+        }
+        match repo.find_blob(entry.id()) {
+            Ok(blob) => {
+                let path = format!("{}{}", dir, entry.name().unwrap_or_default());
+                files.insert(path, String::from_utf8_lossy(blob.content()).into_owned());
+                TreeWalkResult::Ok
+            }
+            Err(e) => {
+                failure = Some(e);
+                TreeWalkResult::Abort
+            }
+        }
+    })?;
+    match failure {
+        Some(e) => Err(e.into()),
+        None => Ok(files),
+    }
+}
+
+// ============================================================================
+// Describing changes
+// ============================================================================
+
+/// A document or entity as named in change descriptions
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    entity: bool,
+    name: String,
+    /// The file text and its sidecar, to tell edits apart
+    text: String,
+}
+
+fn items(files: &BTreeMap<String, String>) -> BTreeMap<String, Item> {
+    let mut items = BTreeMap::new();
+    for (path, text) in files {
+        let entity = path.starts_with(&format!("{}/", ENTITIES_DIR));
+        let row = if entity {
+            rows::from_toml_str(text).ok()
+        } else if path.starts_with(&format!("{}/", DOCUMENTS_DIR)) {
+            rows::split_front_matter(text).ok().map(|(row, _)| row)
+        } else {
+            None
+        };
+        let Some(row) = row else {
+            continue;
+        };
+        let Some(id) = row.get("id").and_then(Value::as_str) else {
+            continue;
+        };
+        let field = if entity { "name" } else { "title" };
+        let name = match row.get(field).and_then(Value::as_str) {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => "Untitled".to_string(),
+        };
+        let sidecar = files
+            .get(&tree_path(&folder::sidecar_path(id)))
+            .map(String::as_str)
+            .unwrap_or_default();
+        items.insert(
+            id.to_string(),
+            Item {
+                entity,
+                name,
+                text: format!("{}\0{}", text, sidecar),
+            },
+        );
+    }
+    items
+}
+
+/// One line per change between two sets of folder files, documents first
+pub fn describe_changes(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let (before, after) = (items(old), items(new));
+    let mut changes = Vec::new();
+    for entity in [false, true] {
+        let noun = if entity { "entity " } else { "" };
+        let ids: BTreeSet<&String> = before
+            .iter()
+            .chain(&after)
+            .filter(|(_, item)| item.entity == entity)
+            .map(|(id, _)| id)
+            .collect();
+        for id in ids {
+            changes.push(match (before.get(id), after.get(id)) {
+                (None, Some(item)) => format!("added {}{}", noun, item.name),
+                (Some(item), None) => format!("deleted {}{}", noun, item.name),
+                (Some(old), Some(new)) if old.name != new.name => {
+                    format!("renamed {}{} to {}", noun, old.name, new.name)
+                }
+                (Some(old), Some(new)) if old.text != new.text => {
+                    format!("edited {}{}", noun, new.name)
+                }
+                _ => continue,
+            });
+        }
+    }
+    if old.get(MANIFEST_FILE) != new.get(MANIFEST_FILE) && !old.is_empty() {
+        changes.push("updated relationships".to_string());
+    }
+    let derived = |files: &BTreeMap<String, String>, table: &str| {
+        files.get(&format!("{}/{}.json", META_DIR, table)).cloned()
+    };
+    for table in [
+        SyncTable::Mentions,
+        SyncTable::Analysis,
+        SyncTable::Captures,
+    ] {
+        if derived(old, table.as_str()) != derived(new, table.as_str()) {
+            changes.push(format!("updated {}", table.as_str()));
+        }
+    }
+    changes
+}
+
+/// "Edited Chapter 3; added entity Kael", then every change on its own line
+pub fn commit_message(changes: &[String]) -> String {
+    let mut summary = match changes.len() {
+        0 => "updated project".to_string(),
+        n if n <= SUMMARY_CHANGES + 1 => changes.join("; "),
+        n => format!(
+            "{}; and {} more changes",
+            changes[..SUMMARY_CHANGES].join("; "),
+            n - SUMMARY_CHANGES
+        ),
+    };
+    if let Some(first) = summary.get(..1) {
+        summary.replace_range(..1, &first.to_uppercase());
+    }
+    if changes.len() <= 1 {
+        return summary;
+    }
+    let details: Vec<String> = changes.iter().map(|c| format!("- {}", c)).collect();
+    format!("{}\n\n{}\n", summary, details.join("\n"))
+}
+
+// ============================================================================
+// Committing, listing, diffing, checking out
+// ============================================================================
+
+/// Commit the snapshot if it differs from the last commit. `message`
+/// replaces the generated one.
+pub fn commit_snapshot(
+    dir: &Path,
+    snapshot: &ProjectSnapshot,
+    message: Option<&str>,
+    now: i64,
+) -> Result<Option<Revision>, HistoryError> {
+    let files = project_files(snapshot)?;
+    let repo = match open_repo(dir, &snapshot.project_id)? {
+        Some(repo) => repo,
+        None => Repository::init_bare(repo_path(dir, &snapshot.project_id))?,
+    };
+    let parent = head_commit(&repo)?;
+    let tree = repo.find_tree(write_tree(&repo, &files)?)?;
+    let previous = match &parent {
+        Some(parent) if parent.tree_id() == tree.id() => return Ok(None),
+        Some(parent) => tree_files(&repo, &parent.tree()?)?,
+        None => BTreeMap::new(),
+    };
+
+    let message = match message.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(message) => message.to_string(),
+        None => commit_message(&describe_changes(&previous, &files)),
+    };
+    let signature = Signature::new(AUTHOR_NAME, AUTHOR_EMAIL, &Time::new(now / 1000, 0))?;
+    let parents: Vec<&git2::Commit<'_>> = parent.iter().collect();
+    let id = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &parents,
+    )?;
+    let revision = Revision::from_commit(&repo.find_commit(id)?);
+    Ok(Some(revision))
+}
+
+/// Revisions from newest to oldest
+pub fn list_revisions(
+    dir: &Path,
+    project_id: &str,
+    limit: usize,
+) -> Result<Vec<Revision>, HistoryError> {
+    let Some(repo) = open_repo(dir, project_id)? else {
+        return Ok(Vec::new());
+    };
+    if head_commit(&repo)?.is_none() {
+        return Ok(Vec::new());
+    }
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(Sort::TIME | Sort::TOPOLOGICAL)?;
+    walk.take(limit)
+        .map(|id| Ok(Revision::from_commit(&repo.find_commit(id?)?)))
+        .collect()
+}
+
+/// Changes from `from` (default: the parent of `to`) to `to`
+pub fn diff_revisions(
+    dir: &Path,
+    project_id: &str,
+    from: Option<&str>,
+    to: &str,
+) -> Result<RevisionDiff, HistoryError> {
+    let repo = open_repo(dir, project_id)?
+        .ok_or_else(|| HistoryError::RevisionNotFound(to.to_string()))?;
+    let new = find_commit(&repo, to)?;
+    let old = match from {
+        Some(from) => Some(find_commit(&repo, from)?),
+        None => new.parents().next(),
+    };
+    let new_tree = new.tree()?;
+    let old_tree = old.as_ref().map(|c| c.tree()).transpose()?;
+
+    let mut diff = repo.diff_tree_to_tree(
+        old_tree.as_ref(),
+        Some(&new_tree),
+        Some(DiffOptions::new().context_lines(2)),
+    )?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+    let mut files = Vec::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let status = match delta.status() {
+            git2::Delta::Added => FileStatus::Added,
+            git2::Delta::Deleted => FileStatus::Deleted,
+            git2::Delta::Renamed => FileStatus::Renamed,
+            _ => FileStatus::Modified,
+        };
+        let path_of = |file: git2::DiffFile<'_>| file.path().map(tree_path);
+        let path = path_of(delta.new_file())
+            .or_else(|| path_of(delta.old_file()))
+            .unwrap_or_default();
+        let patch = match Patch::from_diff(&diff, i)? {
+            Some(mut patch) => String::from_utf8_lossy(&patch.to_buf()?).into_owned(),
+            None => String::new(),
+        };
+        files.push(FileDiff {
+            old_path: (status == FileStatus::Renamed)
+                .then(|| path_of(delta.old_file()))
+                .flatten(),
+            path,
+            status,
+            patch,
+        });
+    }
+
+    let old_files = match &old_tree {
+        Some(tree) => tree_files(&repo, tree)?,
+        None => BTreeMap::new(),
+    };
+    Ok(RevisionDiff {
+        from: old.map(|c| c.id().to_string()),
+        to: new.id().to_string(),
+        changes: describe_changes(&old_files, &tree_files(&repo, &new_tree)?),
+        files,
+    })
+}
+
+/// A document as it was at `revision`
+pub fn document_at(
+    dir: &Path,
+    project_id: &str,
+    revision: &str,
+    document_id: &str,
+) -> Result<(Map<String, Value>, Revision), HistoryError> {
+    let repo = open_repo(dir, project_id)?
+        .ok_or_else(|| HistoryError::RevisionNotFound(revision.to_string()))?;
+    let commit = find_commit(&repo, revision)?;
+    let files = tree_files(&repo, &commit.tree()?)?;
+    let not_found = || HistoryError::DocumentNotFound {
+        revision: revision.to_string(),
+        document_id: document_id.to_string(),
+    };
+
+    let sidecar: Option<Map<String, Value>> = files
+        .get(&tree_path(&folder::sidecar_path(document_id)))
+        .map(|json| serde_json::from_str(json))
+        .transpose()
+        .map_err(FolderError::from)?;
+    let prefix = format!("{}/", DOCUMENTS_DIR);
+    for (path, text) in files.range(prefix.clone()..) {
+        if !path.starts_with(&prefix) {
+            break;
+        }
+        let Ok((front, _)) = rows::split_front_matter(text) else {
+            continue;
+        };
+        if front.get("id").and_then(Value::as_str) == Some(document_id) {
+            let row = folder::read_document(text, sidecar.as_ref()).map_err(|_| not_found())?;
+            return Ok((row, Revision::from_commit(&commit)));
+        }
+    }
+    Err(not_found())
+}
+
+/// Copy a document from `revision` into a new note next to the current one.
+/// The copy is applied locally and queued for sync like any other edit.
+pub fn checkout_document(
+    store: &ProjectStore,
+    dir: &Path,
+    project_id: &str,
+    revision: &str,
+    document_id: &str,
+) -> Result<Value, HistoryError> {
+    let (mut row, revision) = document_at(dir, project_id, revision, document_id)?;
+    let id = random_uuid();
+    let now = iso_timestamp(now_ms());
+    let title = row
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or("Untitled");
+    let as_of = iso_timestamp(revision.created_at_ms)[..16].replace('T', " ");
+    let title = format!("{} (as of {})", title, as_of);
+
+    let mut metadata = match row.remove("metadata") {
+        Some(Value::Object(metadata)) => metadata,
+        _ => Map::new(),
+    };
+    metadata.insert(
+        "restoredFrom".into(),
+        serde_json::json!({"documentId": document_id, "revision": revision.id}),
+    );
+    for (key, value) in [
+        ("id", Value::String(id.clone())),
+        ("projectId", Value::String(project_id.to_string())),
+        ("type", Value::String("note".into())),
+        ("title", Value::String(title)),
+        ("metadata", Value::Object(metadata)),
+        ("createdAt", Value::String(now.clone())),
+        ("updatedAt", Value::String(now)),
+    ] {
+        row.insert(key.to_string(), value);
+    }
+
+    let row = Value::Object(row);
+    store.upsert(SyncTable::Documents, &row)?;
+    let mut mutation = Mutation::local(project_id, SyncTable::Documents, MutationType::Upsert, &id);
+    mutation.row = Some(row.clone());
+    outbox::enqueue(store, &mutation)?;
+    Ok(row)
+}
+
+// ============================================================================
+// Scheduling
+// ============================================================================
+
+struct SchedulerState {
+    settings: HistorySettings,
+    woken: bool,
+}
+
+pub struct History {
+    dir: PathBuf,
+    state: Mutex<SchedulerState>,
+    wake: Condvar,
+    running: Mutex<()>,
+}
+
+impl History {
+    /// Repositories in `dir`, scheduled by the settings saved in `store`
+    pub fn load(dir: PathBuf, store: &ProjectStore) -> Result<Self, StoreError> {
+        Ok(Self {
+            dir,
+            state: Mutex::new(SchedulerState {
+                settings: store.setting(SETTINGS_SETTING)?.unwrap_or_default(),
+                woken: false,
+            }),
+            wake: Condvar::new(),
+            running: Mutex::new(()),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn settings(&self) -> HistorySettings {
+        self.state.lock().unwrap().settings
+    }
+
+    /// Change and save the settings; the schedule restarts from now
+    pub fn configure(
+        &self,
+        store: &ProjectStore,
+        settings: HistorySettings,
+    ) -> Result<(), StoreError> {
+        let mut state = self.state.lock().unwrap();
+        store.set_setting(SETTINGS_SETTING, &settings)?;
+        state.settings = settings;
+        state.woken = true;
+        self.wake.notify_all();
+        Ok(())
+    }
+
+    /// Pick up the settings `store` holds, e.g. after a backup replaced it
+    pub fn reload(&self, store: &ProjectStore) -> Result<(), StoreError> {
+        let settings = store.setting(SETTINGS_SETTING)?.unwrap_or_default();
+        let mut state = self.state.lock().unwrap();
+        state.settings = settings;
+        state.woken = true;
+        self.wake.notify_all();
+        Ok(())
+    }
+
+    pub fn commit(
+        &self,
+        store: &ProjectStore,
+        project_id: &str,
+        message: Option<&str>,
+    ) -> Result<Option<Revision>, HistoryError> {
+        let _running = self.running.lock().unwrap();
+        commit_snapshot(&self.dir, &store.snapshot(project_id)?, message, now_ms())
+    }
+
+    /// Commit every stored project that changed
+    pub fn commit_all(&self, store: &ProjectStore) -> Result<Vec<Revision>, HistoryError> {
+        let mut revisions = Vec::new();
+        for project_id in store.project_ids()? {
+            revisions.extend(self.commit(store, &project_id, None)?);
+        }
+        Ok(revisions)
+    }
+
+    /// Sleep for the interval; `false` if woken early by `configure`
+    fn wait_interval(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.woken = false;
+        let interval = state.settings.interval_minutes;
+        if interval == 0 {
+            let _state = self.wake.wait_while(state, |s| !s.woken).unwrap();
+            return false;
+        }
+        let timeout = Duration::from_secs(interval * 60);
+        let (_state, result) = self
+            .wake
+            .wait_timeout_while(state, timeout, |s| !s.woken)
+            .unwrap();
+        result.timed_out()
+    }
+}
+
+/// Run automatic commits on a dedicated thread
+pub fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let history = app.state::<History>();
+        if !history.wait_interval() {
+            continue;
+        }
+        if let Err(e) = history.commit_all(&app.state::<ProjectStore>()) {
+            eprintln!("[history] Scheduled commit failed: {}", e);
+        }
+    });
+}
+
+/// Final commit from `run()`'s exit handler
+pub fn commit_on_exit(app: &AppHandle) {
+    let (Some(history), Some(store)) =
+        (app.try_state::<History>(), app.try_state::<ProjectStore>())
+    else {
+        return;
+    };
+    match history.commit_all(&store) {
+        Ok(revisions) => println!("[history] Committed {} project(s)", revisions.len()),
+        Err(e) => eprintln!("[history] Commit on quit failed: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_history_settings(history: State<'_, History>) -> HistorySettings {
+    history.settings()
+}
+
+#[tauri::command]
+pub fn configure_history(
+    history: State<'_, History>,
+    store: State<'_, ProjectStore>,
+    settings: HistorySettings,
+) -> Result<(), HistoryError> {
+    Ok(history.configure(&store, settings)?)
+}
+
+/// Commit now; `null` if nothing changed since the last revision
+#[tauri::command(rename_all = "camelCase")]
+pub async fn commit_project_history(
+    app: AppHandle,
+    project_id: String,
+    message: Option<String>,
+) -> Result<Option<Revision>, HistoryError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<History>().commit(
+            &app.state::<ProjectStore>(),
+            &project_id,
+            message.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| HistoryError::Io(std::io::Error::other(e)))?
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn list_project_history(
+    history: State<'_, History>,
+    project_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<Revision>, HistoryError> {
+    list_revisions(history.dir(), &project_id, limit.unwrap_or(100))
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn diff_project_revisions(
+    app: AppHandle,
+    project_id: String,
+    from: Option<String>,
+    to: String,
+) -> Result<RevisionDiff, HistoryError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        diff_revisions(
+            app.state::<History>().dir(),
+            &project_id,
+            from.as_deref(),
+            &to,
+        )
+    })
+    .await
+    .map_err(|e| HistoryError::Io(std::io::Error::other(e)))?
+}
+
+/// Returns the new note's row
+#[tauri::command(rename_all = "camelCase")]
+pub fn checkout_document_revision(
+    history: State<'_, History>,
+    store: State<'_, ProjectStore>,
+    worker: State<'_, OutboxWorker>,
+    project_id: String,
+    revision: String,
+    document_id: String,
+) -> Result<Value, HistoryError> {
+    let row = checkout_document(&store, history.dir(), &project_id, &revision, &document_id)?;
+    worker.notify();
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn doc(text: &str) -> Value {
+        json!({"type": "doc", "content": [{"type": "paragraph", "content": [{"type": "text", "text": text}]}]})
+    }
+
+    fn snapshot() -> ProjectSnapshot {
+        ProjectSnapshot {
+            project_id: "p1".into(),
+            version: 4,
+            documents: vec![
+                json!({"id": "d1", "projectId": "p1", "type": "chapter", "title": "Chapter 1",
+                       "orderIndex": 0, "content": doc("The storm broke."),
+                       "contentText": "The storm broke.", "wordCount": 3}),
+                json!({"id": "d3", "projectId": "p1", "type": "chapter", "title": "Chapter 3",
+                       "orderIndex": 2, "content": doc("Kael waited."),
+                       "contentText": "Kael waited.", "wordCount": 2}),
+            ],
+            entities: vec![
+                json!({"id": "e1", "projectId": "p1", "type": "character", "name": "Ada"}),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn set_document(snapshot: &mut ProjectSnapshot, id: &str, text: &str) {
+        let row = snapshot
+            .documents
+            .iter_mut()
+            .find(|d| d["id"] == id)
+            .unwrap();
+        row["content"] = doc(text);
+        row["contentText"] = json!(text);
+        row["wordCount"] = json!(text.split_whitespace().count());
+    }
+
+    #[test]
+    fn commits_only_changes_with_descriptive_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut snapshot = snapshot();
+        let first = commit_snapshot(dir.path(), &snapshot, None, 1_000_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            first.summary,
+            "Added Chapter 1; added Chapter 3; added entity Ada"
+        );
+        assert_eq!(first.parent, None);
+
+        // A new sync position alone isn't a change
+        snapshot.version = 5;
+        snapshot.synced_at = "2024-05-01T10:00:00Z".into();
+        assert_eq!(
+            commit_snapshot(dir.path(), &snapshot, None, 2_000_000).unwrap(),
+            None
+        );
+
+        set_document(&mut snapshot, "d3", "Kael waited by the dock.");
+        snapshot
+            .entities
+            .push(json!({"id": "e2", "projectId": "p1", "type": "character", "name": "Kael"}));
+        let second = commit_snapshot(dir.path(), &snapshot, None, 3_000_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.summary, "Edited Chapter 3; added entity Kael");
+        assert_eq!(
+            second.message,
+            "Edited Chapter 3; added entity Kael\n\n- edited Chapter 3\n- added entity Kael"
+        );
+        assert_eq!(second.parent.as_deref(), Some(first.id.as_str()));
+
+        snapshot.documents[0]["title"] = json!("Prologue");
+        snapshot.entities.retain(|e| e["id"] != "e1");
+        let manual = commit_snapshot(dir.path(), &snapshot, Some("Before the rewrite"), 4_000_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(manual.summary, "Before the rewrite");
+
+        let history = list_revisions(dir.path(), "p1", 10).unwrap();
+        let ids: Vec<&str> = history.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, [&manual.id, &second.id, &first.id]);
+        assert_eq!(history[1].created_at_ms, 3_000_000);
+        assert!(list_revisions(dir.path(), "unknown", 10)
+            .unwrap()
+            .is_empty());
+
+        let diff = diff_revisions(dir.path(), "p1", None, &manual.id).unwrap();
+        assert_eq!(
+            diff.changes,
+            ["renamed Chapter 1 to Prologue", "deleted entity Ada"]
+        );
+        let statuses: Vec<(&str, FileStatus)> = diff
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("documents/prologue.md", FileStatus::Renamed),
+                ("entities/character/ada.toml", FileStatus::Deleted),
+            ]
+        );
+
+        assert_eq!(
+            diff.files[0].old_path.as_deref(),
+            Some("documents/chapter-1.md")
+        );
+
+        let diff = diff_revisions(dir.path(), "p1", Some(&first.id[..8]), &second.id).unwrap();
+        let chapter = diff
+            .files
+            .iter()
+            .find(|f| f.path == "documents/chapter-3.md")
+            .unwrap();
+        assert!(
+            chapter
+                .patch
+                .contains("-Kael waited.\n+Kael waited by the dock.\n"),
+            "{}",
+            chapter.patch
+        );
+        assert!(matches!(
+            diff_revisions(dir.path(), "p1", Some("nope"), &second.id),
+            Err(HistoryError::RevisionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn settings_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        let history = History::load(dir.path().to_path_buf(), &store).unwrap();
+        assert_eq!(history.settings(), HistorySettings::default());
+
+        let settings = HistorySettings {
+            interval_minutes: 0,
+        };
+        history.configure(&store, settings).unwrap();
+        let history = History::load(dir.path().to_path_buf(), &store).unwrap();
+        assert_eq!(history.settings(), settings);
+    }
+
+    #[test]
+    fn checks_out_a_past_version_as_a_scratch_note() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        let mut snapshot = snapshot();
+        store.bootstrap_project(&snapshot).unwrap();
+        let history = History::load(dir.path().to_path_buf(), &store).unwrap();
+        let first = history.commit(&store, "p1", None).unwrap().unwrap();
+
+        set_document(&mut snapshot, "d3", "Kael left.");
+        store.bootstrap_project(&snapshot).unwrap();
+        assert_eq!(history.commit_all(&store).unwrap().len(), 1);
+
+        let row = checkout_document(&store, dir.path(), "p1", &first.id, "d3").unwrap();
+        let id = row["id"].as_str().unwrap();
+        assert_ne!(id, "d3");
+        assert_eq!(row["type"], "note");
+        assert_eq!(row["contentText"], "Kael waited.");
+        assert!(row["title"]
+            .as_str()
+            .unwrap()
+            .starts_with("Chapter 3 (as of "));
+        assert_eq!(row["metadata"]["restoredFrom"]["revision"], first.id);
+        assert_eq!(
+            store.get(SyncTable::Documents, id).unwrap(),
+            Some(row.clone())
+        );
+        assert_eq!(
+            store.get(SyncTable::Documents, "d3").unwrap().unwrap()["contentText"],
+            "Kael left."
+        );
+        assert_eq!(outbox::pending(&store).unwrap().len(), 1);
+
+        assert!(matches!(
+            checkout_document(&store, dir.path(), "p1", &first.id, "missing"),
+            Err(HistoryError::DocumentNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,612 @@
+//! Word (.docx) reading
+//!
+//! Paragraph kinds come from styles: an outline level or a "heading N" name
+//! makes a heading, "quote" in the name a block quote, and numbering a list
+//! item, following `basedOn` to built-in styles. Only direct and character
+//! style formatting becomes marks; a heading's bold face is its style.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use super::{
+    attr, read_xml, zip_part, Anchor, Block, BlockKind, ChangeKind, Comment, ImportError, Inline,
+    Marks, ParsedDocument, TrackedChange, XmlEvent,
+};
+
+/// Subtrees with no equivalent, and what to call them in warnings
+const SKIPPED: [(&str, Option<&str>); 9] = [
+    ("w:drawing", Some("images")),
+    ("w:pict", Some("images")),
+    ("w:object", Some("embedded objects")),
+    ("w:txbxContent", Some("text boxes")),
+    ("w:footnoteReference", Some("footnotes")),
+    ("w:endnoteReference", Some("endnotes")),
+    ("mc:Fallback", None),
+    ("w:instrText", None),
+    ("w:delInstrText", None),
+];
+
+#[derive(Debug, Default)]
+struct Style {
+    name: String,
+    based_on: Option<String>,
+    outline: Option<u8>,
+    numbered: bool,
+    marks: Marks,
+}
+
+fn on(attrs: &[(String, String)]) -> bool {
+    !matches!(attr(attrs, "w:val"), Some("0" | "false" | "off" | "none"))
+}
+
+/// Apply a run property element to `marks`
+fn run_property(name: &str, attrs: &[(String, String)], marks: &mut Marks) {
+    match name {
+        "w:b" => marks.bold = on(attrs),
+        "w:i" => marks.italic = on(attrs),
+        "w:u" => marks.underline = on(attrs),
+        "w:strike" | "w:dstrike" => marks.strike = on(attrs),
+        _ => {}
+    }
+}
+
+fn heading_level(outline: &str) -> Option<u8> {
+    outline.parse::<u8>().ok().filter(|l| *l < 9).map(|l| l + 1)
+}
+
+#[derive(Default)]
+struct Styles(HashMap<String, Style>);
+
+impl Styles {
+    fn parse(xml: &str) -> Result<Self, ImportError> {
+        let mut styles = HashMap::new();
+        let mut current: Option<(String, Style)> = None;
+        let mut in_rpr = false;
+        read_xml(xml, |event| {
+            match event {
+                XmlEvent::Start("w:style", attrs) => {
+                    let id = attr(attrs, "w:styleId").unwrap_or_default().to_string();
+                    current = Some((id, Style::default()));
+                }
+                XmlEvent::Start("w:rPr", _) => in_rpr = true,
+                XmlEvent::End("w:rPr") => in_rpr = false,
+                XmlEvent::Start(name, attrs) => {
+                    if let Some((_, style)) = &mut current {
+                        let val = attr(attrs, "w:val").unwrap_or_default();
+                        match name {
+                            "w:name" => style.name = val.to_lowercase(),
+                            "w:basedOn" => style.based_on = Some(val.to_string()),
+                            "w:outlineLvl" => style.outline = heading_level(val),
+                            "w:numPr" => style.numbered = true,
+                            _ if in_rpr => run_property(name, attrs, &mut style.marks),
+                            _ => {}
+                        }
+                    }
+                }
+                XmlEvent::End("w:style") => {
+                    if let Some((id, style)) = current.take() {
+                        styles.insert(id, style);
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(Self(styles))
+    }
+
+    /// The style and those it's based on, nearest first
+    fn chain<'a>(&'a self, id: &str) -> impl Iterator<Item = &'a Style> + 'a {
+        let mut next = self.0.get(id);
+        // Bounded, in case of a `basedOn` cycle
+        std::iter::from_fn(move || {
+            let style = next?;
+            next = style.based_on.as_deref().and_then(|id| self.0.get(id));
+            Some(style)
+        })
+        .take(16)
+    }
+
+    fn block_kind(&self, id: &str) -> Option<BlockKind> {
+        self.chain(id).find_map(|style| {
+            let named_level = style
+                .name
+                .strip_prefix("heading ")
+                .and_then(|n| n.trim().parse::<u8>().ok());
+            if let Some(level) = style.outline.or(named_level) {
+                Some(BlockKind::Heading(level))
+            } else if style.name.contains("quote") {
+                Some(BlockKind::Quote)
+            } else if style.numbered || style.name.starts_with("list") {
+                Some(BlockKind::ListItem)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn marks(&self, id: &str) -> Marks {
+        let chain: Vec<&Style> = self.chain(id).collect();
+        let mut marks = Marks::default();
+        for style in chain.into_iter().rev() {
+            marks.bold |= style.marks.bold;
+            marks.italic |= style.marks.italic;
+            marks.underline |= style.marks.underline;
+            marks.strike |= style.marks.strike;
+        }
+        marks
+    }
+}
+
+fn comments(xml: &str) -> Result<Vec<Comment>, ImportError> {
+    let mut comments = Vec::new();
+    let mut current: Option<Comment> = None;
+    let mut in_text = false;
+    read_xml(xml, |event| {
+        match event {
+            XmlEvent::Start("w:comment", attrs) => {
+                current = Some(Comment {
+                    id: attr(attrs, "w:id").unwrap_or_default().to_string(),
+                    author: attr(attrs, "w:author").map(str::to_string),
+                    date: attr(attrs, "w:date").map(str::to_string),
+                    text: String::new(),
+                });
+            }
+            XmlEvent::Start("w:p", _) => {
+                if let Some(comment) = &mut current {
+                    if !comment.text.is_empty() {
+                        comment.text.push('\n');
+                    }
+                }
+            }
+            XmlEvent::Start("w:t", _) => in_text = true,
+            XmlEvent::End("w:t") => in_text = false,
+            XmlEvent::Text(text) if in_text => {
+                if let Some(comment) = &mut current {
+                    comment.text.push_str(text);
+                }
+            }
+            XmlEvent::End("w:comment") => comments.extend(current.take()),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(comments)
+}
+
+/// Walk state for `word/document.xml`
+struct Body<'a> {
+    styles: &'a Styles,
+    out: ParsedDocument,
+    stack: Vec<String>,
+    skip_depth: usize,
+    paragraph: Option<Block>,
+    style: Option<String>,
+    outline: Option<u8>,
+    numbered: bool,
+    marks: Marks,
+    in_text: bool,
+    open_changes: Vec<usize>,
+    /// Comment starts between paragraphs, held for the next one
+    pending: Vec<Inline>,
+}
+
+impl Body<'_> {
+    fn anchor(&mut self, anchor: Anchor, at_start: bool) {
+        match &mut self.paragraph {
+            Some(block) => block.inlines.push(Inline::Anchor(anchor)),
+            None if at_start => self.pending.push(Inline::Anchor(anchor)),
+            None => match self.out.blocks.last_mut() {
+                Some(block) => block.inlines.push(Inline::Anchor(anchor)),
+                None => self.pending.push(Inline::Anchor(anchor)),
+            },
+        }
+    }
+
+    fn start(&mut self, name: &str, attrs: &[(String, String)]) {
+        if self.skip_depth > 0 {
+            self.skip_depth += 1;
+            return;
+        }
+        if let Some((_, what)) = SKIPPED.iter().find(|(element, _)| *element == name) {
+            if let Some(what) = what {
+                self.out.skip(what);
+            }
+            self.skip_depth = 1;
+            return;
+        }
+        let parent = self.stack.last().map(String::as_str);
+        let in_properties = self.stack.iter().any(|n| n.ends_with("Pr"));
+        match (name, parent) {
+            ("w:p", _) => {
+                let mut block = Block::new(BlockKind::Paragraph);
+                block.inlines.append(&mut self.pending);
+                self.paragraph = Some(block);
+                self.style = None;
+                self.outline = None;
+                self.numbered = false;
+            }
+            ("w:pStyle", Some("w:pPr")) => self.style = attr(attrs, "w:val").map(str::to_string),
+            ("w:outlineLvl", Some("w:pPr")) => {
+                self.outline = attr(attrs, "w:val").and_then(heading_level)
+            }
+            ("w:numPr", Some("w:pPr")) => self.numbered = true,
+            ("w:r", _) => self.marks = Marks::default(),
+            ("w:rStyle", Some("w:rPr")) => {
+                self.marks = self.styles.marks(attr(attrs, "w:val").unwrap_or_default())
+            }
+            (_, Some("w:rPr")) if self.stack.iter().rev().nth(1).is_some_and(|n| n == "w:r") => {
+                run_property(name, attrs, &mut self.marks)
+            }
+            ("w:t" | "w:delText", Some("w:r")) => self.in_text = true,
+            ("w:tab", Some("w:r")) => self.text("\t"),
+            ("w:br", Some("w:r")) if !matches!(attr(attrs, "w:type"), Some("page" | "column")) => {
+                self.inline(Inline::Break)
+            }
+            ("w:cr", Some("w:r")) => self.inline(Inline::Break),
+            ("w:ins" | "w:del" | "w:moveTo" | "w:moveFrom", _)
+                if !in_properties && self.paragraph.is_some() =>
+            {
+                let kind = if matches!(name, "w:ins" | "w:moveTo") {
+                    ChangeKind::Insert
+                } else {
+                    ChangeKind::Delete
+                };
+                let index = self.out.changes.len();
+                self.out.changes.push(TrackedChange {
+                    kind,
+                    author: attr(attrs, "w:author").map(str::to_string),
+                    date: attr(attrs, "w:date").map(str::to_string),
+                    replaces: None,
+                });
+                self.open_changes.push(index);
+                self.anchor(Anchor::ChangeStart(index), true);
+            }
+            ("w:commentRangeStart", _) => {
+                let id = attr(attrs, "w:id").unwrap_or_default().to_string();
+                self.anchor(Anchor::CommentStart(id), true);
+            }
+            ("w:commentRangeEnd", _) => {
+                let id = attr(attrs, "w:id").unwrap_or_default().to_string();
+                self.anchor(Anchor::CommentEnd(id), false);
+            }
+            _ => {}
+        }
+        self.stack.push(name.to_string());
+    }
+
+    fn end(&mut self, name: &str) {
+        if self.skip_depth > 0 {
+            self.skip_depth -= 1;
+            return;
+        }
+        self.stack.pop();
+        let in_properties = self.stack.iter().any(|n| n.ends_with("Pr"));
+        match name {
+            "w:p" => {
+                if let Some(mut block) = self.paragraph.take() {
+                    let styled = self
+                        .style
+                        .as_deref()
+                        .and_then(|s| self.styles.block_kind(s));
+                    block.kind = match (self.outline, styled) {
+                        (Some(level), _) => BlockKind::Heading(level),
+                        (None, Some(kind)) => kind,
+                        (None, None) if self.numbered => BlockKind::ListItem,
+                        (None, None) => BlockKind::Paragraph,
+                    };
+                    self.out.blocks.push(block);
+                }
+            }
+            "w:t" | "w:delText" => self.in_text = false,
+            "w:ins" | "w:del" | "w:moveTo" | "w:moveFrom" if !in_properties => {
+                if let Some(index) = self.open_changes.pop() {
+                    self.anchor(Anchor::ChangeEnd(index), false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn inline(&mut self, inline: Inline) {
+        if let Some(block) = &mut self.paragraph {
+            block.inlines.push(inline);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let marks = self.marks;
+        if let Some(block) = &mut self.paragraph {
+            block.push_text(text, marks);
+        }
+    }
+}
+
+pub fn read(path: &Path) -> Result<ParsedDocument, ImportError> {
+    let mut zip =
+        zip::ZipArchive::new(File::open(path)?).map_err(|_| ImportError::NotADocument("DOCX"))?;
+    let document =
+        zip_part(&mut zip, "word/document.xml")?.ok_or(ImportError::NotADocument("DOCX"))?;
+    let styles = match zip_part(&mut zip, "word/styles.xml")? {
+        Some(xml) => Styles::parse(&xml)?,
+        None => Styles::default(),
+    };
+
+    let mut body = Body {
+        styles: &styles,
+        out: ParsedDocument::default(),
+        stack: Vec::new(),
+        skip_depth: 0,
+        paragraph: None,
+        style: None,
+        outline: None,
+        numbered: false,
+        marks: Marks::default(),
+        in_text: false,
+        open_changes: Vec::new(),
+        pending: Vec::new(),
+    };
+    read_xml(&document, |event| {
+        match event {
+            XmlEvent::Start(name, attrs) => body.start(name, attrs),
+            XmlEvent::End(name) => body.end(name),
+            XmlEvent::Text(text) if body.in_text && body.skip_depth == 0 => body.text(text),
+            XmlEvent::Text(_) => {}
+        }
+        Ok(())
+    })?;
+    let mut parsed = body.out;
+    if let Some(xml) = zip_part(&mut zip, "word/comments.xml")? {
+        parsed.comments = comments(&xml)?;
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::protocol::SuggestionType;
+    use crate::db::{ProjectStore, SyncTable};
+    use crate::export::write_zip;
+    use crate::import::tests::text_between;
+    use crate::import::{import_file, take_annotations, ImportError, ImportedAnnotations};
+    use crate::sync::outbox;
+    use serde_json::Value;
+
+    const W: &str = "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\"";
+
+    fn docx(dir: &Path, body: &str, comments: Option<&str>) -> std::path::PathBuf {
+        let styles = format!(
+            "<w:styles {W}>\
+             <w:style w:type=\"paragraph\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"Heading1\"><w:name w:val=\"heading 1\"/>\
+             <w:basedOn w:val=\"Normal\"/><w:pPr><w:outlineLvl w:val=\"0\"/></w:pPr>\
+             <w:rPr><w:b/></w:rPr></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"Subhead\"><w:name w:val=\"heading 2\"/></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"IntenseQuote\"><w:name w:val=\"Intense Quote\"/></w:style>\
+             <w:style w:type=\"character\" w:styleId=\"Emphasis\"><w:name w:val=\"Emphasis\"/>\
+             <w:rPr><w:i/></w:rPr></w:style>\
+             </w:styles>"
+        );
+        let mut entries = vec![
+            ("[Content_Types].xml".to_string(), b"<Types/>".to_vec()),
+            (
+                "word/document.xml".to_string(),
+                format!("<w:document {W}><w:body>{body}</w:body></w:document>").into_bytes(),
+            ),
+            ("word/styles.xml".to_string(), styles.into_bytes()),
+        ];
+        if let Some(comments) = comments {
+            entries.push((
+                "word/comments.xml".to_string(),
+                format!("<w:comments {W}>{comments}</w:comments>").into_bytes(),
+            ));
+        }
+        let path = dir.join("My Novel.docx");
+        write_zip(&path, &entries).unwrap();
+        path
+    }
+
+    fn p(style: &str, runs: &str) -> String {
+        let props = if style.is_empty() {
+            String::new()
+        } else {
+            format!("<w:pPr><w:pStyle w:val=\"{style}\"/></w:pPr>")
+        };
+        format!("<w:p>{props}{runs}</w:p>")
+    }
+
+    fn r(text: &str) -> String {
+        format!("<w:r><w:t xml:space=\"preserve\">{text}</w:t></w:r>")
+    }
+
+    #[test]
+    fn imports_chapters_scenes_comments_and_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = [
+            p("", &r("Front matter")),
+            p("Heading1", &r("Chapter One")),
+            p(
+                "",
+                &format!(
+                    "{}<w:commentRangeStart w:id=\"0\"/><w:r><w:rPr><w:b/></w:rPr>\
+                     <w:t>dark</w:t></w:r><w:commentRangeEnd w:id=\"0\"/>\
+                     <w:r><w:rPr><w:rStyle w:val=\"Emphasis\"/></w:rPr><w:t>.</w:t></w:r>",
+                    r("It was &amp; ")
+                ),
+            ),
+            p("", &r("* * *")),
+            p(
+                "",
+                &format!(
+                    "{}<w:del w:id=\"1\" w:author=\"Ann\"><w:r><w:delText>old</w:delText></w:r></w:del>\
+                     <w:ins w:id=\"2\" w:author=\"Ann\" w:date=\"2026-01-02T00:00:00Z\">{}</w:ins>{}",
+                    r("Second "),
+                    r("new"),
+                    r(" scene")
+                ),
+            ),
+            p(
+                "",
+                &format!(
+                    "{}<w:ins w:id=\"3\" w:author=\"Bo\">{}{}</w:ins>\
+                     <w:del w:id=\"4\" w:author=\"Ann\"><w:r><w:delText>cut</w:delText></w:r></w:del>",
+                    r("Kept "),
+                    r("add"),
+                    r("ed ")
+                ),
+            ),
+            p("", "<w:r><w:drawing><w:t>no</w:t></w:drawing></w:r>"),
+            p("Heading1", &r("Chapter Two")),
+            p("Subhead", &r("Part")),
+            p("IntenseQuote", &r("Quoted")),
+            "<w:p><w:pPr><w:numPr><w:numId w:val=\"1\"/></w:numPr></w:pPr>"
+                .to_string()
+                + &r("Item")
+                + "<w:r><w:br/><w:t>two</w:t></w:r></w:p>",
+        ]
+        .concat();
+        let comments = "<w:comment w:id=\"0\" w:author=\"Ed\" w:date=\"2026-01-01T00:00:00Z\">\
+                        <w:p><w:r><w:t>Too</w:t></w:r></w:p><w:p><w:r><w:t>vague</w:t></w:r></w:p>\
+                        </w:comment>";
+        let path = docx(dir.path(), &body, Some(comments));
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .upsert(
+                SyncTable::Documents,
+                &serde_json::json!({"id": "d0", "projectId": "p1", "type": "chapter", "orderIndex": 4}),
+            )
+            .unwrap();
+        let report = import_file(&store, "p1", &path).unwrap();
+        assert_eq!(report.format, "docx");
+        let titles: Vec<&str> = report.documents.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(titles, ["My Novel", "Chapter One", "Chapter Two"]);
+        assert_eq!(report.documents[1].scenes, 2);
+        assert_eq!(report.warnings, ["images left out: 1"]);
+
+        let row = |id: &str| store.get(SyncTable::Documents, id).unwrap().unwrap();
+        let one = row(&report.documents[1].id);
+        assert_eq!(one["orderIndex"], 6);
+        assert_eq!(one["metadata"]["importedFrom"], "My Novel");
+        let scenes = one["content"]["content"].as_array().unwrap();
+        assert_eq!(scenes.len(), 2);
+        assert_eq!(scenes[1]["type"], "sceneBlock");
+        assert_eq!(scenes[1]["attrs"]["sceneName"], "Scene 2");
+        let first = &scenes[0]["content"][0]["content"];
+        assert_eq!(first[0]["text"], "It was & ");
+        assert_eq!(first[1]["marks"][0]["type"], "bold");
+        assert_eq!(first[2]["marks"][0]["type"], "italic");
+
+        let comment = &report.comments[0];
+        assert_eq!(comment.document_id, report.documents[1].id);
+        assert_eq!(comment.content, "Too\nvague");
+        assert_eq!(comment.author.as_deref(), Some("Ed"));
+        let range = comment.selection_range.as_ref().unwrap();
+        assert_eq!(text_between(&one["content"], range.from, range.to), "dark");
+
+        let suggestions: Vec<_> = report
+            .suggestions
+            .iter()
+            .map(|s| {
+                let payload = &s.suggestion;
+                (
+                    payload.kind,
+                    payload.content.as_str(),
+                    payload.original_content.as_deref(),
+                    text_between(&one["content"], payload.from as usize, payload.to as usize),
+                    s.author.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            suggestions,
+            [
+                (
+                    SuggestionType::Replace,
+                    "new",
+                    Some("old"),
+                    "new".to_string(),
+                    Some("Ann")
+                ),
+                (
+                    SuggestionType::Insert,
+                    "added ",
+                    None,
+                    "added ".to_string(),
+                    Some("Bo")
+                ),
+                (
+                    SuggestionType::Delete,
+                    "",
+                    Some("cut"),
+                    "cut".to_string(),
+                    Some("Ann")
+                ),
+            ]
+        );
+        assert_eq!(
+            report.suggestions[0].created_at.as_deref(),
+            Some("2026-01-02T00:00:00Z")
+        );
+        assert!(one["contentText"]
+            .as_str()
+            .unwrap()
+            .contains("Second new scene"));
+
+        let two = row(&report.documents[2].id);
+        let kinds: Vec<&str> = two["content"]["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, ["heading", "blockquote", "bulletList"]);
+        assert_eq!(two["content"]["content"][0]["attrs"]["level"], 2);
+        assert_eq!(
+            two["content"]["content"][2]["content"][0]["content"][0]["content"][1]["type"],
+            "hardBreak"
+        );
+
+        let queued: Vec<Value> = outbox::pending(&store)
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| entry.mutation.row)
+            .collect();
+        assert_eq!(queued.len(), 3);
+        assert_eq!(queued[1]["title"], "Chapter One");
+
+        // Kept with the chapter until the editor takes them, once
+        let id = &report.documents[1].id;
+        assert!(one["metadata"]["importedComments"].is_array());
+        let annotations = take_annotations(&store, id).unwrap();
+        assert_eq!(annotations.comments, report.comments);
+        assert_eq!(annotations.suggestions, report.suggestions);
+        assert_eq!(
+            row(id)["metadata"],
+            serde_json::json!({"importedFrom": "My Novel"})
+        );
+        assert_eq!(
+            take_annotations(&store, id).unwrap(),
+            ImportedAnnotations::default()
+        );
+        assert_eq!(outbox::pending(&store).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        let path = dir.path().join("notes.docx");
+        std::fs::write(&path, "plain text").unwrap();
+        assert!(matches!(
+            import_file(&store, "p1", &path),
+            Err(ImportError::NotADocument("DOCX"))
+        ));
+        let path = dir.path().join("notes.rtf");
+        std::fs::write(&path, "{\\rtf1}").unwrap();
+        let err = import_file(&store, "p1", &path).unwrap_err();
+        assert_eq!(err.code(), "import_unsupported_format");
+    }
+}
@@ -0,0 +1,935 @@
+//! Fountain screenplays
+//!
+//! Fountain (fountain.io) is plain text whose elements are told apart by
+//! their shape: `INT.`/`EXT.` lines are scene headings, capitalised lines
+//! over speech are character cues, `TO:` lines are transitions, and a
+//! handful of prefixes (`.`, `@`, `>`, `!`, `~`, `#`, `=`) force the rest.
+//!
+//! Each element keeps its markup as written (emphasis, notes, scene numbers,
+//! forcing prefixes) so a script comes back out the way it went in. Scene
+//! headings open scene blocks named after them, sections become headings,
+//! and dialogue becomes a blockquote whose first paragraph is the cue, with
+//! the character's name linked to a `Character` entity when one matches.
+//! Everything else is a paragraph of its own Fountain text.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use super::{
+    canonical_name, insert_document, next_root_order, ImportError, ImportedDocument, NewDocument,
+    NewRows,
+};
+use crate::db::{ProjectStore, SyncTable};
+use crate::sync::outbox::OutboxWorker;
+use crate::sync::random_uuid;
+
+/// Title page keys that can open a script
+const TITLE_KEYS: [&str; 11] = [
+    "title",
+    "credit",
+    "author",
+    "authors",
+    "source",
+    "draft date",
+    "date",
+    "contact",
+    "copyright",
+    "notes",
+    "revision",
+];
+
+/// Scene heading openings, matched case-insensitively before `.` or a space
+const HEADING_PREFIXES: [&str; 6] = ["INT./EXT", "INT/EXT", "I/E", "INT", "EXT", "EST"];
+
+/// A line under a character cue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Parenthetical(String),
+    /// One or more lines of speech
+    Speech(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    /// Without the `.` that forces one
+    SceneHeading(String),
+    Action(String),
+    Dialogue {
+        cue: String,
+        lines: Vec<Line>,
+    },
+    Transition(String),
+    Section {
+        level: u8,
+        text: String,
+    },
+    Synopsis(String),
+    PageBreak(String),
+    /// `/* ... */`, kept whole
+    Boneyard(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Screenplay {
+    /// The `Key: value` lines before the first blank line, as written
+    pub title_page: Option<String>,
+    pub elements: Vec<Element>,
+}
+
+impl Screenplay {
+    /// A title page value, with any emphasis around it trimmed off
+    pub fn title_value(&self, key: &str) -> Option<String> {
+        let page = self.title_page.as_deref()?;
+        let mut lines = page.lines();
+        while let Some(line) = lines.next() {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            if line.starts_with(char::is_whitespace) || !name.trim().eq_ignore_ascii_case(key) {
+                continue;
+            }
+            // An empty value continues on indented lines
+            let value = match value.trim() {
+                "" => lines
+                    .next()
+                    .filter(|next| next.starts_with(char::is_whitespace))?,
+                value => value,
+            };
+            let value = value.trim().trim_matches(|c| c == '*' || c == '_').trim();
+            return (!value.is_empty()).then(|| value.to_string());
+        }
+        None
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn is_upper(text: &str) -> bool {
+    text.chars().any(char::is_alphabetic) && !text.chars().any(char::is_lowercase)
+}
+
+fn is_title_start(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(key, _)| {
+        TITLE_KEYS
+            .iter()
+            .any(|known| key.trim_end().eq_ignore_ascii_case(known))
+    }) && !line.starts_with(char::is_whitespace)
+}
+
+/// Whether a line reads as a scene heading without a `.` to force it
+pub fn is_natural_heading(line: &str) -> bool {
+    let upper = line.trim_start().to_uppercase();
+    HEADING_PREFIXES.iter().any(|prefix| {
+        upper
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(['.', ' ']))
+    })
+}
+
+fn is_centered(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 1 && line.starts_with('>') && line.ends_with('<')
+}
+
+/// The name in a character cue, without `@`, `^` or extensions such as
+/// `(V.O.)`
+pub fn cue_name(cue: &str) -> &str {
+    let mut name = cue.trim();
+    name = name.strip_prefix('@').unwrap_or(name);
+    name = name.strip_suffix('^').unwrap_or(name).trim_end();
+    while name.ends_with(')') {
+        match name.rfind('(') {
+            Some(open) => name = name[..open].trim_end(),
+            None => break,
+        }
+    }
+    name
+}
+
+fn is_cue(line: &str) -> bool {
+    let line = line.trim();
+    match line.strip_prefix('@') {
+        Some(name) => !name.trim().is_empty(),
+        None => is_upper(cue_name(line)),
+    }
+}
+
+/// A paragraph that stands alone between blank lines
+fn single(line: &str) -> Element {
+    let trimmed = line.trim();
+    if trimmed.starts_with(['!', '~']) {
+        return Element::Action(line.to_string());
+    }
+    if trimmed.len() >= 3 && trimmed.chars().all(|c| c == '=') {
+        return Element::PageBreak(line.to_string());
+    }
+    if trimmed.starts_with('#') {
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        return Element::Section {
+            level: level.min(6) as u8,
+            text: trimmed[level..].trim().to_string(),
+        };
+    }
+    if trimmed.starts_with('=') {
+        return Element::Synopsis(line.to_string());
+    }
+    if let Some(heading) = trimmed.strip_prefix('.').filter(|h| !h.starts_with('.')) {
+        return Element::SceneHeading(heading.to_string());
+    }
+    if is_natural_heading(trimmed) {
+        return Element::SceneHeading(trimmed.to_string());
+    }
+    if is_centered(trimmed) {
+        return Element::Action(line.to_string());
+    }
+    if trimmed.starts_with('>') || (is_upper(trimmed) && trimmed.ends_with("TO:")) {
+        return Element::Transition(line.to_string());
+    }
+    Element::Action(line.to_string())
+}
+
+fn dialogue(lines: &[&str]) -> Element {
+    let mut out: Vec<Line> = Vec::new();
+    for line in &lines[1..] {
+        if line.trim_start().starts_with('(') {
+            out.push(Line::Parenthetical(line.to_string()));
+        } else if let Some(Line::Speech(speech)) = out.last_mut() {
+            speech.push('\n');
+            speech.push_str(line);
+        } else {
+            out.push(Line::Speech(line.to_string()));
+        }
+    }
+    Element::Dialogue {
+        cue: lines[0].to_string(),
+        lines: out,
+    }
+}
+
+/// The elements of a paragraph: lines up to a blank one
+fn classify(lines: &[&str], out: &mut Vec<Element>) {
+    let first = lines[0];
+    if lines.len() == 1 {
+        out.push(single(first));
+        return;
+    }
+    let trimmed = first.trim_start();
+    if trimmed.starts_with(['!', '~']) || is_centered(first) {
+        out.push(Element::Action(lines.join("\n")));
+    } else if trimmed.starts_with('@') {
+        out.push(dialogue(lines));
+    } else {
+        match single(first) {
+            Element::Action(_) if is_cue(first) => out.push(dialogue(lines)),
+            Element::Action(_) => out.push(Element::Action(lines.join("\n"))),
+            // A heading or transition written flush against what follows
+            element => {
+                out.push(element);
+                classify(&lines[1..], out);
+            }
+        }
+    }
+}
+
+/// Parse a Fountain script; anything unrecognised is action
+pub fn parse(source: &str) -> Screenplay {
+    let source = source.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let lines: Vec<&str> = source.split('\n').collect();
+    let mut screenplay = Screenplay::default();
+    let mut i = 0;
+    if lines.first().is_some_and(|line| is_title_start(line)) {
+        let end = lines
+            .iter()
+            .position(|line| is_blank(line))
+            .unwrap_or(lines.len());
+        screenplay.title_page = Some(lines[..end].join("\n"));
+        i = end;
+    }
+    while i < lines.len() {
+        let line = lines[i];
+        if is_blank(line) {
+            i += 1;
+            continue;
+        }
+        if let Some(open) = line
+            .find("/*")
+            .filter(|_| line.trim_start().starts_with("/*"))
+        {
+            let end = (i..lines.len())
+                .find(|&j| {
+                    let from = if j == i { open + 2 } else { 0 };
+                    lines[j][from..].contains("*/")
+                })
+                .unwrap_or(lines.len() - 1);
+            screenplay
+                .elements
+                .push(Element::Boneyard(lines[i..=end].join("\n")));
+            i = end + 1;
+            continue;
+        }
+        // Two spaces on an otherwise empty line keep a paragraph together
+        let mut end = i + 1;
+        while end < lines.len()
+            && (!is_blank(lines[end])
+                || (lines[end].len() >= 2
+                    && lines.get(end + 1).is_some_and(|next| !is_blank(next))))
+        {
+            end += 1;
+        }
+        classify(&lines[i..end], &mut screenplay.elements);
+        i = end;
+    }
+    screenplay
+}
+
+// ============================================================================
+// ProseMirror
+// ============================================================================
+
+fn inlines(text: &str) -> Vec<Value> {
+    let mut out = Vec::new();
+    for (n, part) in text.split('\n').enumerate() {
+        if n > 0 {
+            out.push(json!({"type": "hardBreak"}));
+        }
+        if !part.is_empty() {
+            out.push(json!({"type": "text", "text": part}));
+        }
+    }
+    out
+}
+
+fn paragraph(content: Vec<Value>) -> Value {
+    if content.is_empty() {
+        json!({"type": "paragraph"})
+    } else {
+        json!({"type": "paragraph", "content": content})
+    }
+}
+
+/// The cue paragraph, its name linked to the character's entity
+fn cue_paragraph(cue: &str, entity_id: Option<&String>) -> Value {
+    let name = cue_name(cue);
+    let (Some(entity_id), Some(start)) = (entity_id, cue.find(name).filter(|_| !name.is_empty()))
+    else {
+        return paragraph(inlines(cue));
+    };
+    let end = start + name.len();
+    let mut content = inlines(&cue[..start]);
+    content.push(json!({
+        "type": "text",
+        "text": name,
+        "marks": [{"type": "entity", "attrs": {"entityId": entity_id, "entityType": "character"}}],
+    }));
+    content.extend(inlines(&cue[end..]));
+    paragraph(content)
+}
+
+fn scene_block(name: String, mut content: Vec<Value>) -> Value {
+    if content.is_empty() {
+        content.push(paragraph(Vec::new()));
+    }
+    json!({
+        "type": "sceneBlock",
+        "attrs": {"sceneId": random_uuid(), "sceneName": name, "tensionLevel": 5},
+        "content": content,
+    })
+}
+
+/// ProseMirror content for a script. `characters` maps canonical cue names
+/// to the entity ids to link them to.
+pub fn to_doc(elements: &[Element], characters: &HashMap<String, String>) -> Value {
+    let mut content = Vec::new();
+    let mut scene: Option<(String, Vec<Value>)> = None;
+    for element in elements {
+        let node = match element {
+            Element::SceneHeading(heading) => {
+                if let Some((name, nodes)) = scene.replace((heading.clone(), Vec::new())) {
+                    content.push(scene_block(name, nodes));
+                }
+                continue;
+            }
+            Element::Section { level, text } => {
+                if let Some((name, nodes)) = scene.take() {
+                    content.push(scene_block(name, nodes));
+                }
+                let mut heading =
+                    json!({"type": "heading", "attrs": {"level": (*level).clamp(1, 6)}});
+                let text = inlines(text);
+                if !text.is_empty() {
+                    heading["content"] = Value::Array(text);
+                }
+                content.push(heading);
+                continue;
+            }
+            Element::Dialogue { cue, lines } => {
+                let entity_id = characters.get(&canonical_name(cue_name(cue)));
+                let mut paragraphs = vec![cue_paragraph(cue, entity_id)];
+                for line in lines {
+                    let (Line::Parenthetical(text) | Line::Speech(text)) = line;
+                    paragraphs.push(paragraph(inlines(text)));
+                }
+                json!({"type": "blockquote", "content": paragraphs})
+            }
+            Element::Action(text)
+            | Element::Transition(text)
+            | Element::Synopsis(text)
+            | Element::PageBreak(text)
+            | Element::Boneyard(text) => paragraph(inlines(text)),
+        };
+        match &mut scene {
+            Some((_, nodes)) => nodes.push(node),
+            None => content.push(node),
+        }
+    }
+    if let Some((name, nodes)) = scene {
+        content.push(scene_block(name, nodes));
+    }
+    if content.is_empty() {
+        content.push(paragraph(Vec::new()));
+    }
+    json!({"type": "doc", "content": content})
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+/// Inline content as Fountain, with bold, italic and underline marks as
+/// `**`, `*` and `_`
+fn inline_text(node: &Value, out: &mut String) {
+    for child in children(node) {
+        match node_type(child) {
+            "text" => {
+                let mut text = child
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let marks = child.get("marks").and_then(Value::as_array);
+                let has =
+                    |name: &str| marks.is_some_and(|m| m.iter().any(|m| node_type(m) == name));
+                if has("italic") {
+                    text = format!("*{}*", text);
+                }
+                if has("bold") {
+                    text = format!("**{}**", text);
+                }
+                if has("underline") {
+                    text = format!("_{}_", text);
+                }
+                out.push_str(&text);
+            }
+            "hardBreak" => out.push('\n'),
+            _ => inline_text(child, out),
+        }
+    }
+}
+
+/// Whether a paragraph of prose would read back as plain action
+fn reads_as_action(text: &str) -> bool {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut elements = Vec::new();
+    classify(&lines, &mut elements);
+    matches!(elements.as_slice(), [Element::Action(_)])
+}
+
+fn blocks(node: &Value, prose: bool, out: &mut Vec<String>) {
+    match node_type(node) {
+        "sceneBlock" => {
+            let name = node
+                .pointer("/attrs/sceneName")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim();
+            if !name.is_empty() {
+                out.push(match is_natural_heading(name) {
+                    true => name.to_string(),
+                    false => format!(".{}", name),
+                });
+            }
+            for child in children(node) {
+                blocks(child, prose, out);
+            }
+        }
+        "heading" => {
+            let level = node
+                .pointer("/attrs/level")
+                .and_then(Value::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6) as usize;
+            let mut text = String::new();
+            inline_text(node, &mut text);
+            out.push(format!("{} {}", "#".repeat(level), text.trim()));
+        }
+        "paragraph" | "codeBlock" => {
+            let mut text = String::new();
+            inline_text(node, &mut text);
+            if is_blank(&text) {
+                return;
+            }
+            // Prose that happens to look like a cue or heading stays action
+            if prose && !reads_as_action(&text) {
+                text.insert(0, '!');
+            }
+            out.push(text);
+        }
+        "blockquote" if !prose => {
+            let mut lines = Vec::new();
+            for child in children(node) {
+                blocks(child, prose, &mut lines);
+            }
+            if !lines.is_empty() {
+                out.push(lines.join("\n"));
+            }
+        }
+        "horizontalRule" => out.push("===".to_string()),
+        _ => {
+            for child in children(node) {
+                blocks(child, prose, out);
+            }
+        }
+    }
+}
+
+/// A ProseMirror doc as Fountain elements, one string per paragraph of the
+/// script. Docs that weren't written as scripts (`prose`) have any paragraph
+/// that would read as another element forced to action.
+pub fn doc_elements(doc: &Value, prose: bool) -> Vec<String> {
+    let mut out = Vec::new();
+    blocks(doc, prose, &mut out);
+    out
+}
+
+/// A complete script: the title page, then elements a blank line apart
+pub fn to_fountain(title_page: Option<&str>, elements: &[String]) -> String {
+    let mut out = String::new();
+    if let Some(page) = title_page.map(str::trim_end).filter(|p| !p.is_empty()) {
+        out.push_str(page);
+        out.push_str("\n\n");
+    }
+    out.push_str(&elements.join("\n\n"));
+    out.push('\n');
+    out
+}
+
+// ============================================================================
+// Into the project
+// ============================================================================
+
+/// A character speaking in the script
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptCharacter {
+    /// As written in the cues
+    pub name: String,
+    /// The linked `Character` entity; none for a proposed new one
+    pub entity_id: Option<String>,
+    /// Cues spoken
+    pub cues: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FountainReport {
+    pub project_id: String,
+    pub document: ImportedDocument,
+    /// In order of first appearance
+    pub characters: Vec<ScriptCharacter>,
+}
+
+struct Character {
+    id: String,
+    name: String,
+    aliases: Vec<String>,
+}
+
+/// A cue's character: by full name, then alias, then a single character
+/// with the cue as one of their names
+fn find_character<'a>(characters: &'a [Character], name: &str) -> Option<&'a Character> {
+    characters
+        .iter()
+        .find(|c| c.name == name)
+        .or_else(|| {
+            characters
+                .iter()
+                .find(|c| c.aliases.iter().any(|a| a == name))
+        })
+        .or_else(|| {
+            let mut named = characters
+                .iter()
+                .filter(|c| c.name.split(' ').any(|part| part == name));
+            named.next().filter(|_| named.next().is_none())
+        })
+}
+
+/// Import a `.fountain` script as one screenplay document, linking its
+/// cues to the project's characters
+pub fn import_script(
+    store: &ProjectStore,
+    project_id: &str,
+    path: &Path,
+) -> Result<FountainReport, ImportError> {
+    let source = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let screenplay = parse(&source);
+    if screenplay.elements.is_empty() && screenplay.title_page.is_none() {
+        return Err(ImportError::NotADocument("Fountain"));
+    }
+    let source_name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Imported")
+        .to_string();
+
+    let existing: Vec<Character> = store
+        .list(SyncTable::Entities, project_id)?
+        .iter()
+        .filter(|row| row.get("type").and_then(Value::as_str) == Some("character"))
+        .filter_map(|row| {
+            Some(Character {
+                id: row.get("id")?.as_str()?.to_string(),
+                name: canonical_name(row.get("name")?.as_str()?),
+                aliases: row
+                    .get("aliases")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(canonical_name)
+                    .collect(),
+            })
+        })
+        .collect();
+    let mut characters: Vec<ScriptCharacter> = Vec::new();
+    let mut links = HashMap::new();
+    for element in &screenplay.elements {
+        let Element::Dialogue { cue, .. } = element else {
+            continue;
+        };
+        let name = cue_name(cue);
+        let canonical = canonical_name(name);
+        if canonical.is_empty() {
+            continue;
+        }
+        match characters
+            .iter_mut()
+            .find(|c| canonical_name(&c.name) == canonical)
+        {
+            Some(character) => character.cues += 1,
+            None => {
+                let entity_id = find_character(&existing, &canonical).map(|c| c.id.clone());
+                if let Some(id) = &entity_id {
+                    links.insert(canonical, id.clone());
+                }
+                characters.push(ScriptCharacter {
+                    name: name.to_string(),
+                    entity_id,
+                    cues: 1,
+                });
+            }
+        }
+    }
+
+    let title = screenplay
+        .title_value("title")
+        .unwrap_or_else(|| source_name.clone());
+    let content = to_doc(&screenplay.elements, &links);
+    let mut metadata = json!({"importedFrom": source_name});
+    if let Some(page) = &screenplay.title_page {
+        metadata["fountain"] = json!({"titlePage": page});
+    }
+    let mut rows = NewRows::default();
+    let (id, words) = insert_document(
+        &mut rows,
+        project_id,
+        NewDocument {
+            kind: "screenplay",
+            title: &title,
+            parent_id: None,
+            order: next_root_order(store, project_id)?,
+            content: &content,
+            metadata,
+        },
+    );
+    rows.commit(store, project_id)?;
+    Ok(FountainReport {
+        project_id: project_id.to_string(),
+        document: ImportedDocument {
+            id,
+            title,
+            scenes: screenplay
+                .elements
+                .iter()
+                .filter(|e| matches!(e, Element::SceneHeading(_)))
+                .count(),
+            words,
+        },
+        characters,
+    })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn import_fountain(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+) -> Result<FountainReport, ImportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = import_script(&app.state::<ProjectStore>(), &project_id, Path::new(&path))?;
+        app.state::<OutboxWorker>().notify();
+        Ok(report)
+    })
+    .await
+    .map_err(|e| ImportError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    macro_rules! corpus {
+        ($($name:literal),*) => {
+            [$(($name, include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/testdata/fountain/",
+                $name,
+                ".fountain"
+            )))),*]
+        };
+    }
+
+    /// Hand-written scripts in `testdata/fountain`, one per part of the
+    /// syntax described at fountain.io/syntax. The published sample
+    /// screenplays belong in `testdata/fountain/samples`.
+    pub(crate) const CORPUS: [(&str, &str); 7] = corpus!(
+        "title-page",
+        "scene-headings",
+        "action",
+        "dialogue",
+        "transitions",
+        "structure",
+        "emphasis"
+    );
+
+    fn corpus(name: &str) -> &'static str {
+        CORPUS.iter().find(|(n, _)| *n == name).unwrap().1
+    }
+
+    fn assert_round_trips(name: &str, source: &str) {
+        let screenplay = parse(source);
+        // Linking every cue shows entity marks don't change the text
+        let links: HashMap<String, String> = screenplay
+            .elements
+            .iter()
+            .filter_map(|e| match e {
+                Element::Dialogue { cue, .. } => {
+                    Some((canonical_name(cue_name(cue)), format!("e-{}", cue)))
+                }
+                _ => None,
+            })
+            .collect();
+        let doc = to_doc(&screenplay.elements, &links);
+        let written = to_fountain(screenplay.title_page.as_deref(), &doc_elements(&doc, false));
+        assert_eq!(written, source, "{} changed on the way through", name);
+        assert_eq!(parse(&written), screenplay, "{}", name);
+    }
+
+    #[test]
+    fn round_trips_the_syntax_corpus() {
+        for (name, source) in CORPUS {
+            assert_round_trips(name, source);
+        }
+    }
+
+    #[test]
+    #[ignore = "the published fountain.io samples aren't vendored yet; see testdata/fountain/samples"]
+    fn round_trips_the_published_samples() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/fountain/samples");
+        let mut samples = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "fountain") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                assert_round_trips(&path.display().to_string(), &source);
+                samples += 1;
+            }
+        }
+        assert!(samples > 0, "no samples in testdata/fountain/samples");
+    }
+
+    #[test]
+    fn classifies_elements() {
+        let speech = |s: &str| Line::Speech(s.to_string());
+        let paren = |s: &str| Line::Parenthetical(s.to_string());
+        let dialogue = parse(corpus("dialogue")).elements;
+        assert_eq!(
+            dialogue[3],
+            Element::Dialogue {
+                cue: "ADA (CONT'D)".into(),
+                lines: vec![
+                    paren("(to herself)"),
+                    speech("Easy for him."),
+                    paren("(beat)"),
+                    speech("He never climbs the stairs."),
+                ],
+            }
+        );
+        let cues: Vec<&str> = dialogue
+            .iter()
+            .filter_map(|e| match e {
+                Element::Dialogue { cue, .. } => Some(cue_name(cue)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            cues,
+            ["ADA", "KAEL", "ADA", "McCLANE", "ADA", "KAEL", "HOLLOWAY", "R2D2"]
+        );
+        assert_eq!(
+            dialogue[7],
+            Element::Dialogue {
+                cue: "HOLLOWAY (V.O.)".into(),
+                lines: vec![speech("It was the winter\n  \nthat everything changed.")],
+            }
+        );
+        assert!(matches!(dialogue.last(), Some(Element::Action(a)) if a.starts_with('~')));
+
+        let transitions = parse(corpus("transitions")).elements;
+        assert_eq!(transitions[2], Element::Transition("CUT TO:".into()));
+        assert_eq!(
+            transitions[8],
+            Element::Transition(">FADE TO BLACK.".into())
+        );
+        assert_eq!(transitions[9], Element::Action("> THE END <".into()));
+
+        let structure = parse(corpus("structure")).elements;
+        assert_eq!(
+            structure[0],
+            Element::Section {
+                level: 1,
+                text: "Act One".into()
+            }
+        );
+        assert!(matches!(&structure[1], Element::Synopsis(_)));
+        assert!(matches!(&structure[6], Element::PageBreak(_)));
+        assert!(
+            matches!(&structure[8], Element::Boneyard(b) if b.contains("A loaf") && b.ends_with("*/"))
+        );
+
+        let headings: Vec<String> = parse(corpus("scene-headings"))
+            .elements
+            .into_iter()
+            .filter_map(|e| match e {
+                Element::SceneHeading(h) => Some(h),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(headings.len(), 10);
+        assert_eq!(headings[6], "int. cellar - night");
+        assert_eq!(headings[8], "FLASHBACK");
+        assert!(matches!(
+            parse("INT. HALL - DAY\nShe waits.").elements.as_slice(),
+            [Element::SceneHeading(_), Element::Action(_)]
+        ));
+
+        let titled = parse(corpus("title-page"));
+        assert_eq!(
+            titled.title_value("title").as_deref(),
+            Some("THE LAST LIGHTHOUSE")
+        );
+        assert_eq!(titled.title_value("author").as_deref(), Some("Ada Marsh"));
+        assert_eq!(titled.elements[0], Element::Action("FADE IN:".into()));
+        assert_eq!(parse("FADE IN:\n").title_page, None);
+    }
+
+    #[test]
+    fn imports_scenes_and_links_cues_to_characters() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Lamp Room.fountain");
+        fs::write(&path, corpus("dialogue").replace('\n', "\r\n")).unwrap();
+        let store = ProjectStore::open_in_memory().unwrap();
+        for (id, name, aliases) in [
+            ("e1", "Ada Marsh", json!([])),
+            ("e2", "Kael Dunn", json!(["Kael"])),
+            ("e3", "John McClane", json!([])),
+            ("e4", "Ada Holloway", json!([])),
+        ] {
+            store
+                .upsert(
+                    SyncTable::Entities,
+                    &json!({"id": id, "projectId": "p1", "type": "character", "name": name, "aliases": aliases}),
+                )
+                .unwrap();
+        }
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "l1", "projectId": "p1", "type": "location", "name": "R2D2"}),
+            )
+            .unwrap();
+
+        let report = import_script(&store, "p1", &path).unwrap();
+        assert_eq!(report.document.title, "Lamp Room");
+        assert_eq!(report.document.scenes, 1);
+        let characters: Vec<(&str, Option<&str>, usize)> = report
+            .characters
+            .iter()
+            .map(|c| (c.name.as_str(), c.entity_id.as_deref(), c.cues))
+            .collect();
+        // ADA is two characters' first name, so it's proposed as new
+        assert_eq!(
+            characters,
+            [
+                ("ADA", None, 3),
+                ("KAEL", Some("e2"), 2),
+                ("McCLANE", Some("e3"), 1),
+                ("HOLLOWAY", Some("e4"), 1),
+                ("R2D2", None, 1),
+            ]
+        );
+
+        let row = store
+            .get(SyncTable::Documents, &report.document.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(row["type"], "screenplay");
+        assert_eq!(row["metadata"]["importedFrom"], "Lamp Room");
+        let scene = &row["content"]["content"][0];
+        assert_eq!(scene["type"], "sceneBlock");
+        assert_eq!(scene["attrs"]["sceneName"], "INT. LAMP ROOM - NIGHT");
+        let kael = &scene["content"][1]["content"][0]["content"];
+        assert_eq!(kael[0]["text"], "KAEL");
+        assert_eq!(kael[0]["marks"][0]["attrs"]["entityId"], "e2");
+        assert_eq!(kael[1]["text"], " (O.S.)");
+        assert_eq!(
+            scene["content"][1]["content"][1]["content"][0]["text"],
+            "(shouting)"
+        );
+        assert!(scene["content"][0]["content"][0]["content"][0]
+            .get("marks")
+            .is_none());
+        assert_eq!(outbox_len(&store), 1);
+
+        fs::write(&path, "\n\n").unwrap();
+        let err = import_script(&store, "p1", &path).unwrap_err();
+        assert_eq!(err.code(), "import_not_a_document");
+    }
+
+    fn outbox_len(store: &ProjectStore) -> usize {
+        crate::sync::outbox::pending(store).unwrap().len()
+    }
+}
@@ -0,0 +1,1222 @@
+//! Manuscript imports
+//!
+//! Readers turn a file into a flat list of styled paragraphs with zero-width
+//! anchors where comments and tracked changes start and end. Structure comes
+//! from the paragraphs, not the format: the shallowest heading level starts a
+//! chapter document, `***` and `#` separator lines split a chapter into scene
+//! blocks, and deeper headings stay inside the text.
+//!
+//! Comments and tracked changes have no table in the local store. They are
+//! returned with ProseMirror positions in the new documents: comments in the
+//! shape `comments.add` takes, tracked changes as `addSuggestion` payloads
+//! (insertions and deletions stay in the text for the author to accept or
+//! reject, as in Word's markup view). Each chapter also keeps its own in
+//! `metadata` until `take_imported_annotations` hands them to the editor.
+//!
+//! An import writes nothing until the whole file has been read, then adds
+//! every row in one transaction.
+
+pub mod docx;
+pub mod fountain;
+pub mod odt;
+pub mod rtf;
+pub mod scrivener;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Manager, State};
+
+use crate::bridge::protocol::{NewSuggestionPayload, SuggestionType};
+use crate::db::{put_row, MutationType, ProjectStore, StoreError, SyncTable};
+use crate::folder::markdown::{text_content, word_count};
+use crate::sync::outbox::{self, iso_timestamp, now_ms, OutboxWorker};
+use crate::sync::{random_uuid, Mutation};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("`{0}` files can't be imported")]
+    UnsupportedFormat(String),
+    #[error("not a {0} document")]
+    NotADocument(&'static str),
+    #[error("document is malformed: {0}")]
+    Invalid(String),
+    #[error("import I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("document package is corrupt: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl ImportError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnsupportedFormat(_) => "import_unsupported_format",
+            Self::NotADocument(_) => "import_not_a_document",
+            Self::Invalid(_) => "import_invalid",
+            Self::Io(_) => "import_io",
+            Self::Zip(_) => "import_zip",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(ImportError);
+
+// ============================================================================
+// What readers produce
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Marks {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike: bool,
+}
+
+/// Zero-width marker in the text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    CommentStart(String),
+    CommentEnd(String),
+    /// Index into `ParsedDocument::changes`
+    ChangeStart(usize),
+    ChangeEnd(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String, Marks),
+    Break,
+    Anchor(Anchor),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Paragraph,
+    Heading(u8),
+    Quote,
+    ListItem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub inlines: Vec<Inline>,
+}
+
+impl Block {
+    pub fn new(kind: BlockKind) -> Self {
+        Self {
+            kind,
+            inlines: Vec::new(),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text, _) => text.as_str(),
+                Inline::Break => "\n",
+                Inline::Anchor(_) => "",
+            })
+            .collect()
+    }
+
+    /// Appends text, joining it to the previous run when the marks match
+    pub fn push_text(&mut self, text: &str, marks: Marks) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(Inline::Text(last, last_marks)) = self.inlines.last_mut() {
+            if *last_marks == marks {
+                last.push_str(text);
+                return;
+            }
+        }
+        self.inlines.push(Inline::Text(text.to_string(), marks));
+    }
+
+    /// A scene break line such as `***`, `* * *`, `#` or `⁂`
+    pub fn is_separator(&self) -> bool {
+        let text = self.text();
+        self.kind == BlockKind::Paragraph
+            && !text.trim().is_empty()
+            && text
+                .chars()
+                .all(|c| matches!(c, '*' | '#' | '\u{2042}') || c.is_whitespace())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub id: String,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedChange {
+    pub kind: ChangeKind,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    /// Deleted text this insertion replaced
+    pub replaces: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedDocument {
+    pub blocks: Vec<Block>,
+    pub comments: Vec<Comment>,
+    pub changes: Vec<TrackedChange>,
+    /// Content that had no equivalent, by kind (`images`, `footnotes`, ...)
+    pub skipped: BTreeMap<&'static str, usize>,
+}
+
+impl ParsedDocument {
+    pub fn skip(&mut self, what: &'static str) {
+        *self.skipped.entry(what).or_default() += 1;
+    }
+}
+
+// ============================================================================
+// XML
+// ============================================================================
+
+/// Element events from an XML part; empty elements give a start and an end
+pub enum XmlEvent<'a> {
+    Start(&'a str, &'a [(String, String)]),
+    End(&'a str),
+    Text(&'a str),
+}
+
+pub fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn invalid(e: impl std::fmt::Display) -> ImportError {
+    ImportError::Invalid(e.to_string())
+}
+
+/// The element's name, with its attributes collected into `attrs`
+fn attributes(
+    start: &BytesStart,
+    attrs: &mut Vec<(String, String)>,
+) -> Result<String, ImportError> {
+    attrs.clear();
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(invalid)?;
+        attrs.push((
+            String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+            attribute.unescape_value().map_err(invalid)?.into_owned(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(start.name().as_ref()).into_owned())
+}
+
+/// Stream `xml` through `handler` with entities resolved
+pub fn read_xml(
+    xml: &str,
+    mut handler: impl FnMut(XmlEvent<'_>) -> Result<(), ImportError>,
+) -> Result<(), ImportError> {
+    let mut reader = Reader::from_str(xml);
+    let mut attrs = Vec::new();
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(start) => {
+                let name = attributes(&start, &mut attrs)?;
+                handler(XmlEvent::Start(&name, &attrs))?;
+            }
+            Event::Empty(start) => {
+                let name = attributes(&start, &mut attrs)?;
+                handler(XmlEvent::Start(&name, &attrs))?;
+                handler(XmlEvent::End(&name))?;
+            }
+            Event::End(end) => {
+                handler(XmlEvent::End(&String::from_utf8_lossy(end.name().as_ref())))?;
+            }
+            Event::Text(text) => handler(XmlEvent::Text(&text.decode().map_err(invalid)?))?,
+            Event::CData(text) => handler(XmlEvent::Text(&text.decode().map_err(invalid)?))?,
+            Event::GeneralRef(reference) => {
+                let name = reference.decode().map_err(invalid)?;
+                let c = match &*name {
+                    "amp" => '&',
+                    "lt" => '<',
+                    "gt" => '>',
+                    "quot" => '"',
+                    "apos" => '\'',
+                    _ => reference
+                        .resolve_char_ref()
+                        .map_err(invalid)?
+                        .ok_or_else(|| {
+                            ImportError::Invalid(format!("unknown entity &{};", name))
+                        })?,
+                };
+                handler(XmlEvent::Text(c.encode_utf8(&mut [0; 4])))?;
+            }
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// A part of a zip package as text
+pub fn zip_part(
+    zip: &mut zip::ZipArchive<File>,
+    name: &str,
+) -> Result<Option<String>, ImportError> {
+    use std::io::Read;
+    let mut file = match zip.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut text = String::new();
+    file.read_to_string(&mut text)
+        .map_err(|e| ImportError::Invalid(format!("{}: {}", name, e)))?;
+    Ok(Some(text))
+}
+
+// ============================================================================
+// Structure
+// ============================================================================
+
+/// A chapter's text, split into scenes if it had separators
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub scenes: Vec<Vec<Block>>,
+    pub has_separators: bool,
+}
+
+/// Split paragraphs into chapters at the shallowest heading level. Text
+/// before the first such heading goes in a chapter named `untitled`.
+pub fn chapters(blocks: Vec<Block>, untitled: &str) -> Vec<Chapter> {
+    let chapter_level = blocks
+        .iter()
+        .filter_map(|b| match b.kind {
+            BlockKind::Heading(level) => Some(level),
+            _ => None,
+        })
+        .min();
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for block in blocks {
+        if chapter_level.is_some() && block.kind == BlockKind::Heading(chapter_level.unwrap()) {
+            let title = block
+                .text()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            chapters.push(Chapter {
+                title: if title.is_empty() {
+                    "Untitled".to_string()
+                } else {
+                    title
+                },
+                scenes: vec![Vec::new()],
+                has_separators: false,
+            });
+            continue;
+        }
+        if chapters.is_empty() {
+            if block.text().trim().is_empty() && !has_anchors(&block) {
+                continue;
+            }
+            chapters.push(Chapter {
+                title: untitled.to_string(),
+                scenes: vec![Vec::new()],
+                has_separators: false,
+            });
+        }
+        let chapter = chapters.last_mut().expect("a chapter was just pushed");
+        if block.is_separator() {
+            chapter.has_separators = true;
+            chapter.scenes.push(Vec::new());
+        } else {
+            chapter
+                .scenes
+                .last_mut()
+                .expect("scenes start non-empty")
+                .push(block);
+        }
+    }
+    for chapter in &mut chapters {
+        // Separators at the edges or doubled up don't make empty scenes
+        chapter.scenes.retain(|scene| {
+            scene
+                .iter()
+                .any(|b| !b.text().trim().is_empty() || has_anchors(b))
+        });
+    }
+    chapters
+}
+
+fn has_anchors(block: &Block) -> bool {
+    block.inlines.iter().any(|i| matches!(i, Inline::Anchor(_)))
+}
+
+/// Joins tracked changes Word splits across runs, and pairs a deletion with
+/// the insertion beside it into one replacement: the deleted text leaves the
+/// document and becomes the insertion's `replaces`.
+pub fn merge_changes(parsed: &mut ParsedDocument) {
+    let changes = &mut parsed.changes;
+    let same_author = |a: &TrackedChange, b: &TrackedChange| a.author == b.author;
+    for block in &mut parsed.blocks {
+        let inlines = &mut block.inlines;
+        // Adjacent changes of one kind by one author: `End(a) Start(b)` goes
+        let mut i = 0;
+        while i + 1 < inlines.len() {
+            if let (Inline::Anchor(Anchor::ChangeEnd(a)), Inline::Anchor(Anchor::ChangeStart(b))) =
+                (&inlines[i], &inlines[i + 1])
+            {
+                let (a, b) = (*a, *b);
+                if changes[a].kind == changes[b].kind && same_author(&changes[a], &changes[b]) {
+                    inlines.drain(i..i + 2);
+                    for inline in inlines.iter_mut().skip(i) {
+                        if *inline == Inline::Anchor(Anchor::ChangeEnd(b)) {
+                            *inline = Inline::Anchor(Anchor::ChangeEnd(a));
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            }
+            i += 1;
+        }
+
+        // A deletion next to an insertion
+        let mut i = 0;
+        while i < inlines.len() {
+            let Inline::Anchor(Anchor::ChangeStart(first)) = inlines[i] else {
+                i += 1;
+                continue;
+            };
+            let Some(end) = inlines[i..]
+                .iter()
+                .position(|x| *x == Inline::Anchor(Anchor::ChangeEnd(first)))
+                .map(|p| p + i)
+            else {
+                i += 1;
+                continue;
+            };
+            let Some(Inline::Anchor(Anchor::ChangeStart(second))) = inlines.get(end + 1).cloned()
+            else {
+                i += 1;
+                continue;
+            };
+            let Some(second_end) = inlines[end + 1..]
+                .iter()
+                .position(|x| *x == Inline::Anchor(Anchor::ChangeEnd(second)))
+                .map(|p| p + end + 1)
+            else {
+                i += 1;
+                continue;
+            };
+            let kinds = (changes[first].kind, changes[second].kind);
+            if !same_author(&changes[first], &changes[second])
+                || !matches!(
+                    kinds,
+                    (ChangeKind::Delete, ChangeKind::Insert)
+                        | (ChangeKind::Insert, ChangeKind::Delete)
+                )
+            {
+                i += 1;
+                continue;
+            }
+            let (deletion, insertion) = if kinds.0 == ChangeKind::Delete {
+                ((i, end), second)
+            } else {
+                ((end + 1, second_end), first)
+            };
+            let deleted: String = inlines[deletion.0..=deletion.1]
+                .iter()
+                .map(|inline| match inline {
+                    Inline::Text(text, _) => text.as_str(),
+                    Inline::Break => "\n",
+                    Inline::Anchor(_) => "",
+                })
+                .collect();
+            changes[insertion].replaces = Some(deleted);
+            inlines.drain(deletion.0..=deletion.1);
+            i += 1;
+        }
+    }
+}
+
+// ============================================================================
+// ProseMirror
+// ============================================================================
+
+/// A document's content with where its anchors ended up
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuiltContent {
+    pub doc: Value,
+    pub positions: HashMap<Anchor, usize>,
+    /// Text inside each tracked change
+    pub change_text: HashMap<usize, String>,
+}
+
+struct Builder {
+    pos: usize,
+    positions: HashMap<Anchor, usize>,
+    open_changes: Vec<usize>,
+    change_text: HashMap<usize, String>,
+}
+
+fn mark_json(marks: Marks) -> Vec<Value> {
+    let mut out = Vec::new();
+    for (on, name) in [
+        (marks.bold, "bold"),
+        (marks.italic, "italic"),
+        (marks.underline, "underline"),
+        (marks.strike, "strike"),
+    ] {
+        if on {
+            out.push(json!({"type": name}));
+        }
+    }
+    out
+}
+
+impl Builder {
+    fn inlines(&mut self, inlines: &[Inline]) -> Vec<Value> {
+        let mut out = Vec::new();
+        for inline in inlines {
+            match inline {
+                Inline::Text(text, marks) => {
+                    let mut node = json!({"type": "text", "text": text});
+                    let marks = mark_json(*marks);
+                    if !marks.is_empty() {
+                        node["marks"] = Value::Array(marks);
+                    }
+                    out.push(node);
+                    self.pos += text.encode_utf16().count();
+                    self.change_text_push(text);
+                }
+                Inline::Break => {
+                    out.push(json!({"type": "hardBreak"}));
+                    self.pos += 1;
+                    self.change_text_push("\n");
+                }
+                Inline::Anchor(anchor) => {
+                    match anchor {
+                        Anchor::ChangeStart(i) => self.open_changes.push(*i),
+                        Anchor::ChangeEnd(i) => self.open_changes.retain(|c| c != i),
+                        _ => {}
+                    }
+                    self.positions.insert(anchor.clone(), self.pos);
+                }
+            }
+        }
+        out
+    }
+
+    fn change_text_push(&mut self, text: &str) {
+        for i in &self.open_changes {
+            self.change_text.entry(*i).or_default().push_str(text);
+        }
+    }
+
+    /// `{"type": kind, "content": ...}`, counting the node's open and close
+    fn node(
+        &mut self,
+        kind: &str,
+        attrs: Option<Value>,
+        fill: impl FnOnce(&mut Self) -> Vec<Value>,
+    ) -> Value {
+        self.pos += 1;
+        let content = fill(self);
+        self.pos += 1;
+        let mut node = json!({"type": kind});
+        if let Some(attrs) = attrs {
+            node["attrs"] = attrs;
+        }
+        if !content.is_empty() {
+            node["content"] = Value::Array(content);
+        }
+        node
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) -> Vec<Value> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < blocks.len() {
+            let kind = blocks[i].kind;
+            let run = blocks[i..].iter().take_while(|b| b.kind == kind).count();
+            let group = &blocks[i..i + run];
+            match kind {
+                BlockKind::Paragraph => {
+                    for block in group {
+                        out.push(self.node("paragraph", None, |b| b.inlines(&block.inlines)));
+                    }
+                }
+                BlockKind::Heading(level) => {
+                    for block in group {
+                        let attrs = json!({"level": level.clamp(1, 6)});
+                        out.push(self.node("heading", Some(attrs), |b| b.inlines(&block.inlines)));
+                    }
+                }
+                BlockKind::Quote => out.push(self.node("blockquote", None, |b| {
+                    group
+                        .iter()
+                        .map(|block| b.node("paragraph", None, |b| b.inlines(&block.inlines)))
+                        .collect()
+                })),
+                BlockKind::ListItem => out.push(self.node("bulletList", None, |b| {
+                    group
+                        .iter()
+                        .map(|block| {
+                            b.node("listItem", None, |b| {
+                                vec![b.node("paragraph", None, |b| b.inlines(&block.inlines))]
+                            })
+                        })
+                        .collect()
+                })),
+            }
+            i += run;
+        }
+        out
+    }
+}
+
+/// ProseMirror content for a chapter: its scenes as scene blocks when the
+/// source separated them, plain blocks otherwise
+pub fn build_content(chapter: &Chapter) -> BuiltContent {
+    let mut builder = Builder {
+        pos: 0,
+        positions: HashMap::new(),
+        open_changes: Vec::new(),
+        change_text: HashMap::new(),
+    };
+    let mut content = Vec::new();
+    for (n, scene) in chapter.scenes.iter().enumerate() {
+        if chapter.has_separators {
+            let attrs = json!({
+                "sceneId": random_uuid(),
+                "sceneName": format!("Scene {}", n + 1),
+                "tensionLevel": 5,
+            });
+            content.push(builder.node("sceneBlock", Some(attrs), |b| b.blocks(scene)));
+        } else {
+            content.extend(builder.blocks(scene));
+        }
+    }
+    if content.is_empty() {
+        content.push(json!({"type": "paragraph"}));
+    }
+    BuiltContent {
+        doc: json!({"type": "doc", "content": content}),
+        positions: builder.positions,
+        change_text: builder.change_text,
+    }
+}
+
+// ============================================================================
+// Into the project
+// ============================================================================
+
+/// Document metadata keys holding imported annotations not yet applied
+const COMMENTS_KEY: &str = "importedComments";
+const SUGGESTIONS_KEY: &str = "importedSuggestions";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionRange {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// A comment for `comments.add`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedComment {
+    pub document_id: String,
+    pub content: String,
+    /// Missing when the commented text didn't survive the import
+    pub selection_range: Option<SelectionRange>,
+    pub author: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// A tracked change, ready for the editor's `addSuggestion`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedSuggestion {
+    pub document_id: String,
+    pub suggestion: NewSuggestionPayload,
+    pub author: Option<String>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedDocument {
+    pub id: String,
+    pub title: String,
+    pub scenes: usize,
+    pub words: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub project_id: String,
+    /// `docx` or `odt`
+    pub format: String,
+    pub documents: Vec<ImportedDocument>,
+    pub comments: Vec<ImportedComment>,
+    pub suggestions: Vec<ImportedSuggestion>,
+    /// What had no equivalent and was left out
+    pub warnings: Vec<String>,
+}
+
+/// An entity name as matched against others: lowercase, single-spaced
+pub(crate) fn canonical_name(name: &str) -> String {
+    name.to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The `orderIndex` after the project's last top-level document
+pub(crate) fn next_root_order(store: &ProjectStore, project_id: &str) -> Result<i64, ImportError> {
+    let last = store
+        .list(SyncTable::Documents, project_id)?
+        .iter()
+        .filter(|row| row.get("parentId").and_then(Value::as_str).is_none())
+        .filter_map(|row| row.get("orderIndex").and_then(Value::as_f64))
+        .fold(-1.0_f64, f64::max);
+    Ok(last.floor() as i64 + 1)
+}
+
+/// Rows an import adds, held until the whole file has been read
+#[derive(Default)]
+pub(crate) struct NewRows {
+    rows: Vec<(SyncTable, Map<String, Value>)>,
+}
+
+impl NewRows {
+    pub fn insert(&mut self, table: SyncTable, row: Map<String, Value>) {
+        self.rows.push((table, row));
+    }
+
+    fn get_mut(&mut self, table: SyncTable, id: &str) -> Option<&mut Map<String, Value>> {
+        self.rows
+            .iter_mut()
+            .find(|(t, row)| *t == table && row.get("id").and_then(Value::as_str) == Some(id))
+            .map(|(_, row)| row)
+    }
+
+    /// Store the rows and queue each for sync, all or nothing
+    pub fn commit(self, store: &ProjectStore, project_id: &str) -> Result<(), ImportError> {
+        store.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for (table, row) in self.rows {
+                let id = row
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let row = Value::Object(row);
+                put_row(&tx, table, None, &row, None)?;
+                let mut mutation = Mutation::local(project_id, table, MutationType::Upsert, &id);
+                mutation.row = Some(row);
+                outbox::enqueue_in(&tx, &mutation)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+/// A document to add to the project
+pub(crate) struct NewDocument<'a> {
+    pub kind: &'a str,
+    pub title: &'a str,
+    pub parent_id: Option<&'a str>,
+    pub order: i64,
+    /// ProseMirror doc
+    pub content: &'a Value,
+    pub metadata: Value,
+}
+
+/// Add a document with its text and word count derived from the content;
+/// returns its id and word count
+pub(crate) fn insert_document(
+    rows: &mut NewRows,
+    project_id: &str,
+    document: NewDocument,
+) -> (String, usize) {
+    let id = random_uuid();
+    let text = text_content(document.content);
+    let words = word_count(&text);
+    let now = iso_timestamp(now_ms());
+    let mut row = Map::new();
+    for (key, value) in [
+        ("id", json!(id)),
+        ("projectId", json!(project_id)),
+        ("type", json!(document.kind)),
+        ("title", json!(document.title)),
+        ("content", document.content.clone()),
+        ("contentText", json!(text)),
+        ("wordCount", json!(words)),
+        ("orderIndex", json!(document.order)),
+        ("metadata", document.metadata),
+        ("createdAt", json!(now)),
+        ("updatedAt", json!(now)),
+    ] {
+        row.insert(key.to_string(), value);
+    }
+    if let Some(parent_id) = document.parent_id {
+        row.insert("parentId".to_string(), json!(parent_id));
+    }
+    rows.insert(SyncTable::Documents, row);
+    (id, words)
+}
+
+/// Add a parsed file's chapters to the project after its existing
+/// top-level documents, queueing each for sync
+pub fn import_parsed(
+    store: &ProjectStore,
+    project_id: &str,
+    format: &str,
+    source_name: &str,
+    mut parsed: ParsedDocument,
+) -> Result<ImportReport, ImportError> {
+    merge_changes(&mut parsed);
+    // Changes merged into a neighbour no longer have anchors of their own
+    let anchored: HashSet<usize> = parsed
+        .blocks
+        .iter()
+        .flat_map(|block| &block.inlines)
+        .filter_map(|inline| match inline {
+            Inline::Anchor(Anchor::ChangeStart(i)) => Some(*i),
+            _ => None,
+        })
+        .collect();
+    let ParsedDocument {
+        blocks,
+        comments,
+        changes,
+        skipped,
+    } = parsed;
+
+    let first_order = next_root_order(store, project_id)?;
+    let mut report = ImportReport {
+        project_id: project_id.to_string(),
+        format: format.to_string(),
+        documents: Vec::new(),
+        comments: Vec::new(),
+        suggestions: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut rows = NewRows::default();
+    let mut built = Vec::new();
+    for (order, chapter) in (first_order..).zip(chapters(blocks, source_name)) {
+        let content = build_content(&chapter);
+        let (id, words) = insert_document(
+            &mut rows,
+            project_id,
+            NewDocument {
+                kind: "chapter",
+                title: &chapter.title,
+                parent_id: None,
+                order,
+                content: &content.doc,
+                metadata: json!({"importedFrom": source_name}),
+            },
+        );
+        report.documents.push(ImportedDocument {
+            id: id.clone(),
+            title: chapter.title.clone(),
+            scenes: if chapter.has_separators {
+                chapter.scenes.len()
+            } else {
+                0
+            },
+            words,
+        });
+        built.push((id, content));
+    }
+
+    let find = |anchor: &Anchor| {
+        built
+            .iter()
+            .find_map(|(id, content)| Some((id, *content.positions.get(anchor)?)))
+    };
+    for comment in comments {
+        let start = find(&Anchor::CommentStart(comment.id.clone()));
+        let end = find(&Anchor::CommentEnd(comment.id.clone()));
+        let (document_id, selection_range) = match (start, end) {
+            (Some((doc, from)), Some((end_doc, to))) if doc == end_doc && to >= from => {
+                (doc.clone(), Some(SelectionRange { from, to }))
+            }
+            (Some((doc, from)), _) => (doc.clone(), Some(SelectionRange { from, to: from })),
+            _ => match built.first() {
+                Some((doc, _)) => (doc.clone(), None),
+                None => continue,
+            },
+        };
+        report.comments.push(ImportedComment {
+            document_id,
+            content: comment.text,
+            selection_range,
+            author: comment.author,
+            created_at: comment.date,
+        });
+    }
+
+    let mut unplaced = 0;
+    for (i, change) in changes.into_iter().enumerate() {
+        if !anchored.contains(&i) {
+            continue;
+        }
+        let (Some((doc, from)), Some((end_doc, to))) =
+            (find(&Anchor::ChangeStart(i)), find(&Anchor::ChangeEnd(i)))
+        else {
+            // Inside a chapter heading, which becomes the title
+            unplaced += 1;
+            continue;
+        };
+        if doc != end_doc || to < from {
+            unplaced += 1;
+            continue;
+        }
+        let text = built
+            .iter()
+            .find(|(id, _)| id == doc)
+            .and_then(|(_, c)| c.change_text.get(&i).cloned())
+            .unwrap_or_default();
+        let (kind, content, original_content) = match (change.kind, change.replaces) {
+            (ChangeKind::Insert, Some(original)) => (SuggestionType::Replace, text, Some(original)),
+            (ChangeKind::Insert, None) => (SuggestionType::Insert, text, None),
+            (ChangeKind::Delete, _) => (SuggestionType::Delete, String::new(), Some(text)),
+        };
+        report.suggestions.push(ImportedSuggestion {
+            document_id: doc.clone(),
+            suggestion: NewSuggestionPayload {
+                id: random_uuid(),
+                from: from as u32,
+                to: to as u32,
+                content,
+                original_content,
+                kind,
+                model: None,
+            },
+            author: change.author,
+            created_at: change.date,
+        });
+    }
+
+    for (what, count) in skipped {
+        report
+            .warnings
+            .push(format!("{} left out: {}", what, count));
+    }
+    if unplaced > 0 {
+        report.warnings.push(format!(
+            "tracked changes across chapters or in headings left out: {}",
+            unplaced
+        ));
+    }
+
+    // Kept with each chapter until the editor has added them
+    for (id, _) in &built {
+        let comments: Vec<&ImportedComment> = report
+            .comments
+            .iter()
+            .filter(|c| c.document_id == *id)
+            .collect();
+        let suggestions: Vec<&ImportedSuggestion> = report
+            .suggestions
+            .iter()
+            .filter(|s| s.document_id == *id)
+            .collect();
+        let Some(metadata) = rows
+            .get_mut(SyncTable::Documents, id)
+            .and_then(|row| row.get_mut("metadata"))
+            .and_then(Value::as_object_mut)
+        else {
+            continue;
+        };
+        if !comments.is_empty() {
+            metadata.insert(COMMENTS_KEY.into(), json!(comments));
+        }
+        if !suggestions.is_empty() {
+            metadata.insert(SUGGESTIONS_KEY.into(), json!(suggestions));
+        }
+    }
+    rows.commit(store, project_id)?;
+    Ok(report)
+}
+
+/// Comments and tracked changes of an imported document
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedAnnotations {
+    pub comments: Vec<ImportedComment>,
+    pub suggestions: Vec<ImportedSuggestion>,
+}
+
+/// Remove the annotations kept in a document's metadata and return them,
+/// queueing the cleaned-up row for sync. Empty once taken.
+pub fn take_annotations(
+    store: &ProjectStore,
+    document_id: &str,
+) -> Result<ImportedAnnotations, ImportError> {
+    Ok(store.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        let stored: Option<(String, i64)> = tx
+            .query_row(
+                "SELECT data, version FROM documents WHERE id = ?1",
+                params![document_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((data, version)) = stored else {
+            return Ok(ImportedAnnotations::default());
+        };
+        let mut row: Value = serde_json::from_str(&data)?;
+        let Some(metadata) = row.get_mut("metadata").and_then(Value::as_object_mut) else {
+            return Ok(ImportedAnnotations::default());
+        };
+        let (comments, suggestions) = (
+            metadata.remove(COMMENTS_KEY),
+            metadata.remove(SUGGESTIONS_KEY),
+        );
+        if comments.is_none() && suggestions.is_none() {
+            return Ok(ImportedAnnotations::default());
+        }
+        let annotations = ImportedAnnotations {
+            comments: comments
+                .map(serde_json::from_value)
+                .transpose()?
+                .unwrap_or_default(),
+            suggestions: suggestions
+                .map(serde_json::from_value)
+                .transpose()?
+                .unwrap_or_default(),
+        };
+
+        let project_id = row
+            .get("projectId")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        put_row(&tx, SyncTable::Documents, None, &row, None)?;
+        let mut mutation = Mutation::local(
+            &project_id,
+            SyncTable::Documents,
+            MutationType::Upsert,
+            document_id,
+        );
+        mutation.base_version = Some(version);
+        mutation.row = Some(row);
+        outbox::enqueue_in(&tx, &mutation)?;
+        tx.commit()?;
+        Ok(annotations)
+    })?)
+}
+
+/// Import a `.docx` or `.odt` file into the project
+pub fn import_file(
+    store: &ProjectStore,
+    project_id: &str,
+    path: &Path,
+) -> Result<ImportReport, ImportError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Imported")
+        .to_string();
+    let parsed = match extension.as_str() {
+        "docx" => docx::read(path)?,
+        "odt" => odt::read(path)?,
+        other => return Err(ImportError::UnsupportedFormat(other.to_string())),
+    };
+    import_parsed(store, project_id, &extension, &name, parsed)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn import_manuscript(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+) -> Result<ImportReport, ImportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = import_file(&app.state::<ProjectStore>(), &project_id, Path::new(&path))?;
+        app.state::<OutboxWorker>().notify();
+        Ok(report)
+    })
+    .await
+    .map_err(|e| ImportError::Io(std::io::Error::other(e)))?
+}
+
+/// An imported document's comments and tracked changes, for the editor to
+/// add when it first opens the document
+#[tauri::command(rename_all = "camelCase")]
+pub fn take_imported_annotations(
+    store: State<'_, ProjectStore>,
+    worker: State<'_, OutboxWorker>,
+    document_id: String,
+) -> Result<ImportedAnnotations, ImportError> {
+    let annotations = take_annotations(&store, &document_id)?;
+    if annotations != ImportedAnnotations::default() {
+        worker.notify();
+    }
+    Ok(annotations)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The text a ProseMirror range covers
+    pub(crate) fn text_between(doc: &Value, from: usize, to: usize) -> String {
+        fn walk(node: &Value, pos: &mut usize, range: (usize, usize), out: &mut String) {
+            if let Some(text) = node.get("text").and_then(Value::as_str) {
+                for unit in text.encode_utf16() {
+                    if (range.0..range.1).contains(pos) {
+                        out.push_str(&String::from_utf16_lossy(&[unit]));
+                    }
+                    *pos += 1;
+                }
+                return;
+            }
+            let children = node.get("content").and_then(Value::as_array);
+            if children.is_none() && node.get("type").and_then(Value::as_str) == Some("hardBreak") {
+                if (range.0..range.1).contains(pos) {
+                    out.push('\n');
+                }
+                *pos += 1;
+                return;
+            }
+            *pos += 1;
+            for child in children.into_iter().flatten() {
+                walk(child, pos, range, out);
+            }
+            *pos += 1;
+        }
+        let mut out = String::new();
+        let mut pos = 0;
+        for child in doc["content"].as_array().into_iter().flatten() {
+            walk(child, &mut pos, (from, to), &mut out);
+        }
+        out
+    }
+
+    fn block(kind: BlockKind, text: &str) -> Block {
+        let mut block = Block::new(kind);
+        block.push_text(text, Marks::default());
+        block
+    }
+
+    #[test]
+    fn splits_chapters_at_the_shallowest_heading() {
+        let blocks = vec![
+            block(BlockKind::Paragraph, ""),
+            block(BlockKind::Heading(2), "  One \t Two "),
+            block(BlockKind::Paragraph, "***"),
+            block(BlockKind::Paragraph, "Opening"),
+            block(BlockKind::Heading(3), "Aside"),
+            block(BlockKind::Paragraph, " * * * "),
+            block(BlockKind::Paragraph, "#"),
+            block(BlockKind::Paragraph, "Closing"),
+            block(BlockKind::Heading(2), ""),
+            block(BlockKind::Paragraph, "No scenes"),
+        ];
+        let chapters = chapters(blocks, "Draft");
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "One Two");
+        assert!(chapters[0].has_separators);
+        let texts = |scene: &[Block]| scene.iter().map(Block::text).collect::<Vec<_>>();
+        assert_eq!(texts(&chapters[0].scenes[0]), ["Opening", "Aside"]);
+        assert_eq!(texts(&chapters[0].scenes[1]), ["Closing"]);
+        assert_eq!(chapters[1].title, "Untitled");
+        assert!(!chapters[1].has_separators);
+
+        let built = build_content(&chapters[0]);
+        assert_eq!(built.doc["content"][1]["attrs"]["sceneName"], "Scene 2");
+        assert_eq!(built.doc["content"][0]["content"][1]["attrs"]["level"], 3);
+        assert_eq!(text_between(&built.doc, 2, 9), "Opening");
+
+        let untitled = super::chapters(vec![block(BlockKind::Quote, "Only")], "Draft");
+        assert_eq!(untitled[0].title, "Draft");
+    }
+
+    #[test]
+    fn merges_split_and_paired_changes() {
+        let change = |kind, author: &str| TrackedChange {
+            kind,
+            author: Some(author.to_string()),
+            date: None,
+            replaces: None,
+        };
+        let text = |t: &str| Inline::Text(t.to_string(), Marks::default());
+        let anchor = |a| Inline::Anchor(a);
+        let mut parsed = ParsedDocument {
+            blocks: vec![Block {
+                kind: BlockKind::Paragraph,
+                inlines: vec![
+                    anchor(Anchor::ChangeStart(0)),
+                    text("ne"),
+                    anchor(Anchor::ChangeEnd(0)),
+                    anchor(Anchor::ChangeStart(1)),
+                    text("w"),
+                    anchor(Anchor::ChangeEnd(1)),
+                    anchor(Anchor::ChangeStart(2)),
+                    text("old"),
+                    anchor(Anchor::ChangeEnd(2)),
+                    text(" kept"),
+                ],
+            }],
+            changes: vec![
+                change(ChangeKind::Insert, "Ann"),
+                change(ChangeKind::Insert, "Ann"),
+                change(ChangeKind::Delete, "Ann"),
+            ],
+            ..Default::default()
+        };
+        merge_changes(&mut parsed);
+        assert_eq!(parsed.blocks[0].text(), "new kept");
+        assert_eq!(parsed.changes[0].replaces.as_deref(), Some("old"));
+        assert_eq!(
+            parsed.blocks[0].inlines.first(),
+            Some(&anchor(Anchor::ChangeStart(0)))
+        );
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        let report = import_parsed(&store, "p1", "docx", "Draft", parsed).unwrap();
+        assert_eq!(report.suggestions.len(), 1);
+        let suggestion = &report.suggestions[0].suggestion;
+        assert_eq!(suggestion.kind, SuggestionType::Replace);
+        assert_eq!((suggestion.from, suggestion.to), (1, 4));
+        assert_eq!(suggestion.content, "new");
+        assert!(report.warnings.is_empty());
+    }
+}
@@ -0,0 +1,584 @@
+//! OpenDocument text (.odt) reading
+//!
+//! Headings are `text:h` elements or paragraphs whose style chain has a
+//! default outline level; "quot" in a style name (LibreOffice's
+//! "Quotations") makes a block quote. Marks come from span styles.
+//!
+//! Tracked changes are listed up front in `text:tracked-changes`: an
+//! insertion is bracketed in the text by `text:change-start` and
+//! `text:change-end`, while a deletion is a `text:change` point with the
+//! removed paragraphs kept in its region, which go back in at that point.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use super::{
+    attr, read_xml, zip_part, Anchor, Block, BlockKind, ChangeKind, Comment, ImportError, Inline,
+    Marks, ParsedDocument, TrackedChange, XmlEvent,
+};
+
+/// Subtrees with no equivalent, and what to call them in warnings
+const SKIPPED: [(&str, Option<&str>); 6] = [
+    ("draw:frame", Some("images")),
+    ("text:note", Some("footnotes")),
+    ("text:table-of-content", Some("tables of contents")),
+    ("text:sequence-decls", None),
+    ("office:forms", None),
+    ("text:format-change", None),
+];
+
+#[derive(Debug, Default)]
+struct Style {
+    name: String,
+    parent: Option<String>,
+    outline: Option<u8>,
+    marks: Marks,
+}
+
+#[derive(Default)]
+struct Styles(HashMap<String, Style>);
+
+impl Styles {
+    /// Add the styles in a `styles.xml` or `content.xml` part
+    fn parse(&mut self, xml: &str) -> Result<(), ImportError> {
+        let mut current: Option<(String, Style)> = None;
+        read_xml(xml, |event| {
+            match event {
+                XmlEvent::Start("style:style", attrs) => {
+                    let name = attr(attrs, "style:name").unwrap_or_default();
+                    let display = attr(attrs, "style:display-name").unwrap_or(name);
+                    current = Some((
+                        name.to_string(),
+                        Style {
+                            name: display.to_lowercase(),
+                            parent: attr(attrs, "style:parent-style-name").map(str::to_string),
+                            outline: attr(attrs, "style:default-outline-level")
+                                .and_then(|l| l.parse().ok()),
+                            marks: Marks::default(),
+                        },
+                    ));
+                }
+                XmlEvent::Start("style:text-properties", attrs) => {
+                    if let Some((_, style)) = &mut current {
+                        let marks = &mut style.marks;
+                        if let Some(weight) = attr(attrs, "fo:font-weight") {
+                            marks.bold =
+                                weight == "bold" || weight.parse().is_ok_and(|w: u16| w >= 600);
+                        }
+                        if let Some(font_style) = attr(attrs, "fo:font-style") {
+                            marks.italic = matches!(font_style, "italic" | "oblique");
+                        }
+                        if let Some(underline) = attr(attrs, "style:text-underline-style") {
+                            marks.underline = underline != "none";
+                        }
+                        if let Some(strike) = attr(attrs, "style:text-line-through-style") {
+                            marks.strike = strike != "none";
+                        }
+                    }
+                }
+                XmlEvent::End("style:style") => {
+                    if let Some((name, style)) = current.take() {
+                        self.0.insert(name, style);
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        })
+    }
+
+    /// The style and its parents, nearest first
+    fn chain<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Style> + 'a {
+        let mut next = self.0.get(name);
+        // Bounded, in case of a parent cycle
+        std::iter::from_fn(move || {
+            let style = next?;
+            next = style.parent.as_deref().and_then(|name| self.0.get(name));
+            Some(style)
+        })
+        .take(16)
+    }
+
+    fn block_kind(&self, name: &str) -> Option<BlockKind> {
+        self.chain(name).find_map(|style| match style.outline {
+            Some(level) if level > 0 => Some(BlockKind::Heading(level)),
+            _ if style.name.contains("quot") => Some(BlockKind::Quote),
+            _ => None,
+        })
+    }
+
+    fn marks(&self, name: &str) -> Marks {
+        self.chain(name)
+            .next()
+            .map(|style| style.marks)
+            .unwrap_or_default()
+    }
+}
+
+/// A `text:changed-region` being read
+struct Region {
+    id: String,
+    kind: Option<ChangeKind>,
+    author: Option<String>,
+    date: Option<String>,
+    /// Deleted paragraphs
+    blocks: Vec<Block>,
+}
+
+/// Walk state for `content.xml`
+struct Body<'a> {
+    styles: &'a Styles,
+    out: ParsedDocument,
+    skip_depth: usize,
+    in_text: bool,
+    paragraphs: Vec<Block>,
+    list_items: usize,
+    marks: Vec<Marks>,
+    region: Option<Region>,
+    /// Deletions by region id, and insertions' change indexes
+    deletions: HashMap<String, Region>,
+    insertions: HashMap<String, usize>,
+    annotation: Option<Comment>,
+    /// `dc:creator` or `dc:date` being read, for an annotation or change
+    field: Option<&'static str>,
+    unnamed_comments: usize,
+}
+
+impl Body<'_> {
+    fn marks(&self) -> Marks {
+        self.marks.last().copied().unwrap_or_default()
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some(field) = self.field {
+            let (author, date) = match (&mut self.annotation, &mut self.region) {
+                (Some(comment), _) => (&mut comment.author, &mut comment.date),
+                (None, Some(region)) => (&mut region.author, &mut region.date),
+                (None, None) => return,
+            };
+            let value = if field == "dc:creator" { author } else { date };
+            value.get_or_insert_default().push_str(text);
+            return;
+        }
+        if let Some(comment) = &mut self.annotation {
+            comment.text.push_str(text);
+            return;
+        }
+        let marks = self.marks();
+        if let Some(block) = self.paragraphs.last_mut() {
+            // Runs of whitespace in the markup are a single space, and
+            // there's none at the start of a paragraph
+            let existing = block.text();
+            let mut space = existing.is_empty() || existing.ends_with(' ');
+            let mut collapsed = String::new();
+            for c in text.chars() {
+                if !c.is_whitespace() {
+                    collapsed.push(c);
+                    space = false;
+                } else if !space {
+                    collapsed.push(' ');
+                    space = true;
+                }
+            }
+            block.push_text(&collapsed, marks);
+        }
+    }
+
+    fn inline(&mut self, inline: Inline) {
+        if self.annotation.is_none() {
+            if let Some(block) = self.paragraphs.last_mut() {
+                block.inlines.push(inline);
+            }
+        }
+    }
+
+    fn change(&mut self, kind: ChangeKind, author: Option<String>, date: Option<String>) -> usize {
+        self.out.changes.push(TrackedChange {
+            kind,
+            author,
+            date,
+            replaces: None,
+        });
+        self.out.changes.len() - 1
+    }
+
+    fn start(&mut self, name: &str, attrs: &[(String, String)]) {
+        if self.skip_depth > 0 {
+            self.skip_depth += 1;
+            return;
+        }
+        if let Some((_, what)) = SKIPPED.iter().find(|(element, _)| *element == name) {
+            if let Some(what) = what {
+                self.out.skip(what);
+            }
+            self.skip_depth = 1;
+            return;
+        }
+        if self.annotation.is_some() {
+            match name {
+                "dc:creator" => self.field = Some("dc:creator"),
+                "dc:date" => self.field = Some("dc:date"),
+                "text:p" | "text:h" => {
+                    if let Some(comment) = &mut self.annotation {
+                        if !comment.text.is_empty() {
+                            comment.text.push('\n');
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        match name {
+            "office:text" => self.in_text = true,
+            "text:changed-region" => {
+                self.region = Some(Region {
+                    id: attr(attrs, "text:id").unwrap_or_default().to_string(),
+                    kind: None,
+                    author: None,
+                    date: None,
+                    blocks: Vec::new(),
+                });
+            }
+            "text:insertion" | "text:deletion" => {
+                if let Some(region) = &mut self.region {
+                    region.kind = Some(if name == "text:insertion" {
+                        ChangeKind::Insert
+                    } else {
+                        ChangeKind::Delete
+                    });
+                }
+            }
+            "dc:creator" if self.region.is_some() => self.field = Some("dc:creator"),
+            "dc:date" if self.region.is_some() => self.field = Some("dc:date"),
+            "text:list-item" | "text:list-header" => self.list_items += 1,
+            "text:p" | "text:h" => {
+                let style = attr(attrs, "text:style-name").unwrap_or_default();
+                let kind = if name == "text:h" {
+                    let level = attr(attrs, "text:outline-level")
+                        .and_then(|l| l.parse().ok())
+                        .unwrap_or(1);
+                    BlockKind::Heading(level)
+                } else {
+                    match self.styles.block_kind(style) {
+                        Some(kind) => kind,
+                        None if self.list_items > 0 => BlockKind::ListItem,
+                        None => BlockKind::Paragraph,
+                    }
+                };
+                let kind = match kind {
+                    BlockKind::Paragraph | BlockKind::Quote if self.list_items > 0 => {
+                        BlockKind::ListItem
+                    }
+                    kind => kind,
+                };
+                self.paragraphs.push(Block::new(kind));
+                self.marks.push(Marks::default());
+            }
+            "text:span" => {
+                let mut marks = self.marks();
+                let span = self
+                    .styles
+                    .marks(attr(attrs, "text:style-name").unwrap_or_default());
+                marks.bold |= span.bold;
+                marks.italic |= span.italic;
+                marks.underline |= span.underline;
+                marks.strike |= span.strike;
+                self.marks.push(marks);
+            }
+            "text:s" => {
+                let count = attr(attrs, "text:c")
+                    .and_then(|c| c.parse().ok())
+                    .unwrap_or(1);
+                let marks = self.marks();
+                if let Some(block) = self.paragraphs.last_mut() {
+                    block.push_text(&" ".repeat(count), marks);
+                }
+            }
+            "text:tab" => {
+                let marks = self.marks();
+                if let Some(block) = self.paragraphs.last_mut() {
+                    block.push_text("\t", marks);
+                }
+            }
+            "text:line-break" => self.inline(Inline::Break),
+            "office:annotation" => {
+                let id = match attr(attrs, "office:name") {
+                    Some(name) => name.to_string(),
+                    None => {
+                        self.unnamed_comments += 1;
+                        format!("#{}", self.unnamed_comments)
+                    }
+                };
+                self.inline(Inline::Anchor(Anchor::CommentStart(id.clone())));
+                self.annotation = Some(Comment {
+                    id,
+                    author: None,
+                    date: None,
+                    text: String::new(),
+                });
+            }
+            "office:annotation-end" => {
+                let id = attr(attrs, "office:name").unwrap_or_default().to_string();
+                self.inline(Inline::Anchor(Anchor::CommentEnd(id)));
+            }
+            "text:change-start" => {
+                let id = attr(attrs, "text:change-id").unwrap_or_default();
+                if let Some(&index) = self.insertions.get(id) {
+                    self.inline(Inline::Anchor(Anchor::ChangeStart(index)));
+                }
+            }
+            "text:change-end" => {
+                let id = attr(attrs, "text:change-id").unwrap_or_default();
+                if let Some(&index) = self.insertions.get(id) {
+                    self.inline(Inline::Anchor(Anchor::ChangeEnd(index)));
+                }
+            }
+            "text:change" => {
+                let id = attr(attrs, "text:change-id").unwrap_or_default();
+                if let Some(region) = self.deletions.remove(id) {
+                    let index = self.change(ChangeKind::Delete, region.author, region.date);
+                    self.inline(Inline::Anchor(Anchor::ChangeStart(index)));
+                    for (n, block) in region.blocks.into_iter().enumerate() {
+                        if n > 0 {
+                            self.inline(Inline::Break);
+                        }
+                        for inline in block.inlines {
+                            self.inline(inline);
+                        }
+                    }
+                    self.inline(Inline::Anchor(Anchor::ChangeEnd(index)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if self.skip_depth > 0 {
+            self.skip_depth -= 1;
+            return;
+        }
+        match name {
+            "dc:creator" | "dc:date" => self.field = None,
+            "office:annotation" => {
+                if let Some(comment) = self.annotation.take() {
+                    if comment.id.starts_with('#') {
+                        self.inline(Inline::Anchor(Anchor::CommentEnd(comment.id.clone())));
+                    }
+                    self.out.comments.push(comment);
+                }
+            }
+            _ if self.annotation.is_some() => {}
+            "office:text" => self.in_text = false,
+            "text:list-item" | "text:list-header" => self.list_items -= 1,
+            "text:p" | "text:h" => {
+                self.marks.pop();
+                if let Some(block) = self.paragraphs.pop() {
+                    match (&mut self.region, self.paragraphs.last_mut()) {
+                        (Some(region), _) => region.blocks.push(block),
+                        // A paragraph inside another only happens in
+                        // things like captions; keep its text inline
+                        (None, Some(outer)) => outer.inlines.extend(block.inlines),
+                        (None, None) => self.out.blocks.push(block),
+                    }
+                }
+            }
+            "text:span" => {
+                self.marks.pop();
+            }
+            "text:changed-region" => {
+                if let Some(region) = self.region.take() {
+                    match region.kind {
+                        Some(ChangeKind::Insert) => {
+                            let index = self.change(ChangeKind::Insert, region.author, region.date);
+                            self.insertions.insert(region.id, index);
+                        }
+                        Some(ChangeKind::Delete) => {
+                            self.deletions.insert(region.id.clone(), region);
+                        }
+                        None => self.out.skip("formatting changes"),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn read(path: &Path) -> Result<ParsedDocument, ImportError> {
+    let mut zip =
+        zip::ZipArchive::new(File::open(path)?).map_err(|_| ImportError::NotADocument("ODT"))?;
+    let mimetype = zip_part(&mut zip, "mimetype")?;
+    if mimetype.is_some_and(|m| m.trim() != "application/vnd.oasis.opendocument.text") {
+        return Err(ImportError::NotADocument("ODT"));
+    }
+    let content = zip_part(&mut zip, "content.xml")?.ok_or(ImportError::NotADocument("ODT"))?;
+    let mut styles = Styles::default();
+    if let Some(xml) = zip_part(&mut zip, "styles.xml")? {
+        styles.parse(&xml)?;
+    }
+    styles.parse(&content)?;
+
+    let mut body = Body {
+        styles: &styles,
+        out: ParsedDocument::default(),
+        skip_depth: 0,
+        in_text: false,
+        paragraphs: Vec::new(),
+        list_items: 0,
+        marks: Vec::new(),
+        region: None,
+        deletions: HashMap::new(),
+        insertions: HashMap::new(),
+        annotation: None,
+        field: None,
+        unnamed_comments: 0,
+    };
+    read_xml(&content, |event| {
+        match event {
+            XmlEvent::Start(name, attrs) => body.start(name, attrs),
+            XmlEvent::End(name) => body.end(name),
+            XmlEvent::Text(text) if body.in_text && body.skip_depth == 0 => body.text(text),
+            XmlEvent::Text(_) => {}
+        }
+        Ok(())
+    })?;
+    Ok(body.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bridge::protocol::SuggestionType;
+    use crate::db::{ProjectStore, SyncTable};
+    use crate::export::write_zip;
+    use crate::import::import_file;
+    use crate::import::tests::text_between;
+
+    const NS: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+                      xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+                      xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+                      xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+                      xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+                      xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\"";
+
+    #[test]
+    fn imports_chapters_scenes_comments_and_changes() {
+        let styles = format!(
+            "<office:document-styles {NS}><office:styles>\
+             <style:style style:name=\"Heading_20_1\" style:display-name=\"Heading 1\" \
+              style:family=\"paragraph\" style:default-outline-level=\"1\"/>\
+             <style:style style:name=\"Quotations\" style:family=\"paragraph\"/>\
+             </office:styles></office:document-styles>"
+        );
+        let content = format!(
+            "<office:document-content {NS}><office:automatic-styles>\
+             <style:style style:name=\"P1\" style:family=\"paragraph\" style:parent-style-name=\"Heading_20_1\"/>\
+             <style:style style:name=\"T1\" style:family=\"text\">\
+             <style:text-properties fo:font-style=\"italic\" fo:font-weight=\"700\"/></style:style>\
+             </office:automatic-styles><office:body><office:text>\
+             <text:sequence-decls><text:sequence-decl text:name=\"Figure\"/></text:sequence-decls>\
+             <text:tracked-changes>\
+             <text:changed-region text:id=\"ct1\"><text:insertion><office:change-info>\
+             <dc:creator>Ann</dc:creator><dc:date>2026-02-01T10:00:00</dc:date>\
+             </office:change-info></text:insertion></text:changed-region>\
+             <text:changed-region text:id=\"ct2\"><text:deletion><office:change-info>\
+             <dc:creator>Bo</dc:creator></office:change-info><text:p>gone</text:p>\
+             </text:deletion></text:changed-region>\
+             </text:tracked-changes>\
+             <text:h text:outline-level=\"1\">Arrival</text:h>\
+             <text:p>The <office:annotation office:name=\"c1\"><dc:creator>Ed</dc:creator>\
+             <dc:date>2026-01-01T00:00:00</dc:date><text:p>Which?</text:p></office:annotation>\
+             <text:span text:style-name=\"T1\">train</text:span><office:annotation-end office:name=\"c1\"/> \
+             came<text:s text:c=\"2\"/>in.</text:p>\
+             <text:p>⁂</text:p>\
+             <text:p>She <text:change-start text:change-id=\"ct1\"/>finally<text:change-end text:change-id=\"ct1\"/> \
+             left<text:change text:change-id=\"ct2\"/>.<draw:frame><draw:image/></draw:frame></text:p>\
+             <text:p text:style-name=\"P1\">Departure</text:p>\
+             <text:p text:style-name=\"Quotations\">Said<text:line-break/>so</text:p>\
+             <text:list><text:list-item><text:p>First</text:p></text:list-item></text:list>\
+             </office:text></office:body></office:document-content>"
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Draft.odt");
+        write_zip(
+            &path,
+            &[
+                (
+                    "mimetype".to_string(),
+                    b"application/vnd.oasis.opendocument.text".to_vec(),
+                ),
+                ("styles.xml".to_string(), styles.into_bytes()),
+                ("content.xml".to_string(), content.into_bytes()),
+            ],
+        )
+        .unwrap();
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        let report = import_file(&store, "p1", &path).unwrap();
+        assert_eq!(report.format, "odt");
+        let titles: Vec<&str> = report.documents.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(titles, ["Arrival", "Departure"]);
+        assert_eq!(report.documents[0].scenes, 2);
+        assert_eq!(report.warnings, ["images left out: 1"]);
+
+        let row = |id: &str| store.get(SyncTable::Documents, id).unwrap().unwrap();
+        let arrival = row(&report.documents[0].id);
+        let content = &arrival["content"];
+        let first = &content["content"][0]["content"][0]["content"];
+        assert_eq!(first[0]["text"], "The ");
+        assert_eq!(first[1]["text"], "train");
+        assert_eq!(first[1]["marks"].as_array().unwrap().len(), 2);
+        assert_eq!(first[2]["text"], " came  in.");
+        assert_eq!(arrival["orderIndex"], 0);
+
+        let comment = &report.comments[0];
+        assert_eq!(comment.content, "Which?");
+        assert_eq!(comment.author.as_deref(), Some("Ed"));
+        assert_eq!(comment.created_at.as_deref(), Some("2026-01-01T00:00:00"));
+        let range = comment.selection_range.as_ref().unwrap();
+        assert_eq!(text_between(content, range.from, range.to), "train");
+
+        let suggestions: Vec<_> = report
+            .suggestions
+            .iter()
+            .map(|s| {
+                let payload = &s.suggestion;
+                (
+                    payload.kind,
+                    payload.original_content.as_deref(),
+                    text_between(content, payload.from as usize, payload.to as usize),
+                    s.author.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            suggestions,
+            [
+                (
+                    SuggestionType::Insert,
+                    None,
+                    "finally".to_string(),
+                    Some("Ann")
+                ),
+                (
+                    SuggestionType::Delete,
+                    Some("gone"),
+                    "gone".to_string(),
+                    Some("Bo")
+                ),
+            ]
+        );
+        assert_eq!(
+            arrival["contentText"].as_str().unwrap().lines().last(),
+            Some("She finally leftgone.")
+        );
+
+        let departure = row(&report.documents[1].id);
+        let nodes = departure["content"]["content"].as_array().unwrap();
+        assert_eq!(nodes[0]["type"], "blockquote");
+        assert_eq!(nodes[0]["content"][0]["content"][1]["type"], "hardBreak");
+        assert_eq!(nodes[1]["type"], "bulletList");
+    }
+}
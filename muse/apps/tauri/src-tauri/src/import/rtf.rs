@@ -0,0 +1,315 @@
+//! RTF reading
+//!
+//! Enough of RTF for text written in an editor: paragraphs, line breaks,
+//! bold, italic, underline and strikethrough, Unicode escapes and code page
+//! 1252 bytes. Headers, tables of fonts and colours and other destinations
+//! are skipped; pictures, footnotes and annotations are counted as left out.
+
+use super::{Block, BlockKind, Inline, Marks, ParsedDocument};
+
+/// Destinations whose text isn't part of the body, and what to call them
+/// in warnings
+const DESTINATIONS: [(&str, Option<&str>); 28] = [
+    ("fonttbl", None),
+    ("colortbl", None),
+    ("stylesheet", None),
+    ("info", None),
+    ("listtable", None),
+    ("listoverridetable", None),
+    ("revtbl", None),
+    ("rsidtbl", None),
+    ("generator", None),
+    ("xmlnstbl", None),
+    ("themedata", None),
+    ("colorschememapping", None),
+    ("latentstyles", None),
+    ("datastore", None),
+    ("filetbl", None),
+    ("expandedcolortbl", None),
+    ("header", None),
+    ("headerl", None),
+    ("headerr", None),
+    ("headerf", None),
+    ("footer", None),
+    ("footerl", None),
+    ("footerr", None),
+    ("footerf", None),
+    ("pict", Some("images")),
+    ("NeXTGraphic", Some("images")),
+    ("object", Some("embedded objects")),
+    ("footnote", Some("footnotes")),
+];
+
+/// Code page 1252 for 0x80..=0x9F; the rest of the high half is Latin-1
+const CP1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+fn cp1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9f => CP1252[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Group {
+    marks: Marks,
+    skip: bool,
+    /// Fallback characters after a `\u` escape
+    uc: usize,
+}
+
+struct Reader {
+    groups: Vec<Group>,
+    out: ParsedDocument,
+    block: Block,
+    /// Fallback characters still to drop after a `\u` escape
+    fallback: usize,
+    high_surrogate: Option<u16>,
+}
+
+impl Reader {
+    fn group(&mut self) -> &mut Group {
+        self.groups
+            .last_mut()
+            .expect("the document group is never popped")
+    }
+
+    fn text(&mut self, c: char) {
+        if self.fallback > 0 {
+            self.fallback -= 1;
+            return;
+        }
+        let group = *self.group();
+        if !group.skip {
+            self.block
+                .push_text(c.encode_utf8(&mut [0; 4]), group.marks);
+        }
+    }
+
+    fn paragraph(&mut self) {
+        if !self.group().skip {
+            let block = std::mem::replace(&mut self.block, Block::new(BlockKind::Paragraph));
+            self.out.blocks.push(block);
+        }
+    }
+
+    fn unicode(&mut self, code: i32) {
+        // Negative values are the upper half of the 16-bit range
+        let unit = code.rem_euclid(0x10000) as u16;
+        let c = match (self.high_surrogate.take(), unit) {
+            (_, 0xd800..=0xdbff) => {
+                self.high_surrogate = Some(unit);
+                None
+            }
+            (Some(high), 0xdc00..=0xdfff) => {
+                char::decode_utf16([high, unit]).next().and_then(Result::ok)
+            }
+            (_, _) => char::from_u32(u32::from(unit)),
+        };
+        self.fallback = 0;
+        if let Some(c) = c {
+            self.text(c);
+        }
+        self.fallback = self.group().uc;
+    }
+
+    fn word(&mut self, word: &str, param: Option<i32>, group_start: bool) {
+        if group_start {
+            if let Some((_, what)) = DESTINATIONS.iter().find(|(name, _)| *name == word) {
+                if let Some(what) = what.filter(|_| !self.group().skip) {
+                    self.out.skip(what);
+                }
+                self.group().skip = true;
+                return;
+            }
+        }
+        let on = param != Some(0);
+        match word {
+            "par" | "sect" => self.paragraph(),
+            "line" if !self.group().skip => self.block.inlines.push(Inline::Break),
+            "tab" => self.text('\t'),
+            "emdash" => self.text('—'),
+            "endash" => self.text('–'),
+            "lquote" => self.text('‘'),
+            "rquote" => self.text('’'),
+            "ldblquote" => self.text('“'),
+            "rdblquote" => self.text('”'),
+            "bullet" => self.text('•'),
+            "emspace" | "enspace" | "qmspace" => self.text(' '),
+            "u" => self.unicode(param.unwrap_or_default()),
+            "uc" => self.group().uc = param.unwrap_or(1).max(0) as usize,
+            "plain" => self.group().marks = Marks::default(),
+            "b" => self.group().marks.bold = on,
+            "i" => self.group().marks.italic = on,
+            "strike" | "striked" => self.group().marks.strike = on,
+            "ulnone" => self.group().marks.underline = false,
+            _ if word.starts_with("ul") && !word.starts_with("ulc") => {
+                self.group().marks.underline = on
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Paragraphs of an RTF file; never fails, since anything unrecognised is
+/// skipped
+pub fn read(rtf: &[u8]) -> ParsedDocument {
+    let mut reader = Reader {
+        groups: vec![Group {
+            marks: Marks::default(),
+            skip: false,
+            uc: 1,
+        }],
+        out: ParsedDocument::default(),
+        block: Block::new(BlockKind::Paragraph),
+        fallback: 0,
+        high_surrogate: None,
+    };
+    let mut group_start = false;
+    let mut i = 0;
+    while i < rtf.len() {
+        let byte = rtf[i];
+        i += 1;
+        match byte {
+            b'{' => {
+                let group = *reader.group();
+                reader.groups.push(group);
+                group_start = true;
+                continue;
+            }
+            b'}' => {
+                if reader.groups.len() > 1 {
+                    reader.groups.pop();
+                }
+            }
+            b'\r' | b'\n' => continue,
+            b'\\' => match rtf.get(i).copied() {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let start = i;
+                    while rtf.get(i).is_some_and(u8::is_ascii_alphabetic) {
+                        i += 1;
+                    }
+                    let word = String::from_utf8_lossy(&rtf[start..i]).into_owned();
+                    let digits = i;
+                    if rtf.get(i) == Some(&b'-') {
+                        i += 1;
+                    }
+                    while rtf.get(i).is_some_and(u8::is_ascii_digit) {
+                        i += 1;
+                    }
+                    let param = std::str::from_utf8(&rtf[digits..i])
+                        .ok()
+                        .and_then(|p| p.parse().ok());
+                    // One space ends a control word and isn't text
+                    if rtf.get(i) == Some(&b' ') {
+                        i += 1;
+                    }
+                    reader.word(&word, param, group_start);
+                }
+                Some(b'\'') => {
+                    let hex = rtf.get(i + 1..i + 3).unwrap_or_default();
+                    i += 3;
+                    if let Some(byte) = std::str::from_utf8(hex)
+                        .ok()
+                        .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    {
+                        reader.text(cp1252(byte));
+                    }
+                }
+                Some(b'*') => {
+                    i += 1;
+                    // Optional destinations this reader doesn't know
+                    reader.group().skip = true;
+                }
+                Some(b'\r' | b'\n') => {
+                    i += 1;
+                    reader.paragraph();
+                }
+                Some(b'~') => {
+                    i += 1;
+                    reader.text('\u{a0}');
+                }
+                Some(b'_') => {
+                    i += 1;
+                    reader.text('\u{2011}');
+                }
+                Some(b'-') => i += 1,
+                Some(c) => {
+                    i += 1;
+                    reader.text(char::from(c));
+                }
+                None => {}
+            },
+            _ => reader.text(cp1252(byte)),
+        }
+        group_start = false;
+    }
+    if !reader.block.inlines.is_empty() {
+        reader.out.blocks.push(reader.block);
+    }
+    while reader
+        .out
+        .blocks
+        .last()
+        .is_some_and(|block| block.inlines.is_empty())
+    {
+        reader.out.blocks.pop();
+    }
+    reader.out
+}
+
+/// The text of an RTF file, a line per paragraph
+pub fn plain_text(rtf: &[u8]) -> String {
+    read(rtf)
+        .blocks
+        .iter()
+        .map(Block::text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_paragraphs_marks_and_escapes() {
+        let rtf =
+            br#"{\rtf1\ansi\ansicpg1252\uc1{\fonttbl{\f0 Palatino;}}{\colortbl;\red0\green0\blue0;}
+{\*\expandedcolortbl;;}{\info{\title Ignored}}\pard\f0 It was a \b dark\b0  and {\i stormy} night
+ \'97 caf\'e9 \ldblquote quoted\rdblquote  \u-10179 ?\u-8704 ?.\par
+{\ul under}\ulnone  {\strike out}\line next\tab col{\*\fldinst HYPERLINK "x"}{\fldrslt link}\par
+{\pict\pngblip 89504e47}{\footnote note}\par\par}"#;
+        let parsed = read(rtf);
+        let texts: Vec<String> = parsed.blocks.iter().map(Block::text).collect();
+        assert_eq!(
+            texts,
+            [
+                "It was a dark and stormy night \u{2014} caf\u{e9} \u{201c}quoted\u{201d} \u{1f600}.",
+                "under out\nnext\tcollink",
+            ]
+        );
+        let bold = Marks {
+            bold: true,
+            ..Marks::default()
+        };
+        assert_eq!(
+            parsed.blocks[0].inlines[1],
+            Inline::Text("dark".into(), bold)
+        );
+        assert!(matches!(
+            &parsed.blocks[1].inlines[0],
+            Inline::Text(text, marks) if text == "under" && marks.underline
+        ));
+        assert!(matches!(
+            &parsed.blocks[1].inlines[2],
+            Inline::Text(text, marks) if text == "out" && marks.strike
+        ));
+        assert_eq!(parsed.skipped.get("images"), Some(&1));
+        assert_eq!(parsed.skipped.get("footnotes"), Some(&1));
+        assert!(plain_text(rtf).ends_with(".\nunder out\nnext\tcollink"));
+    }
+}
@@ -0,0 +1,797 @@
+//! Scrivener project (.scriv) importing
+//!
+//! A `.scriv` package is a `.scrivx` XML binder plus a file per item:
+//! `Files/Data/<UUID>/content.rtf` in Scrivener 3, `Files/Docs/<ID>.rtf` in
+//! Scrivener 2, each with optional `notes.rtf` and `synopsis.txt` beside it.
+//!
+//! The manuscript (draft) folder becomes chapters and scenes: folders and
+//! texts with children are chapters, other texts scenes. Texts filed under
+//! a folder named like "Characters" or "Places", anywhere outside the
+//! manuscript, become entities of that type, with `Field: value` lines of
+//! their sheet as properties. Other binder texts and folders become notes.
+//! Labels, status and synopses are kept as document metadata or entity
+//! properties. What can't be mapped (the trash, images, PDFs, web archives,
+//! pictures inside text, duplicate entities) is logged per binder item.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Manager};
+
+use super::{
+    attr, build_content, canonical_name, insert_document, next_root_order, read_xml, rtf, Chapter,
+    ImportError, ImportedDocument, NewDocument, NewRows, XmlEvent,
+};
+use crate::db::{ProjectStore, SyncTable};
+use crate::sync::outbox::{iso_timestamp, now_ms, OutboxWorker};
+use crate::sync::random_uuid;
+
+/// Binder folder names whose texts are entity sheets, and the entity type
+const ENTITY_FOLDERS: [(&str, &str); 14] = [
+    ("characters", "character"),
+    ("character", "character"),
+    ("character sketches", "character"),
+    ("cast", "character"),
+    ("people", "character"),
+    ("places", "location"),
+    ("place sketches", "location"),
+    ("locations", "location"),
+    ("settings", "location"),
+    ("setting", "location"),
+    ("items", "item"),
+    ("objects", "item"),
+    ("factions", "faction"),
+    ("organizations", "faction"),
+];
+
+/// Longest `Field:` taken as a property name on an entity sheet
+const MAX_FIELD_WORDS: usize = 5;
+
+#[derive(Debug, Default)]
+struct BinderItem {
+    uuid: String,
+    /// Scrivener 2's numeric id, which names its files
+    id: Option<String>,
+    kind: String,
+    title: String,
+    label: Option<String>,
+    status: Option<String>,
+    include_in_compile: Option<bool>,
+    synopsis: Option<String>,
+    children: Vec<BinderItem>,
+}
+
+#[derive(Debug, Default)]
+struct Binder {
+    items: Vec<BinderItem>,
+    labels: HashMap<String, String>,
+    statuses: HashMap<String, String>,
+}
+
+fn parse_binder(xml: &str) -> Result<Binder, ImportError> {
+    let mut binder = Binder::default();
+    let mut names: Vec<String> = Vec::new();
+    let mut open: Vec<BinderItem> = Vec::new();
+    let mut text = String::new();
+    let mut setting_id = String::new();
+    read_xml(xml, |event| {
+        match event {
+            XmlEvent::Start(name, attrs) => {
+                text.clear();
+                match name {
+                    "BinderItem" => open.push(BinderItem {
+                        uuid: attr(attrs, "UUID")
+                            .or(attr(attrs, "ID"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        id: attr(attrs, "ID").map(str::to_string),
+                        kind: attr(attrs, "Type").unwrap_or_default().to_string(),
+                        ..BinderItem::default()
+                    }),
+                    "Label" | "Status" => {
+                        setting_id = attr(attrs, "ID").unwrap_or_default().to_string()
+                    }
+                    _ => {}
+                }
+                names.push(name.to_string());
+            }
+            XmlEvent::Text(t) => text.push_str(t),
+            XmlEvent::End(name) => {
+                names.pop();
+                let value = text.trim().to_string();
+                let parent = names.last().map(String::as_str);
+                let item = open.last_mut();
+                match (name, parent, item) {
+                    ("Title", Some("BinderItem"), Some(item)) => item.title = value,
+                    ("Synopsis", Some("BinderItem"), Some(item)) => item.synopsis = Some(value),
+                    ("LabelID", Some("MetaData"), Some(item)) => item.label = Some(value),
+                    ("StatusID", Some("MetaData"), Some(item)) => item.status = Some(value),
+                    ("IncludeInCompile", Some("MetaData"), Some(item)) => {
+                        item.include_in_compile = Some(value.eq_ignore_ascii_case("yes"))
+                    }
+                    ("Label", Some("Labels"), _) => {
+                        binder.labels.insert(setting_id.clone(), value);
+                    }
+                    ("Status", Some("StatusItems"), _) => {
+                        binder.statuses.insert(setting_id.clone(), value);
+                    }
+                    ("BinderItem", _, _) => {
+                        let item = open.pop().expect("a BinderItem is open");
+                        match open.last_mut() {
+                            Some(parent) => parent.children.push(item),
+                            None => binder.items.push(item),
+                        }
+                    }
+                    _ => {}
+                }
+                text.clear();
+            }
+        }
+        Ok(())
+    })?;
+    Ok(binder)
+}
+
+/// Why a binder item, or part of one, wasn't imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmappedReason {
+    /// In Scrivener's trash
+    Trashed,
+    /// An image, PDF, web archive or other non-text item
+    UnsupportedType,
+    /// Imported, but without pictures, footnotes and the like
+    DroppedContent,
+    /// A sheet for an entity the project already has
+    DuplicateEntity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmappedItem {
+    /// Titles from the top of the binder, joined with ` / `
+    pub binder_path: String,
+    pub uuid: String,
+    /// Scrivener's item type, such as `Image` or `PDF`
+    pub item_type: String,
+    pub reason: UnmappedReason,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedEntity {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrivenerReport {
+    pub project_id: String,
+    pub documents: Vec<ImportedDocument>,
+    pub entities: Vec<ImportedEntity>,
+    pub unmapped: Vec<UnmappedItem>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Area {
+    Manuscript,
+    Notes,
+    Entities(&'static str),
+}
+
+fn entity_type(title: &str) -> Option<&'static str> {
+    let title = title.trim().to_lowercase();
+    ENTITY_FOLDERS
+        .iter()
+        .find(|(folder, _)| *folder == title)
+        .map(|(_, kind)| *kind)
+}
+
+/// `Role in Story` as `roleInStory`
+fn property_name(field: &str) -> String {
+    let mut name = String::new();
+    for (i, word) in field
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .enumerate()
+    {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            if i == 0 {
+                name.extend(first.to_lowercase());
+            } else {
+                name.extend(first.to_uppercase());
+            }
+            name.push_str(&chars.as_str().to_lowercase());
+        }
+    }
+    name
+}
+
+/// An entity sheet's `Field: value` lines as properties, and the rest
+fn sheet_fields(text: &str) -> (Map<String, Value>, Vec<String>) {
+    let mut properties = Map::new();
+    let mut rest = Vec::new();
+    for line in text.lines() {
+        let field = line.split_once(':').and_then(|(field, value)| {
+            let words = field.split_whitespace().count();
+            let name = property_name(field);
+            (words > 0 && words <= MAX_FIELD_WORDS && !name.is_empty() && !value.trim().is_empty())
+                .then(|| (name, value.trim().to_string()))
+        });
+        match field {
+            Some((name, value)) if !properties.contains_key(&name) => {
+                properties.insert(name, Value::String(value));
+            }
+            _ => rest.push(line.to_string()),
+        }
+    }
+    while rest.last().is_some_and(|line| line.trim().is_empty()) {
+        rest.pop();
+    }
+    (properties, rest)
+}
+
+struct Files {
+    content: Option<Vec<u8>>,
+    notes: Option<String>,
+    synopsis: Option<String>,
+}
+
+struct Importer<'a> {
+    rows: NewRows,
+    project_id: &'a str,
+    root: PathBuf,
+    source_name: String,
+    labels: HashMap<String, String>,
+    statuses: HashMap<String, String>,
+    /// Existing and imported entities by type and canonical name
+    entities: HashSet<(String, String)>,
+    next_root: i64,
+    report: ScrivenerReport,
+}
+
+impl Importer<'_> {
+    fn files(&self, item: &BinderItem) -> Files {
+        let read = |path: PathBuf| fs::read(path).ok();
+        let data = self.root.join("Files").join("Data").join(&item.uuid);
+        let docs = self.root.join("Files").join("Docs");
+        let pick = |v3: &str, v2: &str| {
+            read(data.join(v3)).or_else(|| {
+                let id = item.id.as_deref()?;
+                read(docs.join(format!("{}{}", id, v2)))
+            })
+        };
+        let text = |bytes: Vec<u8>| String::from_utf8_lossy(&bytes).trim().to_string();
+        Files {
+            content: pick("content.rtf", ".rtf"),
+            notes: pick("notes.rtf", "_notes.rtf")
+                .map(|rtf| rtf::plain_text(&rtf).trim().to_string())
+                .filter(|notes| !notes.is_empty()),
+            synopsis: pick("synopsis.txt", "_synopsis.txt")
+                .map(text)
+                .or(item.synopsis.clone())
+                .filter(|synopsis| !synopsis.is_empty()),
+        }
+    }
+
+    fn log(
+        &mut self,
+        path: &str,
+        item: &BinderItem,
+        reason: UnmappedReason,
+        detail: Option<String>,
+    ) {
+        self.report.unmapped.push(UnmappedItem {
+            binder_path: path.to_string(),
+            uuid: item.uuid.clone(),
+            item_type: item.kind.clone(),
+            reason,
+            detail,
+        });
+    }
+
+    /// Label and status names; "No Label" and "No Status" are `-1`
+    fn settings(&self, item: &BinderItem) -> Map<String, Value> {
+        let mut out = Map::new();
+        for (key, id, names) in [
+            ("label", &item.label, &self.labels),
+            ("status", &item.status, &self.statuses),
+        ] {
+            if let Some(name) = id
+                .as_deref()
+                .filter(|id| *id != "-1")
+                .and_then(|id| names.get(id))
+            {
+                out.insert(key.to_string(), json!(name));
+            }
+        }
+        out
+    }
+
+    fn visit(
+        &mut self,
+        item: &BinderItem,
+        area: Area,
+        parent: Option<&str>,
+        order: i64,
+        path: &str,
+    ) -> Result<(), ImportError> {
+        let title = match item.title.trim() {
+            "" => "Untitled",
+            title => title,
+        };
+        let path = if path.is_empty() {
+            title.to_string()
+        } else {
+            format!("{} / {}", path, title)
+        };
+        let is_folder = matches!(
+            item.kind.as_str(),
+            "Folder" | "DraftFolder" | "ResearchFolder"
+        );
+        if item.kind == "TrashFolder" {
+            for child in &item.children {
+                let inside = count(child) - 1;
+                let detail = (inside > 0).then(|| format!("items inside: {}", inside));
+                let child_path = format!("{} / {}", path, child.title.trim());
+                self.log(&child_path, child, UnmappedReason::Trashed, detail);
+            }
+            return Ok(());
+        }
+        if !is_folder && item.kind != "Text" {
+            self.log(&path, item, UnmappedReason::UnsupportedType, None);
+            return Ok(());
+        }
+
+        let area = match (area, entity_type(title)) {
+            (Area::Manuscript, _) => Area::Manuscript,
+            (_, Some(kind)) if is_folder => Area::Entities(kind),
+            (area, _) => area,
+        };
+        if item.kind == "DraftFolder" {
+            return self.children(item, Area::Manuscript, None, &path);
+        }
+        if let Area::Entities(kind) = area {
+            if is_folder {
+                return self.children(item, area, None, &path);
+            }
+            self.entity(item, kind, title, &path)?;
+            return self.children(item, area, None, &path);
+        }
+
+        let files = self.files(item);
+        let parsed = files.content.as_deref().map(rtf::read).unwrap_or_default();
+        if !parsed.skipped.is_empty() {
+            let detail = parsed
+                .skipped
+                .iter()
+                .map(|(what, count)| format!("{} left out: {}", what, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.log(&path, item, UnmappedReason::DroppedContent, Some(detail));
+        }
+        let content = build_content(&Chapter {
+            title: title.to_string(),
+            scenes: vec![parsed.blocks],
+            has_separators: false,
+        });
+
+        let mut metadata = self.settings(item);
+        metadata.insert("importedFrom".into(), json!(self.source_name));
+        metadata.insert("scrivenerUuid".into(), json!(item.uuid));
+        if let Some(synopsis) = files.synopsis {
+            metadata.insert("synopsis".into(), json!(synopsis));
+        }
+        if let Some(notes) = files.notes {
+            metadata.insert("notes".into(), json!(notes));
+        }
+        if item.include_in_compile == Some(false) {
+            metadata.insert("includeInCompile".into(), json!(false));
+        }
+        let kind = match area {
+            Area::Manuscript if is_folder || !item.children.is_empty() => "chapter",
+            Area::Manuscript => "scene",
+            _ => "note",
+        };
+        let order = match parent {
+            Some(_) => order,
+            None => {
+                self.next_root += 1;
+                self.next_root - 1
+            }
+        };
+        let (id, words) = insert_document(
+            &mut self.rows,
+            self.project_id,
+            NewDocument {
+                kind,
+                title,
+                parent_id: parent,
+                order,
+                content: &content.doc,
+                metadata: Value::Object(metadata),
+            },
+        );
+        self.report.documents.push(ImportedDocument {
+            id: id.clone(),
+            title: title.to_string(),
+            scenes: 0,
+            words,
+        });
+        self.children(item, area, Some(&id), &path)
+    }
+
+    fn children(
+        &mut self,
+        item: &BinderItem,
+        area: Area,
+        parent: Option<&str>,
+        path: &str,
+    ) -> Result<(), ImportError> {
+        for (order, child) in (0..).zip(&item.children) {
+            self.visit(child, area, parent, order, path)?;
+        }
+        Ok(())
+    }
+
+    fn entity(
+        &mut self,
+        item: &BinderItem,
+        kind: &str,
+        name: &str,
+        path: &str,
+    ) -> Result<(), ImportError> {
+        let canonical = canonical_name(name);
+        if !self.entities.insert((kind.to_string(), canonical.clone())) {
+            let detail = format!("a {} named {} already exists", kind, name);
+            self.log(path, item, UnmappedReason::DuplicateEntity, Some(detail));
+            return Ok(());
+        }
+        let files = self.files(item);
+        let sheet = files
+            .content
+            .as_deref()
+            .map(rtf::plain_text)
+            .unwrap_or_default();
+        let (mut properties, rest) = sheet_fields(&sheet);
+        properties.extend(self.settings(item));
+        if let Some(synopsis) = files.synopsis {
+            properties.insert("synopsis".into(), json!(synopsis));
+        }
+        let mut notes = rest.join("\n").trim().to_string();
+        if let Some(extra) = files.notes {
+            if !notes.is_empty() {
+                notes.push_str("\n\n");
+            }
+            notes.push_str(&extra);
+        }
+
+        let id = random_uuid();
+        let now = iso_timestamp(now_ms());
+        let mut row = Map::new();
+        for (key, value) in [
+            ("id", json!(id)),
+            ("projectId", json!(self.project_id)),
+            ("type", json!(kind)),
+            ("name", json!(name)),
+            ("canonicalName", json!(canonical)),
+            ("aliases", json!([])),
+            ("properties", Value::Object(properties)),
+            ("createdAt", json!(now)),
+            ("updatedAt", json!(now)),
+        ] {
+            row.insert(key.to_string(), value);
+        }
+        if !notes.is_empty() {
+            row.insert("notes".into(), json!(notes));
+        }
+        self.rows.insert(SyncTable::Entities, row);
+        self.report.entities.push(ImportedEntity {
+            id,
+            name: name.to_string(),
+            kind: kind.to_string(),
+        });
+        Ok(())
+    }
+}
+
+/// The item and everything under it
+fn count(item: &BinderItem) -> usize {
+    1 + item.children.iter().map(count).sum::<usize>()
+}
+
+/// The package directory and its `.scrivx`, given either
+fn locate(path: &Path) -> Result<(PathBuf, PathBuf), ImportError> {
+    let is_scrivx = |p: &Path| {
+        p.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("scrivx"))
+    };
+    if path.is_file() && is_scrivx(path) {
+        let root = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        return Ok((root, path.to_path_buf()));
+    }
+    if !path.is_dir() {
+        return Err(ImportError::NotADocument("Scrivener"));
+    }
+    let mut found: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file() && is_scrivx(p))
+        .collect();
+    found.sort();
+    match found.into_iter().next() {
+        Some(scrivx) => Ok((path.to_path_buf(), scrivx)),
+        None => Err(ImportError::NotADocument("Scrivener")),
+    }
+}
+
+/// Import a `.scriv` package into the project
+pub fn import_project(
+    store: &ProjectStore,
+    project_id: &str,
+    path: &Path,
+) -> Result<ScrivenerReport, ImportError> {
+    let (root, scrivx) = locate(path)?;
+    let binder = parse_binder(&fs::read_to_string(&scrivx)?)?;
+    let source_name = scrivx
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Scrivener")
+        .to_string();
+    let entities = store
+        .list(SyncTable::Entities, project_id)?
+        .iter()
+        .filter_map(|row| {
+            let kind = row.get("type")?.as_str()?;
+            let name = row.get("name")?.as_str()?;
+            Some((kind.to_string(), canonical_name(name)))
+        })
+        .collect();
+
+    let mut importer = Importer {
+        rows: NewRows::default(),
+        project_id,
+        root,
+        source_name,
+        labels: binder.labels,
+        statuses: binder.statuses,
+        entities,
+        next_root: next_root_order(store, project_id)?,
+        report: ScrivenerReport {
+            project_id: project_id.to_string(),
+            documents: Vec::new(),
+            entities: Vec::new(),
+            unmapped: Vec::new(),
+        },
+    };
+    // The manuscript first, whatever its place in the binder
+    let mut items: Vec<&BinderItem> = binder.items.iter().collect();
+    items.sort_by_key(|item| item.kind != "DraftFolder");
+    for item in items {
+        importer.visit(item, Area::Notes, None, 0, "")?;
+    }
+    importer.rows.commit(store, project_id)?;
+    Ok(importer.report)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn import_scrivener_project(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+) -> Result<ScrivenerReport, ImportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = import_project(&app.state::<ProjectStore>(), &project_id, Path::new(&path))?;
+        app.state::<OutboxWorker>().notify();
+        Ok(report)
+    })
+    .await
+    .map_err(|e| ImportError::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIVX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ScrivenerProject Version="2.0">
+<Binder>
+<BinderItem UUID="R1" Type="ResearchFolder"><Title>Research</Title><Children>
+  <BinderItem UUID="I1" Type="Image"><Title>Map</Title></BinderItem>
+  <BinderItem UUID="N1" Type="Text"><Title>Tides</Title></BinderItem>
+  <BinderItem UUID="P0" Type="Folder"><Title>Places</Title><Children>
+    <BinderItem UUID="P1" Type="Text"><Title>Harbour</Title></BinderItem>
+  </Children></BinderItem>
+</Children></BinderItem>
+<BinderItem UUID="D0" Type="DraftFolder"><Title>Manuscript</Title><Children>
+  <BinderItem UUID="C1" Type="Folder"><Title>Chapter 1</Title>
+    <MetaData><LabelID>1</LabelID><StatusID>2</StatusID></MetaData><Children>
+    <BinderItem UUID="S1" Type="Text"><Title>Arrival</Title>
+      <MetaData><StatusID>-1</StatusID><IncludeInCompile>No</IncludeInCompile></MetaData>
+    </BinderItem>
+    <BinderItem UUID="S2" Type="Text"><Title>  </Title></BinderItem>
+  </Children></BinderItem>
+</Children></BinderItem>
+<BinderItem UUID="K0" Type="Folder"><Title>Characters</Title><Children>
+  <BinderItem UUID="K1" Type="Text"><Title>Ada  Lovelace</Title><MetaData><LabelID>1</LabelID></MetaData></BinderItem>
+  <BinderItem UUID="K2" Type="Text"><Title>Kael</Title></BinderItem>
+</Children></BinderItem>
+<BinderItem UUID="T0" Type="TrashFolder"><Title>Trash</Title><Children>
+  <BinderItem UUID="T1" Type="Folder"><Title>Old</Title><Children>
+    <BinderItem UUID="T2" Type="Text"><Title>Draft</Title></BinderItem>
+  </Children></BinderItem>
+</Children></BinderItem>
+</Binder>
+<LabelSettings><Labels><Label ID="-1">No Label</Label><Label ID="1" Color="0 0 1">Main plot</Label></Labels></LabelSettings>
+<StatusSettings><StatusItems><Status ID="-1">No Status</Status><Status ID="2">First Draft</Status></StatusItems></StatusSettings>
+</ScrivenerProject>"#;
+
+    fn write(root: &Path, uuid: &str, name: &str, text: &str) {
+        let dir = root.join("Files").join("Data").join(uuid);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(name), text).unwrap();
+    }
+
+    #[test]
+    fn maps_the_binder_to_documents_and_entities() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Novel.scriv");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("Novel.scrivx"), SCRIVX).unwrap();
+        write(
+            &root,
+            "S1",
+            "content.rtf",
+            r"{\rtf1 The \i boat\i0  came.\par{\pict 00}Then rain.\par}",
+        );
+        write(&root, "S1", "synopsis.txt", "Ada lands.\n");
+        write(&root, "S1", "notes.rtf", r"{\rtf1 Check tides.}");
+        write(
+            &root,
+            "K1",
+            "content.rtf",
+            r"{\rtf1 Role in Story: Engineer\par Age: 36\par\par Keeps the light.\par}",
+        );
+        write(&root, "N1", "content.rtf", r"{\rtf1 Twice daily.}");
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "e1", "projectId": "p1", "type": "character", "name": "KAEL"}),
+            )
+            .unwrap();
+        let report = import_project(&store, "p1", &root).unwrap();
+
+        let titles: Vec<&str> = report.documents.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["Chapter 1", "Arrival", "Untitled", "Research", "Tides"]
+        );
+        let row = |i: usize| {
+            store
+                .get(SyncTable::Documents, &report.documents[i].id)
+                .unwrap()
+                .unwrap()
+        };
+        let (chapter, arrival, research, tides) = (row(0), row(1), row(3), row(4));
+        assert_eq!(chapter["type"], "chapter");
+        assert_eq!(chapter["orderIndex"], 0);
+        assert_eq!(chapter["metadata"]["label"], "Main plot");
+        assert_eq!(chapter["metadata"]["status"], "First Draft");
+        assert_eq!(arrival["type"], "scene");
+        assert_eq!(arrival["parentId"], chapter["id"]);
+        assert_eq!(arrival["contentText"], "The boat came.\n\nThen rain.");
+        assert_eq!(
+            arrival["content"]["content"][0]["content"][1]["marks"][0]["type"],
+            "italic"
+        );
+        assert_eq!(arrival["metadata"]["synopsis"], "Ada lands.");
+        assert_eq!(arrival["metadata"]["notes"], "Check tides.");
+        assert_eq!(arrival["metadata"]["includeInCompile"], false);
+        assert_eq!(arrival["metadata"]["importedFrom"], "Novel");
+        assert!(arrival["metadata"].get("status").is_none());
+        assert_eq!(row(2)["orderIndex"], 1);
+        assert_eq!(research["type"], "note");
+        assert_eq!(research["orderIndex"], 1);
+        assert_eq!(tides["parentId"], research["id"]);
+
+        let entities: Vec<(&str, &str)> = report
+            .entities
+            .iter()
+            .map(|e| (e.kind.as_str(), e.name.as_str()))
+            .collect();
+        assert_eq!(
+            entities,
+            [("location", "Harbour"), ("character", "Ada  Lovelace")]
+        );
+        let ada = store
+            .get(SyncTable::Entities, &report.entities[1].id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ada["canonicalName"], "ada lovelace");
+        assert_eq!(
+            ada["properties"],
+            json!({"roleInStory": "Engineer", "age": "36", "label": "Main plot"})
+        );
+        assert_eq!(ada["notes"], "Keeps the light.");
+
+        let unmapped: Vec<(&str, UnmappedReason, Option<&str>)> = report
+            .unmapped
+            .iter()
+            .map(|u| (u.binder_path.as_str(), u.reason, u.detail.as_deref()))
+            .collect();
+        assert_eq!(
+            unmapped,
+            [
+                (
+                    "Manuscript / Chapter 1 / Arrival",
+                    UnmappedReason::DroppedContent,
+                    Some("images left out: 1")
+                ),
+                ("Research / Map", UnmappedReason::UnsupportedType, None),
+                (
+                    "Characters / Kael",
+                    UnmappedReason::DuplicateEntity,
+                    Some("a character named Kael already exists")
+                ),
+                (
+                    "Trash / Old",
+                    UnmappedReason::Trashed,
+                    Some("items inside: 1")
+                ),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&report.unmapped[1]).unwrap(),
+            json!({"binderPath": "Research / Map", "uuid": "I1", "itemType": "Image",
+                   "reason": "unsupported_type", "detail": null})
+        );
+
+        let err = import_project(&store, "p1", dir.path()).unwrap_err();
+        assert_eq!(err.code(), "import_not_a_document");
+    }
+
+    #[test]
+    fn reads_scrivener_2_files_and_entity_sheets() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Old.scriv");
+        fs::create_dir_all(root.join("Files").join("Docs")).unwrap();
+        let scrivx = r#"<ScrivenerProject><Binder>
+            <BinderItem ID="0" Type="DraftFolder"><Title>Draft</Title><Children>
+              <BinderItem ID="4" Type="Text"><Title>Only</Title><Synopsis>Short</Synopsis></BinderItem>
+            </Children></BinderItem></Binder></ScrivenerProject>"#;
+        fs::write(root.join("Old.scrivx"), scrivx).unwrap();
+        fs::write(root.join("Files/Docs/4.rtf"), r"{\rtf1 Old words.}").unwrap();
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        let report = import_project(&store, "p1", &root.join("Old.scrivx")).unwrap();
+        let only = store
+            .get(SyncTable::Documents, &report.documents[0].id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(only["contentText"], "Old words.");
+        assert_eq!(only["metadata"]["synopsis"], "Short");
+        assert_eq!(only["metadata"]["scrivenerUuid"], "4");
+
+        let (properties, rest) =
+            sheet_fields("Name: Ada\nA note: with colon\nLong field name that is a sentence: no\n");
+        assert_eq!(
+            properties,
+            json!({"name": "Ada", "aNote": "with colon"})
+                .as_object()
+                .cloned()
+                .unwrap()
+        );
+        assert_eq!(rest, ["Long field name that is a sentence: no"]);
+    }
+}
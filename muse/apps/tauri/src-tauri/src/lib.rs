@@ -3,24 +3,102 @@
 //! Features:
 //! - Editor WebView bridge
 //! - Deep link handling for OAuth
+//! - Secure credential storage
+//! - Local SQLite project store for offline editing
+//! - Durable mutation outbox with retry and backoff
+//! - Conflict detection with a review queue
+//! - Portable project archives
+//! - Story-as-code folder mirror that picks up external edits
+//! - Automatic local backups with rotation
+//! - Built-in git history of each project
+//! - EPUB 3 and standard manuscript DOCX export
+//! - DOCX and ODT manuscript import with comments and tracked changes
+//! - Scrivener project import
+//! - Fountain screenplay import and export
+//! - Offline full-text and vector search
+//! - Pluggable local embedding providers with background jobs
 //! - In-App Purchases (Mac App Store)
 
-use tauri::{AppHandle, Emitter};
-use tauri_plugin_deep_link::DeepLinkExt;
+pub mod archive;
+pub mod backup;
+pub mod bridge;
+pub mod credentials;
+pub mod db;
+pub mod deep_link;
+pub mod export;
+pub mod folder;
+pub mod history;
+pub mod import;
+pub mod oauth;
+pub mod search;
+#[cfg(desktop)]
+pub mod single_instance;
+pub mod sync;
 
-/// Receives messages from the editor WebView and emits to React frontend
-#[tauri::command]
-fn editor_message(app: AppHandle, message: String) -> Result<(), String> {
-    app.emit("editor-message", &message)
-        .map_err(|e| e.to_string())
-}
+use tauri::webview::PageLoadEvent;
+use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+
+    // Must be registered first: a second launch forwards its links here and exits
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(
+        single_instance::on_second_instance,
+    ));
+
+    builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_deep_link::init())
+        .manage(bridge::BridgeHub::default())
+        .manage(bridge::recorder::BridgeRecorder::default())
+        .manage(deep_link::pending::PendingDeepLinks::default())
+        .manage(folder::watcher::FolderWatcher::default())
+        .manage(oauth::OAuthFlows::default())
+        .manage(sync::outbox::OutboxWorker::default())
+        .on_page_load(|webview, payload| {
+            // A reloaded frontend has lost its listeners; queue links until it drains again
+            if payload.event() == PageLoadEvent::Started && webview.label() == "main" {
+                webview
+                    .state::<deep_link::pending::PendingDeepLinks>()
+                    .reset();
+            }
+        })
         .setup(|app| {
+            // Fallback file lives beside app data; the OS secret store is preferred
+            let credentials_dir = app.path().app_data_dir()?.join("credentials");
+            app.manage(credentials::CredentialStore::new(credentials_dir));
+
+            // Offline copy of the synced tables
+            let db_path = app.path().app_data_dir()?.join(db::DB_FILE);
+            let store = db::ProjectStore::open(&db_path)?;
+            app.manage(sync::conflicts::ConflictPolicy::load(&store)?);
+            app.manage(search::embedding_jobs::EmbeddingWorker::load(&store)?);
+            app.manage(store);
+            sync::outbox::spawn_worker(app.handle().clone());
+            search::embedding_jobs::spawn_worker(app.handle().clone());
+
+            // Scheduled copies of the store; one more is taken on exit
+            let backup_dir = app.path().app_data_dir()?.join(backup::BACKUP_DIR);
+            app.manage(backup::Backups::new(backup_dir));
+            backup::spawn_scheduler(app.handle().clone());
+
+            let history_dir = app.path().app_data_dir()?.join(history::HISTORY_DIR);
+            let history = history::History::load(history_dir, &app.state::<db::ProjectStore>())?;
+            app.manage(history);
+            history::spawn_scheduler(app.handle().clone());
+
+            // Opt-in bridge traffic recording from launch
+            if let Some(path) = std::env::var_os(bridge::recorder::RECORD_ENV) {
+                let path = std::path::PathBuf::from(path);
+                match app.state::<bridge::recorder::BridgeRecorder>().start(&path) {
+                    Ok(_) => println!("[bridge] Recording session to {}", path.display()),
+                    Err(e) => eprintln!("[bridge] Failed to start recording: {}", e),
+                }
+            }
+
             // Register deep link scheme for OAuth callbacks
             // Note: This only works in bundled builds, not dev mode
             #[cfg(desktop)]
@@ -34,19 +112,103 @@ pub fn run() {
                     Err(e) => eprintln!("[deep-link] Failed to register (expected in dev): {}", e),
                 }
 
+                // URLs that launched the app (argv on Linux/Windows); queued until the frontend drains them
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    let urls: Vec<String> = urls.iter().map(|u| u.to_string()).collect();
+                    deep_link::handle_urls(&handle, urls.iter().map(String::as_str));
+                }
+
                 // Listen for deep link events (still set up handler for when it works)
                 app.deep_link().on_open_url(move |event| {
-                    let urls = event.urls();
-                    for url in urls {
-                        // Emit to frontend for handling
-                        let _ = handle.emit("deep-link://new-url", url.to_string());
-                    }
+                    let urls: Vec<String> = event.urls().iter().map(|u| u.to_string()).collect();
+                    // Parse and route before anything reaches the frontend
+                    deep_link::handle_urls(&handle, urls.iter().map(String::as_str));
                 });
             }
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![editor_message])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(tauri::generate_handler![
+            archive::export_project_archive,
+            archive::import_project_archive,
+            backup::configure_backups,
+            backup::create_backup_now,
+            backup::get_backup_settings,
+            backup::list_backups,
+            backup::restore_backup,
+            bridge::configure_editor_bridge,
+            bridge::editor_bridge_protocol,
+            bridge::editor_message,
+            bridge::send_editor_message,
+            bridge::set_editor_document,
+            bridge::recorder::replay_bridge_session,
+            bridge::recorder::start_bridge_recording,
+            bridge::recorder::stop_bridge_recording,
+            credentials::delete_credential,
+            credentials::get_credential,
+            credentials::set_credential,
+            db::bootstrap_local_project,
+            db::clear_local_project,
+            db::delete_local_row,
+            db::get_last_sync_version,
+            db::get_local_row,
+            db::list_local_rows,
+            db::set_last_sync_version,
+            db::upsert_local_row,
+            deep_link::parse_deep_link,
+            deep_link::pending::take_pending_auth_callbacks,
+            deep_link::pending::take_pending_deep_links,
+            export::docx::export_docx,
+            export::epub::export_epub,
+            export::fountain::export_fountain,
+            folder::export_story_folder,
+            folder::import_story_folder,
+            folder::watcher::get_watched_story_folder,
+            folder::watcher::unwatch_story_folder,
+            folder::watcher::watch_story_folder,
+            history::checkout_document_revision,
+            history::commit_project_history,
+            history::configure_history,
+            history::diff_project_revisions,
+            history::get_history_settings,
+            history::list_project_history,
+            import::fountain::import_fountain,
+            import::import_manuscript,
+            import::take_imported_annotations,
+            import::scrivener::import_scrivener_project,
+            oauth::complete_oauth,
+            oauth::start_auth_redirect,
+            oauth::start_oauth,
+            search::embedding_jobs::configure_embedding_provider,
+            search::embedding_jobs::embed_texts,
+            search::embedding_jobs::get_embedding_provider,
+            search::embedding_jobs::list_embedding_jobs,
+            search::embedding_jobs::semantic_search,
+            search::fulltext::rebuild_search_index,
+            search::fulltext::search_local,
+            search::hybrid::hybrid_search,
+            search::vector::delete_embedding,
+            search::vector::upsert_embeddings,
+            search::vector::vector_search,
+            sync::conflicts::apply_sync_events,
+            sync::conflicts::get_conflict_strategies,
+            sync::conflicts::list_conflicts,
+            sync::conflicts::resolve_conflict,
+            sync::conflicts::set_conflict_strategy,
+            sync::outbox::configure_sync_endpoint,
+            sync::outbox::discard_dead_mutation,
+            sync::outbox::enqueue_mutation,
+            sync::outbox::flush_outbox,
+            sync::outbox::list_dead_letters,
+            sync::outbox::outbox_status,
+            sync::outbox::retry_dead_mutation,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                backup::backup_on_exit(app);
+                history::commit_on_exit(app);
+            }
+        });
 }
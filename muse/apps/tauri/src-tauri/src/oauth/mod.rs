@@ -0,0 +1,554 @@
+//! Native OAuth (authorization code + PKCE) owned by the shell
+//!
+//! `start_oauth` creates the verifier, challenge and `state` and returns the
+//! authorization URL for the system browser. The provider redirects back to
+//! `rhei://auth/callback`, and the deep-link handler hands the callback to
+//! `OAuthFlows::accept_callback`, which only accepts a `state` it issued and
+//! only once. `complete_oauth` waits for that callback and exchanges the code
+//! (with the verifier, which never leaves Rust) at the token endpoint.
+//!
+//! Convex Auth sign-ins (OAuth via Convex and magic links) redirect to a URL
+//! from `start_auth_redirect` instead, whose `state` is accepted once and the
+//! callback forwarded to the frontend. Callbacks with a missing or unknown
+//! `state` are dropped.
+
+pub mod pkce;
+
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// Redirect URI registered with providers
+pub const DEFAULT_REDIRECT_URI: &str = "rhei://auth/callback";
+
+/// How long a started flow accepts its callback
+pub const FLOW_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long a Convex Auth redirect accepts its callback (covers magic links)
+pub const REDIRECT_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthProviderConfig {
+    pub provider: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+}
+
+impl OAuthProviderConfig {
+    pub fn redirect_uri(&self) -> &str {
+        self.redirect_uri.as_deref().unwrap_or(DEFAULT_REDIRECT_URI)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthStart {
+    pub authorization_url: String,
+    pub state: String,
+}
+
+/// A `redirectTo` for Convex Auth carrying a shell-issued `state`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthRedirect {
+    pub redirect_to: String,
+    pub state: String,
+}
+
+/// Token endpoint response (RFC 6749 §5.1)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub token_type: String,
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub id_token: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OAuthError {
+    #[error("invalid OAuth configuration: {0}")]
+    InvalidConfig(String),
+    #[error("callback is missing its state")]
+    MissingState,
+    #[error("callback state does not match any pending sign-in")]
+    UnknownState,
+    #[error("sign-in expired")]
+    Expired,
+    #[error("callback was already received for this sign-in")]
+    Replayed,
+    #[error("callback is missing the authorization code")]
+    MissingCode,
+    #[error("provider returned {error}: {description}")]
+    Provider { error: String, description: String },
+    #[error("timed out waiting for the sign-in callback")]
+    Timeout,
+    #[error("token exchange failed: {0}")]
+    Exchange(String),
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidConfig(_) => "oauth_invalid_config",
+            Self::MissingState => "oauth_missing_state",
+            Self::UnknownState => "oauth_unknown_state",
+            Self::Expired => "oauth_expired",
+            Self::Replayed => "oauth_replayed",
+            Self::MissingCode => "oauth_missing_code",
+            Self::Provider { .. } => "oauth_provider_error",
+            Self::Timeout => "oauth_timeout",
+            Self::Exchange(_) => "oauth_exchange_failed",
+        }
+    }
+}
+
+crate::db::serialize_error!(OAuthError);
+
+/// What the deep-link handler should do with an auth callback URL
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallbackDisposition {
+    /// Matched a pending flow; the waiter in `complete_oauth` was woken
+    Accepted,
+    /// Matched a Convex Auth redirect; forward it to the frontend
+    Forward,
+    /// Forged, replayed or expired; drop it
+    Rejected(OAuthError),
+}
+
+/// A callback matched to its flow, ready for the token exchange
+#[derive(Debug, Clone)]
+pub struct ReceivedCallback {
+    pub config: OAuthProviderConfig,
+    pub verifier: String,
+    pub code: String,
+}
+
+struct PendingFlow {
+    config: OAuthProviderConfig,
+    verifier: String,
+    started: Instant,
+    callback: Option<Result<String, OAuthError>>,
+}
+
+/// Managed registry of in-flight sign-ins, keyed by `state`
+#[derive(Default)]
+pub struct OAuthFlows {
+    flows: Mutex<HashMap<String, PendingFlow>>,
+    arrived: Condvar,
+    redirects: Mutex<HashMap<String, Instant>>,
+}
+
+impl OAuthFlows {
+    /// Start a flow, returning the authorization URL to open
+    pub fn begin(&self, config: OAuthProviderConfig) -> Result<OAuthStart, OAuthError> {
+        let mut url = url::Url::parse(&config.authorization_endpoint)
+            .map_err(|e| OAuthError::InvalidConfig(e.to_string()))?;
+        url::Url::parse(&config.token_endpoint)
+            .map_err(|e| OAuthError::InvalidConfig(e.to_string()))?;
+
+        let verifier = pkce::code_verifier();
+        let state = pkce::random_token(24);
+
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &config.client_id)
+                .append_pair("redirect_uri", config.redirect_uri())
+                .append_pair("state", &state)
+                .append_pair("code_challenge", &pkce::code_challenge(&verifier))
+                .append_pair("code_challenge_method", "S256");
+            if !config.scopes.is_empty() {
+                query.append_pair("scope", &config.scopes.join(" "));
+            }
+        }
+
+        let mut flows = self.flows.lock().unwrap();
+        flows.retain(|_, flow| flow.started.elapsed() < FLOW_TTL);
+        flows.insert(
+            state.clone(),
+            PendingFlow {
+                config,
+                verifier,
+                started: Instant::now(),
+                callback: None,
+            },
+        );
+
+        Ok(OAuthStart {
+            authorization_url: url.to_string(),
+            state,
+        })
+    }
+
+    /// Issue a single-use `redirectTo` for a Convex Auth sign-in
+    pub fn begin_redirect(&self) -> AuthRedirect {
+        let state = pkce::random_token(24);
+        let mut redirect = url::Url::parse(DEFAULT_REDIRECT_URI).expect("valid redirect URI");
+        redirect.query_pairs_mut().append_pair("state", &state);
+
+        let mut redirects = self.redirects.lock().unwrap();
+        redirects.retain(|_, started| started.elapsed() < REDIRECT_TTL);
+        redirects.insert(state.clone(), Instant::now());
+
+        AuthRedirect {
+            redirect_to: redirect.to_string(),
+            state,
+        }
+    }
+
+    /// Validate an auth callback URL against the pending flows and redirects
+    pub fn accept_callback(&self, callback_url: &str) -> CallbackDisposition {
+        let Ok(url) = url::Url::parse(callback_url) else {
+            return CallbackDisposition::Rejected(OAuthError::MissingState);
+        };
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let Some(state) = params.get("state") else {
+            return CallbackDisposition::Rejected(OAuthError::MissingState);
+        };
+
+        if let Some(started) = self.redirects.lock().unwrap().remove(state) {
+            if started.elapsed() >= REDIRECT_TTL {
+                return CallbackDisposition::Rejected(OAuthError::Expired);
+            }
+            return CallbackDisposition::Forward;
+        }
+
+        let mut flows = self.flows.lock().unwrap();
+        let Some(flow) = flows.get_mut(state) else {
+            return CallbackDisposition::Rejected(OAuthError::UnknownState);
+        };
+        if flow.callback.is_some() {
+            return CallbackDisposition::Rejected(OAuthError::Replayed);
+        }
+        if flow.started.elapsed() >= FLOW_TTL {
+            flows.remove(state);
+            return CallbackDisposition::Rejected(OAuthError::Expired);
+        }
+
+        flow.callback = Some(match (params.get("error"), params.get("code")) {
+            (Some(error), _) => Err(OAuthError::Provider {
+                error: error.clone(),
+                description: params.get("error_description").cloned().unwrap_or_default(),
+            }),
+            (None, Some(code)) if !code.is_empty() => Ok(code.clone()),
+            (None, _) => Err(OAuthError::MissingCode),
+        });
+        self.arrived.notify_all();
+        CallbackDisposition::Accepted
+    }
+
+    /// Block until the callback for `state` arrives, consuming the flow
+    pub fn wait_for_callback(
+        &self,
+        state: &str,
+        timeout: Duration,
+    ) -> Result<ReceivedCallback, OAuthError> {
+        let deadline = Instant::now() + timeout;
+        let mut flows = self.flows.lock().unwrap();
+        loop {
+            let flow = flows.get(state).ok_or(OAuthError::UnknownState)?;
+            if flow.callback.is_some() {
+                let flow = flows.remove(state).expect("flow present");
+                let code = flow.callback.expect("callback present")?;
+                return Ok(ReceivedCallback {
+                    config: flow.config,
+                    verifier: flow.verifier,
+                    code,
+                });
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                flows.remove(state);
+                return Err(OAuthError::Timeout);
+            }
+            flows = self.arrived.wait_timeout(flows, deadline - now).unwrap().0;
+        }
+    }
+}
+
+/// Exchange an authorization code for tokens (RFC 7636 §4.5)
+pub fn exchange_code(callback: &ReceivedCallback) -> Result<OAuthTokens, OAuthError> {
+    let config = &callback.config;
+    let response = ureq::post(&config.token_endpoint)
+        .set("Accept", "application/json")
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", &callback.code),
+            ("redirect_uri", config.redirect_uri()),
+            ("client_id", &config.client_id),
+            ("code_verifier", &callback.verifier),
+        ]);
+
+    match response {
+        Ok(response) => response
+            .into_json::<OAuthTokens>()
+            .map_err(|e| OAuthError::Exchange(e.to_string())),
+        Err(ureq::Error::Status(status, response)) => {
+            let body: serde_json::Value = response.into_json().unwrap_or_default();
+            Err(OAuthError::Provider {
+                error: body["error"]
+                    .as_str()
+                    .map_or_else(|| format!("http_{}", status), str::to_string),
+                description: body["error_description"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        }
+        Err(e) => Err(OAuthError::Exchange(e.to_string())),
+    }
+}
+
+/// Starts a PKCE sign-in; open the returned URL in the system browser
+#[tauri::command]
+pub fn start_oauth(
+    flows: tauri::State<'_, OAuthFlows>,
+    config: OAuthProviderConfig,
+) -> Result<OAuthStart, OAuthError> {
+    flows.begin(config)
+}
+
+/// Returns a `redirectTo` for Convex Auth whose callback the shell will accept
+#[tauri::command]
+pub fn start_auth_redirect(flows: tauri::State<'_, OAuthFlows>) -> AuthRedirect {
+    flows.begin_redirect()
+}
+
+/// Waits for the sign-in callback and returns the provider's tokens
+#[tauri::command(rename_all = "camelCase")]
+pub async fn complete_oauth(
+    app: AppHandle,
+    state: String,
+    timeout_secs: Option<u64>,
+) -> Result<OAuthTokens, OAuthError> {
+    let timeout = timeout_secs.map_or(FLOW_TTL, Duration::from_secs);
+    tauri::async_runtime::spawn_blocking(move || {
+        let callback = app
+            .state::<OAuthFlows>()
+            .wait_for_callback(&state, timeout)?;
+        exchange_code(&callback)
+    })
+    .await
+    .map_err(|e| OAuthError::Exchange(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// One-shot token endpoint that checks the PKCE verifier against `challenge`
+    fn mock_token_server(challenge: String) -> (String, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let form: HashMap<String, String> =
+                url::form_urlencoded::parse(&body).into_owned().collect();
+
+            let ok = form.get("grant_type").map(String::as_str) == Some("authorization_code")
+                && form.get("code").map(String::as_str) == Some("auth-code")
+                && form
+                    .get("code_verifier")
+                    .is_some_and(|v| pkce::code_challenge(v) == challenge);
+            let (status, body) = if ok {
+                (
+                    "200 OK",
+                    r#"{"access_token":"at","token_type":"Bearer","expires_in":3600,"refresh_token":"rt"}"#,
+                )
+            } else {
+                (
+                    "400 Bad Request",
+                    r#"{"error":"invalid_grant","error_description":"bad verifier"}"#,
+                )
+            };
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        (url, handle)
+    }
+
+    fn config(token_endpoint: &str) -> OAuthProviderConfig {
+        OAuthProviderConfig {
+            provider: "mock".into(),
+            authorization_endpoint: "https://auth.example.test/authorize".into(),
+            token_endpoint: token_endpoint.into(),
+            client_id: "rhei-desktop".into(),
+            scopes: vec!["openid".into(), "email".into()],
+            redirect_uri: None,
+        }
+    }
+
+    fn query(url: &str) -> HashMap<String, String> {
+        url::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    #[test]
+    fn completes_flow_against_mock_server() {
+        let flows = OAuthFlows::default();
+        let start = flows.begin(config("http://unused.test/token")).unwrap();
+        let params = query(&start.authorization_url);
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["state"], start.state);
+
+        let (token_url, server) = mock_token_server(params["code_challenge"].clone());
+        flows
+            .flows
+            .lock()
+            .unwrap()
+            .get_mut(&start.state)
+            .unwrap()
+            .config
+            .token_endpoint = token_url;
+
+        let callback = format!("rhei://auth/callback?code=auth-code&state={}", start.state);
+        assert_eq!(
+            flows.accept_callback(&callback),
+            CallbackDisposition::Accepted
+        );
+
+        let received = flows
+            .wait_for_callback(&start.state, Duration::from_secs(1))
+            .unwrap();
+        let tokens = exchange_code(&received).unwrap();
+        server.join().unwrap();
+        assert_eq!(tokens.access_token, "at");
+        assert_eq!(tokens.refresh_token.as_deref(), Some("rt"));
+
+        // The flow is consumed: replaying the same callback is rejected
+        assert_eq!(
+            flows.accept_callback(&callback),
+            CallbackDisposition::Rejected(OAuthError::UnknownState)
+        );
+    }
+
+    #[test]
+    fn rejects_forged_and_duplicate_callbacks() {
+        let flows = OAuthFlows::default();
+        let start = flows
+            .begin(config("https://auth.example.test/token"))
+            .unwrap();
+
+        assert_eq!(
+            flows.accept_callback("rhei://auth/callback?code=x&state=forged"),
+            CallbackDisposition::Rejected(OAuthError::UnknownState)
+        );
+
+        let callback = format!("rhei://auth/callback?code=x&state={}", start.state);
+        assert_eq!(
+            flows.accept_callback(&callback),
+            CallbackDisposition::Accepted
+        );
+        assert_eq!(
+            flows.accept_callback(&callback),
+            CallbackDisposition::Rejected(OAuthError::Replayed)
+        );
+    }
+
+    #[test]
+    fn drops_callbacks_without_state() {
+        let flows = OAuthFlows::default();
+        assert_eq!(
+            flows.accept_callback("rhei://auth/callback?code=magic"),
+            CallbackDisposition::Rejected(OAuthError::MissingState)
+        );
+        assert_eq!(
+            flows.accept_callback("not a url"),
+            CallbackDisposition::Rejected(OAuthError::MissingState)
+        );
+    }
+
+    #[test]
+    fn forwards_issued_redirects_once() {
+        let flows = OAuthFlows::default();
+        let redirect = flows.begin_redirect();
+        assert_eq!(query(&redirect.redirect_to)["state"], redirect.state);
+
+        // Convex Auth appends the code to the redirect URL
+        let callback = format!("{}&code=magic", redirect.redirect_to);
+        assert_eq!(
+            flows.accept_callback(&callback),
+            CallbackDisposition::Forward
+        );
+        assert_eq!(
+            flows.accept_callback(&callback),
+            CallbackDisposition::Rejected(OAuthError::UnknownState)
+        );
+    }
+
+    #[test]
+    fn surfaces_provider_errors_and_timeouts() {
+        let flows = OAuthFlows::default();
+        let start = flows
+            .begin(config("https://auth.example.test/token"))
+            .unwrap();
+        flows.accept_callback(&format!(
+            "rhei://auth/callback?error=access_denied&error_description=nope&state={}",
+            start.state
+        ));
+        assert_eq!(
+            flows
+                .wait_for_callback(&start.state, Duration::from_secs(1))
+                .unwrap_err(),
+            OAuthError::Provider {
+                error: "access_denied".into(),
+                description: "nope".into(),
+            }
+        );
+
+        let start = flows
+            .begin(config("https://auth.example.test/token"))
+            .unwrap();
+        assert_eq!(
+            flows
+                .wait_for_callback(&start.state, Duration::from_millis(10))
+                .unwrap_err(),
+            OAuthError::Timeout
+        );
+    }
+}
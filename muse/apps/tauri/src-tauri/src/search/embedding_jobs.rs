@@ -0,0 +1,872 @@
+//! Background embedding jobs
+//!
+//! The local counterpart of the server's `embedding_generation` analysis
+//! jobs. Triggers queue a job whenever a document or entity row is written;
+//! a worker embeds due jobs with the configured `EmbeddingProvider` and
+//! caches the result in `vector`. Text is prepared the way the server does
+//! it: a document's plain text in paragraph-aligned chunks (averaged into
+//! one vector here), an entity's name, aliases, notes and attributes.
+//!
+//! A job whose text hash and model match the cached vector is dropped
+//! without calling the provider. Provider failures are retried with backoff.
+//! The store lock is released while the provider runs.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::embeddings::{EmbeddingError, EmbeddingProvider, ProviderConfig};
+use super::fulltext::document_text;
+use super::hybrid::{self, HybridHit, HybridQuery};
+use super::vector::{self, EmbeddingKind, EmbeddingRecord, VectorError};
+use crate::db::{ProjectStore, StoreError};
+use crate::sync::outbox::{now_ms, RetryPolicy};
+
+/// Job kind, as in the server's `analysisJobs`
+pub const EMBEDDING_GENERATION: &str = "embedding_generation";
+
+/// Event carrying an `EmbeddingProgress` after each run that did work
+pub const EMBEDDING_PROGRESS_EVENT: &str = "embeddings://progress";
+
+/// Jobs embedded per provider call (`MAX_EMBED_BATCH` on the server)
+const EMBED_BATCH: u32 = 8;
+
+/// Chunk size for document text (`MAX_CHUNK_CHARS` on the server)
+const MAX_CHUNK_CHARS: usize = 1200;
+
+/// How often the worker looks for new jobs
+const POLL: Duration = Duration::from_secs(15);
+
+/// Setting holding the configured `ProviderConfig`
+const PROVIDER_SETTING: &str = "embeddingProvider";
+
+/// Mirrors the server's `EmbeddingPayload`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingPayload {
+    pub target_type: EmbeddingKind,
+    pub target_id: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingJob {
+    pub kind: &'static str,
+    pub payload: EmbeddingPayload,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingProgress {
+    pub embedded: usize,
+    /// Unchanged since they were last embedded
+    pub skipped: usize,
+    /// Rows deleted or left without text
+    pub removed: usize,
+    pub failed: usize,
+    /// Jobs still queued, including those waiting to retry
+    pub pending: usize,
+    pub last_error: Option<String>,
+}
+
+/// Split text into paragraph-aligned chunks (mirrors the server's `chunkText`)
+pub fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
+    let normalized = text.replace("\r\n", "\n");
+    let mut chunks = Vec::new();
+    let mut buffer = String::new();
+
+    let mut paragraphs = Vec::new();
+    let mut current = Vec::new();
+    for line in normalized.trim().split('\n') {
+        // Two or more newlines separate paragraphs
+        if line.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join("\n"));
+    }
+
+    for paragraph in paragraphs {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+        let length = paragraph.chars().count();
+        if length > max_chars {
+            if !buffer.trim().is_empty() {
+                chunks.push(buffer.trim().to_string());
+            }
+            buffer.clear();
+            let chars: Vec<char> = paragraph.chars().collect();
+            chunks.extend(chars.chunks(max_chars).map(|c| c.iter().collect()));
+            continue;
+        }
+        if !buffer.is_empty() && buffer.chars().count() + length + 2 > max_chars {
+            chunks.push(buffer.trim().to_string());
+            buffer.clear();
+        }
+        if !buffer.is_empty() {
+            buffer.push_str("\n\n");
+        }
+        buffer.push_str(paragraph);
+    }
+    if !buffer.trim().is_empty() {
+        chunks.push(buffer.trim().to_string());
+    }
+    chunks
+}
+
+/// Text embedded for an entity (mirrors the server's `buildEntityText`)
+pub fn entity_text(row: &Map<String, Value>) -> String {
+    let mut parts = Vec::new();
+    if let Some(name) = row.get("name").and_then(Value::as_str) {
+        parts.push(name.to_string());
+    }
+    let aliases: Vec<&str> = row
+        .get("aliases")
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if !aliases.is_empty() {
+        parts.push(format!("Aliases: {}", aliases.join(", ")));
+    }
+    if let Some(notes) = row.get("notes").and_then(Value::as_str) {
+        if !notes.is_empty() {
+            parts.push(notes.to_string());
+        }
+    }
+    if let Some(properties) = row.get("properties").and_then(Value::as_object) {
+        if !properties.is_empty() {
+            parts.push(format!("Attributes: {}", Value::Object(properties.clone())));
+        }
+    }
+    parts.join("\n")
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+struct QueuedJob {
+    seq: i64,
+    kind: EmbeddingKind,
+    id: String,
+}
+
+/// What a due job needs
+enum Prepared {
+    Embed {
+        job: QueuedJob,
+        project_id: String,
+        content_hash: String,
+        chunks: Vec<String>,
+    },
+    Skip(QueuedJob),
+    Remove(QueuedJob),
+}
+
+fn parse_kind(target_type: &str) -> EmbeddingKind {
+    EmbeddingKind::parse(target_type).unwrap_or(EmbeddingKind::Memory)
+}
+
+fn prepare(conn: &Connection, model: &str, now: i64) -> Result<Vec<Prepared>, VectorError> {
+    let mut stmt = conn.prepare(
+        "SELECT seq, target_type, target_id FROM embedding_jobs
+         WHERE next_attempt_at <= ?1 ORDER BY seq LIMIT ?2",
+    )?;
+    let jobs = stmt
+        .query_map(params![now, EMBED_BATCH], |row| {
+            Ok(QueuedJob {
+                seq: row.get(0)?,
+                kind: parse_kind(&row.get::<_, String>(1)?),
+                id: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut prepared = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let table = match job.kind {
+            EmbeddingKind::Document => "documents",
+            EmbeddingKind::Entity => "entities",
+            // Memories aren't stored locally; their vectors only come from the server
+            EmbeddingKind::Memory => {
+                prepared.push(Prepared::Skip(job));
+                continue;
+            }
+        };
+        let row: Option<(String, String)> = conn
+            .query_row(
+                &format!("SELECT project_id, data FROM {} WHERE id = ?1", table),
+                params![job.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((project_id, data)) = row else {
+            prepared.push(Prepared::Remove(job));
+            continue;
+        };
+        let row: Map<String, Value> = serde_json::from_str(&data).map_err(StoreError::from)?;
+        let text = match job.kind {
+            EmbeddingKind::Document => document_text(&row),
+            _ => entity_text(&row),
+        };
+        let chunks = chunk_text(&text, MAX_CHUNK_CHARS);
+        if chunks.is_empty() {
+            prepared.push(Prepared::Remove(job));
+            continue;
+        }
+
+        let content_hash = sha256_hex(&text);
+        let cached: Option<(Option<String>, Option<String>)> = conn
+            .query_row(
+                "SELECT model, content_hash FROM vectors WHERE kind = ?1 AND id = ?2",
+                params![job.kind.as_str(), job.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        if cached == Some((Some(model.to_string()), Some(content_hash.clone()))) {
+            prepared.push(Prepared::Skip(job));
+        } else {
+            prepared.push(Prepared::Embed {
+                job,
+                project_id,
+                content_hash,
+                chunks,
+            });
+        }
+    }
+    Ok(prepared)
+}
+
+fn complete(conn: &Connection, seq: i64) -> Result<(), VectorError> {
+    // A job re-queued meanwhile has a new `seq` and survives
+    conn.execute("DELETE FROM embedding_jobs WHERE seq = ?1", params![seq])?;
+    Ok(())
+}
+
+fn fail(
+    conn: &Connection,
+    seq: i64,
+    policy: &RetryPolicy,
+    now: i64,
+    error: &str,
+) -> Result<(), VectorError> {
+    let attempts: u32 = conn
+        .query_row(
+            "SELECT attempts FROM embedding_jobs WHERE seq = ?1",
+            params![seq],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0)
+        + 1;
+    let next = now + policy.backoff(attempts).as_millis() as i64;
+    conn.execute(
+        "UPDATE embedding_jobs SET attempts = ?2, next_attempt_at = ?3, last_error = ?4
+         WHERE seq = ?1",
+        params![seq, attempts, next, error],
+    )?;
+    Ok(())
+}
+
+/// Mean of the chunk vectors, each normalized first so long chunks don't dominate
+fn mean_vector(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let mut mean = vec![0.0; vectors.first().map_or(0, Vec::len)];
+    for vector in vectors {
+        let norm = vector
+            .iter()
+            .map(|x| x * x)
+            .sum::<f32>()
+            .sqrt()
+            .max(f32::EPSILON);
+        for (sum, x) in mean.iter_mut().zip(vector) {
+            *sum += x / norm;
+        }
+    }
+    mean
+}
+
+/// Embed every due job; provider failures are rescheduled, not returned
+pub fn run_jobs(
+    store: &ProjectStore,
+    provider: &dyn EmbeddingProvider,
+    policy: &RetryPolicy,
+    now: i64,
+) -> Result<EmbeddingProgress, VectorError> {
+    let mut progress = EmbeddingProgress::default();
+    loop {
+        let batch = prepare(&store.lock(), provider.model(), now)?;
+        if batch.is_empty() {
+            break;
+        }
+
+        let mut work = Vec::new();
+        {
+            let conn = store.lock();
+            for prepared in batch {
+                match prepared {
+                    Prepared::Skip(job) => {
+                        progress.skipped += 1;
+                        complete(&conn, job.seq)?;
+                    }
+                    Prepared::Remove(job) => {
+                        progress.removed += 1;
+                        vector::remove(&conn, job.kind, &job.id)?;
+                        complete(&conn, job.seq)?;
+                    }
+                    Prepared::Embed {
+                        job,
+                        project_id,
+                        content_hash,
+                        chunks,
+                    } => work.push((job, project_id, content_hash, chunks)),
+                }
+            }
+        }
+        if work.is_empty() {
+            continue;
+        }
+
+        let texts: Vec<String> = work
+            .iter()
+            .flat_map(|(_, _, _, chunks)| chunks.iter().cloned())
+            .collect();
+        let embedded = provider.embed(&texts).and_then(|vectors| {
+            if vectors.len() == texts.len() {
+                Ok(vectors)
+            } else {
+                Err(EmbeddingError::InvalidResponse(format!(
+                    "{} embeddings for {} inputs",
+                    vectors.len(),
+                    texts.len()
+                )))
+            }
+        });
+
+        let mut conn = store.lock();
+        let tx = conn.transaction()?;
+        let vectors = match embedded {
+            Ok(vectors) => vectors,
+            Err(e) => {
+                let message = e.to_string();
+                for (job, ..) in &work {
+                    fail(&tx, job.seq, policy, now, &message)?;
+                }
+                tx.commit()?;
+                progress.failed += work.len();
+                progress.last_error = Some(message);
+                break;
+            }
+        };
+        let mut offset = 0;
+        for (job, project_id, content_hash, chunks) in work {
+            let record = EmbeddingRecord {
+                kind: job.kind,
+                id: job.id.clone(),
+                project_id,
+                vector: mean_vector(&vectors[offset..offset + chunks.len()]),
+                model: Some(provider.model().to_string()),
+                content_hash: Some(content_hash),
+            };
+            offset += chunks.len();
+            match vector::insert(&tx, &record) {
+                Ok(()) => progress.embedded += 1,
+                // Nothing to embed, e.g. text that is all punctuation
+                Err(VectorError::InvalidVector) => {
+                    vector::remove(&tx, job.kind, &job.id)?;
+                    progress.removed += 1;
+                }
+                Err(VectorError::Store(e)) => return Err(e.into()),
+                Err(e) => {
+                    let message = e.to_string();
+                    fail(&tx, job.seq, policy, now, &message)?;
+                    progress.failed += 1;
+                    progress.last_error = Some(message);
+                    continue;
+                }
+            }
+            complete(&tx, job.seq)?;
+        }
+        tx.commit()?;
+    }
+
+    progress.pending = store
+        .lock()
+        .query_row("SELECT COUNT(*) FROM embedding_jobs", [], |row| {
+            row.get::<_, i64>(0)
+        })? as usize;
+    Ok(progress)
+}
+
+/// Switch the cache to `model`: document and entity vectors from other
+/// models can't be compared with its queries, so they are dropped and their
+/// rows queued again. Memory vectors come from the frontend and are kept.
+pub fn adopt_model(conn: &Connection, model: &str) -> Result<usize, VectorError> {
+    let dropped = conn.execute(
+        "DELETE FROM vectors WHERE model IS NOT ?1 AND kind IN ('document', 'entity')",
+        params![model],
+    )?;
+    for (kind, table) in [("document", "documents"), ("entity", "entities")] {
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO embedding_jobs (target_type, target_id, source)
+                 SELECT ?1, t.id, 'model_change' FROM {} t
+                 WHERE NOT EXISTS (SELECT 1 FROM vectors v WHERE v.kind = ?1 AND v.id = t.id)",
+                table
+            ),
+            params![kind],
+        )?;
+    }
+    Ok(dropped)
+}
+
+pub fn list_jobs(conn: &Connection, limit: u32) -> Result<Vec<EmbeddingJob>, VectorError> {
+    let mut stmt = conn.prepare(
+        "SELECT target_type, target_id, source, attempts, next_attempt_at, last_error
+         FROM embedding_jobs ORDER BY seq LIMIT ?1",
+    )?;
+    let jobs = stmt
+        .query_map(params![limit], |row| {
+            Ok(EmbeddingJob {
+                kind: EMBEDDING_GENERATION,
+                payload: EmbeddingPayload {
+                    target_type: parse_kind(&row.get::<_, String>(0)?),
+                    target_id: row.get(1)?,
+                    source: row.get(2)?,
+                },
+                attempts: row.get(3)?,
+                next_attempt_at: row.get(4)?,
+                last_error: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(jobs)
+}
+
+#[derive(Default)]
+struct WorkerState {
+    config: Option<ProviderConfig>,
+    provider: Option<Arc<dyn EmbeddingProvider>>,
+    woken: bool,
+}
+
+/// Managed handle to the background embedding worker
+#[derive(Default)]
+pub struct EmbeddingWorker {
+    state: Mutex<WorkerState>,
+    wake: Condvar,
+}
+
+impl EmbeddingWorker {
+    /// The worker with the provider saved in `store`, paused without one
+    pub fn load(store: &ProjectStore) -> Result<Self, StoreError> {
+        let worker = Self::default();
+        worker.reload(store)?;
+        Ok(worker)
+    }
+
+    /// Switch to the provider saved in `store`, e.g. after a backup replaced it
+    pub fn reload(&self, store: &ProjectStore) -> Result<(), StoreError> {
+        let saved: Option<ProviderConfig> = store.setting(PROVIDER_SETTING)?.flatten();
+        let built = saved.and_then(|config| match config.build() {
+            Ok(provider) => Some((config, provider)),
+            Err(e) => {
+                eprintln!("[embeddings] Ignoring saved provider: {}", e);
+                None
+            }
+        });
+        let mut state = self.state.lock().unwrap();
+        (state.config, state.provider) = built.unzip();
+        state.woken = true;
+        self.wake.notify_all();
+        Ok(())
+    }
+
+    /// Use `config` for new embeddings (`None` pauses the worker); the
+    /// choice is saved in `store` for the next launch
+    pub fn configure(
+        &self,
+        store: &ProjectStore,
+        config: Option<ProviderConfig>,
+    ) -> Result<(), EmbeddingError> {
+        let provider = config.as_ref().map(ProviderConfig::build).transpose()?;
+        if let Some(provider) = &provider {
+            let dropped =
+                vector::with_transaction(store, |conn| adopt_model(conn, provider.model()))?;
+            if dropped > 0 {
+                println!(
+                    "[embeddings] Dropped {} vectors from other models; re-embedding with {}",
+                    dropped,
+                    provider.model()
+                );
+            }
+        }
+        store
+            .set_setting(PROVIDER_SETTING, &config)
+            .map_err(VectorError::from)?;
+        let mut state = self.state.lock().unwrap();
+        state.config = config;
+        state.provider = provider;
+        state.woken = true;
+        self.wake.notify_all();
+        Ok(())
+    }
+
+    pub fn config(&self) -> Option<ProviderConfig> {
+        self.state.lock().unwrap().config.clone()
+    }
+
+    pub fn provider(&self) -> Option<Arc<dyn EmbeddingProvider>> {
+        self.state.lock().unwrap().provider.clone()
+    }
+
+    fn wait(&self, timeout: Duration) -> Option<Arc<dyn EmbeddingProvider>> {
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .wake
+            .wait_timeout_while(state, timeout, |s| !s.woken)
+            .unwrap();
+        state.woken = false;
+        state.provider.clone()
+    }
+}
+
+/// Run due embedding jobs on a dedicated thread
+pub fn spawn_worker(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let Some(provider) = app.state::<EmbeddingWorker>().wait(POLL) else {
+            continue;
+        };
+        let store = app.state::<ProjectStore>();
+        match run_jobs(&store, provider.as_ref(), &RetryPolicy::default(), now_ms()) {
+            Ok(progress) => {
+                if progress
+                    != (EmbeddingProgress {
+                        pending: progress.pending,
+                        ..Default::default()
+                    })
+                {
+                    let _ = app.emit(EMBEDDING_PROGRESS_EVENT, &progress);
+                }
+            }
+            Err(e) => eprintln!("[embeddings] Run failed: {}", e),
+        }
+    });
+}
+
+#[tauri::command]
+pub fn configure_embedding_provider(
+    store: State<'_, ProjectStore>,
+    worker: State<'_, EmbeddingWorker>,
+    config: Option<ProviderConfig>,
+) -> Result<(), EmbeddingError> {
+    worker.configure(&store, config)
+}
+
+#[tauri::command]
+pub fn get_embedding_provider(worker: State<'_, EmbeddingWorker>) -> Option<ProviderConfig> {
+    worker.config()
+}
+
+#[tauri::command]
+pub fn list_embedding_jobs(
+    store: State<'_, ProjectStore>,
+    limit: Option<u32>,
+) -> Result<Vec<EmbeddingJob>, VectorError> {
+    list_jobs(&store.lock(), limit.unwrap_or(100))
+}
+
+/// Embed texts with the configured provider, e.g. for `vector_search`
+#[tauri::command]
+pub async fn embed_texts(
+    app: AppHandle,
+    texts: Vec<String>,
+) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    let provider = app
+        .state::<EmbeddingWorker>()
+        .provider()
+        .ok_or(EmbeddingError::NotConfigured)?;
+    tauri::async_runtime::spawn_blocking(move || provider.embed(&texts))
+        .await
+        .map_err(|e| EmbeddingError::Request(e.to_string()))?
+}
+
+/// Hybrid search with the query embedded locally; text-only without a provider
+#[tauri::command]
+pub async fn semantic_search(
+    app: AppHandle,
+    mut query: HybridQuery,
+) -> Result<Vec<HybridHit>, EmbeddingError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        if let (None, Some(provider)) = (&query.vector, app.state::<EmbeddingWorker>().provider()) {
+            query.vector = provider.embed(&[query.text.clone()])?.pop();
+        }
+        Ok(hybrid::search(&app.state::<ProjectStore>().lock(), &query)?)
+    })
+    .await
+    .map_err(|e| EmbeddingError::Request(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SyncTable;
+    use crate::search::embeddings::HashingProvider;
+    use crate::search::vector::VectorQuery;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Hashing provider that counts calls and can be made to fail
+    struct Scripted {
+        inner: HashingProvider,
+        calls: AtomicUsize,
+        fail: bool,
+    }
+
+    impl Scripted {
+        fn new(fail: bool) -> Self {
+            Self {
+                inner: HashingProvider::new(64),
+                calls: AtomicUsize::new(0),
+                fail,
+            }
+        }
+    }
+
+    impl EmbeddingProvider for Scripted {
+        fn model(&self) -> &str {
+            self.inner.model()
+        }
+
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err(EmbeddingError::Request("connection refused".into()));
+            }
+            self.inner.embed(texts)
+        }
+    }
+
+    fn store() -> ProjectStore {
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .upsert(
+                SyncTable::Documents,
+                &json!({"id": "d1", "projectId": "p1", "title": "Storm",
+                        "contentText": "Thunder rolled over the harbour.\n\nThe keeper lit the lamp."}),
+            )
+            .unwrap();
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "e1", "projectId": "p1", "name": "Ada", "aliases": ["The Keeper"],
+                        "notes": "Keeps the lighthouse", "properties": {"age": 40}}),
+            )
+            .unwrap();
+        store
+            .upsert(
+                SyncTable::Documents,
+                &json!({"id": "empty", "projectId": "p1", "title": "Blank", "contentText": ""}),
+            )
+            .unwrap();
+        store
+    }
+
+    fn pending(store: &ProjectStore) -> Vec<String> {
+        list_jobs(&store.lock(), 100)
+            .unwrap()
+            .into_iter()
+            .map(|job| job.payload.target_id)
+            .collect()
+    }
+
+    #[test]
+    fn mirrors_server_text_preparation() {
+        assert_eq!(
+            chunk_text("One.\n\n\nTwo.\r\n\r\nThree is longer", 12),
+            ["One.\n\nTwo.", "Three is lon", "ger"]
+        );
+        let row = json!({"name": "Ada", "aliases": ["Addie", "The Keeper"], "notes": "Quiet.",
+                         "properties": {"age": 40}});
+        assert_eq!(
+            entity_text(row.as_object().unwrap()),
+            "Ada\nAliases: Addie, The Keeper\nQuiet.\nAttributes: {\"age\":40}"
+        );
+    }
+
+    #[test]
+    fn writes_queue_jobs_and_runs_embed_them() {
+        let store = store();
+        assert_eq!(pending(&store), ["d1", "e1", "empty"]);
+
+        let provider = Scripted::new(false);
+        let progress = run_jobs(&store, &provider, &RetryPolicy::default(), 0).unwrap();
+        assert_eq!(
+            progress,
+            EmbeddingProgress {
+                embedded: 2,
+                removed: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+        let query = provider
+            .inner
+            .embed(&["keeper lamp harbour".into()])
+            .unwrap();
+        let hits = vector::search(
+            &store.lock(),
+            &VectorQuery {
+                project_id: "p1".into(),
+                vector: query[0].clone(),
+                k: 5,
+                kinds: None,
+                model: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, "d1");
+
+        // Rewriting the same text is skipped without calling the provider
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "e1", "projectId": "p1", "name": "Ada", "aliases": ["The Keeper"],
+                        "notes": "Keeps the lighthouse", "properties": {"age": 40}, "color": "red"}),
+            )
+            .unwrap();
+        let progress = run_jobs(&store, &provider, &RetryPolicy::default(), 0).unwrap();
+        assert_eq!(progress.skipped, 1);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+        // Deleted rows lose their vectors
+        store.delete(SyncTable::Documents, "d1").unwrap();
+        run_jobs(&store, &provider, &RetryPolicy::default(), 0).unwrap();
+        let left: i64 = store
+            .lock()
+            .query_row("SELECT COUNT(*) FROM vectors", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(left, 1);
+    }
+
+    #[test]
+    fn failures_back_off_and_keep_the_job() {
+        let store = store();
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: 3,
+        };
+        let progress = run_jobs(&store, &Scripted::new(true), &policy, 1_000).unwrap();
+        assert_eq!(progress.failed, 2);
+        assert_eq!(progress.pending, 2);
+        assert_eq!(
+            progress.last_error.as_deref(),
+            Some("embedding request failed: connection refused")
+        );
+
+        let jobs = list_jobs(&store.lock(), 10).unwrap();
+        assert!(jobs
+            .iter()
+            .all(|j| j.attempts == 1 && j.next_attempt_at == 2_000));
+        assert_eq!(jobs[0].kind, EMBEDDING_GENERATION);
+
+        // Not due yet, then retried successfully
+        let provider = Scripted::new(false);
+        assert_eq!(
+            run_jobs(&store, &provider, &policy, 1_500)
+                .unwrap()
+                .embedded,
+            0
+        );
+        assert_eq!(
+            run_jobs(&store, &provider, &policy, 2_000)
+                .unwrap()
+                .embedded,
+            2
+        );
+        assert!(pending(&store).is_empty());
+    }
+
+    #[test]
+    fn changing_models_reembeds_everything() {
+        let store = store();
+        run_jobs(&store, &Scripted::new(false), &RetryPolicy::default(), 0).unwrap();
+        assert!(pending(&store).is_empty());
+
+        let other = HashingProvider::new(32);
+        assert_eq!(adopt_model(&store.lock(), other.model()).unwrap(), 2);
+        assert_eq!(pending(&store), ["d1", "empty", "e1"]);
+        let progress = run_jobs(&store, &other, &RetryPolicy::default(), 0).unwrap();
+        assert_eq!(progress.embedded, 2);
+    }
+
+    #[test]
+    fn changing_models_keeps_memory_vectors() {
+        let store = store();
+        run_jobs(&store, &Scripted::new(false), &RetryPolicy::default(), 0).unwrap();
+        vector::upsert_embeddings_in(
+            &store,
+            &[EmbeddingRecord {
+                kind: EmbeddingKind::Memory,
+                id: "m1".into(),
+                project_id: "p1".into(),
+                vector: vec![1.0, 0.0, 0.0],
+                model: Some("frontend".into()),
+                content_hash: None,
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(adopt_model(&store.lock(), "other").unwrap(), 2);
+        let memories: i64 = store
+            .lock()
+            .query_row(
+                "SELECT COUNT(*) FROM vectors WHERE kind = 'memory'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(memories, 1);
+        assert_eq!(pending(&store), ["d1", "empty", "e1"]);
+    }
+
+    #[test]
+    fn provider_survives_a_restart() {
+        let store = store();
+        let config = ProviderConfig::Hashing { dimensions: 32 };
+        EmbeddingWorker::default()
+            .configure(&store, Some(config.clone()))
+            .unwrap();
+
+        let worker = EmbeddingWorker::load(&store).unwrap();
+        assert_eq!(worker.config(), Some(config));
+        assert!(worker.provider().is_some());
+
+        worker.configure(&store, None).unwrap();
+        let worker = EmbeddingWorker::load(&store).unwrap();
+        assert_eq!(worker.config(), None);
+        assert!(worker.provider().is_none());
+    }
+}
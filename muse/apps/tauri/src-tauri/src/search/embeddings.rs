@@ -0,0 +1,351 @@
+//! Local embedding providers
+//!
+//! `EmbeddingProvider` turns text into vectors for the local index. Two
+//! implementations ship with the shell:
+//!
+//! - `HashingProvider` hashes tokens into a fixed number of buckets. It is
+//!   deterministic and needs no model, which makes it the test provider and a
+//!   usable keyword-ish fallback.
+//! - `OpenAiCompatibleProvider` calls an OpenAI-style `/embeddings` endpoint
+//!   (Ollama, LM Studio, llama.cpp server, ...). It only accepts loopback
+//!   URLs, so text never leaves the machine.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::vector::VectorError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbeddingError {
+    #[error("no embedding provider is configured")]
+    NotConfigured,
+    #[error("`{0}` is not a local address")]
+    NotLocal(String),
+    #[error("invalid provider URL: {0}")]
+    InvalidUrl(String),
+    #[error("embedding request failed: {0}")]
+    Request(String),
+    #[error("unexpected embedding response: {0}")]
+    InvalidResponse(String),
+    #[error(transparent)]
+    Vector(#[from] VectorError),
+}
+
+impl EmbeddingError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotConfigured => "embedding_not_configured",
+            Self::NotLocal(_) => "embedding_not_local",
+            Self::InvalidUrl(_) => "embedding_invalid_url",
+            Self::Request(_) => "embedding_request_failed",
+            Self::InvalidResponse(_) => "embedding_invalid_response",
+            Self::Vector(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(EmbeddingError);
+
+/// Turns text into embedding vectors
+pub trait EmbeddingProvider: Send + Sync {
+    /// Identifies the vector space; stored with each cached vector
+    fn model(&self) -> &str;
+
+    /// One vector per input, in order
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>;
+}
+
+/// Feature hashing over lowercased word tokens
+pub struct HashingProvider {
+    dimensions: usize,
+    model: String,
+}
+
+impl HashingProvider {
+    pub fn new(dimensions: usize) -> Self {
+        let dimensions = dimensions.max(1);
+        Self {
+            dimensions,
+            model: format!("hashing-v1-{}", dimensions),
+        }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let lowered = text.to_lowercase();
+        for token in lowered.split(|c: char| !c.is_alphanumeric()) {
+            if token.is_empty() {
+                continue;
+            }
+            // FNV-1a; the top bit picks the sign so collisions tend to cancel
+            let hash = token.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimensions as u64) as usize] += sign;
+        }
+        vector
+    }
+}
+
+impl EmbeddingProvider for HashingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// Client for a loopback OpenAI-compatible embeddings API
+pub struct OpenAiCompatibleProvider {
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+/// Whether `url` points at this machine
+fn is_loopback(url: &url::Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip).is_loopback(),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip).is_loopback(),
+        None => false,
+    }
+}
+
+impl OpenAiCompatibleProvider {
+    /// `base_url` is the API root, e.g. `http://localhost:11434/v1`
+    pub fn new(
+        base_url: &str,
+        model: String,
+        api_key: Option<String>,
+    ) -> Result<Self, EmbeddingError> {
+        let url =
+            url::Url::parse(base_url).map_err(|e| EmbeddingError::InvalidUrl(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(EmbeddingError::InvalidUrl(base_url.to_string()));
+        }
+        if !is_loopback(&url) {
+            return Err(EmbeddingError::NotLocal(base_url.to_string()));
+        }
+        Ok(Self {
+            endpoint: format!("{}/embeddings", base_url.trim_end_matches('/')),
+            model,
+            api_key,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(120))
+                .build(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingDatum>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingDatum {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: Option<usize>,
+}
+
+impl EmbeddingProvider for OpenAiCompatibleProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut request = self.agent.post(&self.endpoint);
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let response: EmbeddingResponse =
+            match request.send_json(json!({"model": self.model, "input": texts})) {
+                Ok(response) => response
+                    .into_json()
+                    .map_err(|e| EmbeddingError::InvalidResponse(e.to_string()))?,
+                Err(ureq::Error::Status(status, response)) => {
+                    let body = response.into_string().unwrap_or_default();
+                    return Err(EmbeddingError::Request(format!(
+                        "HTTP {}: {}",
+                        status,
+                        body.trim()
+                    )));
+                }
+                Err(e) => return Err(EmbeddingError::Request(e.to_string())),
+            };
+
+        if response.data.len() != texts.len() {
+            return Err(EmbeddingError::InvalidResponse(format!(
+                "{} embeddings for {} inputs",
+                response.data.len(),
+                texts.len()
+            )));
+        }
+        let mut data = response.data;
+        // Servers may answer out of order; `index` is authoritative when present
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+/// Provider selection as sent by the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ProviderConfig {
+    Hashing {
+        dimensions: usize,
+    },
+    OpenAiCompatible {
+        url: String,
+        model: String,
+        #[serde(default)]
+        api_key: Option<String>,
+    },
+}
+
+impl ProviderConfig {
+    pub fn build(&self) -> Result<Arc<dyn EmbeddingProvider>, EmbeddingError> {
+        Ok(match self {
+            Self::Hashing { dimensions } => Arc::new(HashingProvider::new(*dimensions)),
+            Self::OpenAiCompatible {
+                url,
+                model,
+                api_key,
+            } => Arc::new(OpenAiCompatibleProvider::new(
+                url,
+                model.clone(),
+                api_key.clone(),
+            )?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (norm(a) * norm(b))
+    }
+
+    #[test]
+    fn hashing_is_deterministic_and_word_sensitive() {
+        let provider = HashingProvider::new(256);
+        let texts = [
+            "The lighthouse keeper watched the storm".to_string(),
+            "the STORM, watched by the lighthouse keeper".to_string(),
+            "Recipes for lemon cake".to_string(),
+        ];
+        let first = provider.embed(&texts).unwrap();
+        assert_eq!(first, provider.embed(&texts).unwrap());
+        assert_eq!(first[0].len(), 256);
+        assert!(cosine(&first[0], &first[1]) > 0.9);
+        assert!(cosine(&first[0], &first[2]) < 0.3);
+        assert_eq!(provider.model(), "hashing-v1-256");
+    }
+
+    /// Embeddings endpoint answering once, in reverse order, recording the body
+    fn mock_embeddings_endpoint() -> (String, thread::JoinHandle<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("POST /v1/embeddings "));
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+            let response = json!({"object": "list", "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.0, 1.0]},
+                {"object": "embedding", "index": 0, "embedding": [1.0, 0.0]}
+            ]})
+            .to_string();
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn openai_adapter_posts_batches_and_orders_by_index() {
+        let (url, server) = mock_embeddings_endpoint();
+        let provider =
+            OpenAiCompatibleProvider::new(&url, "nomic-embed-text".into(), None).unwrap();
+        let vectors = provider
+            .embed(&["first".to_string(), "second".to_string()])
+            .unwrap();
+        assert_eq!(vectors, [vec![1.0, 0.0], vec![0.0, 1.0]]);
+        assert_eq!(
+            server.join().unwrap(),
+            json!({"model": "nomic-embed-text", "input": ["first", "second"]})
+        );
+    }
+
+    #[test]
+    fn openai_adapter_only_accepts_loopback_urls() {
+        for url in [
+            "http://localhost:11434/v1",
+            "http://127.0.0.1:1234/v1",
+            "http://[::1]:8080",
+        ] {
+            assert!(
+                OpenAiCompatibleProvider::new(url, "m".into(), None).is_ok(),
+                "{}",
+                url
+            );
+        }
+        for url in ["https://api.openai.com/v1", "http://192.168.1.20:11434/v1"] {
+            assert!(matches!(
+                OpenAiCompatibleProvider::new(url, "m".into(), None),
+                Err(EmbeddingError::NotLocal(_))
+            ));
+        }
+        assert!(matches!(
+            OpenAiCompatibleProvider::new("file:///tmp/socket", "m".into(), None),
+            Err(EmbeddingError::InvalidUrl(_))
+        ));
+    }
+}
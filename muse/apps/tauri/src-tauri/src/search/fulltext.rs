@@ -0,0 +1,656 @@
+//! Full-text index over documents and entities
+//!
+//! Two FTS5 tables (`documents_fts`, `entities_fts`) keyed by the rowid of
+//! the row they index. Queries are parsed here rather than passed to FTS5 as
+//! is, so user input can't produce a syntax error:
+//!
+//! - `word` matches the token (case and diacritics are ignored)
+//! - `"a phrase"` matches the tokens in order
+//! - `pre*` matches tokens starting with `pre`
+//! - `word~` (or every bare word with `fuzzy`) also matches indexed tokens
+//!   within one or two edits, found through the `fts5vocab` tables
+//!
+//! All terms must match. Hits carry highlight ranges in UTF-16 code units so
+//! the frontend can slice JS strings with them directly.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::bridge::protocol::EditorToNativeMessage;
+use crate::db::{ProjectStore, StoreError, SyncTable};
+
+/// Delimiters passed to `highlight()`/`snippet()`; stripped from indexed text
+const MARK_START: char = '\u{2}';
+const MARK_END: char = '\u{3}';
+
+/// Tokens of context around a snippet's matches
+const SNIPPET_TOKENS: i64 = 16;
+
+/// Most vocabulary terms a fuzzy term expands to
+const MAX_FUZZY_EXPANSIONS: usize = 16;
+
+fn default_limit() -> usize {
+    20
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HitKind {
+    Document,
+    Entity,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    pub project_id: String,
+    pub text: String,
+    /// Restrict to these kinds; all when omitted
+    #[serde(default)]
+    pub kinds: Option<Vec<HitKind>>,
+    /// Treat every bare word as `word~`
+    #[serde(default)]
+    pub fuzzy: bool,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Text with the ranges that matched, as `[start, end)` UTF-16 offsets
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Highlighted {
+    pub text: String,
+    pub ranges: Vec<[usize; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub kind: HitKind,
+    pub id: String,
+    /// Document title or entity name
+    pub title: Highlighted,
+    pub snippet: Highlighted,
+    /// Higher is better; comparable across kinds within one query
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryTerm {
+    Word(String),
+    Prefix(String),
+    Fuzzy(String),
+    Phrase(Vec<String>),
+}
+
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Split user input into terms (see the module docs for the syntax)
+pub fn parse_query(text: &str, fuzzy: bool) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('"') {
+            // An unclosed quote runs to the end of the input
+            let (inner, tail) = after.split_once('"').unwrap_or((after, ""));
+            let words = tokens(inner);
+            if !words.is_empty() {
+                terms.push(QueryTerm::Phrase(words));
+            }
+            rest = tail;
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        let (chunk, modifier) = match chunk.char_indices().last() {
+            Some((i, c @ ('*' | '~'))) => (&chunk[..i], Some(c)),
+            _ => (chunk, None),
+        };
+        let mut words = tokens(chunk);
+        let term = match (words.len(), modifier) {
+            (0, _) => continue,
+            (1, Some('*')) => QueryTerm::Prefix(words.remove(0)),
+            (1, Some('~')) => QueryTerm::Fuzzy(words.remove(0)),
+            (1, _) if fuzzy => QueryTerm::Fuzzy(words.remove(0)),
+            (1, _) => QueryTerm::Word(words.remove(0)),
+            // `don't`, `half-elf`: tokenized apart at index time too
+            _ => QueryTerm::Phrase(words),
+        };
+        terms.push(term);
+    }
+    terms
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != *cb);
+            row.push(substitute.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+/// Edits a fuzzy term of `len` characters tolerates
+fn max_edits(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Indexed terms close to `term`, nearest and most common first
+fn fuzzy_candidates(conn: &Connection, vocab: &str, term: &str) -> Result<Vec<String>, StoreError> {
+    let len = term.chars().count();
+    let edits = max_edits(len);
+    if edits == 0 {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT term, doc FROM {} WHERE length(term) BETWEEN ?1 AND ?2",
+        vocab
+    ))?;
+    let rows = stmt.query_map(
+        params![len.saturating_sub(edits) as i64, (len + edits) as i64],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+    )?;
+    let mut candidates = Vec::new();
+    for row in rows {
+        let (candidate, docs) = row?;
+        let distance = edit_distance(term, &candidate);
+        if distance > 0 && distance <= edits {
+            candidates.push((distance, -docs, candidate));
+        }
+    }
+    candidates.sort();
+    Ok(candidates
+        .into_iter()
+        .take(MAX_FUZZY_EXPANSIONS)
+        .map(|(_, _, candidate)| candidate)
+        .collect())
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// FTS5 MATCH expression for `terms` against one table's vocabulary
+fn match_expression(
+    conn: &Connection,
+    vocab: &str,
+    terms: &[QueryTerm],
+) -> Result<String, StoreError> {
+    let mut parts = Vec::with_capacity(terms.len());
+    for term in terms {
+        parts.push(match term {
+            QueryTerm::Word(word) => quote(word),
+            QueryTerm::Prefix(prefix) => format!("{} *", quote(prefix)),
+            QueryTerm::Phrase(words) => quote(&words.join(" ")),
+            QueryTerm::Fuzzy(word) => {
+                let mut alternatives = vec![quote(word)];
+                alternatives.extend(
+                    fuzzy_candidates(conn, vocab, word)?
+                        .iter()
+                        .map(|c| quote(c)),
+                );
+                format!("({})", alternatives.join(" OR "))
+            }
+        });
+    }
+    Ok(parts.join(" AND "))
+}
+
+/// Split `highlight()`/`snippet()` output into text and ranges
+fn highlighted(marked: &str) -> Highlighted {
+    let mut result = Highlighted::default();
+    let mut offset = 0;
+    let mut start = None;
+    for c in marked.chars() {
+        match c {
+            MARK_START => start = Some(offset),
+            MARK_END => {
+                if let Some(start) = start.take() {
+                    result.ranges.push([start, offset]);
+                }
+            }
+            c => {
+                result.text.push(c);
+                offset += c.len_utf16();
+            }
+        }
+    }
+    result
+}
+
+fn clean(text: &str) -> String {
+    text.replace([MARK_START, MARK_END], "")
+}
+
+fn collect_text(node: &Value, out: &mut String) {
+    match node.get("type").and_then(Value::as_str) {
+        Some("text") => {
+            if let Some(text) = node.get("text").and_then(Value::as_str) {
+                out.push_str(text);
+            }
+            return;
+        }
+        Some("hardBreak") => {
+            out.push('\n');
+            return;
+        }
+        _ => {}
+    }
+    if let Some(children) = node.get("content").and_then(Value::as_array) {
+        for child in children {
+            collect_text(child, out);
+        }
+        // Blocks end a line so words in adjacent paragraphs don't join
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+}
+
+/// Plain text of a document row: the server's `contentText` when present,
+/// otherwise the text of its ProseMirror `content`
+pub fn document_text(row: &Map<String, Value>) -> String {
+    if let Some(text) = row.get("contentText").and_then(Value::as_str) {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    match row.get("content") {
+        // Some rows carry the JSON serialized
+        Some(Value::String(content)) => match serde_json::from_str::<Value>(content) {
+            Ok(json) => collect_text(&json, &mut out),
+            Err(_) => out.push_str(content),
+        },
+        Some(content) => collect_text(content, &mut out),
+        None => {}
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn string_field(row: &Map<String, Value>, key: &str) -> String {
+    clean(row.get(key).and_then(Value::as_str).unwrap_or_default())
+}
+
+/// (Re)index a stored row; tables other than documents and entities are skipped
+pub fn index_row(
+    conn: &Connection,
+    table: SyncTable,
+    rowid: i64,
+    row: &Map<String, Value>,
+) -> Result<(), StoreError> {
+    match table {
+        SyncTable::Documents => {
+            conn.execute("DELETE FROM documents_fts WHERE rowid = ?1", params![rowid])?;
+            conn.execute(
+                "INSERT INTO documents_fts (rowid, title, body) VALUES (?1, ?2, ?3)",
+                params![
+                    rowid,
+                    string_field(row, "title"),
+                    clean(&document_text(row))
+                ],
+            )?;
+        }
+        SyncTable::Entities => {
+            let aliases: Vec<&str> = row
+                .get("aliases")
+                .and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            conn.execute("DELETE FROM entities_fts WHERE rowid = ?1", params![rowid])?;
+            conn.execute(
+                "INSERT INTO entities_fts (rowid, name, aliases, notes) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    rowid,
+                    string_field(row, "name"),
+                    clean(&aliases.join("\n")),
+                    string_field(row, "notes")
+                ],
+            )?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Reindex every document and entity, or only those of one project
+pub fn rebuild(conn: &Connection, project_id: Option<&str>) -> Result<(), StoreError> {
+    for table in [SyncTable::Documents, SyncTable::Entities] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, data FROM {} WHERE ?1 IS NULL OR project_id = ?1",
+            table.as_str()
+        ))?;
+        let rows = stmt
+            .query_map(params![project_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (rowid, data) in rows {
+            if let Value::Object(row) = serde_json::from_str(&data)? {
+                index_row(conn, table, rowid, &row)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Update a stored document's indexed body or title from editor traffic,
+/// ahead of the row itself being saved
+pub fn apply_editor_message(
+    conn: &Connection,
+    document_id: &str,
+    message: &EditorToNativeMessage,
+) -> Result<(), StoreError> {
+    let (column, text) = match message {
+        EditorToNativeMessage::ContentChange { content, .. } => ("body", content),
+        EditorToNativeMessage::TitleChange { title } => ("title", title),
+        _ => return Ok(()),
+    };
+    conn.execute(
+        &format!(
+            "UPDATE documents_fts SET {} = ?2
+             WHERE rowid = (SELECT rowid FROM documents WHERE id = ?1)",
+            column
+        ),
+        params![document_id, clean(text)],
+    )?;
+    Ok(())
+}
+
+pub fn search(conn: &Connection, query: &SearchQuery) -> Result<Vec<SearchHit>, StoreError> {
+    let terms = parse_query(&query.text, query.fuzzy);
+    if terms.is_empty() || query.limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut hits = Vec::new();
+    for kind in [HitKind::Document, HitKind::Entity] {
+        if query
+            .kinds
+            .as_ref()
+            .is_some_and(|kinds| !kinds.contains(&kind))
+        {
+            continue;
+        }
+        // Title/name matches outrank body matches
+        let (sql, vocab) = match kind {
+            HitKind::Document => (
+                "SELECT d.id,
+                        highlight(documents_fts, 0, char(2), char(3)),
+                        snippet(documents_fts, 1, char(2), char(3), '…', ?4),
+                        bm25(documents_fts, 10.0, 1.0) AS score
+                 FROM documents_fts JOIN documents d ON d.rowid = documents_fts.rowid
+                 WHERE documents_fts MATCH ?1 AND d.project_id = ?2
+                 ORDER BY score LIMIT ?3",
+                "documents_vocab",
+            ),
+            HitKind::Entity => (
+                "SELECT e.id,
+                        highlight(entities_fts, 0, char(2), char(3)),
+                        snippet(entities_fts, -1, char(2), char(3), '…', ?4),
+                        bm25(entities_fts, 10.0, 5.0, 1.0) AS score
+                 FROM entities_fts JOIN entities e ON e.rowid = entities_fts.rowid
+                 WHERE entities_fts MATCH ?1 AND e.project_id = ?2
+                 ORDER BY score LIMIT ?3",
+                "entities_vocab",
+            ),
+        };
+        let expression = match_expression(conn, vocab, &terms)?;
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(
+            params![
+                expression,
+                query.project_id,
+                query.limit as i64,
+                SNIPPET_TOKENS
+            ],
+            |row| {
+                Ok(SearchHit {
+                    kind,
+                    id: row.get(0)?,
+                    title: highlighted(&row.get::<_, String>(1)?),
+                    snippet: highlighted(&row.get::<_, String>(2)?),
+                    // bm25() is lower-is-better
+                    score: -row.get::<_, f64>(3)?,
+                })
+            },
+        )?;
+        for hit in rows {
+            hits.push(hit?);
+        }
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(query.limit);
+    Ok(hits)
+}
+
+#[tauri::command]
+pub fn search_local(
+    store: State<'_, ProjectStore>,
+    query: SearchQuery,
+) -> Result<Vec<SearchHit>, StoreError> {
+    store.with_conn(|conn| search(conn, &query))
+}
+
+/// Reindex from the stored rows, for one project or all of them
+#[tauri::command(rename_all = "camelCase")]
+pub fn rebuild_search_index(
+    store: State<'_, ProjectStore>,
+    project_id: Option<String>,
+) -> Result<(), StoreError> {
+    store.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        rebuild(&tx, project_id.as_deref())?;
+        tx.commit()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store() -> ProjectStore {
+        let store = ProjectStore::open_in_memory().unwrap();
+        let rows = [
+            (
+                SyncTable::Documents,
+                json!({
+                    "id": "ch1", "projectId": "p1", "type": "chapter", "title": "The Harbour",
+                    "content": {"type": "doc", "content": [
+                        {"type": "paragraph", "content": [
+                            {"type": "text", "text": "Elizabeth walked to the "},
+                            {"type": "text", "text": "light", "marks": [{"type": "bold"}]},
+                            {"type": "text", "text": "house at dawn."}
+                        ]},
+                        {"type": "paragraph", "content": [{"type": "text", "text": "The sea was grey."}]}
+                    ]}
+                }),
+            ),
+            (
+                SyncTable::Documents,
+                json!({"id": "sc1", "projectId": "p1", "parentId": "ch1", "title": "Storm",
+                       "contentText": "Thunder rolled over the grey water."}),
+            ),
+            (
+                SyncTable::Entities,
+                json!({"id": "e1", "projectId": "p1", "name": "Elizabeth Bennet",
+                       "aliases": ["Lizzy", "Eliza"], "notes": "Second daughter, sharp-witted."}),
+            ),
+            (
+                SyncTable::Documents,
+                json!({"id": "other", "projectId": "p2", "title": "Elsewhere",
+                       "contentText": "Elizabeth is in another project."}),
+            ),
+        ];
+        for (table, row) in rows {
+            store.upsert(table, &row).unwrap();
+        }
+        store
+    }
+
+    fn query(text: &str) -> SearchQuery {
+        SearchQuery {
+            project_id: "p1".into(),
+            text: text.into(),
+            kinds: None,
+            fuzzy: false,
+            limit: 20,
+        }
+    }
+
+    fn ids(store: &ProjectStore, query: &SearchQuery) -> Vec<String> {
+        let mut ids: Vec<String> = store
+            .with_conn(|conn| search(conn, query))
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn parses_query_syntax() {
+        assert_eq!(
+            parse_query(r#"storm "grey  water" light* Elizbeth~ half-elf"#, false),
+            [
+                QueryTerm::Word("storm".into()),
+                QueryTerm::Phrase(vec!["grey".into(), "water".into()]),
+                QueryTerm::Prefix("light".into()),
+                QueryTerm::Fuzzy("elizbeth".into()),
+                QueryTerm::Phrase(vec!["half".into(), "elf".into()]),
+            ]
+        );
+        assert_eq!(
+            parse_query("sea \"unclosed", true),
+            [
+                QueryTerm::Fuzzy("sea".into()),
+                QueryTerm::Phrase(vec!["unclosed".into()]),
+            ]
+        );
+        assert!(parse_query(" * ~ \"\" ", false).is_empty());
+    }
+
+    #[test]
+    fn extracts_prosemirror_text_by_block() {
+        let row = json!({"content": {"type": "doc", "content": [
+            {"type": "heading", "content": [{"type": "text", "text": "One"}]},
+            {"type": "paragraph", "content": [
+                {"type": "text", "text": "to"}, {"type": "text", "text": "gether"},
+                {"type": "hardBreak"}, {"type": "text", "text": "again"}
+            ]}
+        ]}});
+        assert_eq!(
+            document_text(row.as_object().unwrap()),
+            "One\ntogether\nagain"
+        );
+    }
+
+    #[test]
+    fn matches_words_phrases_and_prefixes_within_the_project() {
+        let store = store();
+        assert_eq!(ids(&store, &query("elizabeth")), ["ch1", "e1"]);
+        assert_eq!(ids(&store, &query("lighthouse")), ["ch1"]);
+        assert_eq!(ids(&store, &query("\"grey water\"")), ["sc1"]);
+        assert!(ids(&store, &query("\"water grey\"")).is_empty());
+        assert_eq!(ids(&store, &query("liz*")), ["e1"]);
+        assert_eq!(ids(&store, &query("grey thunder")), ["sc1"]);
+
+        let mut entities_only = query("elizabeth");
+        entities_only.kinds = Some(vec![HitKind::Entity]);
+        assert_eq!(ids(&store, &entities_only), ["e1"]);
+    }
+
+    #[test]
+    fn fuzzy_terms_tolerate_typos() {
+        let store = store();
+        assert!(ids(&store, &query("Elizbeth")).is_empty());
+        assert_eq!(ids(&store, &query("Elizbeth~")), ["ch1", "e1"]);
+
+        let mut fuzzy = query("thundr");
+        fuzzy.fuzzy = true;
+        assert_eq!(ids(&store, &fuzzy), ["sc1"]);
+    }
+
+    #[test]
+    fn highlights_in_utf16_offsets() {
+        let store = store();
+        store
+            .upsert(
+                SyncTable::Documents,
+                &json!({"id": "emoji", "projectId": "p1", "title": "🌊 Tide", "contentText": "x"}),
+            )
+            .unwrap();
+        let hits = store
+            .with_conn(|conn| search(conn, &query("tide")))
+            .unwrap();
+        assert_eq!(
+            hits[0].title,
+            Highlighted {
+                text: "🌊 Tide".into(),
+                ranges: vec![[3, 7]],
+            }
+        );
+
+        let hits = store
+            .with_conn(|conn| search(conn, &query("sharp")))
+            .unwrap();
+        let snippet = &hits[0].snippet;
+        let [start, end] = snippet.ranges[0];
+        let utf16: Vec<u16> = snippet.text.encode_utf16().collect();
+        assert_eq!(String::from_utf16(&utf16[start..end]).unwrap(), "sharp");
+    }
+
+    #[test]
+    fn follows_edits_deletes_and_editor_changes() {
+        let store = store();
+        let change = EditorToNativeMessage::ContentChange {
+            content: "A lantern flickered.".into(),
+            html: String::new(),
+            json: Value::Null,
+        };
+        store
+            .with_conn(|conn| apply_editor_message(conn, "sc1", &change))
+            .unwrap();
+        assert_eq!(ids(&store, &query("lantern")), ["sc1"]);
+        assert!(ids(&store, &query("thunder")).is_empty());
+
+        store
+            .upsert(
+                SyncTable::Entities,
+                &json!({"id": "e1", "projectId": "p1", "name": "Jane Bennet"}),
+            )
+            .unwrap();
+        assert!(ids(&store, &query("lizzy")).is_empty());
+        assert_eq!(ids(&store, &query("jane")), ["e1"]);
+
+        // Deleting the chapter drops its scenes from the index too
+        store.delete(SyncTable::Documents, "ch1").unwrap();
+        assert!(ids(&store, &query("lantern")).is_empty());
+        assert!(ids(&store, &query("harbour")).is_empty());
+    }
+}
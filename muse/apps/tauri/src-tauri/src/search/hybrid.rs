@@ -0,0 +1,197 @@
+//! Hybrid ranking
+//!
+//! Fuses full-text and vector results with reciprocal rank fusion: a hit
+//! scores `1 / (RRF_K + rank)` in each list it appears in, so rows that both
+//! match the words and sit near the query embedding rise to the top without
+//! calibrating bm25 against cosine similarity.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::fulltext::{self, HitKind, SearchHit, SearchQuery};
+use super::vector::{self, EmbeddingKind, VectorError, VectorQuery};
+use crate::db::ProjectStore;
+
+/// Rank offset; damps the difference between the first few places
+pub const RRF_K: f64 = 60.0;
+
+fn default_limit() -> usize {
+    20
+}
+
+impl From<HitKind> for EmbeddingKind {
+    fn from(kind: HitKind) -> Self {
+        match kind {
+            HitKind::Document => EmbeddingKind::Document,
+            HitKind::Entity => EmbeddingKind::Entity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HybridQuery {
+    pub project_id: String,
+    pub text: String,
+    /// Query embedding; text-only ranking without it
+    #[serde(default)]
+    pub vector: Option<Vec<f32>>,
+    #[serde(default)]
+    pub fuzzy: bool,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HybridHit {
+    pub kind: EmbeddingKind,
+    pub id: String,
+    pub score: f64,
+    /// The full-text hit, with highlights, when the words matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<SearchHit>,
+    /// Cosine similarity, when the row has a cached embedding nearby
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+fn slot<'a>(
+    fused: &'a mut HashMap<(EmbeddingKind, String), HybridHit>,
+    kind: EmbeddingKind,
+    id: &str,
+) -> &'a mut HybridHit {
+    fused
+        .entry((kind, id.to_string()))
+        .or_insert_with(|| HybridHit {
+            kind,
+            id: id.to_string(),
+            score: 0.0,
+            text: None,
+            similarity: None,
+        })
+}
+
+pub fn search(conn: &Connection, query: &HybridQuery) -> Result<Vec<HybridHit>, VectorError> {
+    // Each list contributes more candidates than the final page
+    let depth = query.limit * 2;
+    let text_hits = fulltext::search(
+        conn,
+        &SearchQuery {
+            project_id: query.project_id.clone(),
+            text: query.text.clone(),
+            kinds: None,
+            fuzzy: query.fuzzy,
+            limit: depth,
+        },
+    )?;
+    let vector_hits = match &query.vector {
+        Some(vector) => vector::search(
+            conn,
+            &VectorQuery {
+                project_id: query.project_id.clone(),
+                vector: vector.clone(),
+                k: depth,
+                kinds: None,
+                model: None,
+            },
+        )?,
+        None => Vec::new(),
+    };
+
+    let mut fused: HashMap<(EmbeddingKind, String), HybridHit> = HashMap::new();
+    for (rank, hit) in text_hits.into_iter().enumerate() {
+        let fused_hit = slot(&mut fused, hit.kind.into(), &hit.id);
+        fused_hit.score += 1.0 / (RRF_K + rank as f64 + 1.0);
+        fused_hit.text = Some(hit);
+    }
+    for (rank, hit) in vector_hits.into_iter().enumerate() {
+        let fused_hit = slot(&mut fused, hit.kind, &hit.id);
+        fused_hit.score += 1.0 / (RRF_K + rank as f64 + 1.0);
+        fused_hit.similarity = Some(hit.score);
+    }
+
+    let mut hits: Vec<HybridHit> = fused.into_values().collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+    hits.truncate(query.limit);
+    Ok(hits)
+}
+
+#[tauri::command]
+pub fn hybrid_search(
+    store: State<'_, ProjectStore>,
+    query: HybridQuery,
+) -> Result<Vec<HybridHit>, VectorError> {
+    search(&store.lock(), &query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SyncTable;
+    use crate::search::vector::{upsert_embeddings_in, EmbeddingRecord};
+    use serde_json::json;
+
+    #[test]
+    fn ranks_rows_found_both_ways_first() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        for (id, text) in [
+            ("storm", "Thunder over the harbour"),
+            ("harbour", "The harbour at dawn, quiet and grey"),
+            ("market", "Fishmongers shouting in the market"),
+        ] {
+            store
+                .upsert(
+                    SyncTable::Documents,
+                    &json!({"id": id, "projectId": "p1", "title": id, "contentText": text}),
+                )
+                .unwrap();
+        }
+        let embed = |kind, id: &str, vector: [f32; 2]| EmbeddingRecord {
+            kind,
+            id: id.into(),
+            project_id: "p1".into(),
+            vector: vector.to_vec(),
+            model: None,
+            content_hash: None,
+        };
+        upsert_embeddings_in(
+            &store,
+            &[
+                embed(EmbeddingKind::Document, "storm", [1.0, 0.1]),
+                embed(EmbeddingKind::Document, "market", [0.0, 1.0]),
+                embed(EmbeddingKind::Memory, "m1", [0.9, 0.2]),
+            ],
+        )
+        .unwrap();
+
+        let query = HybridQuery {
+            project_id: "p1".into(),
+            text: "harbour".into(),
+            vector: Some(vec![1.0, 0.0]),
+            fuzzy: false,
+            limit: 10,
+        };
+        let hits = search(&store.lock(), &query).unwrap();
+        let ids: Vec<&str> = hits.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids[0], "storm");
+        assert!(hits[0].text.is_some() && hits[0].similarity.is_some());
+        assert!(ids.contains(&"harbour") && ids.contains(&"m1"));
+        assert_eq!(ids.last(), Some(&"market"));
+
+        // Without an embedding it is plain full-text ranking
+        let text_only = search(
+            &store.lock(),
+            &HybridQuery {
+                vector: None,
+                ..query
+            },
+        )
+        .unwrap();
+        assert_eq!(text_only.len(), 2);
+        assert!(text_only.iter().all(|h| h.similarity.is_none()));
+    }
+}
@@ -0,0 +1,17 @@
+//! Local search
+//!
+//! Indexes live in the project store next to the rows they cover, so search
+//! works offline. `fulltext` is an FTS5 index over document text and entity
+//! names, aliases and notes, kept current by `db::put_row` and by the
+//! editor's `contentChange`/`titleChange` bridge messages. `vector` caches
+//! embeddings in an HNSW graph for similarity search, and `hybrid` fuses the
+//! two rankings.
+//! `embeddings` defines pluggable local embedding providers, and
+//! `embedding_jobs` keeps the vector cache current with them in the
+//! background.
+
+pub mod embedding_jobs;
+pub mod embeddings;
+pub mod fulltext;
+pub mod hybrid;
+pub mod vector;
@@ -0,0 +1,860 @@
+//! Cached embeddings with an approximate nearest neighbour index
+//!
+//! Embeddings computed by the server, or locally by `embedding_jobs`, are
+//! kept in the project store so similarity search works offline. Vectors from
+//! different models can't be compared, so each project and model pair has its
+//! own HNSW graph and dimension: a node's layer is derived from a hash of its
+//! key, the entry point is the graph's highest node, and neighbour lists live
+//! in `vector_edges`. Vectors are normalized on insert, so similarity is
+//! cosine.
+//!
+//! Removing a node through `remove` reconnects its neighbours. Rows deleted by
+//! the `documents`/`entities` triggers leave dangling edges behind, which
+//! searches skip.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+use crate::db::{ProjectStore, StoreError};
+
+/// Neighbours per node on upper layers; layer 0 keeps twice as many
+pub const MAX_NEIGHBORS: usize = 16;
+
+/// Candidate list size while inserting
+const EF_CONSTRUCTION: usize = 100;
+
+/// Minimum candidate list size while searching
+const EF_SEARCH: usize = 64;
+
+/// Layers above this are never assigned
+const MAX_LEVEL: usize = 12;
+
+fn default_k() -> usize {
+    10
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VectorError {
+    #[error("expected a {expected}-dimensional vector, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+    #[error("vector is empty, zero or not finite")]
+    InvalidVector,
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl From<rusqlite::Error> for VectorError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Store(e.into())
+    }
+}
+
+impl VectorError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DimensionMismatch { .. } => "vector_dimension_mismatch",
+            Self::InvalidVector => "vector_invalid",
+            Self::Store(e) => e.code(),
+        }
+    }
+}
+
+crate::db::serialize_error!(VectorError);
+
+/// What an embedding was computed from (the server embeds the same three)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingKind {
+    Document,
+    Entity,
+    Memory,
+}
+
+impl EmbeddingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Document => "document",
+            Self::Entity => "entity",
+            Self::Memory => "memory",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        [Self::Document, Self::Entity, Self::Memory]
+            .into_iter()
+            .find(|k| k.as_str() == s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingRecord {
+    pub kind: EmbeddingKind,
+    pub id: String,
+    pub project_id: String,
+    pub vector: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Hash of the embedded text, to tell when it needs re-embedding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorQuery {
+    pub project_id: String,
+    pub vector: Vec<f32>,
+    #[serde(default = "default_k")]
+    pub k: usize,
+    #[serde(default)]
+    pub kinds: Option<Vec<EmbeddingKind>>,
+    /// Search this model's embeddings; by default, those of the model most
+    /// recently cached with the query's dimensions
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorHit {
+    pub kind: EmbeddingKind,
+    pub id: String,
+    /// Cosine similarity
+    pub score: f32,
+}
+
+/// Candidate ordered by distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: i64,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+fn normalize(vector: &[f32]) -> Result<Vec<f32>, VectorError> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if vector.is_empty() || !norm.is_finite() || norm == 0.0 {
+        return Err(VectorError::InvalidVector);
+    }
+    Ok(vector.iter().map(|x| x / norm).collect())
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+fn encode_nodes(nodes: &[i64]) -> Vec<u8> {
+    nodes.iter().flat_map(|n| n.to_le_bytes()).collect()
+}
+
+fn decode_nodes(bytes: &[u8]) -> Vec<i64> {
+    bytes
+        .chunks_exact(8)
+        .map(|c| i64::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+/// HNSW layer for a key: geometric with ratio 1/M, stable across re-inserts
+fn level_for(kind: EmbeddingKind, id: &str) -> usize {
+    let digest = Sha256::digest(format!("{}\0{}", kind.as_str(), id));
+    let bits = u64::from_le_bytes(digest[..8].try_into().unwrap()) >> 11;
+    let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+    let level = -uniform.ln() / (MAX_NEIGHBORS as f64).ln();
+    (level as usize).min(MAX_LEVEL)
+}
+
+fn layer_capacity(layer: usize) -> usize {
+    if layer == 0 {
+        2 * MAX_NEIGHBORS
+    } else {
+        MAX_NEIGHBORS
+    }
+}
+
+fn node_vector(conn: &Connection, node: i64) -> Result<Option<Vec<f32>>, VectorError> {
+    let bytes: Option<Vec<u8>> = conn
+        .prepare_cached("SELECT vector FROM vectors WHERE node = ?1")?
+        .query_row(params![node], |row| row.get(0))
+        .optional()?;
+    Ok(bytes.map(|b| decode_vector(&b)))
+}
+
+fn neighbors(conn: &Connection, node: i64, layer: usize) -> Result<Vec<i64>, VectorError> {
+    let bytes: Option<Vec<u8>> = conn
+        .prepare_cached("SELECT neighbors FROM vector_edges WHERE node = ?1 AND layer = ?2")?
+        .query_row(params![node, layer as i64], |row| row.get(0))
+        .optional()?;
+    Ok(bytes.map(|b| decode_nodes(&b)).unwrap_or_default())
+}
+
+fn set_neighbors(
+    conn: &Connection,
+    node: i64,
+    layer: usize,
+    nodes: &[i64],
+) -> Result<(), VectorError> {
+    conn.prepare_cached(
+        "INSERT INTO vector_edges (node, layer, neighbors) VALUES (?1, ?2, ?3)
+         ON CONFLICT(node, layer) DO UPDATE SET neighbors = excluded.neighbors",
+    )?
+    .execute(params![node, layer as i64, encode_nodes(nodes)])?;
+    Ok(())
+}
+
+/// The graph's highest node, where searches start
+fn entry_point(
+    conn: &Connection,
+    project_id: &str,
+    model: Option<&str>,
+) -> Result<Option<(i64, usize)>, VectorError> {
+    Ok(conn
+        .prepare_cached(
+            "SELECT node, level FROM vectors WHERE project_id = ?1 AND model IS ?2
+             ORDER BY level DESC, node LIMIT 1",
+        )?
+        .query_row(params![project_id, model], |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as usize))
+        })
+        .optional()?)
+}
+
+/// Dimensions of a model's vectors in a project, `None` while it has none
+pub fn dimensions(
+    conn: &Connection,
+    project_id: &str,
+    model: Option<&str>,
+) -> Result<Option<usize>, VectorError> {
+    Ok(conn
+        .prepare_cached(
+            "SELECT length(vector) / 4 FROM vectors WHERE project_id = ?1 AND model IS ?2 LIMIT 1",
+        )?
+        .query_row(params![project_id, model], |row| row.get::<_, i64>(0))
+        .optional()?
+        .map(|d| d as usize))
+}
+
+fn check_dimensions(
+    conn: &Connection,
+    project_id: &str,
+    model: Option<&str>,
+    vector: &[f32],
+) -> Result<(), VectorError> {
+    match dimensions(conn, project_id, model)? {
+        Some(expected) if expected != vector.len() => Err(VectorError::DimensionMismatch {
+            expected,
+            actual: vector.len(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The model whose graph a query searches, `None` if the project has no
+/// vectors to search
+fn query_model(
+    conn: &Connection,
+    query: &VectorQuery,
+    vector: &[f32],
+) -> Result<Option<Option<String>>, VectorError> {
+    if let Some(model) = &query.model {
+        check_dimensions(conn, &query.project_id, Some(model), vector)?;
+        return Ok(Some(Some(model.clone())));
+    }
+    let latest: Option<(Option<String>, i64)> = conn
+        .query_row(
+            "SELECT model, length(vector) / 4 FROM vectors WHERE project_id = ?1
+             ORDER BY length(vector) / 4 = ?2 DESC, node DESC LIMIT 1",
+            params![query.project_id, vector.len() as i64],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    match latest {
+        Some((model, dims)) if dims as usize == vector.len() => Ok(Some(model)),
+        Some((_, dims)) => Err(VectorError::DimensionMismatch {
+            expected: dims as usize,
+            actual: vector.len(),
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Best-first search of one layer from `entry`, returning up to `ef` nodes
+/// nearest first
+fn search_layer(
+    conn: &Connection,
+    query: &[f32],
+    entry: &[Candidate],
+    ef: usize,
+    layer: usize,
+) -> Result<Vec<Candidate>, VectorError> {
+    let mut visited: HashSet<i64> = entry.iter().map(|c| c.node).collect();
+    let mut frontier: BinaryHeap<std::cmp::Reverse<Candidate>> =
+        entry.iter().copied().map(std::cmp::Reverse).collect();
+    let mut best: BinaryHeap<Candidate> = entry.iter().copied().collect();
+
+    while let Some(std::cmp::Reverse(current)) = frontier.pop() {
+        if best.len() >= ef
+            && best
+                .peek()
+                .is_some_and(|worst| current.distance > worst.distance)
+        {
+            break;
+        }
+        for node in neighbors(conn, current.node, layer)? {
+            if !visited.insert(node) {
+                continue;
+            }
+            // Dangling edge to a row removed by trigger
+            let Some(vector) = node_vector(conn, node)? else {
+                continue;
+            };
+            let candidate = Candidate {
+                distance: distance(query, &vector),
+                node,
+            };
+            if best.len() < ef || best.peek().is_some_and(|worst| candidate < *worst) {
+                frontier.push(std::cmp::Reverse(candidate));
+                best.push(candidate);
+                if best.len() > ef {
+                    best.pop();
+                }
+            }
+        }
+    }
+    Ok(best.into_sorted_vec())
+}
+
+/// Descend from the entry point to `layer`, keeping the single nearest node
+fn descend(
+    conn: &Connection,
+    query: &[f32],
+    project_id: &str,
+    model: Option<&str>,
+    layer: usize,
+) -> Result<Option<(Vec<Candidate>, usize)>, VectorError> {
+    let Some((entry, top)) = entry_point(conn, project_id, model)? else {
+        return Ok(None);
+    };
+    let vector = node_vector(conn, entry)?.unwrap_or_default();
+    let mut nearest = vec![Candidate {
+        distance: distance(query, &vector),
+        node: entry,
+    }];
+    for upper in (layer + 1..=top).rev() {
+        nearest = search_layer(conn, query, &nearest, 1, upper)?;
+    }
+    Ok(Some((nearest, top)))
+}
+
+/// Keep the `capacity` nodes nearest to `node`'s vector
+fn nearest_to(
+    conn: &Connection,
+    vector: &[f32],
+    nodes: impl IntoIterator<Item = i64>,
+    capacity: usize,
+) -> Result<Vec<i64>, VectorError> {
+    let mut candidates = Vec::new();
+    for node in nodes {
+        if let Some(other) = node_vector(conn, node)? {
+            candidates.push(Candidate {
+                distance: distance(vector, &other),
+                node,
+            });
+        }
+    }
+    candidates.sort();
+    candidates.dedup_by_key(|c| c.node);
+    Ok(candidates
+        .into_iter()
+        .take(capacity)
+        .map(|c| c.node)
+        .collect())
+}
+
+/// Cache an embedding, replacing any previous one for the same row
+pub fn insert(conn: &Connection, record: &EmbeddingRecord) -> Result<(), VectorError> {
+    let vector = normalize(&record.vector)?;
+    remove(conn, record.kind, &record.id)?;
+    let model = record.model.as_deref();
+    check_dimensions(conn, &record.project_id, model, &vector)?;
+
+    let level = level_for(record.kind, &record.id);
+    let descent = descend(conn, &vector, &record.project_id, model, level)?;
+    let node: i64 = conn.query_row(
+        "INSERT INTO vectors (kind, id, project_id, level, model, content_hash, vector)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) RETURNING node",
+        params![
+            record.kind.as_str(),
+            record.id,
+            record.project_id,
+            level as i64,
+            record.model,
+            record.content_hash,
+            encode_vector(&vector)
+        ],
+        |row| row.get(0),
+    )?;
+    let Some((mut nearest, top)) = descent else {
+        // First node of the project's graph for this model
+        return Ok(());
+    };
+
+    for layer in (0..=level.min(top)).rev() {
+        nearest = search_layer(conn, &vector, &nearest, EF_CONSTRUCTION, layer)?;
+        let linked: Vec<i64> = nearest.iter().take(MAX_NEIGHBORS).map(|c| c.node).collect();
+        set_neighbors(conn, node, layer, &linked)?;
+        for &other in &linked {
+            let mut list = neighbors(conn, other, layer)?;
+            list.push(node);
+            if list.len() > layer_capacity(layer) {
+                let Some(other_vector) = node_vector(conn, other)? else {
+                    continue;
+                };
+                list = nearest_to(conn, &other_vector, list, layer_capacity(layer))?;
+            }
+            set_neighbors(conn, other, layer, &list)?;
+        }
+    }
+    Ok(())
+}
+
+/// Drop a cached embedding, reconnecting its neighbours; `false` if absent
+pub fn remove(conn: &Connection, kind: EmbeddingKind, id: &str) -> Result<bool, VectorError> {
+    let found: Option<(i64, i64)> = conn
+        .query_row(
+            "SELECT node, level FROM vectors WHERE kind = ?1 AND id = ?2",
+            params![kind.as_str(), id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((node, level)) = found else {
+        return Ok(false);
+    };
+
+    for layer in 0..=level as usize {
+        let orphaned = neighbors(conn, node, layer)?;
+        for &other in &orphaned {
+            let Some(other_vector) = node_vector(conn, other)? else {
+                continue;
+            };
+            let list = neighbors(conn, other, layer)?
+                .into_iter()
+                .chain(orphaned.iter().copied())
+                .filter(|&n| n != node && n != other);
+            let list = nearest_to(conn, &other_vector, list, layer_capacity(layer))?;
+            set_neighbors(conn, other, layer, &list)?;
+        }
+    }
+    conn.execute("DELETE FROM vectors WHERE node = ?1", params![node])?;
+    Ok(true)
+}
+
+/// Approximate `k` most similar cached embeddings in a project
+pub fn search(conn: &Connection, query: &VectorQuery) -> Result<Vec<VectorHit>, VectorError> {
+    let vector = normalize(&query.vector)?;
+    let Some(model) = query_model(conn, query, &vector)? else {
+        return Ok(Vec::new());
+    };
+    if query.k == 0 {
+        return Ok(Vec::new());
+    }
+    let Some((nearest, _)) = descend(conn, &vector, &query.project_id, model.as_deref(), 0)? else {
+        return Ok(Vec::new());
+    };
+    // Oversample when filtering, since other kinds take up candidate slots
+    let ef = match &query.kinds {
+        Some(_) => EF_SEARCH.max(query.k * 4),
+        None => EF_SEARCH.max(query.k),
+    };
+    let found = search_layer(conn, &vector, &nearest, ef, 0)?;
+
+    let mut stmt = conn.prepare_cached("SELECT kind, id FROM vectors WHERE node = ?1")?;
+    let mut hits = Vec::new();
+    for candidate in found {
+        let Some((kind, id)) = stmt
+            .query_row(params![candidate.node], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .optional()?
+        else {
+            continue;
+        };
+        let Some(kind) = EmbeddingKind::parse(&kind) else {
+            continue;
+        };
+        if query
+            .kinds
+            .as_ref()
+            .is_some_and(|kinds| !kinds.contains(&kind))
+        {
+            continue;
+        }
+        hits.push(VectorHit {
+            kind,
+            id,
+            score: 1.0 - candidate.distance,
+        });
+        if hits.len() == query.k {
+            break;
+        }
+    }
+    Ok(hits)
+}
+
+pub(crate) fn with_transaction<T>(
+    store: &ProjectStore,
+    f: impl FnOnce(&Connection) -> Result<T, VectorError>,
+) -> Result<T, VectorError> {
+    let mut conn = store.lock();
+    let tx = conn.transaction()?;
+    let value = f(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+/// Cache a batch of embeddings in one transaction
+pub fn upsert_embeddings_in(
+    store: &ProjectStore,
+    records: &[EmbeddingRecord],
+) -> Result<(), VectorError> {
+    with_transaction(store, |conn| {
+        records.iter().try_for_each(|record| insert(conn, record))
+    })
+}
+
+#[tauri::command]
+pub fn upsert_embeddings(
+    store: State<'_, ProjectStore>,
+    records: Vec<EmbeddingRecord>,
+) -> Result<(), VectorError> {
+    upsert_embeddings_in(&store, &records)
+}
+
+#[tauri::command]
+pub fn delete_embedding(
+    store: State<'_, ProjectStore>,
+    kind: EmbeddingKind,
+    id: String,
+) -> Result<bool, VectorError> {
+    with_transaction(&store, |conn| remove(conn, kind, &id))
+}
+
+#[tauri::command]
+pub fn vector_search(
+    store: State<'_, ProjectStore>,
+    query: VectorQuery,
+) -> Result<Vec<VectorHit>, VectorError> {
+    search(&store.lock(), &query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random vectors
+    fn fake_vectors(count: usize, dims: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        (0..count)
+            .map(|_| {
+                (0..dims)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn record(kind: EmbeddingKind, id: &str, project_id: &str, vector: &[f32]) -> EmbeddingRecord {
+        EmbeddingRecord {
+            kind,
+            id: id.into(),
+            project_id: project_id.into(),
+            vector: vector.to_vec(),
+            model: Some("fake".into()),
+            content_hash: None,
+        }
+    }
+
+    fn query(vector: &[f32], k: usize) -> VectorQuery {
+        VectorQuery {
+            project_id: "p1".into(),
+            vector: vector.to_vec(),
+            k,
+            kinds: None,
+            model: None,
+        }
+    }
+
+    fn brute_force(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+        let query = normalize(query).unwrap();
+        let mut scored: Vec<(f32, usize)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (distance(&query, &normalize(v).unwrap()), i))
+            .collect();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        scored
+            .iter()
+            .take(k)
+            .map(|(_, i)| format!("d{}", i))
+            .collect()
+    }
+
+    fn ids(hits: &[VectorHit]) -> Vec<String> {
+        hits.iter().map(|h| h.id.clone()).collect()
+    }
+
+    #[test]
+    fn finds_nearest_neighbours_with_high_recall() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        let vectors = fake_vectors(400, 16, 7);
+        let records: Vec<EmbeddingRecord> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| record(EmbeddingKind::Document, &format!("d{}", i), "p1", v))
+            .collect();
+        upsert_embeddings_in(&store, &records).unwrap();
+
+        let mut found = 0;
+        let queries = fake_vectors(20, 16, 99);
+        for q in &queries {
+            let expected = brute_force(&vectors, q, 10);
+            let hits = search(&store.lock(), &query(q, 10)).unwrap();
+            found += ids(&hits).iter().filter(|id| expected.contains(id)).count();
+        }
+        let recall = found as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.95, "recall {}", recall);
+
+        // A stored vector is its own nearest neighbour
+        let hits = search(&store.lock(), &query(&vectors[42], 1)).unwrap();
+        assert_eq!(hits[0].id, "d42");
+        assert!((hits[0].score - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn scopes_by_project_and_kind() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        upsert_embeddings_in(
+            &store,
+            &[
+                record(EmbeddingKind::Document, "doc", "p1", &[1.0, 0.0, 0.0]),
+                record(EmbeddingKind::Entity, "ent", "p1", &[0.9, 0.1, 0.0]),
+                record(EmbeddingKind::Memory, "mem", "p1", &[0.0, 1.0, 0.0]),
+                record(EmbeddingKind::Document, "elsewhere", "p2", &[1.0, 0.0, 0.0]),
+            ],
+        )
+        .unwrap();
+
+        let all = search(&store.lock(), &query(&[1.0, 0.0, 0.0], 10)).unwrap();
+        assert_eq!(ids(&all), ["doc", "ent", "mem"]);
+
+        let mut entities = query(&[1.0, 0.0, 0.0], 10);
+        entities.kinds = Some(vec![EmbeddingKind::Entity, EmbeddingKind::Memory]);
+        let hits = search(&store.lock(), &entities).unwrap();
+        assert_eq!(ids(&hits), ["ent", "mem"]);
+    }
+
+    #[test]
+    fn removal_keeps_the_graph_searchable() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        let vectors = fake_vectors(150, 8, 3);
+        let records: Vec<EmbeddingRecord> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| record(EmbeddingKind::Document, &format!("d{}", i), "p1", v))
+            .collect();
+        upsert_embeddings_in(&store, &records).unwrap();
+
+        // Remove every other node, and whichever is the entry point
+        let conn = store.lock();
+        let (entry, _) = entry_point(&conn, "p1", Some("fake")).unwrap().unwrap();
+        let entry_id: String = conn
+            .query_row("SELECT id FROM vectors WHERE node = ?1", [entry], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        let mut removed: HashSet<String> = (0..150).step_by(2).map(|i| format!("d{}", i)).collect();
+        removed.insert(entry_id);
+        for id in &removed {
+            remove(&conn, EmbeddingKind::Document, id).unwrap();
+        }
+
+        for (i, vector) in vectors.iter().enumerate() {
+            let id = format!("d{}", i);
+            let hits = search(&conn, &query(vector, 1)).unwrap();
+            if removed.contains(&id) {
+                assert_ne!(hits[0].id, id);
+            } else {
+                assert_eq!(hits[0].id, id);
+            }
+        }
+    }
+
+    #[test]
+    fn replaces_and_drops_with_their_rows() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        store
+            .upsert(
+                crate::db::SyncTable::Documents,
+                &serde_json::json!({"id": "doc", "projectId": "p1"}),
+            )
+            .unwrap();
+        upsert_embeddings_in(
+            &store,
+            &[
+                record(EmbeddingKind::Document, "doc", "p1", &[1.0, 0.0]),
+                record(EmbeddingKind::Memory, "mem", "p1", &[0.0, 1.0]),
+            ],
+        )
+        .unwrap();
+        upsert_embeddings_in(
+            &store,
+            &[record(EmbeddingKind::Memory, "mem", "p1", &[1.0, 0.1])],
+        )
+        .unwrap();
+
+        let hits = search(&store.lock(), &query(&[1.0, 0.0], 2)).unwrap();
+        assert_eq!(ids(&hits), ["doc", "mem"]);
+
+        store
+            .delete(crate::db::SyncTable::Documents, "doc")
+            .unwrap();
+        let hits = search(&store.lock(), &query(&[1.0, 0.0], 2)).unwrap();
+        assert_eq!(ids(&hits), ["mem"]);
+    }
+
+    #[test]
+    fn rejects_bad_vectors() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        upsert_embeddings_in(
+            &store,
+            &[record(EmbeddingKind::Entity, "e", "p1", &[1.0, 0.0])],
+        )
+        .unwrap();
+        assert!(matches!(
+            upsert_embeddings_in(
+                &store,
+                &[record(EmbeddingKind::Entity, "f", "p1", &[1.0, 0.0, 0.0])]
+            ),
+            Err(VectorError::DimensionMismatch {
+                expected: 2,
+                actual: 3
+            })
+        ));
+        assert!(matches!(
+            upsert_embeddings_in(
+                &store,
+                &[record(EmbeddingKind::Entity, "g", "p1", &[0.0, 0.0])]
+            ),
+            Err(VectorError::InvalidVector)
+        ));
+    }
+
+    #[test]
+    fn keeps_a_graph_and_dimension_per_model() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        let with_model = |id: &str, model: &str, vector: &[f32]| EmbeddingRecord {
+            model: Some(model.into()),
+            ..record(EmbeddingKind::Document, id, "p1", vector)
+        };
+        upsert_embeddings_in(
+            &store,
+            &[
+                with_model("small-a", "small", &[1.0, 0.0]),
+                with_model("small-b", "small", &[0.0, 1.0]),
+                with_model("large-a", "large", &[1.0, 0.0, 0.0]),
+                with_model("large-b", "large", &[0.0, 0.0, 1.0]),
+                // Another project may use different dimensions for the same model
+                EmbeddingRecord {
+                    model: Some("small".into()),
+                    ..record(
+                        EmbeddingKind::Document,
+                        "other",
+                        "p2",
+                        &[1.0, 0.0, 0.0, 0.0],
+                    )
+                },
+            ],
+        )
+        .unwrap();
+
+        let conn = store.lock();
+        assert_eq!(dimensions(&conn, "p1", Some("small")).unwrap(), Some(2));
+        assert_eq!(dimensions(&conn, "p1", Some("large")).unwrap(), Some(3));
+        assert_eq!(dimensions(&conn, "p2", Some("small")).unwrap(), Some(4));
+
+        // Without a model, the query's dimensions pick the graph
+        let hits = search(&conn, &query(&[1.0, 0.1], 5)).unwrap();
+        assert_eq!(ids(&hits), ["small-a", "small-b"]);
+        let hits = search(&conn, &query(&[0.0, 0.1, 1.0], 5)).unwrap();
+        assert_eq!(ids(&hits), ["large-b", "large-a"]);
+
+        let mut large = query(&[1.0, 0.0], 5);
+        large.model = Some("large".into());
+        assert!(matches!(
+            search(&conn, &large),
+            Err(VectorError::DimensionMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            search(&conn, &query(&[1.0; 5], 5)),
+            Err(VectorError::DimensionMismatch { actual: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn persists_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rhei.db");
+        let vectors = fake_vectors(50, 8, 11);
+        {
+            let store = ProjectStore::open(&path).unwrap();
+            let records: Vec<EmbeddingRecord> = vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| record(EmbeddingKind::Document, &format!("d{}", i), "p1", v))
+                .collect();
+            upsert_embeddings_in(&store, &records).unwrap();
+        }
+        let store = ProjectStore::open(&path).unwrap();
+        let hits = search(&store.lock(), &query(&vectors[7], 3)).unwrap();
+        assert_eq!(ids(&hits), brute_force(&vectors, &vectors[7], 3));
+    }
+}
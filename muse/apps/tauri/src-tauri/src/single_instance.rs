@@ -0,0 +1,67 @@
+//! Single-instance handling
+//!
+//! On Linux (and Windows) the OS starts a fresh process for every `rhei://`
+//! link. The second process hands its argv to the running one and exits; the
+//! running instance focuses `main` and routes any links through the same
+//! deep-link path as `on_open_url`.
+
+use tauri::{AppHandle, Manager};
+
+use crate::deep_link;
+
+/// Label of the primary window
+pub const MAIN_WINDOW: &str = "main";
+
+/// Deep link URLs among a second instance's arguments
+pub fn deep_link_args(argv: &[String]) -> Vec<&str> {
+    argv.iter()
+        .map(String::as_str)
+        .filter(|arg| deep_link::link::is_rhei_deep_link(arg))
+        .collect()
+}
+
+/// Bring the main window to the front
+pub fn focus_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+        return;
+    };
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// Called in the running instance when another launch is attempted
+pub fn on_second_instance(app: &AppHandle, argv: Vec<String>, _cwd: String) {
+    focus_main_window(app);
+    deep_link::handle_urls(app, deep_link_args(&argv));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_deep_link_args() {
+        let argv: Vec<String> = [
+            "/Applications/Rhei.app/Contents/MacOS/rhei",
+            "--flag",
+            "rhei://project/p1",
+            "https://rhei.team",
+            "mythos://project/p2/document/d1",
+            "-psn_0_12345",
+            "rhei://auth/callback?code=c&state=s",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(
+            deep_link_args(&argv),
+            vec![
+                "rhei://project/p1",
+                "mythos://project/p2/document/d1",
+                "rhei://auth/callback?code=c&state=s",
+            ]
+        );
+        assert!(deep_link_args(&argv[..2]).is_empty());
+    }
+}
@@ -0,0 +1,1196 @@
+//! Conflict detection for offline mutations
+//!
+//! Every server event is checked against the outbox before it touches the
+//! local rows. An event whose `version` is newer than the `baseVersion` of a
+//! pending mutation for the same row means both sides edited it; what happens
+//! next depends on the table's `ConflictStrategy`:
+//!
+//! - `LastWriterWins` keeps whichever side was written last
+//! - `Merge` does a field-level three-way merge against the row's shadow (the
+//!   server copy the local edit was based on) and escalates to review when the
+//!   same field changed on both sides
+//! - `Manual` holds the row's mutations and queues the conflict for review
+//!
+//! Nothing is overwritten silently: review conflicts keep both values until
+//! `resolve_conflict` is called. Our own mutations coming back from the server
+//! (matched against `sync_echoes`) only advance the base of later edits.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use super::outbox::now_ms;
+use super::Mutation;
+use crate::db::{self, MutationType, ProjectStore, StoreError, SyncEvent, SyncTable};
+
+/// Event carrying a `Conflict` newly queued for review
+pub const CONFLICT_EVENT: &str = "sync://conflict";
+
+/// Mirrors `ConflictStrategy` in `packages/sync/src/types.ts`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    LastWriterWins,
+    ServerWins,
+    ClientWins,
+    Merge,
+    Manual,
+}
+
+impl ConflictStrategy {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::LastWriterWins => "last_writer_wins",
+            Self::ServerWins => "server_wins",
+            Self::ClientWins => "client_wins",
+            Self::Merge => "merge",
+            Self::Manual => "manual",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "last_writer_wins" => Self::LastWriterWins,
+            "server_wins" => Self::ServerWins,
+            "client_wins" => Self::ClientWins,
+            "merge" => Self::Merge,
+            _ => Self::Manual,
+        }
+    }
+}
+
+/// Settings key of the strategies changed from their defaults
+const STRATEGIES_SETTING: &str = "conflictStrategies";
+
+/// Managed per-table strategies, saved in the project store
+pub struct ConflictPolicy {
+    strategies: Mutex<HashMap<SyncTable, ConflictStrategy>>,
+}
+
+impl Default for ConflictPolicy {
+    fn default() -> Self {
+        let strategies = SyncTable::ALL
+            .into_iter()
+            .map(|table| (table, Self::default_strategy(table)))
+            .collect();
+        Self {
+            strategies: Mutex::new(strategies),
+        }
+    }
+}
+
+impl ConflictPolicy {
+    /// Analysis is regenerated freely, entities are edited field by field,
+    /// and prose is never merged without the writer; the rest goes to review
+    pub fn default_strategy(table: SyncTable) -> ConflictStrategy {
+        match table {
+            SyncTable::Analysis => ConflictStrategy::LastWriterWins,
+            SyncTable::Entities => ConflictStrategy::Merge,
+            _ => ConflictStrategy::Manual,
+        }
+    }
+
+    /// The defaults, overridden by the strategies saved in `store`
+    pub fn load(store: &ProjectStore) -> Result<Self, StoreError> {
+        let policy = Self::default();
+        policy.reload(store)?;
+        Ok(policy)
+    }
+
+    /// Start over from what `store` holds, e.g. after a backup replaced it
+    pub fn reload(&self, store: &ProjectStore) -> Result<(), StoreError> {
+        let saved: HashMap<SyncTable, ConflictStrategy> =
+            store.setting(STRATEGIES_SETTING)?.unwrap_or_default();
+        let mut strategies = self.strategies.lock().unwrap();
+        for table in SyncTable::ALL {
+            strategies.insert(table, Self::default_strategy(table));
+        }
+        strategies.extend(saved);
+        Ok(())
+    }
+
+    pub fn strategy(&self, table: SyncTable) -> ConflictStrategy {
+        self.strategies.lock().unwrap()[&table]
+    }
+
+    pub fn set(
+        &self,
+        store: &ProjectStore,
+        table: SyncTable,
+        strategy: ConflictStrategy,
+    ) -> Result<(), StoreError> {
+        let mut strategies = self.strategies.lock().unwrap();
+        strategies.insert(table, strategy);
+        store.set_setting(STRATEGIES_SETTING, &*strategies)
+    }
+
+    pub fn all(&self) -> HashMap<SyncTable, ConflictStrategy> {
+        self.strategies.lock().unwrap().clone()
+    }
+}
+
+/// A conflict in (or through) the review queue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub id: String,
+    pub project_id: String,
+    pub table: SyncTable,
+    pub pk: String,
+    pub strategy: ConflictStrategy,
+    /// `None` when the local side deleted the row
+    pub local_value: Option<Value>,
+    /// `None` when the server deleted the row
+    pub server_value: Option<Value>,
+    pub base_value: Option<Value>,
+    pub server_version: i64,
+    /// Fields changed on both sides (merge escalations)
+    pub fields: Vec<String>,
+    pub created_at: i64,
+    pub resolved_at: Option<i64>,
+    pub resolution: Option<Resolution>,
+}
+
+/// How a reviewed conflict was settled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Resolution {
+    /// Keep the local edit and push it over the server version
+    Local,
+    /// Take the server version and drop the local edit
+    Server,
+    /// Push a hand-merged row
+    Merged { row: Value },
+}
+
+/// Outcome of applying a batch of server events
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyReport {
+    /// Events written to the local rows
+    pub applied: u32,
+    /// Conflicts settled by the table's strategy
+    pub auto_resolved: u32,
+    /// Conflicts newly queued for review
+    pub conflicts: Vec<Conflict>,
+}
+
+struct PendingMutation {
+    id: String,
+    mutation: Mutation,
+}
+
+fn pending_for_row(
+    conn: &Connection,
+    table: SyncTable,
+    pk: &str,
+) -> Result<Vec<PendingMutation>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, mutation FROM outbox WHERE dead = 0 AND tbl = ?1 AND pk = ?2 ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map(params![table.as_str(), pk], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    rows.map(|row| {
+        let (id, mutation) = row?;
+        Ok(PendingMutation {
+            id,
+            mutation: serde_json::from_str(&mutation)?,
+        })
+    })
+    .collect()
+}
+
+fn save_mutation(conn: &Connection, mutation: &Mutation) -> Result<(), StoreError> {
+    conn.execute(
+        "UPDATE outbox SET mutation = ?2 WHERE id = ?1",
+        params![mutation.id, serde_json::to_string(mutation)?],
+    )?;
+    Ok(())
+}
+
+/// Point a row's pending mutations at `version`, so they push as deliberate
+/// overwrites of what the server has now
+fn rebase(
+    conn: &Connection,
+    pending: &mut [PendingMutation],
+    version: i64,
+) -> Result<(), StoreError> {
+    for entry in pending.iter_mut() {
+        if entry
+            .mutation
+            .base_version
+            .is_none_or(|base| base < version)
+        {
+            entry.mutation.base_version = Some(version);
+            save_mutation(conn, &entry.mutation)?;
+        }
+    }
+    Ok(())
+}
+
+fn set_held(conn: &Connection, table: SyncTable, pk: &str, held: bool) -> Result<(), StoreError> {
+    conn.execute(
+        "UPDATE outbox SET held = ?3 WHERE dead = 0 AND tbl = ?1 AND pk = ?2",
+        params![table.as_str(), pk, held],
+    )?;
+    Ok(())
+}
+
+fn drop_pending(conn: &Connection, table: SyncTable, pk: &str) -> Result<(), StoreError> {
+    conn.execute(
+        "DELETE FROM outbox WHERE dead = 0 AND tbl = ?1 AND pk = ?2",
+        params![table.as_str(), pk],
+    )?;
+    Ok(())
+}
+
+/// Record the server's side as the new base without touching the local row
+fn advance_shadow(conn: &Connection, event: &SyncEvent, pk: &str) -> Result<(), StoreError> {
+    match event.kind {
+        MutationType::Upsert => {
+            let project_id = event.row["projectId"].as_str().unwrap_or_default();
+            db::put_shadow(
+                conn,
+                event.table,
+                project_id,
+                pk,
+                event.version,
+                &serde_json::to_string(&event.row)?,
+            )
+        }
+        MutationType::Delete => db::remove_shadow(conn, event.table, pk),
+    }
+}
+
+/// Whether `event` is the server echoing a mutation we delivered
+fn take_echo(conn: &Connection, event: &SyncEvent, pk: &str) -> Result<bool, StoreError> {
+    let mut stmt =
+        conn.prepare("SELECT mutation_id, mutation FROM sync_echoes WHERE tbl = ?1 AND pk = ?2")?;
+    let echoes = stmt
+        .query_map(params![event.table.as_str(), pk], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (mutation_id, mutation) in echoes {
+        let mutation: Mutation = serde_json::from_str(&mutation)?;
+        if is_echo(&mutation, event) {
+            conn.execute(
+                "DELETE FROM sync_echoes WHERE mutation_id = ?1",
+                params![mutation_id],
+            )?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The event carries every field the mutation wrote (the server may add more)
+fn is_echo(mutation: &Mutation, event: &SyncEvent) -> bool {
+    match (mutation.kind, event.kind) {
+        (MutationType::Delete, MutationType::Delete) => true,
+        (MutationType::Upsert, MutationType::Upsert) => {
+            let (Some(Value::Object(written)), Value::Object(received)) =
+                (&mutation.row, &event.row)
+            else {
+                return false;
+            };
+            written
+                .iter()
+                .filter(|(field, _)| field.as_str() != "projectId")
+                .all(|(field, value)| received.get(field) == Some(value))
+        }
+        _ => false,
+    }
+}
+
+/// Field-level three-way merge; `Err` lists the fields changed on both sides
+pub fn merge_fields(base: &Value, local: &Value, server: &Value) -> Result<Value, Vec<String>> {
+    let empty = Map::new();
+    let base = base.as_object().unwrap_or(&empty);
+    let local = local.as_object().unwrap_or(&empty);
+    let server = server.as_object().unwrap_or(&empty);
+
+    let fields: BTreeSet<&String> = base
+        .keys()
+        .chain(local.keys())
+        .chain(server.keys())
+        .collect();
+    let mut merged = Map::new();
+    let mut conflicting = Vec::new();
+    for field in fields {
+        let (b, l, s) = (base.get(field), local.get(field), server.get(field));
+        let value = if l == s || s == b {
+            l
+        } else if l == b {
+            s
+        } else {
+            conflicting.push(field.clone());
+            continue;
+        };
+        if let Some(value) = value {
+            merged.insert(field.clone(), value.clone());
+        }
+    }
+    if conflicting.is_empty() {
+        Ok(Value::Object(merged))
+    } else {
+        Err(conflicting)
+    }
+}
+
+fn read_conflict(row: &rusqlite::Row<'_>) -> rusqlite::Result<ConflictRow> {
+    Ok(ConflictRow {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        table: row.get(2)?,
+        pk: row.get(3)?,
+        strategy: row.get(4)?,
+        local_value: row.get(5)?,
+        server_value: row.get(6)?,
+        base_value: row.get(7)?,
+        server_version: row.get(8)?,
+        fields: row.get(9)?,
+        created_at: row.get(10)?,
+        resolved_at: row.get(11)?,
+        resolution: row.get(12)?,
+    })
+}
+
+struct ConflictRow {
+    id: String,
+    project_id: String,
+    table: String,
+    pk: String,
+    strategy: String,
+    local_value: Option<String>,
+    server_value: Option<String>,
+    base_value: Option<String>,
+    server_version: i64,
+    fields: String,
+    created_at: i64,
+    resolved_at: Option<i64>,
+    resolution: Option<String>,
+}
+
+const CONFLICT_COLUMNS: &str = "id, project_id, tbl, pk, strategy, local_value, server_value, \
+     base_value, server_version, fields, created_at, resolved_at, resolution";
+
+fn parse_json<T: serde::de::DeserializeOwned>(
+    value: Option<String>,
+) -> Result<Option<T>, StoreError> {
+    Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+}
+
+impl ConflictRow {
+    fn into_conflict(self) -> Result<Conflict, StoreError> {
+        Ok(Conflict {
+            id: self.id,
+            project_id: self.project_id,
+            table: serde_json::from_value(Value::String(self.table))?,
+            pk: self.pk,
+            strategy: ConflictStrategy::parse(&self.strategy),
+            local_value: parse_json(self.local_value)?,
+            server_value: parse_json(self.server_value)?,
+            base_value: parse_json(self.base_value)?,
+            server_version: self.server_version,
+            fields: serde_json::from_str(&self.fields)?,
+            created_at: self.created_at,
+            resolved_at: self.resolved_at,
+            resolution: parse_json(self.resolution)?,
+        })
+    }
+}
+
+fn open_conflict(
+    conn: &Connection,
+    table: SyncTable,
+    pk: &str,
+) -> Result<Option<Conflict>, StoreError> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM conflicts WHERE tbl = ?1 AND pk = ?2 AND resolved_at IS NULL",
+            CONFLICT_COLUMNS
+        ),
+        params![table.as_str(), pk],
+        read_conflict,
+    )
+    .optional()?
+    .map(ConflictRow::into_conflict)
+    .transpose()
+}
+
+fn to_json(value: &Option<Value>) -> Result<Option<String>, StoreError> {
+    Ok(value.as_ref().map(serde_json::to_string).transpose()?)
+}
+
+fn insert_conflict(conn: &Connection, conflict: &Conflict) -> Result<(), StoreError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO conflicts ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, NULL, NULL)",
+            CONFLICT_COLUMNS
+        ),
+        params![
+            conflict.id,
+            conflict.project_id,
+            conflict.table.as_str(),
+            conflict.pk,
+            conflict.strategy.as_str(),
+            to_json(&conflict.local_value)?,
+            to_json(&conflict.server_value)?,
+            to_json(&conflict.base_value)?,
+            conflict.server_version,
+            serde_json::to_string(&conflict.fields)?,
+            conflict.created_at,
+        ],
+    )?;
+    Ok(())
+}
+
+fn server_value(event: &SyncEvent) -> Option<Value> {
+    (event.kind == MutationType::Upsert).then(|| event.row.clone())
+}
+
+fn local_value(mutation: &Mutation) -> Option<Value> {
+    match mutation.kind {
+        MutationType::Upsert => mutation.row.clone(),
+        MutationType::Delete => None,
+    }
+}
+
+/// The local edit wins: keep the local row, move the base forward
+fn keep_local(
+    conn: &Connection,
+    event: &SyncEvent,
+    pk: &str,
+    pending: &mut [PendingMutation],
+) -> Result<(), StoreError> {
+    advance_shadow(conn, event, pk)?;
+    rebase(conn, pending, event.version)
+}
+
+/// The server wins: take its row and forget the local edits
+fn take_server(conn: &Connection, event: &SyncEvent, pk: &str) -> Result<(), StoreError> {
+    db::apply_server_event(conn, event)?;
+    drop_pending(conn, event.table, pk)
+}
+
+/// Write a merged row locally and collapse the row's pending mutations into
+/// one upsert of it, based on the server version it was merged with
+fn push_merged(
+    conn: &Connection,
+    event: &SyncEvent,
+    pk: &str,
+    pending: &mut Vec<PendingMutation>,
+    merged: Value,
+) -> Result<(), StoreError> {
+    advance_shadow(conn, event, pk)?;
+    db::put_row(conn, event.table, None, &merged, None)?;
+    let Some(mut last) = pending.pop() else {
+        return Ok(());
+    };
+    for earlier in pending.drain(..) {
+        conn.execute("DELETE FROM outbox WHERE id = ?1", params![earlier.id])?;
+    }
+    last.mutation.kind = MutationType::Upsert;
+    last.mutation.row = Some(merged);
+    last.mutation.base_version = Some(event.version);
+    save_mutation(conn, &last.mutation)
+}
+
+enum Outcome {
+    Applied,
+    AutoResolved,
+    Queued(Box<Conflict>),
+    Ignored,
+}
+
+fn receive_event(
+    conn: &Connection,
+    policy: &ConflictPolicy,
+    event: &SyncEvent,
+    now: i64,
+) -> Result<Outcome, StoreError> {
+    let (_, pk) = db::row_object(&event.row)?;
+    let mut pending = pending_for_row(conn, event.table, pk)?;
+
+    if pending.is_empty() {
+        conn.execute(
+            "DELETE FROM sync_echoes WHERE tbl = ?1 AND pk = ?2",
+            params![event.table.as_str(), pk],
+        )?;
+        db::apply_server_event(conn, event)?;
+        return Ok(Outcome::Applied);
+    }
+
+    if take_echo(conn, event, pk)? {
+        keep_local(conn, event, pk, &mut pending)?;
+        return Ok(Outcome::Ignored);
+    }
+
+    if let Some(conflict) = open_conflict(conn, event.table, pk)? {
+        // Keep the review current with the latest server side
+        conn.execute(
+            "UPDATE conflicts SET server_value = ?2, server_version = ?3 WHERE id = ?1",
+            params![conflict.id, to_json(&server_value(event))?, event.version],
+        )?;
+        return Ok(Outcome::Ignored);
+    }
+
+    // A mutation without a base never saw a server copy, so it conflicts
+    // with any server version (`None` orders first)
+    let base = pending
+        .iter()
+        .map(|p| p.mutation.base_version)
+        .min()
+        .flatten();
+    if base.is_some_and(|base| event.version <= base) {
+        // Already accounted for by the local edits
+        return Ok(Outcome::Ignored);
+    }
+
+    let local = &pending.last().expect("pending is non-empty").mutation;
+    let strategy = policy.strategy(event.table);
+    let shadow = db::shadow(conn, event.table, pk)?;
+    let base_value = shadow
+        .filter(|(version, _)| Some(*version) == base)
+        .map(|(_, data)| data);
+    let mut conflict = Conflict {
+        id: format!("{}:{}:{}", event.table.as_str(), pk, event.version),
+        project_id: local.project_id.clone(),
+        table: event.table,
+        pk: pk.to_string(),
+        strategy,
+        local_value: local_value(local),
+        server_value: server_value(event),
+        base_value,
+        server_version: event.version,
+        fields: Vec::new(),
+        created_at: now,
+        resolved_at: None,
+        resolution: None,
+    };
+
+    match strategy {
+        ConflictStrategy::ServerWins => {
+            take_server(conn, event, pk)?;
+            return Ok(Outcome::AutoResolved);
+        }
+        ConflictStrategy::ClientWins => {
+            keep_local(conn, event, pk, &mut pending)?;
+            return Ok(Outcome::AutoResolved);
+        }
+        ConflictStrategy::LastWriterWins => {
+            // RFC 3339 UTC timestamps order lexicographically
+            if event.timestamp.as_str() >= local.created_at.as_str() {
+                take_server(conn, event, pk)?;
+            } else {
+                keep_local(conn, event, pk, &mut pending)?;
+            }
+            return Ok(Outcome::AutoResolved);
+        }
+        ConflictStrategy::Merge => {
+            if let (Some(base), Some(local), Some(server)) = (
+                &conflict.base_value,
+                &conflict.local_value,
+                &conflict.server_value,
+            ) {
+                match merge_fields(base, local, server) {
+                    Ok(merged) => {
+                        push_merged(conn, event, pk, &mut pending, merged)?;
+                        return Ok(Outcome::AutoResolved);
+                    }
+                    Err(fields) => conflict.fields = fields,
+                }
+            }
+        }
+        ConflictStrategy::Manual => {}
+    }
+
+    insert_conflict(conn, &conflict)?;
+    set_held(conn, event.table, pk, true)?;
+    Ok(Outcome::Queued(Box::new(conflict)))
+}
+
+/// Apply server events, detecting conflicts with pending local mutations
+pub fn apply_remote_events(
+    store: &ProjectStore,
+    policy: &ConflictPolicy,
+    events: &[SyncEvent],
+    now: i64,
+) -> Result<ApplyReport, StoreError> {
+    store.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        let mut report = ApplyReport::default();
+        for event in events {
+            match receive_event(&tx, policy, event, now)? {
+                Outcome::Applied => report.applied += 1,
+                Outcome::AutoResolved => report.auto_resolved += 1,
+                Outcome::Queued(conflict) => report.conflicts.push(*conflict),
+                Outcome::Ignored => {}
+            }
+        }
+        tx.commit()?;
+        Ok(report)
+    })
+}
+
+/// Remember a pushed mutation so its echo isn't mistaken for a remote edit;
+/// `outbox::mark_done` calls this in the transaction that dequeues it
+pub fn record_delivered_in(conn: &Connection, mutation: &Mutation) -> Result<(), StoreError> {
+    let Some(pk) = mutation.row_key() else {
+        return Ok(());
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO sync_echoes (mutation_id, tbl, pk, mutation) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            mutation.id,
+            mutation.table.as_str(),
+            pk,
+            serde_json::to_string(mutation)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// Conflicts awaiting review, oldest first
+pub fn open_conflicts(
+    store: &ProjectStore,
+    project_id: Option<&str>,
+) -> Result<Vec<Conflict>, StoreError> {
+    store.with_conn(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM conflicts
+             WHERE resolved_at IS NULL AND (?1 IS NULL OR project_id = ?1)
+             ORDER BY created_at, id",
+            CONFLICT_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![project_id], read_conflict)?;
+        rows.map(|row| row?.into_conflict()).collect()
+    })
+}
+
+/// Settle a reviewed conflict and release the row's mutations
+pub fn resolve(
+    store: &ProjectStore,
+    id: &str,
+    resolution: &Resolution,
+    now: i64,
+) -> Result<Option<Conflict>, StoreError> {
+    store.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        let conflict = tx
+            .query_row(
+                &format!(
+                    "SELECT {} FROM conflicts WHERE id = ?1 AND resolved_at IS NULL",
+                    CONFLICT_COLUMNS
+                ),
+                params![id],
+                read_conflict,
+            )
+            .optional()?
+            .map(ConflictRow::into_conflict)
+            .transpose()?;
+        let Some(mut conflict) = conflict else {
+            return Ok(None);
+        };
+
+        let server_event = SyncEvent {
+            id: conflict.id.clone(),
+            table: conflict.table,
+            kind: if conflict.server_value.is_some() {
+                MutationType::Upsert
+            } else {
+                MutationType::Delete
+            },
+            row: conflict
+                .server_value
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({ "id": conflict.pk })),
+            version: conflict.server_version,
+            user_id: String::new(),
+            timestamp: String::new(),
+        };
+        let mut pending = pending_for_row(&tx, conflict.table, &conflict.pk)?;
+        match resolution {
+            Resolution::Local => keep_local(&tx, &server_event, &conflict.pk, &mut pending)?,
+            Resolution::Server => take_server(&tx, &server_event, &conflict.pk)?,
+            Resolution::Merged { row } => {
+                let mut row = row.clone();
+                if let Some(object) = row.as_object_mut() {
+                    object.insert("id".into(), Value::String(conflict.pk.clone()));
+                    object
+                        .entry("projectId")
+                        .or_insert_with(|| Value::String(conflict.project_id.clone()));
+                }
+                push_merged(&tx, &server_event, &conflict.pk, &mut pending, row)?
+            }
+        }
+        set_held(&tx, conflict.table, &conflict.pk, false)?;
+        tx.execute(
+            "UPDATE conflicts SET resolved_at = ?2, resolution = ?3 WHERE id = ?1",
+            params![conflict.id, now, serde_json::to_string(resolution)?],
+        )?;
+        tx.commit()?;
+
+        conflict.resolved_at = Some(now);
+        conflict.resolution = Some(resolution.clone());
+        Ok(Some(conflict))
+    })
+}
+
+/// Emit newly queued conflicts to the frontend
+pub fn notify_conflicts(app: &AppHandle, report: &ApplyReport) {
+    for conflict in &report.conflicts {
+        if let Err(e) = app.emit(CONFLICT_EVENT, conflict) {
+            eprintln!("[sync] Failed to emit conflict {}: {}", conflict.id, e);
+        }
+    }
+}
+
+/// Apply server activity to the local store, flagging conflicts
+#[tauri::command]
+pub fn apply_sync_events(
+    app: AppHandle,
+    store: State<'_, ProjectStore>,
+    policy: State<'_, ConflictPolicy>,
+    events: Vec<SyncEvent>,
+) -> Result<ApplyReport, StoreError> {
+    let report = apply_remote_events(&store, &policy, &events, now_ms())?;
+    notify_conflicts(&app, &report);
+    Ok(report)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn list_conflicts(
+    store: State<'_, ProjectStore>,
+    project_id: Option<String>,
+) -> Result<Vec<Conflict>, StoreError> {
+    open_conflicts(&store, project_id.as_deref())
+}
+
+/// Returns the settled conflict, `None` if it isn't open
+#[tauri::command]
+pub fn resolve_conflict(
+    store: State<'_, ProjectStore>,
+    worker: State<'_, super::outbox::OutboxWorker>,
+    id: String,
+    resolution: Resolution,
+) -> Result<Option<Conflict>, StoreError> {
+    let conflict = resolve(&store, &id, &resolution, now_ms())?;
+    worker.notify();
+    Ok(conflict)
+}
+
+#[tauri::command]
+pub fn get_conflict_strategies(
+    policy: State<'_, ConflictPolicy>,
+) -> HashMap<SyncTable, ConflictStrategy> {
+    policy.all()
+}
+
+#[tauri::command]
+pub fn set_conflict_strategy(
+    store: State<'_, ProjectStore>,
+    policy: State<'_, ConflictPolicy>,
+    table: SyncTable,
+    strategy: ConflictStrategy,
+) -> Result<(), StoreError> {
+    policy.set(&store, table, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ProjectSnapshot;
+    use crate::sync::outbox;
+    use serde_json::json;
+
+    fn setup(table: SyncTable, row: Value) -> ProjectStore {
+        let store = ProjectStore::open_in_memory().unwrap();
+        let mut snapshot = ProjectSnapshot {
+            project_id: "p1".into(),
+            version: 1,
+            synced_at: "2026-01-01T00:00:00Z".into(),
+            ..Default::default()
+        };
+        match table {
+            SyncTable::Documents => snapshot.documents.push(row),
+            SyncTable::Entities => snapshot.entities.push(row),
+            SyncTable::Analysis => snapshot.analysis.push(row),
+            _ => unreachable!(),
+        }
+        store.bootstrap_project(&snapshot).unwrap();
+        store
+    }
+
+    fn edit(store: &ProjectStore, id: &str, table: SyncTable, row: Value, at: &str) {
+        store.upsert(table, &row).unwrap();
+        outbox::enqueue(
+            store,
+            &Mutation {
+                id: id.into(),
+                table,
+                kind: MutationType::Upsert,
+                pk: None,
+                row: Some(row),
+                base_version: Some(1),
+                created_at: at.into(),
+                project_id: "p1".into(),
+            },
+        )
+        .unwrap();
+    }
+
+    fn event(table: SyncTable, row: Value, version: i64, at: &str) -> SyncEvent {
+        SyncEvent {
+            id: format!("ev{}", version),
+            table,
+            kind: MutationType::Upsert,
+            row,
+            version,
+            user_id: "someone-else".into(),
+            timestamp: at.into(),
+        }
+    }
+
+    #[test]
+    fn events_without_pending_edits_apply_directly() {
+        let store = setup(SyncTable::Documents, json!({"id": "d1", "title": "A"}));
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(
+                SyncTable::Documents,
+                json!({"id": "d1", "projectId": "p1", "title": "B"}),
+                2,
+                "t",
+            )],
+            0,
+        )
+        .unwrap();
+        assert_eq!(report.applied, 1);
+        assert_eq!(
+            store.get(SyncTable::Documents, "d1").unwrap().unwrap()["title"],
+            "B"
+        );
+    }
+
+    #[test]
+    fn documents_go_to_review_and_hold_the_outbox() {
+        let store = setup(SyncTable::Documents, json!({"id": "d1", "title": "A"}));
+        edit(
+            &store,
+            "m1",
+            SyncTable::Documents,
+            json!({"id": "d1", "projectId": "p1", "title": "Local"}),
+            "2026-01-02T00:00:00Z",
+        );
+
+        let remote = json!({"id": "d1", "projectId": "p1", "title": "Remote"});
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(
+                SyncTable::Documents,
+                remote.clone(),
+                2,
+                "2026-01-03T00:00:00Z",
+            )],
+            5,
+        )
+        .unwrap();
+
+        let conflict = &report.conflicts[0];
+        assert_eq!(conflict.strategy, ConflictStrategy::Manual);
+        assert_eq!(conflict.server_value.as_ref(), Some(&remote));
+        assert_eq!(conflict.local_value.as_ref().unwrap()["title"], "Local");
+        assert_eq!(conflict.base_value.as_ref().unwrap()["title"], "A");
+        // Local row untouched, mutation held back
+        assert_eq!(
+            store.get(SyncTable::Documents, "d1").unwrap().unwrap()["title"],
+            "Local"
+        );
+        assert!(outbox::due(&store, i64::MAX, 10).unwrap().is_empty());
+        assert_eq!(open_conflicts(&store, Some("p1")).unwrap().len(), 1);
+
+        let resolved = resolve(&store, &conflict.id, &Resolution::Local, 9)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolution, Some(Resolution::Local));
+        let due = outbox::due(&store, i64::MAX, 10).unwrap();
+        assert_eq!(due[0].mutation.base_version, Some(2));
+        assert!(open_conflicts(&store, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn server_resolution_drops_local_edits() {
+        let store = setup(SyncTable::Documents, json!({"id": "d1", "title": "A"}));
+        edit(
+            &store,
+            "m1",
+            SyncTable::Documents,
+            json!({"id": "d1", "projectId": "p1", "title": "Local"}),
+            "t1",
+        );
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(
+                SyncTable::Documents,
+                json!({"id": "d1", "projectId": "p1", "title": "Remote"}),
+                2,
+                "t2",
+            )],
+            0,
+        )
+        .unwrap();
+
+        resolve(&store, &report.conflicts[0].id, &Resolution::Server, 1).unwrap();
+        assert_eq!(
+            store.get(SyncTable::Documents, "d1").unwrap().unwrap()["title"],
+            "Remote"
+        );
+        assert_eq!(outbox::status(&store).unwrap().pending, 0);
+    }
+
+    #[test]
+    fn entities_merge_disjoint_fields() {
+        let store = setup(
+            SyncTable::Entities,
+            json!({"id": "e1", "name": "Ada", "notes": "", "type": "character"}),
+        );
+        edit(
+            &store,
+            "m1",
+            SyncTable::Entities,
+            json!({"id": "e1", "projectId": "p1", "name": "Ada", "notes": "Local notes", "type": "character"}),
+            "t1",
+        );
+
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(SyncTable::Entities, json!({"id": "e1", "projectId": "p1", "name": "Ada Lovelace", "notes": "", "type": "character"}), 2, "t2")],
+            0,
+        )
+        .unwrap();
+        assert_eq!((report.auto_resolved, report.conflicts.len()), (1, 0));
+
+        let merged = store.get(SyncTable::Entities, "e1").unwrap().unwrap();
+        assert_eq!(
+            (merged["name"].as_str(), merged["notes"].as_str()),
+            (Some("Ada Lovelace"), Some("Local notes"))
+        );
+        let due = outbox::due(&store, i64::MAX, 10).unwrap();
+        assert_eq!(due[0].mutation.row.as_ref(), Some(&merged));
+        assert_eq!(due[0].mutation.base_version, Some(2));
+    }
+
+    #[test]
+    fn entities_escalate_when_both_sides_change_a_field() {
+        let store = setup(SyncTable::Entities, json!({"id": "e1", "name": "Ada"}));
+        edit(
+            &store,
+            "m1",
+            SyncTable::Entities,
+            json!({"id": "e1", "projectId": "p1", "name": "Local"}),
+            "t1",
+        );
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(
+                SyncTable::Entities,
+                json!({"id": "e1", "projectId": "p1", "name": "Remote"}),
+                2,
+                "t2",
+            )],
+            0,
+        )
+        .unwrap();
+        assert_eq!(report.conflicts[0].fields, ["name"]);
+        assert_eq!(report.conflicts[0].strategy, ConflictStrategy::Merge);
+    }
+
+    #[test]
+    fn edits_without_a_base_conflict_with_any_server_version() {
+        let store = setup(SyncTable::Documents, json!({"id": "d1", "title": "A"}));
+        let row = json!({"id": "d1", "projectId": "p1", "title": "Local"});
+        store.upsert(SyncTable::Documents, &row).unwrap();
+        outbox::enqueue(
+            &store,
+            &Mutation {
+                id: "m1".into(),
+                table: SyncTable::Documents,
+                kind: MutationType::Upsert,
+                pk: None,
+                row: Some(row),
+                base_version: None,
+                created_at: "t1".into(),
+                project_id: "p1".into(),
+            },
+        )
+        .unwrap();
+
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(
+                SyncTable::Documents,
+                json!({"id": "d1", "projectId": "p1", "title": "Remote"}),
+                1,
+                "t2",
+            )],
+            0,
+        )
+        .unwrap();
+        assert_eq!(report.applied, 0);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].base_value, None);
+        assert_eq!(
+            store.get(SyncTable::Documents, "d1").unwrap().unwrap()["title"],
+            "Local"
+        );
+    }
+
+    #[test]
+    fn strategies_are_saved_in_the_store() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        let policy = ConflictPolicy::load(&store).unwrap();
+        assert_eq!(
+            policy.strategy(SyncTable::Documents),
+            ConflictStrategy::Manual
+        );
+        policy
+            .set(&store, SyncTable::Documents, ConflictStrategy::ServerWins)
+            .unwrap();
+
+        let reloaded = ConflictPolicy::load(&store).unwrap();
+        assert_eq!(
+            reloaded.strategy(SyncTable::Documents),
+            ConflictStrategy::ServerWins
+        );
+        assert_eq!(
+            reloaded.strategy(SyncTable::Entities),
+            ConflictStrategy::Merge
+        );
+    }
+
+    #[test]
+    fn analysis_uses_last_writer_wins() {
+        let store = setup(SyncTable::Analysis, json!({"id": "a1", "score": 1}));
+        edit(
+            &store,
+            "m1",
+            SyncTable::Analysis,
+            json!({"id": "a1", "projectId": "p1", "score": 2}),
+            "2026-01-02T00:00:00Z",
+        );
+        let policy = ConflictPolicy::default();
+
+        // Older server write loses
+        apply_remote_events(
+            &store,
+            &policy,
+            &[event(
+                SyncTable::Analysis,
+                json!({"id": "a1", "projectId": "p1", "score": 3}),
+                2,
+                "2026-01-01T12:00:00Z",
+            )],
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            store.get(SyncTable::Analysis, "a1").unwrap().unwrap()["score"],
+            2
+        );
+        assert_eq!(outbox::status(&store).unwrap().pending, 1);
+
+        // Newer server write wins
+        apply_remote_events(
+            &store,
+            &policy,
+            &[event(
+                SyncTable::Analysis,
+                json!({"id": "a1", "projectId": "p1", "score": 4}),
+                3,
+                "2026-01-03T00:00:00Z",
+            )],
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            store.get(SyncTable::Analysis, "a1").unwrap().unwrap()["score"],
+            4
+        );
+        assert_eq!(outbox::status(&store).unwrap().pending, 0);
+    }
+
+    #[test]
+    fn own_echo_is_not_a_conflict() {
+        let store = setup(SyncTable::Documents, json!({"id": "d1", "title": "A"}));
+        let first = json!({"id": "d1", "projectId": "p1", "title": "B"});
+        edit(&store, "m1", SyncTable::Documents, first.clone(), "t1");
+        edit(
+            &store,
+            "m2",
+            SyncTable::Documents,
+            json!({"id": "d1", "projectId": "p1", "title": "C"}),
+            "t2",
+        );
+
+        // m1 is delivered; its echo arrives while m2 is still queued
+        let delivered = outbox::pending(&store).unwrap().remove(0).mutation;
+        outbox::mark_done(&store, &delivered).unwrap();
+
+        let mut echo = first;
+        echo["updatedAt"] = json!("server-stamp");
+        let report = apply_remote_events(
+            &store,
+            &ConflictPolicy::default(),
+            &[event(SyncTable::Documents, echo, 2, "t3")],
+            0,
+        )
+        .unwrap();
+        assert!(report.conflicts.is_empty());
+        assert_eq!(
+            store.get(SyncTable::Documents, "d1").unwrap().unwrap()["title"],
+            "C"
+        );
+        assert_eq!(
+            outbox::pending(&store).unwrap()[0].mutation.base_version,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn three_way_merge_rules() {
+        let base = json!({"a": 1, "b": 1, "c": 1});
+        assert_eq!(
+            merge_fields(
+                &base,
+                &json!({"a": 2, "b": 1}),
+                &json!({"a": 1, "b": 1, "c": 1, "d": 4})
+            ),
+            Ok(json!({"a": 2, "b": 1, "d": 4}))
+        );
+        assert_eq!(
+            merge_fields(
+                &base,
+                &json!({"a": 2, "b": 1, "c": 1}),
+                &json!({"a": 3, "b": 1, "c": 1})
+            ),
+            Err(vec!["a".to_string()])
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! Sync layer
+//!
+//! Local edits are recorded as `Mutation`s (mirroring
+//! `packages/sync/src/types.ts`) in a durable `outbox` next to the project
+//! store, and pushed to the sync endpoint by a background worker. Server
+//! events pass through `conflicts` before they reach the local rows.
+
+pub mod conflicts;
+pub mod outbox;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::{MutationType, SyncTable};
+
+/// Mirrors `Mutation`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mutation {
+    pub id: String,
+    pub table: SyncTable,
+    #[serde(rename = "type")]
+    pub kind: MutationType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pk: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_version: Option<i64>,
+    pub created_at: String,
+    pub project_id: String,
+}
+
+impl Mutation {
+    /// A mutation made by the shell itself, stamped now with a fresh id
+    pub fn local(project_id: &str, table: SyncTable, kind: MutationType, pk: &str) -> Self {
+        Self {
+            id: random_uuid(),
+            table,
+            kind,
+            row: None,
+            pk: Some(pk.to_string()),
+            base_version: None,
+            created_at: outbox::iso_timestamp(outbox::now_ms()),
+            project_id: project_id.to_string(),
+        }
+    }
+
+    /// Primary key of the affected row: `pk`, or the row's `id`
+    pub fn row_key(&self) -> Option<&str> {
+        self.pk
+            .as_deref()
+            .or_else(|| self.row.as_ref()?.get("id")?.as_str())
+    }
+}
+
+/// Random RFC 4122 v4 id, like `crypto.randomUUID()`
+pub fn random_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("OS random source unavailable");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
@@ -0,0 +1,697 @@
+//! Durable mutation outbox
+//!
+//! Mutations are written to the `outbox` table before anything is sent, so a
+//! closed laptop or a crash mid-sync never loses an edit. A background worker
+//! pushes due rows in order, one at a time, to the configured sync endpoint.
+//! Transient failures (network, 408/429, 5xx) are retried with exponential
+//! backoff; rows the server rejects outright, or that exhaust their attempts,
+//! are dead-lettered and kept for inspection instead of blocking the queue.
+//! Later mutations of a row wait while an earlier one for the same row is
+//! still retrying, so a row's edits are never reordered. Rows held for a
+//! conflict review (see `conflicts`) stay queued but aren't pushed.
+
+use std::collections::HashSet;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::{conflicts, Mutation};
+use crate::db::{ProjectStore, StoreError};
+
+/// Event carrying an `OutboxProgress` after each push attempt
+pub const OUTBOX_PROGRESS_EVENT: &str = "outbox://progress";
+
+/// Rows fetched per query while flushing
+const FLUSH_BATCH: u32 = 64;
+
+/// Longest the worker sleeps before re-checking the queue
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+/// Milliseconds since the Unix epoch
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// `ms` as an ISO 8601 UTC timestamp, like `Date.prototype.toISOString`
+pub fn iso_timestamp(ms: i64) -> String {
+    let (days, ms_of_day) = (ms.div_euclid(86_400_000), ms.rem_euclid(86_400_000));
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1000 % 60,
+        ms_of_day % 1000
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Attempts before a row is dead-lettered
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(10 * 60),
+            max_attempts: 12,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the next try after `attempts` failures (1-based)
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(31);
+        self.base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay)
+    }
+}
+
+/// Result of pushing one mutation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushError {
+    /// Worth trying again later
+    Retryable(String),
+    /// The server will never accept this mutation
+    Rejected(String),
+}
+
+/// Where mutations are sent
+pub trait SyncTransport {
+    fn push(&self, mutation: &Mutation) -> Result<(), PushError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncEndpoint {
+    pub url: String,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// POSTs each mutation as JSON; the mutation id doubles as idempotency key
+pub struct HttpTransport {
+    endpoint: SyncEndpoint,
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    pub fn new(endpoint: SyncEndpoint) -> Self {
+        Self {
+            endpoint,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl SyncTransport for HttpTransport {
+    fn push(&self, mutation: &Mutation) -> Result<(), PushError> {
+        let mut request = self
+            .agent
+            .post(&self.endpoint.url)
+            .set("Idempotency-Key", &mutation.id);
+        if let Some(token) = &self.endpoint.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        match request.send_json(mutation) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                let message = format!("HTTP {}: {}", status, body.trim());
+                if status == 408 || status == 429 || status >= 500 {
+                    Err(PushError::Retryable(message))
+                } else {
+                    Err(PushError::Rejected(message))
+                }
+            }
+            Err(e) => Err(PushError::Retryable(e.to_string())),
+        }
+    }
+}
+
+/// A queued mutation with its delivery state
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEntry {
+    pub mutation: Mutation,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub dead: bool,
+    pub held: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxStatus {
+    pub pending: u32,
+    pub dead: u32,
+    /// Pending rows waiting on a conflict review
+    pub held: u32,
+    /// Earliest time (Unix ms) a pending row can next be pushed
+    pub next_attempt_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxProgress {
+    pub pushed: u32,
+    pub retrying: u32,
+    pub dead_lettered: u32,
+    pub status: OutboxStatus,
+    pub last_error: Option<String>,
+}
+
+type EntryRow = (String, u32, i64, Option<String>, bool, bool);
+
+fn entry_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<EntryRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn to_entry(
+    (mutation, attempts, next_attempt_at, last_error, dead, held): EntryRow,
+) -> Result<OutboxEntry, StoreError> {
+    Ok(OutboxEntry {
+        mutation: serde_json::from_str(&mutation)?,
+        attempts,
+        next_attempt_at,
+        last_error,
+        dead,
+        held,
+    })
+}
+
+/// Durably queue a mutation; re-enqueueing the same id replaces it
+pub fn enqueue(store: &ProjectStore, mutation: &Mutation) -> Result<(), StoreError> {
+    store.with_conn(|conn| enqueue_in(conn, mutation))
+}
+
+/// `enqueue` on a connection, e.g. inside a caller's transaction
+pub fn enqueue_in(conn: &Connection, mutation: &Mutation) -> Result<(), StoreError> {
+    conn.execute(
+        "INSERT INTO outbox (id, project_id, tbl, pk, mutation) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET mutation = excluded.mutation, pk = excluded.pk",
+        params![
+            mutation.id,
+            mutation.project_id,
+            mutation.table.as_str(),
+            mutation.row_key(),
+            serde_json::to_string(mutation)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// Pending rows ready to push at `now`, oldest first; skips rows queued
+/// behind an earlier pending mutation of the same row
+pub fn due(store: &ProjectStore, now: i64, limit: u32) -> Result<Vec<OutboxEntry>, StoreError> {
+    store.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT mutation, attempts, next_attempt_at, last_error, dead, held FROM outbox o
+             WHERE dead = 0 AND held = 0 AND next_attempt_at <= ?1
+               AND NOT EXISTS (
+                   SELECT 1 FROM outbox e
+                   WHERE e.dead = 0 AND e.rowid < o.rowid AND e.tbl = o.tbl AND e.pk = o.pk
+               )
+             ORDER BY rowid LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![now, limit], entry_from_row)?;
+        rows.map(|row| to_entry(row?)).collect()
+    })
+}
+
+/// Pending mutations, in push order
+pub fn pending(store: &ProjectStore) -> Result<Vec<OutboxEntry>, StoreError> {
+    list(store, false)
+}
+
+pub fn dead_letters(store: &ProjectStore) -> Result<Vec<OutboxEntry>, StoreError> {
+    list(store, true)
+}
+
+fn list(store: &ProjectStore, dead: bool) -> Result<Vec<OutboxEntry>, StoreError> {
+    store.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT mutation, attempts, next_attempt_at, last_error, dead, held FROM outbox
+             WHERE dead = ?1 ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map(params![dead], entry_from_row)?;
+        rows.map(|row| to_entry(row?)).collect()
+    })
+}
+
+pub fn status(store: &ProjectStore) -> Result<OutboxStatus, StoreError> {
+    store.with_conn(|conn| {
+        Ok(conn.query_row(
+            "SELECT
+                 COALESCE(SUM(dead = 0), 0),
+                 COALESCE(SUM(dead = 1), 0),
+                 COALESCE(SUM(dead = 0 AND held = 1), 0),
+                 MIN(CASE WHEN dead = 0 AND held = 0 AND NOT EXISTS (
+                     SELECT 1 FROM outbox e
+                     WHERE e.dead = 0 AND e.rowid < o.rowid AND e.tbl = o.tbl AND e.pk = o.pk
+                 ) THEN next_attempt_at END)
+             FROM outbox o",
+            [],
+            |row| {
+                Ok(OutboxStatus {
+                    pending: row.get(0)?,
+                    dead: row.get(1)?,
+                    held: row.get(2)?,
+                    next_attempt_at: row.get(3)?,
+                })
+            },
+        )?)
+    })
+}
+
+/// Remove a delivered mutation and remember it for echo detection
+pub fn mark_done(store: &ProjectStore, mutation: &Mutation) -> Result<(), StoreError> {
+    store.with_conn(|conn| {
+        // Together, so a crash can't leave the echo unrecognized or the row queued
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM outbox WHERE id = ?1", params![mutation.id])?;
+        conflicts::record_delivered_in(&tx, mutation)?;
+        tx.commit()?;
+        Ok(())
+    })
+}
+
+/// Record a failed push; returns whether the row was dead-lettered
+pub fn mark_failed(
+    store: &ProjectStore,
+    id: &str,
+    error: &PushError,
+    policy: &RetryPolicy,
+    now: i64,
+) -> Result<bool, StoreError> {
+    store.with_conn(|conn| {
+        let attempts: Option<u32> = conn
+            .query_row(
+                "SELECT attempts FROM outbox WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(attempts) = attempts.map(|a| a + 1) else {
+            return Ok(false);
+        };
+        let (dead, message) = match error {
+            PushError::Rejected(message) => (true, message),
+            PushError::Retryable(message) => (attempts >= policy.max_attempts, message),
+        };
+        let next_attempt_at = now + policy.backoff(attempts).as_millis() as i64;
+        conn.execute(
+            "UPDATE outbox SET attempts = ?2, next_attempt_at = ?3, last_error = ?4, dead = ?5
+             WHERE id = ?1",
+            params![id, attempts, next_attempt_at, message, dead],
+        )?;
+        Ok(dead)
+    })
+}
+
+/// Requeue a dead-lettered mutation for an immediate retry
+pub fn retry_dead_letter(store: &ProjectStore, id: &str) -> Result<bool, StoreError> {
+    store.with_conn(|conn| {
+        let changed = conn.execute(
+            "UPDATE outbox SET dead = 0, attempts = 0, next_attempt_at = 0 WHERE id = ?1 AND dead = 1",
+            params![id],
+        )?;
+        Ok(changed > 0)
+    })
+}
+
+pub fn discard_dead_letter(store: &ProjectStore, id: &str) -> Result<bool, StoreError> {
+    store.with_conn(|conn| {
+        let changed = conn.execute("DELETE FROM outbox WHERE id = ?1 AND dead = 1", params![id])?;
+        Ok(changed > 0)
+    })
+}
+
+/// Push every due mutation once, reporting after each attempt
+pub fn flush_due(
+    store: &ProjectStore,
+    transport: &dyn SyncTransport,
+    policy: &RetryPolicy,
+    now: i64,
+    mut on_progress: impl FnMut(&OutboxProgress),
+) -> Result<OutboxProgress, StoreError> {
+    let mut progress = OutboxProgress::default();
+    // Delivering a row's mutation can make its next one due, so keep
+    // querying; each mutation is tried at most once per pass
+    let mut attempted = HashSet::new();
+
+    loop {
+        let batch: Vec<_> = due(store, now, FLUSH_BATCH)?
+            .into_iter()
+            .filter(|entry| !attempted.contains(&entry.mutation.id))
+            .collect();
+        if batch.is_empty() {
+            break;
+        }
+        for entry in batch {
+            let mutation = &entry.mutation;
+            attempted.insert(mutation.id.clone());
+            match transport.push(mutation) {
+                Ok(()) => {
+                    mark_done(store, mutation)?;
+                    progress.pushed += 1;
+                }
+                Err(error) => {
+                    if mark_failed(store, &mutation.id, &error, policy, now)? {
+                        progress.dead_lettered += 1;
+                    } else {
+                        progress.retrying += 1;
+                    }
+                    let (PushError::Retryable(message) | PushError::Rejected(message)) = error;
+                    progress.last_error = Some(message);
+                }
+            }
+            progress.status = status(store)?;
+            on_progress(&progress);
+        }
+    }
+    progress.status = status(store)?;
+    Ok(progress)
+}
+
+#[derive(Default)]
+struct WorkerState {
+    endpoint: Option<SyncEndpoint>,
+    policy: RetryPolicy,
+    woken: bool,
+}
+
+/// Managed handle to the background push worker
+#[derive(Default)]
+pub struct OutboxWorker {
+    state: Mutex<WorkerState>,
+    wake: Condvar,
+}
+
+impl OutboxWorker {
+    pub fn configure(&self, endpoint: Option<SyncEndpoint>) {
+        self.state.lock().unwrap().endpoint = endpoint;
+        self.notify();
+    }
+
+    /// Ask the worker to check the queue now
+    pub fn notify(&self) {
+        self.state.lock().unwrap().woken = true;
+        self.wake.notify_all();
+    }
+
+    /// Sleep until notified or `timeout`; returns the endpoint and policy to use
+    fn wait(&self, timeout: Duration) -> (Option<SyncEndpoint>, RetryPolicy) {
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .wake
+            .wait_timeout_while(state, timeout, |s| !s.woken)
+            .unwrap();
+        state.woken = false;
+        (state.endpoint.clone(), state.policy)
+    }
+}
+
+/// Run the push loop for the app's outbox on a dedicated thread
+pub fn spawn_worker(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut timeout = Duration::ZERO;
+        loop {
+            let (endpoint, policy) = app.state::<OutboxWorker>().wait(timeout);
+            timeout = IDLE_POLL;
+            let Some(endpoint) = endpoint else {
+                continue;
+            };
+
+            let store = app.state::<ProjectStore>();
+            let transport = HttpTransport::new(endpoint);
+            let result = flush_due(&store, &transport, &policy, now_ms(), |progress| {
+                let _ = app.emit(OUTBOX_PROGRESS_EVENT, progress);
+            });
+            match result {
+                Ok(progress) => {
+                    if let Some(next) = progress.status.next_attempt_at {
+                        let wait = Duration::from_millis((next - now_ms()).max(0) as u64);
+                        timeout = wait.min(IDLE_POLL);
+                    }
+                }
+                Err(e) => eprintln!("[outbox] Flush failed: {}", e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn enqueue_mutation(
+    store: State<'_, ProjectStore>,
+    worker: State<'_, OutboxWorker>,
+    mutation: Mutation,
+) -> Result<(), StoreError> {
+    enqueue(&store, &mutation)?;
+    worker.notify();
+    Ok(())
+}
+
+/// Set (or clear, with `null`) the endpoint the worker pushes to
+#[tauri::command]
+pub fn configure_sync_endpoint(worker: State<'_, OutboxWorker>, endpoint: Option<SyncEndpoint>) {
+    worker.configure(endpoint);
+}
+
+#[tauri::command]
+pub fn flush_outbox(worker: State<'_, OutboxWorker>) {
+    worker.notify();
+}
+
+#[tauri::command]
+pub fn outbox_status(store: State<'_, ProjectStore>) -> Result<OutboxStatus, StoreError> {
+    status(&store)
+}
+
+#[tauri::command]
+pub fn list_dead_letters(store: State<'_, ProjectStore>) -> Result<Vec<OutboxEntry>, StoreError> {
+    dead_letters(&store)
+}
+
+#[tauri::command]
+pub fn retry_dead_mutation(
+    store: State<'_, ProjectStore>,
+    worker: State<'_, OutboxWorker>,
+    id: String,
+) -> Result<bool, StoreError> {
+    let requeued = retry_dead_letter(&store, &id)?;
+    worker.notify();
+    Ok(requeued)
+}
+
+#[tauri::command]
+pub fn discard_dead_mutation(
+    store: State<'_, ProjectStore>,
+    id: String,
+) -> Result<bool, StoreError> {
+    discard_dead_letter(&store, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{MutationType, SyncTable};
+    use serde_json::json;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Sync endpoint answering each request with the next scripted status,
+    /// recording the mutation ids it received
+    fn mock_sync_endpoint(script: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sync", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        thread::spawn(move || {
+            for status in script {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let mutation: Mutation = serde_json::from_slice(&body).unwrap();
+                log.lock().unwrap().push(mutation.id);
+
+                let mut stream = stream;
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Scripted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    fn mutation(id: &str, pk: &str) -> Mutation {
+        Mutation {
+            id: id.into(),
+            table: SyncTable::Documents,
+            kind: MutationType::Upsert,
+            row: Some(json!({"id": pk, "title": id})),
+            pk: None,
+            base_version: Some(1),
+            created_at: "2026-01-01T00:00:00Z".into(),
+            project_id: "p1".into(),
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(1000),
+            max_delay: Duration::from_millis(8000),
+            max_attempts: 3,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = policy();
+        let delays: Vec<_> = (1..=5).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [1000, 2000, 4000, 8000, 8000]);
+    }
+
+    #[test]
+    fn formats_iso_timestamps() {
+        assert_eq!(iso_timestamp(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(iso_timestamp(951_782_400_123), "2000-02-29T00:00:00.123Z");
+        assert_eq!(iso_timestamp(1_714_557_600_000), "2024-05-01T10:00:00.000Z");
+    }
+
+    #[test]
+    fn survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rhei.db");
+        enqueue(&ProjectStore::open(&path).unwrap(), &mutation("m1", "d1")).unwrap();
+
+        let store = ProjectStore::open(&path).unwrap();
+        assert_eq!(pending(&store).unwrap()[0].mutation, mutation("m1", "d1"));
+    }
+
+    #[test]
+    fn retries_with_backoff_against_mock_endpoint() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        enqueue(&store, &mutation("m1", "d1")).unwrap();
+        enqueue(&store, &mutation("m2", "d1")).unwrap();
+
+        let (url, received) = mock_sync_endpoint(vec![503, 200, 200]);
+        let transport = HttpTransport::new(SyncEndpoint { url, token: None });
+
+        // First attempt fails; m2 waits behind m1 for the same row
+        let mut events = Vec::new();
+        let progress =
+            flush_due(&store, &transport, &policy(), 0, |p| events.push(p.clone())).unwrap();
+        assert_eq!((progress.pushed, progress.retrying), (0, 1));
+        assert_eq!(progress.status.next_attempt_at, Some(1000));
+        assert_eq!(events.len(), 1);
+        assert!(due(&store, 999, 10).unwrap().is_empty());
+
+        // After the backoff both go out, in order
+        let progress = flush_due(&store, &transport, &policy(), 1000, |_| {}).unwrap();
+        assert_eq!(progress.pushed, 2);
+        assert_eq!(progress.status, OutboxStatus::default());
+        assert_eq!(*received.lock().unwrap(), ["m1", "m1", "m2"]);
+    }
+
+    #[test]
+    fn dead_letters_poison_rows_without_blocking_others() {
+        let store = ProjectStore::open_in_memory().unwrap();
+        enqueue(&store, &mutation("poison", "d1")).unwrap();
+        enqueue(&store, &mutation("ok", "d2")).unwrap();
+
+        let (url, received) = mock_sync_endpoint(vec![422, 200]);
+        let transport = HttpTransport::new(SyncEndpoint { url, token: None });
+        let progress = flush_due(&store, &transport, &policy(), 0, |_| {}).unwrap();
+
+        assert_eq!((progress.pushed, progress.dead_lettered), (1, 1));
+        assert_eq!(*received.lock().unwrap(), ["poison", "ok"]);
+        let dead = dead_letters(&store).unwrap();
+        assert_eq!(dead[0].mutation.id, "poison");
+        assert!(dead[0]
+            .last_error
+            .as_deref()
+            .unwrap()
+            .starts_with("HTTP 422"));
+
+        assert!(retry_dead_letter(&store, "poison").unwrap());
+        assert_eq!(status(&store).unwrap().pending, 1);
+    }
+
+    #[test]
+    fn dead_letters_after_max_attempts() {
+        struct Offline;
+        impl SyncTransport for Offline {
+            fn push(&self, _: &Mutation) -> Result<(), PushError> {
+                Err(PushError::Retryable("connection refused".into()))
+            }
+        }
+
+        let store = ProjectStore::open_in_memory().unwrap();
+        enqueue(&store, &mutation("m1", "d1")).unwrap();
+        for now in [0, 10_000, 20_000, 30_000] {
+            flush_due(&store, &Offline, &policy(), now, |_| {}).unwrap();
+        }
+        assert_eq!(
+            status(&store).unwrap(),
+            OutboxStatus {
+                pending: 0,
+                dead: 1,
+                held: 0,
+                next_attempt_at: None
+            }
+        );
+        assert_eq!(dead_letters(&store).unwrap()[0].attempts, 3);
+    }
+}
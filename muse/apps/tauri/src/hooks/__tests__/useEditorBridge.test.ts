@@ -0,0 +1,88 @@
+import { beforeEach, describe, expect, it, vi } from 'vitest';
+
+// Also decoded by `decodes_envelopes_forwarded_by_the_host` in src-tauri/src/bridge/mod.rs
+import envelopes from '../../../src-tauri/testdata/bridge/editor-envelopes.json';
+
+const { invoke } = vi.hoisted(() => ({ invoke: vi.fn() }));
+vi.mock('@tauri-apps/api/core', () => ({ invoke }));
+vi.mock('@tauri-apps/api/event', () => ({ listen: vi.fn(async () => () => {}) }));
+
+import { createEditorMessageHandler, type EditorToNativeMessage } from '../useEditorBridge';
+
+const EDITOR_ORIGIN = 'http://localhost:1421';
+
+function setup() {
+  const editorWindow = {} as Window;
+  const received: EditorToNativeMessage[] = [];
+  const rejected: unknown[] = [];
+  const handle = createEditorMessageHandler({
+    editorWindow: () => editorWindow,
+    isOriginAllowed: (origin) => origin === EDITOR_ORIGIN,
+    onMessage: (message) => received.push(message),
+    onRejected: (error) => rejected.push(error),
+  });
+  const post = (data: unknown, source: unknown = editorWindow, origin = EDITOR_ORIGIN) =>
+    handle({ data, source, origin } as MessageEvent);
+  return { post, received, rejected };
+}
+
+describe('editor messages', () => {
+  beforeEach(() => {
+    invoke.mockReset();
+  });
+
+  it('forwards each envelope to the shell decoder and acts on what it returns', async () => {
+    // Stand-in for the shell: hand back the payload it decoded
+    invoke.mockImplementation(async (_command: string, args: { message: string }) => {
+      return JSON.parse(args.message).payload;
+    });
+    const { post, received } = setup();
+
+    for (const envelope of envelopes) {
+      await post(envelope);
+    }
+
+    expect(invoke.mock.calls).toEqual(
+      envelopes.map((envelope) => ['editor_message', { message: JSON.stringify(envelope) }])
+    );
+    expect(received).toEqual(envelopes.map((envelope) => envelope.payload));
+  });
+
+  it('keeps the posting order when the shell answers out of order', async () => {
+    const answers: Array<() => void> = [];
+    invoke.mockImplementation(
+      (_command: string, args: { message: string }) =>
+        new Promise((resolve) => answers.push(() => resolve(JSON.parse(args.message).payload)))
+    );
+    const { post, received } = setup();
+
+    const first = post(envelopes[0]);
+    const second = post(envelopes[1]);
+    answers[1]();
+    answers[0]();
+    await Promise.all([first, second]);
+
+    expect(received.map((message) => message.type)).toEqual(['editorReady', 'titleChange']);
+  });
+
+  it('only forwards what the editor iframe posts from its origin', async () => {
+    const { post, received } = setup();
+
+    await post(envelopes[1], {});
+    await post(envelopes[1], undefined, 'https://evil.example');
+
+    expect(invoke).not.toHaveBeenCalled();
+    expect(received).toEqual([]);
+  });
+
+  it('drops what the shell rejects', async () => {
+    const error = { code: 'bridge_nonce_mismatch', message: 'bridge nonce mismatch' };
+    invoke.mockRejectedValue(error);
+    const { post, received, rejected } = setup();
+
+    await post({ ...envelopes[1], nonce: 'forged' });
+
+    expect(received).toEqual([]);
+    expect(rejected).toEqual([error]);
+  });
+});
@@ -1,17 +1,16 @@
 /**
  * useDeepLinks - Tauri deep link handler for rhei:// URLs
  *
- * Uses the @tauri-apps/plugin-deep-link plugin to handle incoming URLs
- * on macOS desktop. Parses URLs and triggers navigation in the SPA.
+ * URLs are parsed and validated by the Rust shell, which emits typed
+ * `deep-link://navigate` events. Links received before this hook mounts
+ * (e.g. the URL that cold-launched the app) are queued in the shell and
+ * drained once the listener is registered.
  */
 
 import { useEffect, useCallback } from "react";
-import { onOpenUrl, getCurrent } from "@tauri-apps/plugin-deep-link";
-import {
-  parseRheiUrl,
-  isRheiDeepLink,
-  type RheiDeepLink,
-} from "@mythos/core";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { RheiDeepLink } from "@mythos/core";
 import { useArtifactStore, useProjectStore } from "@mythos/state";
 
 interface UseDeepLinksOptions {
@@ -23,19 +22,8 @@ interface UseDeepLinksOptions {
 export function useDeepLinks(options: UseDeepLinksOptions = {}): void {
   const { enabled = true, onDeepLink, onNavigate } = options;
 
-  const handleUrl = useCallback(
-    (url: string) => {
-      if (!isRheiDeepLink(url)) {
-        console.log("[DeepLink] Ignoring non-rhei URL:", url);
-        return;
-      }
-
-      const link = parseRheiUrl(url);
-      if (!link) {
-        console.warn("[DeepLink] Failed to parse URL:", url);
-        return;
-      }
-
+  const handleLink = useCallback(
+    (link: RheiDeepLink) => {
       console.log("[DeepLink] Handling:", link);
 
       // Notify callback
@@ -63,27 +51,7 @@ export function useDeepLinks(options: UseDeepLinksOptions = {}): void {
     [onDeepLink, onNavigate]
   );
 
-  // Handle URLs that triggered app launch
-  useEffect(() => {
-    if (!enabled) return;
-
-    const handleInitialUrls = async () => {
-      try {
-        const urls = await getCurrent();
-        if (urls && urls.length > 0) {
-          // Handle the most recent URL
-          handleUrl(urls[urls.length - 1]);
-        }
-      } catch (error) {
-        // Plugin may not be available in dev mode
-        console.log("[DeepLink] getCurrent not available:", error);
-      }
-    };
-
-    handleInitialUrls();
-  }, [enabled, handleUrl]);
-
-  // Listen for URLs while app is running
+  // Listen for links from the shell, then replay any queued before mount
   useEffect(() => {
     if (!enabled) return;
 
@@ -91,15 +59,18 @@ export function useDeepLinks(options: UseDeepLinksOptions = {}): void {
 
     const setupListener = async () => {
       try {
-        unlistenFn = await onOpenUrl((urls) => {
-          if (urls && urls.length > 0) {
-            // Handle the most recent URL
-            handleUrl(urls[urls.length - 1]);
-          }
+        // Already parsed by the shell; garbage URLs never get here
+        unlistenFn = await listen<RheiDeepLink>("deep-link://navigate", (event) => {
+          handleLink(event.payload);
         });
+
+        // Drain only after listening so nothing falls between queue and events
+        const pending = await invoke<RheiDeepLink[]>("take_pending_deep_links");
+        for (const link of pending) {
+          handleLink(link);
+        }
       } catch (error) {
-        // Plugin may not be available in dev mode
-        console.log("[DeepLink] onOpenUrl not available:", error);
+        console.log("[DeepLink] Navigation listener not available:", error);
       }
     };
 
@@ -108,7 +79,7 @@ export function useDeepLinks(options: UseDeepLinksOptions = {}): void {
     return () => {
       unlistenFn?.();
     };
-  }, [enabled, handleUrl]);
+  }, [enabled, handleLink]);
 }
 
 // Navigation handlers
@@ -1,7 +1,12 @@
 import { useEffect, useRef, useState, useCallback, useMemo } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
-const BRIDGE_VERSION = '1.0.0';
+/**
+ * Stamped on envelopes until the shell has negotiated the editor's version
+ * (see `BASELINE_VERSION` in src-tauri/src/bridge/protocol.rs)
+ */
+const BASELINE_VERSION = '1.0.0';
 
 type BridgeEnvelope<T> = {
   type: 'editor-bridge' | 'editor-bridge-response';
@@ -33,6 +38,7 @@ export type EditorToNativeMessage =
   | { type: 'editorFocused' }
   | { type: 'editorBlurred' }
   | { type: 'aiRequest'; selectedText: string; prompt?: string; action?: string }
+  | { type: 'insertImageRequested'; position: number }
   | { type: 'error'; code: string; message: string };
 
 export type NativeToEditorMessage =
@@ -40,6 +46,7 @@ export type NativeToEditorMessage =
   | { type: 'setTitle'; title: string }
   | { type: 'insertContent'; content: string; at?: number }
   | { type: 'replaceSelection'; content: string }
+  | { type: 'insertImage'; src: string; alt?: string; at?: number }
   | { type: 'addSuggestion'; suggestion: unknown }
   | { type: 'acceptSuggestion'; id: string }
   | { type: 'rejectSuggestion'; id: string }
@@ -62,6 +69,23 @@ export type NativeToEditorMessage =
   | { type: 'redo' }
   | { type: 'configure'; options: BridgeConfigureOptions };
 
+export type BridgeCapability =
+  | 'content'
+  | 'title'
+  | 'media'
+  | 'suggestions'
+  | 'collaboration'
+  | 'editorControl';
+
+/** Outcome of the shell's editorReady version handshake */
+export interface NegotiatedProtocol {
+  nativeVersion: string;
+  editorVersion: string;
+  capabilities: BridgeCapability[];
+  disabled: BridgeCapability[];
+  downgraded: boolean;
+}
+
 export interface EditorState {
   ready: boolean;
   content: string;
@@ -71,6 +95,41 @@ export interface EditorState {
   focused: boolean;
 }
 
+/**
+ * Hand an envelope posted by the editor iframe to the shell, which checks its
+ * nonce, version and size, runs the editorReady handshake and indexes edits.
+ * Resolves to the decoded message; rejects with the shell's BridgeError.
+ */
+export function forwardEditorEnvelope(envelope: unknown): Promise<EditorToNativeMessage> {
+  return invoke<EditorToNativeMessage>('editor_message', { message: JSON.stringify(envelope) });
+}
+
+export interface EditorMessageHandlerOptions {
+  /** The editor iframe's window, once mounted */
+  editorWindow: () => Window | null | undefined;
+  isOriginAllowed: (origin: string) => boolean;
+  /** Called with each message the shell accepted, in the order they were posted */
+  onMessage: (message: EditorToNativeMessage) => void;
+  onRejected?: (error: unknown) => void;
+}
+
+/**
+ * `message` event listener for the host window. The iframe has no Tauri IPC
+ * of its own, so everything it posts goes through the shell's decoder.
+ */
+export function createEditorMessageHandler(options: EditorMessageHandlerOptions) {
+  let queue: Promise<void> = Promise.resolve();
+  return (event: MessageEvent): Promise<void> => {
+    const editorWindow = options.editorWindow();
+    if (!editorWindow || event.source !== editorWindow) return queue;
+    if (!options.isOriginAllowed(event.origin)) return queue;
+
+    const decoded = forwardEditorEnvelope(event.data);
+    queue = queue.then(() => decoded.then(options.onMessage, (error) => options.onRejected?.(error)));
+    return queue;
+  };
+}
+
 export interface UseEditorBridgeOptions {
   onMessage?: (message: EditorToNativeMessage) => void;
   onReady?: () => void;
@@ -80,7 +139,6 @@ export interface UseEditorBridgeOptions {
   bridgeNonce?: string;
   editorOrigin?: string;
   hostOrigin?: string;
-  expectedVersion?: string;
 }
 
 export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
@@ -94,10 +152,12 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
     focused: false,
   });
 
+  const [protocol, setProtocol] = useState<NegotiatedProtocol | null>(null);
+
   const bridgeNonceRef = useRef<string | null>(options.bridgeNonce ?? null);
   const editorOriginRef = useRef<string | null>(options.editorOrigin ?? null);
   const hostOriginRef = useRef<string | null>(options.hostOrigin ?? null);
-  const expectedVersionRef = useRef<string>(options.expectedVersion ?? BRIDGE_VERSION);
+  const protocolRef = useRef<NegotiatedProtocol | null>(null);
   const bridgeDisabledRef = useRef(false);
   const configuredRef = useRef(false);
 
@@ -113,10 +173,6 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
     hostOriginRef.current = options.hostOrigin ?? null;
   }, [options.hostOrigin]);
 
-  useEffect(() => {
-    expectedVersionRef.current = options.expectedVersion ?? BRIDGE_VERSION;
-  }, [options.expectedVersion]);
-
   // Memoize callbacks to avoid effect re-runs
   const callbacks = useMemo(
     () => ({
@@ -164,6 +220,12 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
         return;
       }
 
+      // Title updates need a 1.1.0 editor
+      if (message.type === 'setTitle' && !protocolRef.current?.capabilities.includes('title')) {
+        console.warn('[useEditorBridge] Editor does not support setTitle; dropping message');
+        return;
+      }
+
       const nonce = bridgeNonceRef.current;
       if (!nonce) {
         console.warn('[useEditorBridge] Missing bridge nonce; dropping message');
@@ -180,7 +242,7 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
         type: 'editor-bridge',
         payload: message,
         nonce,
-        version: expectedVersionRef.current,
+        version: protocolRef.current?.editorVersion ?? BASELINE_VERSION,
       };
 
       iframe.contentWindow.postMessage(envelope, targetOrigin);
@@ -199,10 +261,14 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
     const optionsPayload: BridgeConfigureOptions = {
       bridgeNonce: nonce,
       allowedOrigins: hostOrigin ? [hostOrigin] : undefined,
-      nativeVersion: expectedVersionRef.current,
     };
 
     configuredRef.current = true;
+    protocolRef.current = null;
+    setProtocol(null);
+    invoke('configure_editor_bridge', { nonce }).catch((e) => {
+      console.error('[useEditorBridge] Failed to register bridge nonce:', e);
+    });
     postToEditor({ type: 'configure', options: optionsPayload });
   }, [postToEditor]);
 
@@ -234,101 +300,117 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
       user: { id: string; name: string; avatarUrl?: string };
       authToken?: string;
       convexUrl?: string;
-    }) => sendToEditor({ type: 'connectCollaboration', ...payload }),
+    }) => {
+      // Lets the shell index this document's edits for local search
+      invoke('set_editor_document', { documentId: payload.documentId }).catch((e) => {
+        console.error('[useEditorBridge] Failed to set editor document:', e);
+      });
+      sendToEditor({ type: 'connectCollaboration', ...payload });
+    },
     [sendToEditor]
   );
 
-  const disconnectCollaboration = useCallback(
-    () => sendToEditor({ type: 'disconnectCollaboration' }),
-    [sendToEditor]
-  );
+  const disconnectCollaboration = useCallback(() => {
+    invoke('set_editor_document', { documentId: null }).catch(() => {});
+    sendToEditor({ type: 'disconnectCollaboration' });
+  }, [sendToEditor]);
 
-  // Listen for messages from iframe via postMessage
+  // Messages posted by the iframe, once the shell has validated them
   useEffect(() => {
-    const handleMessage = (event: MessageEvent) => {
-      if (bridgeDisabledRef.current) return;
-      const iframeWindow = iframeRef.current?.contentWindow;
-      if (!iframeWindow || event.source !== iframeWindow) return;
-      if (!isOriginAllowed(event.origin)) return;
-
-      const data = event.data as BridgeEnvelope<EditorToNativeMessage> | undefined;
-      if (!data || data.type !== 'editor-bridge-response' || !data.payload) return;
+    const handleMessage = createEditorMessageHandler({
+      editorWindow: () => iframeRef.current?.contentWindow,
+      isOriginAllowed,
+      onMessage: (message) => {
+        if (bridgeDisabledRef.current) return;
+
+        switch (message.type) {
+          case 'editorReady':
+            setEditorState((prev) => ({ ...prev, ready: true }));
+            callbacks.onReady?.();
+            break;
+          case 'contentChange':
+            setEditorState((prev) => ({
+              ...prev,
+              content: message.content,
+              html: message.html,
+              json: message.json ?? null,
+            }));
+            callbacks.onContentChange?.(message.content, message.html, message.json);
+            break;
+          case 'selectionChange':
+            setEditorState((prev) => ({ ...prev, selection: message.selection }));
+            callbacks.onSelectionChange?.(message.selection);
+            break;
+          case 'editorFocused':
+            setEditorState((prev) => ({ ...prev, focused: true }));
+            break;
+          case 'editorBlurred':
+            setEditorState((prev) => ({ ...prev, focused: false }));
+            break;
+          case 'aiRequest':
+            callbacks.onAIRequest?.(message.selectedText, message.prompt, message.action);
+            break;
+        }
 
-      const nonce = bridgeNonceRef.current;
-      if (!nonce || data.nonce !== nonce) return;
+        callbacks.onMessage?.(message);
+      },
+      onRejected: (error) => {
+        console.error('[useEditorBridge] Shell rejected editor message:', error);
+        // An editor outside the supported range fails the handshake
+        const code = (error as { code?: string } | null)?.code;
+        if (code === 'bridge_version_mismatch' && !protocolRef.current) {
+          bridgeDisabledRef.current = true;
+        }
+      },
+    });
 
-      if (data.version !== expectedVersionRef.current) {
-        bridgeDisabledRef.current = true;
-        console.error(
-          `[useEditorBridge] Bridge version mismatch (editor ${data.version}, native ${expectedVersionRef.current})`
-        );
-        return;
-      }
+    const listener = (event: MessageEvent) => {
+      void handleMessage(event);
+    };
+    window.addEventListener('message', listener);
+    return () => window.removeEventListener('message', listener);
+  }, [callbacks, isOriginAllowed]);
 
-      const message = data.payload;
+  // Capabilities negotiated by the shell when the editor reports editorReady
+  useEffect(() => {
+    let unlisten: UnlistenFn | undefined;
 
-      if (message.type === 'editorReady' && message.version !== expectedVersionRef.current) {
-        bridgeDisabledRef.current = true;
-        console.error(
-          `[useEditorBridge] Editor ready version mismatch (editor ${message.version}, native ${expectedVersionRef.current})`
+    listen<NegotiatedProtocol | null>('editor-bridge-negotiated', (event) => {
+      protocolRef.current = event.payload;
+      setProtocol(event.payload);
+      if (event.payload?.downgraded) {
+        console.warn(
+          `[useEditorBridge] Editor bundle ${event.payload.editorVersion} is older than shell ${event.payload.nativeVersion}; disabled:`,
+          event.payload.disabled
         );
-        return;
-      }
-
-      switch (message.type) {
-        case 'editorReady':
-          setEditorState((prev) => ({ ...prev, ready: true }));
-          callbacks.onReady?.();
-          break;
-        case 'contentChange':
-          setEditorState((prev) => ({
-            ...prev,
-            content: message.content,
-            html: message.html,
-            json: message.json ?? null,
-          }));
-          callbacks.onContentChange?.(message.content, message.html, message.json);
-          break;
-        case 'selectionChange':
-          setEditorState((prev) => ({ ...prev, selection: message.selection }));
-          callbacks.onSelectionChange?.(message.selection);
-          break;
-        case 'editorFocused':
-          setEditorState((prev) => ({ ...prev, focused: true }));
-          break;
-        case 'editorBlurred':
-          setEditorState((prev) => ({ ...prev, focused: false }));
-          break;
-        case 'aiRequest':
-          callbacks.onAIRequest?.(message.selectedText, message.prompt, message.action);
-          break;
       }
+    }).then((fn) => {
+      unlisten = fn;
+    });
 
-      callbacks.onMessage?.(message);
+    return () => {
+      unlisten?.();
     };
+  }, []);
 
-    window.addEventListener('message', handleMessage);
-    return () => window.removeEventListener('message', handleMessage);
-  }, [callbacks, isOriginAllowed]);
-
-  // Listen for Tauri events from Rust backend (fallback path when using __TAURI__.invoke)
+  // Forward native commands from the Rust shell (menus, deep links, background jobs).
+  // Envelopes are built and nonce-stamped by the shell; the editor validates them.
   useEffect(() => {
     let unlisten: UnlistenFn | undefined;
 
-    listen<string>('editor-message', (event) => {
-      try {
-        const message = JSON.parse(event.payload) as EditorToNativeMessage;
-        if (message.type === 'editorReady' && message.version !== expectedVersionRef.current) {
-          bridgeDisabledRef.current = true;
-          console.error(
-            `[useEditorBridge] Editor ready version mismatch (editor ${message.version}, native ${expectedVersionRef.current})`
-          );
-          return;
-        }
-        callbacks.onMessage?.(message);
-      } catch (e) {
-        console.error('[useEditorBridge] Failed to parse Tauri event:', e);
+    listen<BridgeEnvelope<NativeToEditorMessage>>('editor-command', (event) => {
+      if (bridgeDisabledRef.current || !configuredRef.current) return;
+
+      const envelope = event.payload;
+      if (envelope.nonce !== bridgeNonceRef.current) {
+        console.warn('[useEditorBridge] Dropping native command with stale nonce');
+        return;
       }
+
+      const iframe = iframeRef.current;
+      const targetOrigin = resolveEditorOrigin();
+      if (!iframe?.contentWindow || !targetOrigin) return;
+      iframe.contentWindow.postMessage(envelope, targetOrigin);
     }).then((fn) => {
       unlisten = fn;
     });
@@ -336,11 +418,12 @@ export function useEditorBridge(options: UseEditorBridgeOptions = {}) {
     return () => {
       unlisten?.();
     };
-  }, [callbacks]);
+  }, [resolveEditorOrigin]);
 
   return {
     iframeRef,
     editorState,
+    protocol,
     sendToEditor,
     configure,
     setContent,
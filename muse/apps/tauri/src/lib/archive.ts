@@ -0,0 +1,37 @@
+/**
+ * Project Archives
+ *
+ * Wrappers around the shell's archive commands (src-tauri/src/archive.rs).
+ * An archive is a single zip with the project's rows, the local files they
+ * reference, and a checksummed manifest.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+export interface ArchiveSummary {
+  projectId: string;
+  snapshotVersion: number;
+  rows: number;
+  assets: number;
+}
+
+export function exportProjectArchive(
+  projectId: string,
+  path: string
+): Promise<ArchiveSummary> {
+  return invoke("export_project_archive", { projectId, path });
+}
+
+/**
+ * Import an archive, optionally under a different project id.
+ * Fails with `archive_project_exists` unless `overwrite` is set, and with
+ * `archive_row_in_use` when this store still holds the archived rows under
+ * another project id.
+ */
+export function importProjectArchive(
+  path: string,
+  projectId?: string,
+  overwrite = false
+): Promise<ArchiveSummary> {
+  return invoke("import_project_archive", { path, projectId: projectId ?? null, overwrite });
+}
@@ -15,7 +15,9 @@
  */
 
 import { useAuthActions } from "@convex-dev/auth/react";
+import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { open } from "@tauri-apps/plugin-shell";
 import { initAuthConfig, setPlatform } from "@mythos/auth";
 
 // Environment variables (from Vite)
@@ -46,8 +48,10 @@ type AuthCallbackParams = {
   error_description?: string;
 };
 
-function getAuthRedirectTo(): string {
-  return `${SCHEME}://auth/callback`;
+// The shell only forwards callbacks carrying a state it issued
+async function getAuthRedirectTo(): Promise<string> {
+  const { redirectTo } = await invoke<{ redirectTo: string }>("start_auth_redirect");
+  return redirectTo;
 }
 
 function parseAuthCallback(url: string): AuthCallbackParams | null {
@@ -88,7 +92,7 @@ export function useSignInWithEmail() {
   return async (email: string) => {
     const formData = new FormData();
     formData.append("email", email);
-    formData.append("redirectTo", getAuthRedirectTo());
+    formData.append("redirectTo", await getAuthRedirectTo());
     return signIn("resend", formData);
   };
 }
@@ -100,9 +104,9 @@ export function useSignInWithEmail() {
  */
 export function useSignInWithGitHub() {
   const { signIn } = useAuthActions();
-  return () => {
+  return async () => {
     console.warn("[auth] GitHub OAuth on desktop requires hosted redirect page (Phase 2)");
-    return signIn("github", { redirectTo: getAuthRedirectTo() });
+    return signIn("github", { redirectTo: await getAuthRedirectTo() });
   };
 }
 
@@ -112,9 +116,9 @@ export function useSignInWithGitHub() {
  */
 export function useSignInWithApple() {
   const { signIn } = useAuthActions();
-  return () => {
+  return async () => {
     console.warn("[auth] Apple OAuth on desktop requires hosted redirect page (Phase 2)");
-    return signIn("apple", { redirectTo: getAuthRedirectTo() });
+    return signIn("apple", { redirectTo: await getAuthRedirectTo() });
   };
 }
 
@@ -124,12 +128,46 @@ export function useSignInWithApple() {
  */
 export function useSignInWithGoogle() {
   const { signIn } = useAuthActions();
-  return () => {
+  return async () => {
     console.warn("[auth] Google OAuth on desktop requires hosted redirect page (Phase 2)");
-    return signIn("google", { redirectTo: getAuthRedirectTo() });
+    return signIn("google", { redirectTo: await getAuthRedirectTo() });
   };
 }
 
+export type NativeOAuthProviderConfig = {
+  provider: string;
+  authorizationEndpoint: string;
+  tokenEndpoint: string;
+  clientId: string;
+  scopes?: string[];
+  redirectUri?: string;
+};
+
+export type NativeOAuthTokens = {
+  accessToken: string;
+  tokenType: string;
+  expiresIn?: number;
+  refreshToken?: string;
+  idToken?: string;
+  scope?: string;
+};
+
+/**
+ * Sign in with a PKCE flow owned by the Rust shell.
+ * The shell generates the verifier/state, validates the rhei:// callback and
+ * exchanges the code; forged or replayed callbacks never reach the webview.
+ */
+export async function signInWithNativeOAuth(
+  config: NativeOAuthProviderConfig
+): Promise<NativeOAuthTokens> {
+  const { authorizationUrl, state } = await invoke<{ authorizationUrl: string; state: string }>(
+    "start_oauth",
+    { config }
+  );
+  await open(authorizationUrl);
+  return invoke<NativeOAuthTokens>("complete_oauth", { state });
+}
+
 /**
  * Sign out hook
  */
@@ -144,20 +182,31 @@ export function useSignOut() {
 
 /**
  * Setup deep link listener for OAuth callbacks
+ *
+ * Callbacks that arrived before this ran (cold launch) are queued by the
+ * shell and replayed in order once the listener is registered.
  */
 export async function setupAuthDeepLinks(
   onAuthCallback?: (params: AuthCallbackParams) => void
 ): Promise<() => void> {
-  try {
-    const unlisten = await listen("deep-link://new-url", async (event) => {
-      const url = event.payload as string;
-      const params = parseAuthCallback(url);
+  const handleCallbackUrl = (url: string) => {
+    const params = parseAuthCallback(url);
 
-      if (params && onAuthCallback) {
-        onAuthCallback(params);
-      }
+    if (params && onAuthCallback) {
+      onAuthCallback(params);
+    }
+  };
+
+  try {
+    const unlisten = await listen<string>("deep-link://auth-callback", (event) => {
+      handleCallbackUrl(event.payload);
     });
 
+    const pending = await invoke<string[]>("take_pending_auth_callbacks");
+    for (const url of pending) {
+      handleCallbackUrl(url);
+    }
+
     return unlisten;
   } catch (error) {
     console.error("[auth] Failed to setup deep link listener:", error);
@@ -0,0 +1,49 @@
+/**
+ * Local Backups
+ *
+ * Wrappers around the shell's backup commands (src-tauri/src/backup.rs).
+ * The shell copies the local store on a schedule and on quit, keeping the
+ * newest copy per hour, day and week according to the retention settings.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+export type BackupReason = "scheduled" | "quit" | "manual" | "pre_restore";
+
+export interface BackupInfo {
+  id: string;
+  createdAtMs: number;
+  reason: BackupReason;
+  size: number;
+}
+
+export interface BackupSettings {
+  /** Minutes between scheduled backups; 0 disables them */
+  intervalMinutes: number;
+  retention: { hourly: number; daily: number; weekly: number };
+}
+
+/** Newest first */
+export function listBackups(): Promise<BackupInfo[]> {
+  return invoke("list_backups");
+}
+
+export function createBackupNow(): Promise<BackupInfo> {
+  return invoke("create_backup_now");
+}
+
+/**
+ * Replace the local store with a backup. Resolves with the backup taken of
+ * the replaced state, which can be restored to undo.
+ */
+export function restoreBackup(id: string): Promise<BackupInfo> {
+  return invoke("restore_backup", { id });
+}
+
+export function getBackupSettings(): Promise<BackupSettings> {
+  return invoke("get_backup_settings");
+}
+
+export function configureBackups(settings: BackupSettings): Promise<void> {
+  return invoke("configure_backups", { settings });
+}
@@ -0,0 +1,75 @@
+/**
+ * Sync Conflicts
+ *
+ * Review queue for conflicts between offline edits and server changes,
+ * detected by the shell (src-tauri/src/sync/conflicts.rs). Per-table
+ * strategies decide what is settled automatically; everything else waits
+ * here with both values until the writer picks one.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { SyncTable } from "./localStore";
+
+export const CONFLICT_EVENT = "sync://conflict";
+
+/** Mirrors `ConflictStrategy` in `packages/sync/src/types.ts` */
+export type ConflictStrategy =
+  | "last_writer_wins"
+  | "server_wins"
+  | "client_wins"
+  | "merge"
+  | "manual";
+
+export type ConflictResolution =
+  | { type: "local" }
+  | { type: "server" }
+  | { type: "merged"; row: Record<string, unknown> };
+
+export interface SyncConflict {
+  id: string;
+  projectId: string;
+  table: SyncTable;
+  pk: string;
+  strategy: ConflictStrategy;
+  /** `null` when the local side deleted the row */
+  localValue: Record<string, unknown> | null;
+  /** `null` when the server deleted the row */
+  serverValue: Record<string, unknown> | null;
+  baseValue: Record<string, unknown> | null;
+  serverVersion: number;
+  /** Fields changed on both sides */
+  fields: string[];
+  createdAt: number;
+  resolvedAt: number | null;
+  resolution: ConflictResolution | null;
+}
+
+export interface ApplyReport {
+  applied: number;
+  autoResolved: number;
+  conflicts: SyncConflict[];
+}
+
+export function listConflicts(projectId?: string): Promise<SyncConflict[]> {
+  return invoke("list_conflicts", { projectId: projectId ?? null });
+}
+
+export function resolveConflict(
+  id: string,
+  resolution: ConflictResolution
+): Promise<SyncConflict | null> {
+  return invoke("resolve_conflict", { id, resolution });
+}
+
+export function getConflictStrategies(): Promise<Record<SyncTable, ConflictStrategy>> {
+  return invoke("get_conflict_strategies");
+}
+
+export function setConflictStrategy(table: SyncTable, strategy: ConflictStrategy): Promise<void> {
+  return invoke("set_conflict_strategy", { table, strategy });
+}
+
+export function onConflict(handler: (conflict: SyncConflict) => void): Promise<UnlistenFn> {
+  return listen<SyncConflict>(CONFLICT_EVENT, (event) => handler(event.payload));
+}
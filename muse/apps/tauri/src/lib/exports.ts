@@ -0,0 +1,116 @@
+/**
+ * Manuscript Exports
+ *
+ * Wrappers around the shell's export commands (src-tauri/src/export/). Books
+ * are assembled in Rust from the local store, off the main thread, so long
+ * manuscripts export without freezing the webview.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+export interface EpubOptions {
+  /** Defaults to "Untitled" */
+  title?: string;
+  authors?: string[];
+  /** BCP 47 tag; defaults to "en" */
+  language?: string;
+  /** ISBN or URN; derived from the project id when omitted */
+  identifier?: string;
+  description?: string;
+  publisher?: string;
+  /** Path to a JPEG, PNG, GIF, WebP or SVG image */
+  coverImage?: string;
+  /** Paths to TTF, OTF, WOFF or WOFF2 files; the first sets the body face */
+  fonts?: string[];
+  /** Export only these documents; otherwise notes, outlines and worldbuilding are left out */
+  documentIds?: string[];
+}
+
+export interface EpubSummary {
+  projectId: string;
+  documents: number;
+  /** Links in the table of contents */
+  tocEntries: number;
+  fonts: number;
+  cover: boolean;
+  bytes: number;
+}
+
+export function exportEpub(
+  projectId: string,
+  path: string,
+  options: EpubOptions = {}
+): Promise<EpubSummary> {
+  return invoke("export_epub", { projectId, path, options });
+}
+
+// ============================================================================
+// Standard manuscript format (DOCX)
+// ============================================================================
+
+export interface DocxOptions {
+  /** Defaults to "Untitled" */
+  title?: string;
+  /** Legal name, first line of the title page contact block */
+  author?: string;
+  /** Byline; the author's name when omitted */
+  penName?: string;
+  /** Address, phone, email and the like, one per line */
+  contact?: string[];
+  /** Running header surname; the byline's last word when omitted */
+  surname?: string;
+  /** Running header keyword; the title in capitals when omitted */
+  keyword?: string;
+  /** Underline instead of italics, as typewritten manuscripts did */
+  italicsAsUnderline?: boolean;
+  /** Defaults to "Times New Roman" */
+  font?: string;
+  /** Export only these documents; otherwise notes, outlines and worldbuilding are left out */
+  documentIds?: string[];
+}
+
+export interface DocxSummary {
+  projectId: string;
+  documents: number;
+  chapters: number;
+  sceneBreaks: number;
+  /** Exact count; the title page shows it rounded */
+  words: number;
+  bytes: number;
+}
+
+export function exportDocx(
+  projectId: string,
+  path: string,
+  options: DocxOptions = {}
+): Promise<DocxSummary> {
+  return invoke("export_docx", { projectId, path, options });
+}
+
+// ============================================================================
+// Fountain screenplays
+// ============================================================================
+
+export interface FountainOptions {
+  /** With `author`, replaces the title page kept from an import */
+  title?: string;
+  author?: string;
+  /** Export only these documents; otherwise the screenplays, or the manuscript when there are none */
+  documentIds?: string[];
+}
+
+export interface FountainSummary {
+  projectId: string;
+  documents: number;
+  /** Scene headings written */
+  scenes: number;
+  bytes: number;
+}
+
+export function exportFountain(
+  projectId: string,
+  path: string,
+  options: FountainOptions = {}
+): Promise<FountainSummary> {
+  return invoke("export_fountain", { projectId, path, options });
+}
@@ -0,0 +1,79 @@
+/**
+ * Project History
+ *
+ * Wrappers around the shell's history commands (src-tauri/src/history.rs).
+ * Each project is committed to a local git repository on a schedule, on quit
+ * and on request, with messages like "Edited Chapter 3; added entity Kael".
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { LocalRow } from "./localStore";
+
+export interface Revision {
+  id: string;
+  summary: string;
+  message: string;
+  createdAtMs: number;
+  parent: string | null;
+}
+
+export type FileStatus = "added" | "modified" | "deleted" | "renamed";
+
+export interface FileDiff {
+  path: string;
+  oldPath: string | null;
+  status: FileStatus;
+  /** Unified diff */
+  patch: string;
+}
+
+export interface RevisionDiff {
+  from: string | null;
+  to: string;
+  changes: string[];
+  files: FileDiff[];
+}
+
+export interface HistorySettings {
+  /** Minutes between automatic commits; 0 commits only on quit and request */
+  intervalMinutes: number;
+}
+
+/** Resolves with `null` when nothing changed since the last revision */
+export function commitProjectHistory(
+  projectId: string,
+  message?: string
+): Promise<Revision | null> {
+  return invoke("commit_project_history", { projectId, message: message ?? null });
+}
+
+/** Newest first */
+export function listProjectHistory(projectId: string, limit?: number): Promise<Revision[]> {
+  return invoke("list_project_history", { projectId, limit: limit ?? null });
+}
+
+/** Compare two revisions; without `from`, `to` is compared with its parent */
+export function diffProjectRevisions(
+  projectId: string,
+  to: string,
+  from?: string
+): Promise<RevisionDiff> {
+  return invoke("diff_project_revisions", { projectId, from: from ?? null, to });
+}
+
+/** Copy a document from a past revision into a new note; resolves with its row */
+export function checkoutDocumentRevision(
+  projectId: string,
+  revision: string,
+  documentId: string
+): Promise<LocalRow> {
+  return invoke("checkout_document_revision", { projectId, revision, documentId });
+}
+
+export function getHistorySettings(): Promise<HistorySettings> {
+  return invoke("get_history_settings");
+}
+
+export function configureHistory(settings: HistorySettings): Promise<void> {
+  return invoke("configure_history", { settings });
+}
@@ -0,0 +1,141 @@
+/**
+ * Manuscript Imports
+ *
+ * Wrappers around the shell's importers (src-tauri/src/import/). Documents
+ * and entities are added to the local store and queued for sync. Comments
+ * and tracked changes in DOCX and ODT files come back with positions in the
+ * new documents, ready for `comments.add` and the editor's `addSuggestion`.
+ * Each chapter also keeps its own until `takeImportedAnnotations` is called.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+export interface ImportedDocument {
+  id: string;
+  title: string;
+  /** Scene blocks made from `***` and `#` separators; 0 if there were none */
+  scenes: number;
+  words: number;
+}
+
+export interface ImportedComment {
+  documentId: string;
+  content: string;
+  /** Missing when the commented text didn't survive the import */
+  selectionRange: { from: number; to: number } | null;
+  author: string | null;
+  createdAt: string | null;
+}
+
+export interface ImportedSuggestion {
+  documentId: string;
+  /** Send as-is in an `addSuggestion` message once the document is open */
+  suggestion: {
+    id: string;
+    from: number;
+    to: number;
+    content: string;
+    originalContent?: string;
+    type: "insert" | "replace" | "delete";
+  };
+  author: string | null;
+  createdAt: string | null;
+}
+
+export interface ImportReport {
+  projectId: string;
+  format: "docx" | "odt";
+  documents: ImportedDocument[];
+  comments: ImportedComment[];
+  suggestions: ImportedSuggestion[];
+  /** What had no equivalent and was left out, such as images and footnotes */
+  warnings: string[];
+}
+
+export function importManuscript(
+  projectId: string,
+  path: string
+): Promise<ImportReport> {
+  return invoke("import_manuscript", { projectId, path });
+}
+
+export interface ImportedAnnotations {
+  comments: ImportedComment[];
+  suggestions: ImportedSuggestion[];
+}
+
+/**
+ * Remove and return a document's imported comments and tracked changes.
+ * Call when a document opens and add them; empty once taken.
+ */
+export function takeImportedAnnotations(
+  documentId: string
+): Promise<ImportedAnnotations> {
+  return invoke("take_imported_annotations", { documentId });
+}
+
+// ============================================================================
+// Scrivener projects
+// ============================================================================
+
+export interface ImportedEntity {
+  id: string;
+  name: string;
+  type: string;
+}
+
+export interface UnmappedItem {
+  /** Titles from the top of the binder, joined with " / " */
+  binderPath: string;
+  uuid: string;
+  /** Scrivener's item type, such as "Image" or "PDF" */
+  itemType: string;
+  reason: "trashed" | "unsupported_type" | "dropped_content" | "duplicate_entity";
+  detail: string | null;
+}
+
+export interface ScrivenerReport {
+  projectId: string;
+  /** Chapters, scenes and notes, in binder order */
+  documents: ImportedDocument[];
+  /** Character, place and other sheets */
+  entities: ImportedEntity[];
+  /** What could not be brought over, item by item */
+  unmapped: UnmappedItem[];
+}
+
+/** Import a `.scriv` package, or the `.scrivx` file inside it */
+export function importScrivenerProject(
+  projectId: string,
+  path: string
+): Promise<ScrivenerReport> {
+  return invoke("import_scrivener_project", { projectId, path });
+}
+
+// ============================================================================
+// Fountain screenplays
+// ============================================================================
+
+export interface ScriptCharacter {
+  /** As written in the cues, without extensions such as (V.O.) */
+  name: string;
+  /** The linked Character entity; null when proposed as a new one */
+  entityId: string | null;
+  /** Cues spoken */
+  cues: number;
+}
+
+export interface FountainReport {
+  projectId: string;
+  /** One screenplay document; `scenes` counts its scene headings */
+  document: ImportedDocument;
+  /** In order of first appearance */
+  characters: ScriptCharacter[];
+}
+
+export function importFountain(
+  projectId: string,
+  path: string
+): Promise<FountainReport> {
+  return invoke("import_fountain", { projectId, path });
+}
@@ -0,0 +1,82 @@
+/**
+ * Local Project Store
+ *
+ * Typed wrappers around the shell's SQLite store (src-tauri/src/db). Holds a
+ * durable offline copy of the synced tables outside the webview, so it
+ * survives cache clears and isn't limited by browser storage quotas.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { ApplyReport } from "./conflicts";
+
+// Mirrors `packages/sync/src/types.ts` (this app doesn't depend on @mythos/sync)
+export type SyncTable = "documents" | "entities" | "relationships" | "mentions" | "analysis" | "captures";
+
+export interface SyncEvent {
+  id: string;
+  table: SyncTable;
+  type: "upsert" | "delete";
+  row: Record<string, unknown>;
+  version: number;
+  userId: string;
+  timestamp: string;
+}
+
+export interface ProjectSnapshot {
+  projectId: string;
+  version: number;
+  documents: Record<string, unknown>[];
+  entities: Record<string, unknown>[];
+  relationships: Record<string, unknown>[];
+  mentions: Record<string, unknown>[];
+  analysis?: Record<string, unknown>[];
+  captures?: Record<string, unknown>[];
+  syncedAt: string;
+}
+
+export type LocalRow = Record<string, unknown> & { id: string };
+
+/** Error shape returned by the store commands */
+export type LocalStoreError = { code: string; message: string };
+
+export function getLocalRow(table: SyncTable, id: string): Promise<LocalRow | null> {
+  return invoke("get_local_row", { table, id });
+}
+
+export function listLocalRows(table: SyncTable, projectId: string): Promise<LocalRow[]> {
+  return invoke("list_local_rows", { table, projectId });
+}
+
+/** Rows must carry `id` and `projectId` */
+export function upsertLocalRow(table: SyncTable, row: LocalRow): Promise<void> {
+  return invoke("upsert_local_row", { table, row });
+}
+
+export function deleteLocalRow(table: SyncTable, id: string): Promise<boolean> {
+  return invoke("delete_local_row", { table, id });
+}
+
+export function bootstrapLocalProject(snapshot: ProjectSnapshot): Promise<void> {
+  return invoke("bootstrap_local_project", { snapshot });
+}
+
+/** Applies server activity; see `./conflicts` for what comes back */
+export function applySyncEvents(events: SyncEvent[]): Promise<ApplyReport> {
+  return invoke("apply_sync_events", { events });
+}
+
+export function getLastSyncVersion(projectId: string): Promise<number> {
+  return invoke("get_last_sync_version", { projectId });
+}
+
+export function setLastSyncVersion(
+  projectId: string,
+  version: number,
+  syncedAt: string = new Date().toISOString()
+): Promise<void> {
+  return invoke("set_last_sync_version", { projectId, version, syncedAt });
+}
+
+export function clearLocalProject(projectId: string): Promise<void> {
+  return invoke("clear_local_project", { projectId });
+}
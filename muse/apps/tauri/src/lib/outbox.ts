@@ -0,0 +1,84 @@
+/**
+ * Mutation Outbox
+ *
+ * Wrappers around the shell's durable outbox (src-tauri/src/sync/outbox.rs).
+ * Mutations are persisted before they are sent and retried with backoff by a
+ * background worker, so edits survive closing the app mid-sync.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { SyncTable } from "./localStore";
+
+export const OUTBOX_PROGRESS_EVENT = "outbox://progress";
+
+/** Mirrors `Mutation` in `packages/sync/src/types.ts` */
+export interface Mutation {
+  id: string;
+  table: SyncTable;
+  type: "upsert" | "delete";
+  row?: Record<string, unknown>;
+  pk?: string;
+  baseVersion?: number;
+  createdAt: string;
+  projectId: string;
+}
+
+export interface OutboxEntry {
+  mutation: Mutation;
+  attempts: number;
+  nextAttemptAt: number;
+  lastError: string | null;
+  dead: boolean;
+}
+
+export interface OutboxStatus {
+  pending: number;
+  dead: number;
+  nextAttemptAt: number | null;
+}
+
+export interface OutboxProgress {
+  pushed: number;
+  retrying: number;
+  deadLettered: number;
+  status: OutboxStatus;
+  lastError: string | null;
+}
+
+export function enqueueMutation(mutation: Mutation): Promise<void> {
+  return invoke("enqueue_mutation", { mutation });
+}
+
+/** Point the worker at a sync endpoint, or pause pushing with `null` */
+export function configureSyncEndpoint(
+  endpoint: { url: string; token?: string } | null
+): Promise<void> {
+  return invoke("configure_sync_endpoint", { endpoint });
+}
+
+export function flushOutbox(): Promise<void> {
+  return invoke("flush_outbox");
+}
+
+export function getOutboxStatus(): Promise<OutboxStatus> {
+  return invoke("outbox_status");
+}
+
+export function listDeadLetters(): Promise<OutboxEntry[]> {
+  return invoke("list_dead_letters");
+}
+
+export function retryDeadMutation(id: string): Promise<boolean> {
+  return invoke("retry_dead_mutation", { id });
+}
+
+export function discardDeadMutation(id: string): Promise<boolean> {
+  return invoke("discard_dead_mutation", { id });
+}
+
+export function onOutboxProgress(
+  handler: (progress: OutboxProgress) => void
+): Promise<UnlistenFn> {
+  return listen<OutboxProgress>(OUTBOX_PROGRESS_EVENT, (event) => handler(event.payload));
+}
@@ -0,0 +1,156 @@
+/**
+ * Local Search
+ *
+ * Wrappers around the shell's full-text index (src-tauri/src/search/fulltext.rs),
+ * which covers document text and entity names, aliases and notes offline.
+ *
+ * Query syntax: `word`, `"a phrase"`, `prefix*`, `typo~`; every term must match.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+export type SearchHitKind = "document" | "entity";
+
+export interface SearchQuery {
+  projectId: string;
+  text: string;
+  kinds?: SearchHitKind[];
+  /** Treat every bare word as `word~` */
+  fuzzy?: boolean;
+  limit?: number;
+}
+
+/** `ranges` are `[start, end)` offsets into `text` (UTF-16, like JS strings) */
+export interface Highlighted {
+  text: string;
+  ranges: [number, number][];
+}
+
+export interface SearchHit {
+  kind: SearchHitKind;
+  id: string;
+  title: Highlighted;
+  snippet: Highlighted;
+  score: number;
+}
+
+export function searchLocal(query: SearchQuery): Promise<SearchHit[]> {
+  return invoke("search_local", { query });
+}
+
+/** Reindex from the local store, for one project or all of them */
+export function rebuildSearchIndex(projectId?: string): Promise<void> {
+  return invoke("rebuild_search_index", { projectId: projectId ?? null });
+}
+
+export type EmbeddingKind = "document" | "entity" | "memory";
+
+export interface EmbeddingRecord {
+  kind: EmbeddingKind;
+  id: string;
+  projectId: string;
+  vector: number[];
+  model?: string;
+  /** Hash of the embedded text, to tell when it needs re-embedding */
+  contentHash?: string;
+}
+
+export interface VectorHit {
+  kind: EmbeddingKind;
+  id: string;
+  /** Cosine similarity */
+  score: number;
+}
+
+export interface HybridHit {
+  kind: EmbeddingKind;
+  id: string;
+  score: number;
+  text?: SearchHit;
+  similarity?: number;
+}
+
+/** Cache embeddings (e.g. fetched from the server) for offline similarity search */
+export function upsertEmbeddings(records: EmbeddingRecord[]): Promise<void> {
+  return invoke("upsert_embeddings", { records });
+}
+
+export function deleteEmbedding(kind: EmbeddingKind, id: string): Promise<boolean> {
+  return invoke("delete_embedding", { kind, id });
+}
+
+export function vectorSearch(query: {
+  projectId: string;
+  vector: number[];
+  k?: number;
+  kinds?: EmbeddingKind[];
+  /** Defaults to the model most recently cached with the vector's dimensions */
+  model?: string;
+}): Promise<VectorHit[]> {
+  return invoke("vector_search", { query });
+}
+
+/** Full-text and vector results fused by reciprocal rank */
+export function hybridSearch(query: {
+  projectId: string;
+  text: string;
+  vector?: number[];
+  fuzzy?: boolean;
+  limit?: number;
+}): Promise<HybridHit[]> {
+  return invoke("hybrid_search", { query });
+}
+
+/** Embedding provider run by the shell; `openAiCompatible` must be on localhost */
+export type EmbeddingProviderConfig =
+  | { type: "hashing"; dimensions: number }
+  | { type: "openAiCompatible"; url: string; model: string; apiKey?: string };
+
+/** Mirrors the server's `embedding_generation` analysis jobs */
+export interface EmbeddingJob {
+  kind: "embedding_generation";
+  payload: { targetType: EmbeddingKind; targetId: string; source: string };
+  attempts: number;
+  nextAttemptAt: number;
+  lastError?: string;
+}
+
+export interface EmbeddingProgress {
+  embedded: number;
+  skipped: number;
+  removed: number;
+  failed: number;
+  pending: number;
+  lastError?: string;
+}
+
+export const EMBEDDING_PROGRESS_EVENT = "embeddings://progress";
+
+/** Start embedding changed rows locally; `null` stops the worker */
+export function configureEmbeddingProvider(
+  config: EmbeddingProviderConfig | null
+): Promise<void> {
+  return invoke("configure_embedding_provider", { config });
+}
+
+export function getEmbeddingProvider(): Promise<EmbeddingProviderConfig | null> {
+  return invoke("get_embedding_provider");
+}
+
+export function listEmbeddingJobs(limit?: number): Promise<EmbeddingJob[]> {
+  return invoke("list_embedding_jobs", { limit: limit ?? null });
+}
+
+export function embedTexts(texts: string[]): Promise<number[][]> {
+  return invoke("embed_texts", { texts });
+}
+
+/** Hybrid search with the query embedded by the local provider */
+export function semanticSearch(query: {
+  projectId: string;
+  text: string;
+  fuzzy?: boolean;
+  limit?: number;
+}): Promise<HybridHit[]> {
+  return invoke("semantic_search", { query });
+}
@@ -0,0 +1,79 @@
+/**
+ * Story Folders
+ *
+ * Wrappers around the shell's folder mirror (src-tauri/src/folder/). A story
+ * folder holds a project as plain text: Markdown documents with TOML front
+ * matter, one TOML file per entity, and relationships in `rhei.toml`.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { SyncTable } from "./localStore";
+import type { Mutation } from "./outbox";
+
+export interface FolderSummary {
+  projectId: string;
+  documents: number;
+  entities: number;
+  relationships: number;
+  /** Documents whose editor JSON is kept next to their Markdown */
+  sidecars: number;
+  /** Files written or removed */
+  changed: number;
+}
+
+export function exportStoryFolder(
+  projectId: string,
+  path: string
+): Promise<FolderSummary> {
+  return invoke("export_story_folder", { projectId, path });
+}
+
+/** Replace the project's local rows with the folder's contents */
+export function importStoryFolder(
+  path: string,
+  projectId?: string
+): Promise<FolderSummary> {
+  return invoke("import_story_folder", { path, projectId: projectId ?? null });
+}
+
+// ============================================================================
+// Watching for external edits
+// ============================================================================
+
+/** Emitted with a `FolderSyncReport` when edits in the folder were applied */
+export const FOLDER_CHANGED_EVENT = "story-folder://changed";
+
+export type FolderChange =
+  | { kind: "updated"; table: SyncTable; id: string; path: string; fields: string[] }
+  | { kind: "created"; table: SyncTable; id: string; path: string }
+  | { kind: "renamed"; table: SyncTable; id: string; from: string; to: string }
+  | { kind: "deleted"; table: SyncTable; id: string; path: string }
+  | { kind: "invalid"; path: string; message: string };
+
+export interface FolderSyncReport {
+  projectId: string;
+  changes: FolderChange[];
+  /** Mutations applied locally and queued in the outbox */
+  mutations: Mutation[];
+}
+
+export interface WatchedFolder {
+  projectId: string;
+  path: string;
+}
+
+/** Watch a story folder for edits made in other tools (one folder at a time) */
+export function watchStoryFolder(
+  projectId: string,
+  path: string
+): Promise<WatchedFolder> {
+  return invoke("watch_story_folder", { projectId, path });
+}
+
+export function unwatchStoryFolder(): Promise<void> {
+  return invoke("unwatch_story_folder");
+}
+
+export function getWatchedStoryFolder(): Promise<WatchedFolder | null> {
+  return invoke("get_watched_story_folder");
+}
@@ -8,6 +8,7 @@ import { initAuth, setupAuthDeepLinks } from './lib/auth';
 import { initAnalytics } from './lib/analytics';
 import { initClarity } from './lib/clarity';
 import { useAuthStore } from '@mythos/auth';
+import { createSecureTauriStorage } from '@mythos/auth/tauri';
 import './styles/global.css';
 
 // Initialize auth configuration
@@ -38,6 +39,9 @@ function decodeJwtPayload(token: string): { sub?: string; exp?: number } | null
   }
 }
 
+// Convex Auth JWT and refresh token live in the OS credential store, not localStorage
+const authStorage = createSecureTauriStorage();
+
 // Auth sync component - syncs Convex Auth with Zustand store
 function AuthSync(): null {
   const { isLoading, isAuthenticated } = useConvexAuth();
@@ -135,7 +139,7 @@ function replaceURL(url: string): void {
 
 ReactDOM.createRoot(document.getElementById('root')!).render(
   <React.StrictMode>
-    <ConvexAuthProvider client={convex} replaceURL={replaceURL}>
+    <ConvexAuthProvider client={convex} storage={authStorage} replaceURL={replaceURL}>
       <AuthSync />
       <DeepLinkHandler>
         <App />
@@ -11,17 +11,17 @@ import { useState, useCallback } from "react";
 import { motion } from "motion/react";
 import { BookOpen, Mail, AlertCircle, Loader2, ArrowLeft, Github } from "lucide-react";
 import { useAuthActions } from "@convex-dev/auth/react";
-import { isTauri, openInBrowser } from "../../lib/tauriAuth";
+import { getOAuthCallbackUrl, isTauri, openInBrowser } from "../../lib/tauriAuth";
 
 type AuthMode = "default" | "email-sent";
 
-function getAuthRedirectTo(): string | undefined {
+async function getAuthRedirectTo(): Promise<string | undefined> {
   if (typeof window === "undefined") {
     return undefined;
   }
-  // Use deep link callback for Tauri, web callback otherwise
+  // Use a shell-issued deep link callback for Tauri, web callback otherwise
   if (isTauri()) {
-    return "rhei://auth/callback";
+    return getOAuthCallbackUrl();
   }
   return `${window.location.origin}/callback`;
 }
@@ -96,7 +96,7 @@ export function AuthScreen({ onBack }: AuthScreenProps) {
     setIsLoading(true);
     setError(null);
     try {
-      const redirectTo = getAuthRedirectTo();
+      const redirectTo = await getAuthRedirectTo();
       const result = redirectTo
         ? await signIn(provider, { redirectTo })
         : await signIn(provider);
@@ -123,7 +123,7 @@ export function AuthScreen({ onBack }: AuthScreenProps) {
     try {
       const formData = new FormData();
       formData.append("email", email);
-      const redirectTo = getAuthRedirectTo();
+      const redirectTo = await getAuthRedirectTo();
       if (redirectTo) {
         formData.append("redirectTo", redirectTo);
       }
@@ -7,7 +7,7 @@
 import { useAuthActions } from "@convex-dev/auth/react";
 import { initAuthConfig, setPlatform } from "@mythos/auth";
 import { invalidateTokenCache } from "./tokenCache";
-import { isTauri } from "./tauriAuth";
+import { getOAuthCallbackUrl, isTauri } from "./tauriAuth";
 
 // Set platform
 setPlatform("web");
@@ -17,13 +17,13 @@ setPlatform("web");
 const CONVEX_SITE_URL = import.meta.env["VITE_CONVEX_SITE_URL"] || "https://rhei.team";
 const CONVEX_URL = import.meta.env["VITE_CONVEX_URL"] || "https://convex.rhei.team";
 
-function getAuthRedirectTo(): string | undefined {
+async function getAuthRedirectTo(): Promise<string | undefined> {
   if (typeof window === "undefined") {
     return undefined;
   }
-  // Use deep link callback for Tauri, web callback otherwise
+  // Use a shell-issued deep link callback for Tauri, web callback otherwise
   if (isTauri()) {
-    return "rhei://auth/callback";
+    return getOAuthCallbackUrl();
   }
   return `${window.location.origin}/callback`;
 }
@@ -53,7 +53,7 @@ export function useSignInWithEmail() {
   return async (email: string) => {
     const formData = new FormData();
     formData.append("email", email);
-    const redirectTo = getAuthRedirectTo();
+    const redirectTo = await getAuthRedirectTo();
     if (redirectTo) {
       formData.append("redirectTo", redirectTo);
     }
@@ -66,8 +66,8 @@ export function useSignInWithEmail() {
  */
 export function useSignInWithGitHub() {
   const { signIn } = useAuthActions();
-  return () => {
-    const redirectTo = getAuthRedirectTo();
+  return async () => {
+    const redirectTo = await getAuthRedirectTo();
     if (redirectTo) {
       return signIn("github", { redirectTo });
     }
@@ -80,8 +80,8 @@ export function useSignInWithGitHub() {
  */
 export function useSignInWithApple() {
   const { signIn } = useAuthActions();
-  return () => {
-    const redirectTo = getAuthRedirectTo();
+  return async () => {
+    const redirectTo = await getAuthRedirectTo();
     if (redirectTo) {
       return signIn("apple", { redirectTo });
     }
@@ -94,8 +94,8 @@ export function useSignInWithApple() {
  */
 export function useSignInWithGoogle() {
   const { signIn } = useAuthActions();
-  return () => {
-    const redirectTo = getAuthRedirectTo();
+  return async () => {
+    const redirectTo = await getAuthRedirectTo();
     if (redirectTo) {
       return signIn("google", { redirectTo });
     }
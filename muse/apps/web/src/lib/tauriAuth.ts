@@ -5,8 +5,6 @@
  * Uses window.__TAURI__ directly to avoid import resolution issues.
  */
 
-const SCHEME = "rhei";
-
 // Type for Tauri's global object
 declare global {
   interface Window {
@@ -49,16 +47,23 @@ export async function openInBrowser(url: string): Promise<void> {
 
 /**
  * Get OAuth callback URL for current platform
+ *
+ * In Tauri the shell issues a single-use `state` on the rhei:// callback and
+ * drops callbacks that don't carry one it issued.
  */
-export function getOAuthCallbackUrl(): string {
-  if (isTauri()) {
-    return `${SCHEME}://auth/callback`;
+export async function getOAuthCallbackUrl(): Promise<string> {
+  if (isTauri() && window.__TAURI__) {
+    const { redirectTo } = await window.__TAURI__.core.invoke<{ redirectTo: string }>(
+      "start_auth_redirect"
+    );
+    return redirectTo;
   }
   return `${window.location.origin}/auth/callback`;
 }
 
 /**
- * Listen for deep link events (Tauri only)
+ * Listen for auth callback deep links (Tauri only)
+ * Also delivers callbacks that arrived before the listener was registered
  */
 export async function listenForDeepLinks(
   callback: (url: string) => void
@@ -69,11 +74,17 @@ export async function listenForDeepLinks(
 
   try {
     const unlisten = await window.__TAURI__.event.listen<string>(
-      "deep-link://new-url",
+      "deep-link://auth-callback",
       (event) => {
         callback(event.payload);
       }
     );
+
+    const pending = await window.__TAURI__.core.invoke<string[]>("take_pending_auth_callbacks");
+    for (const url of pending) {
+      callback(url);
+    }
+
     return unlisten;
   } catch (err) {
     console.error("[tauriAuth] Failed to setup deep link listener:", err);
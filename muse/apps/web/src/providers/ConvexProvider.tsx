@@ -8,6 +8,7 @@ import { useEffect, useMemo } from "react";
 import { ConvexReactClient, useConvexAuth as useConvexAuthBase, useQuery } from "convex/react";
 import { ConvexAuthProvider, useAuthActions } from "@convex-dev/auth/react";
 import { useAuthStore } from "@mythos/auth";
+import { createSecureTauriStorage } from "@mythos/auth/tauri";
 import { ConvexOfflineProvider } from "@mythos/convex-client";
 import { api } from "../../../../convex/_generated/api";
 import { isTauri } from "../lib/tauriAuth";
@@ -24,6 +25,11 @@ function getConvexClient(): ConvexReactClient {
   return convexClient;
 }
 
+// Under Tauri, the Convex Auth JWT and refresh token live in the OS credential
+// store rather than localStorage (tokens left there by older builds move over
+// on first read)
+const authStorage = isTauri() ? createSecureTauriStorage() : undefined;
+
 function replaceURL(url: string): void {
   if (typeof window !== "undefined") {
     window.history.replaceState({}, "", url);
@@ -184,7 +190,7 @@ export function ConvexProvider({ children }: ConvexProviderProps) {
   const client = useMemo(() => getConvexClient(), []);
 
   return (
-    <ConvexAuthProvider client={client} replaceURL={replaceURL}>
+    <ConvexAuthProvider client={client} storage={authStorage} replaceURL={replaceURL}>
       <AuthSync />
       <TauriDeepLinkHandler />
       <ConvexOfflineWrapper>{children}</ConvexOfflineWrapper>
@@ -13,6 +13,7 @@ import type {
   Subscription,
   SubscriptionState,
 } from "./types";
+import { createSecureTauriStorage, isTauriRuntime } from "./tauri/secureStorage";
 
 // ============================================================
 // Auth Lifecycle Events
@@ -155,6 +156,10 @@ export const useAuthStore = create<AuthStore>()(
     {
       name: "mythos-auth",
       storage: createJSONStorage(() => {
+        // Desktop keeps the session (and its token) in the OS credential store
+        if (isTauriRuntime()) {
+          return createSecureTauriStorage();
+        }
         // Use localStorage on web, or a no-op storage for native
         if (typeof window !== "undefined" && window.localStorage) {
           return localStorage;
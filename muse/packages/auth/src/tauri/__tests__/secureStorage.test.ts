@@ -0,0 +1,96 @@
+import { beforeEach, describe, expect, it, vi } from "vitest";
+
+// The shell's credential commands, over an in-memory keychain
+const { keychain, invoke } = vi.hoisted(() => {
+  const keychain = new Map<string, string>();
+  const invoke = vi.fn(async (cmd: string, args: { account: string; key: string; secret?: string }) => {
+    const id = `${args.account}/${args.key}`;
+    switch (cmd) {
+      case "get_credential":
+        return keychain.get(id) ?? null;
+      case "set_credential":
+        keychain.set(id, args.secret ?? "");
+        return undefined;
+      case "delete_credential":
+        keychain.delete(id);
+        return undefined;
+    }
+    throw new Error(`unexpected command ${cmd}`);
+  });
+  return { keychain, invoke };
+});
+vi.mock("@tauri-apps/api/core", () => ({ invoke }));
+
+import { createSecureTauriStorage } from "../secureStorage";
+
+function memoryStorage(entries: Record<string, string>) {
+  const map = new Map(Object.entries(entries));
+  return {
+    map,
+    getItem: (key: string) => map.get(key) ?? null,
+    removeItem: (key: string) => {
+      map.delete(key);
+    },
+  };
+}
+
+const JWT_KEY = "__convexAuthJWT_httpsconvexrheiteam";
+const REFRESH_KEY = "__convexAuthRefreshToken_httpsconvexrheiteam";
+
+describe("createSecureTauriStorage", () => {
+  beforeEach(() => {
+    keychain.clear();
+    invoke.mockClear();
+  });
+
+  it("moves plaintext sessions from an earlier build into the credential store", async () => {
+    const session = JSON.stringify({ state: { isAuthenticated: true }, version: 0 });
+    const legacy = memoryStorage({
+      "mythos-auth": session,
+      [JWT_KEY]: "jwt",
+      [REFRESH_KEY]: "refresh",
+      theme: "dark",
+    });
+    const storage = createSecureTauriStorage("default", legacy);
+
+    // Still signed in after the upgrade
+    expect(await storage.getItem("mythos-auth")).toBe(session);
+    expect(await storage.getItem(JWT_KEY)).toBe("jwt");
+    expect(await storage.getItem(REFRESH_KEY)).toBe("refresh");
+
+    expect([...legacy.map.keys()]).toEqual(["theme"]);
+    expect(Object.fromEntries(keychain)).toEqual({
+      "default/mythos-auth": session,
+      [`default/${JWT_KEY}`]: "jwt",
+      [`default/${REFRESH_KEY}`]: "refresh",
+    });
+
+    // Later launches read the credential store
+    expect(await createSecureTauriStorage("default", legacy).getItem(JWT_KEY)).toBe("jwt");
+  });
+
+  it("keeps the plaintext copy when the credential store fails", async () => {
+    const legacy = memoryStorage({ [JWT_KEY]: "jwt" });
+    const storage = createSecureTauriStorage("default", legacy);
+    invoke.mockRejectedValueOnce(new Error("keychain locked"));
+
+    expect(await storage.getItem(JWT_KEY)).toBe("jwt");
+    expect(legacy.map.get(JWT_KEY)).toBe("jwt");
+
+    expect(await storage.getItem(JWT_KEY)).toBe("jwt");
+    expect(legacy.map.has(JWT_KEY)).toBe(false);
+    expect(keychain.get(`default/${JWT_KEY}`)).toBe("jwt");
+  });
+
+  it("signing out clears both copies", async () => {
+    const legacy = memoryStorage({ [REFRESH_KEY]: "refresh" });
+    keychain.set(`default/${REFRESH_KEY}`, "refresh");
+    const storage = createSecureTauriStorage("default", legacy);
+
+    await storage.removeItem(REFRESH_KEY);
+
+    expect(legacy.map.size).toBe(0);
+    expect(keychain.size).toBe(0);
+    expect(await storage.getItem(REFRESH_KEY)).toBeNull();
+  });
+});
@@ -5,10 +5,19 @@
  * Handles OAuth via system browser with deep link callbacks.
  */
 
+import { createSecureTauriStorage } from "./secureStorage";
+
 // Re-export Convex Auth hooks
 export { useAuthActions, useAuthToken } from "@convex-dev/auth/react";
 export { useConvexAuth, Authenticated, Unauthenticated, AuthLoading } from "convex/react";
 
+export {
+  createSecureTauriStorage,
+  isTauriRuntime,
+  DEFAULT_CREDENTIAL_ACCOUNT,
+} from "./secureStorage";
+export type { SecureStorage } from "./secureStorage";
+
 export type AuthProvider = "github" | "google" | "apple" | "resend";
 
 /**
@@ -62,15 +71,23 @@ export async function setupDeepLinkListener(
 ): Promise<() => void> {
   try {
     const { listen } = await import("@tauri-apps/api/event");
+    const { invoke } = await import("@tauri-apps/api/core");
 
-    const unlisten = await listen<string>("deep-link://new-url", (event) => {
-      const url = event.payload;
-
+    const handleUrl = (url: string) => {
       if (isAuthCallback(url) && onAuthCallback) {
         onAuthCallback(url);
       }
+    };
+
+    const unlisten = await listen<string>("deep-link://auth-callback", (event) => {
+      handleUrl(event.payload);
     });
 
+    const pending = await invoke<string[]>("take_pending_auth_callbacks");
+    for (const url of pending) {
+      handleUrl(url);
+    }
+
     return unlisten;
   } catch (error) {
     console.error("[tauri/auth] Failed to setup deep link listener:", error);
@@ -79,19 +96,9 @@ export async function setupDeepLinkListener(
 }
 
 /**
- * Create localStorage adapter for Tauri
- * Tauri webview has localStorage, but this provides a consistent interface
+ * Create storage adapter for Tauri
+ * Backed by the OS credential store, so tokens never sit in localStorage
  */
-export function createTauriStorage() {
-  return {
-    getItem: (key: string) => Promise.resolve(localStorage.getItem(key)),
-    setItem: (key: string, value: string) => {
-      localStorage.setItem(key, value);
-      return Promise.resolve();
-    },
-    removeItem: (key: string) => {
-      localStorage.removeItem(key);
-      return Promise.resolve();
-    },
-  };
+export function createTauriStorage(account?: string) {
+  return createSecureTauriStorage(account);
 }
@@ -0,0 +1,81 @@
+/**
+ * Secure Storage for Tauri
+ *
+ * Async storage backed by the shell's credential commands (OS keychain, or an
+ * encrypted file where no keychain is available). Keeps session tokens out of
+ * webview localStorage on desktop.
+ *
+ * Earlier desktop builds kept the `mythos-auth` session and Convex Auth's
+ * tokens in localStorage. The first read of such a key moves it into the
+ * credential store, so upgrading doesn't sign anyone out.
+ */
+
+/** Default credential account (one signed-in user per install) */
+export const DEFAULT_CREDENTIAL_ACCOUNT = "default";
+
+export interface SecureStorage {
+  getItem: (key: string) => Promise<string | null>;
+  setItem: (key: string, value: string) => Promise<void>;
+  removeItem: (key: string) => Promise<void>;
+}
+
+/**
+ * Check if running inside the Tauri shell
+ */
+export function isTauriRuntime(): boolean {
+  return (
+    typeof window !== "undefined" &&
+    ("__TAURI_INTERNALS__" in window || "__TAURI__" in window)
+  );
+}
+
+/** The part of `Storage` the migration reads and clears */
+export type LegacyStorage = Pick<Storage, "getItem" | "removeItem">;
+
+function defaultLegacyStorage(): LegacyStorage | null {
+  try {
+    return typeof window !== "undefined" && window.localStorage ? window.localStorage : null;
+  } catch {
+    return null;
+  }
+}
+
+async function invokeCredential<T>(cmd: string, args: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<T>(cmd, args);
+}
+
+/**
+ * Create a storage adapter scoped to a credential account
+ */
+export function createSecureTauriStorage(
+  account: string = DEFAULT_CREDENTIAL_ACCOUNT,
+  legacy: LegacyStorage | null = defaultLegacyStorage()
+): SecureStorage {
+  return {
+    getItem: async (key) => {
+      const plaintext = legacy?.getItem(key) ?? null;
+      if (plaintext !== null) {
+        try {
+          await invokeCredential<void>("set_credential", { account, key, secret: plaintext });
+          legacy?.removeItem(key);
+        } catch (error) {
+          // Left in place to retry on the next read
+          console.error("[tauri/auth] Failed to move credential out of localStorage:", error);
+        }
+        return plaintext;
+      }
+      try {
+        return (await invokeCredential<string | null>("get_credential", { account, key })) ?? null;
+      } catch (error) {
+        console.error("[tauri/auth] Failed to read credential:", error);
+        return null;
+      }
+    },
+    setItem: (key, value) => invokeCredential<void>("set_credential", { account, key, secret: value }),
+    removeItem: async (key) => {
+      legacy?.removeItem(key);
+      await invokeCredential<void>("delete_credential", { account, key });
+    },
+  };
+}
@@ -171,7 +171,8 @@ type BridgeEnvelope<T> = {
   version: string;
 };
 
-const BRIDGE_VERSION = '1.0.0';
+// 1.1.0: setTitle is applied to the title field
+const BRIDGE_VERSION = '1.1.0';
 
 let registeredEditor: EditorInstance | null = null;
 let messageQueue: NativeToEditorMessage[] = [];
@@ -233,6 +234,16 @@ function getParentTargetOrigin(): string {
   return '*';
 }
 
+/**
+ * Whether native speaks a protocol this editor can talk to. The shell works
+ * out the shared capabilities from our editorReady, stamping its baseline
+ * version until then and ours after, so any release of our major is fine.
+ */
+function isCompatibleVersion(version: string): boolean {
+  const major = (v: string) => v.split('.')[0];
+  return /^\d+\.\d+\.\d+$/.test(version) && major(version) === major(BRIDGE_VERSION);
+}
+
 function disableBridge(reason: string): void {
   bridgeDisabled = true;
   console.error(`[EditorBridge] ${reason}`);
@@ -275,12 +286,12 @@ function configureBridge(
     return;
   }
 
-  if (options.nativeVersion && envelope?.version && options.nativeVersion !== envelope.version) {
-    disableBridge('Bridge version mismatch in configure');
+  if (options.nativeVersion && !isCompatibleVersion(options.nativeVersion)) {
+    disableBridge(`Unsupported native bridge version ${options.nativeVersion} in configure`);
     return;
   }
 
-  if (incomingVersion !== BRIDGE_VERSION) {
+  if (!isCompatibleVersion(incomingVersion)) {
     bridgeNonce = incomingNonce;
     sendToNative(
       {
@@ -326,8 +337,27 @@ function sendToNative(message: EditorToNativeMessage, options?: { force?: boolea
 
   // Try different native handlers
   if (window.__TAURI__) {
-    // Tauri v2
-    window.__TAURI__.invoke('editor_message', { message: messageStr }).catch(console.error);
+    // Tauri v2 - the shell validates the envelope nonce/version
+    if (!bridgeConfigured && !options?.force) {
+      outboundQueue.push(message);
+      return;
+    }
+
+    if (!bridgeNonce) {
+      console.warn('[EditorBridge] Missing bridge nonce; dropping message');
+      return;
+    }
+
+    const envelope: BridgeEnvelope<EditorToNativeMessage> = {
+      type: 'editor-bridge-response',
+      payload: message,
+      nonce: bridgeNonce,
+      version: BRIDGE_VERSION,
+    };
+
+    window.__TAURI__
+      .invoke('editor_message', { message: JSON.stringify(envelope) })
+      .catch(console.error);
   } else if (window.webkit?.messageHandlers?.editor) {
     // iOS WebView
     window.webkit.messageHandlers.editor.postMessage(messageStr);
@@ -386,6 +416,10 @@ function handleNativeMessage(
       editor.commands.setContent(message.content);
       break;
 
+    case 'setTitle':
+      // Applied by the Editor component through onMessage
+      break;
+
     case 'insertContent':
       if (message.at !== undefined) {
         editor.commands.insertContentAt(message.at, message.content);
@@ -642,7 +676,7 @@ if (typeof window !== 'undefined') {
 
     if (!isConfigure) {
       if (!bridgeNonce || data.nonce !== bridgeNonce) return;
-      if (data.version !== BRIDGE_VERSION) {
+      if (!isCompatibleVersion(data.version)) {
         disableBridge(`Bridge version mismatch (native ${data.version}, editor ${BRIDGE_VERSION})`);
         return;
       }
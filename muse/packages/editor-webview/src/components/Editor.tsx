@@ -369,9 +369,16 @@ export function Editor({
   }, [editor]);
 
   // Bridge for WebView communication (Tauri/React Native)
+  const handleBridgeMessage = useCallback((message: NativeToEditorMessage) => {
+    if (message.type === 'setTitle') {
+      setLocalTitle(message.title);
+    }
+    onBridgeMessage?.(message);
+  }, [onBridgeMessage]);
+
   const { send: sendBridgeMessage } = useEditorBridge(
     _enableBridge ? (editor as unknown as EditorInstance) : null,
-    { onMessage: onBridgeMessage }
+    { onMessage: handleBridgeMessage }
   );
 
   useEffect(() => {
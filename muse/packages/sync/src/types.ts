@@ -88,7 +88,12 @@ export interface SyncResult {
 /**
  * Conflict resolution strategy
  */
-export type ConflictStrategy = "server_wins" | "client_wins" | "merge" | "manual";
+export type ConflictStrategy =
+  | "last_writer_wins"
+  | "server_wins"
+  | "client_wins"
+  | "merge"
+  | "manual";
 
 /**
  * A conflict between local and remote changes